@@ -99,6 +99,7 @@ const ES_BUILD_OPTIONS = {
     "zlib",
     "node:zlib",
     "llrt:hex",
+    "llrt:mock",
     "llrt:util",
     "llrt:xml",
     "perf_hooks",
@@ -0,0 +1,4 @@
+import { greet } from "./lib.mjs";
+import data from "./data.json";
+
+console.log(greet(data.name));
@@ -0,0 +1,3 @@
+import { format } from "node:util";
+
+export const greet = (name) => format("hello %s!", name);
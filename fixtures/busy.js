@@ -0,0 +1,13 @@
+function fibonacci(n) {
+  return n < 2 ? n : fibonacci(n - 1) + fibonacci(n - 2);
+}
+
+const end = Date.now() + 300;
+while (Date.now() < end) {
+  fibonacci(20);
+}
+
+// Sampling must not leave traces in the program
+if (Error.stackTraceLimit !== 10 || Error.prepareStackTrace !== undefined) {
+  process.exit(1);
+}
@@ -0,0 +1,17 @@
+export const handler = (event, context, callback) => {
+  setTimeout(() => {
+    callback(null, { statusCode: 200, body: "Hello world!" });
+  }, 1);
+};
+
+export const succeed = (event, context) => {
+  context.succeed({ statusCode: 200, body: "Hello world!" });
+};
+
+export const fail = (event, context) => {
+  context.fail(new Error("kaboom"));
+};
+
+export const error = (event, context, callback) => {
+  callback(new Error("kaboom"));
+};
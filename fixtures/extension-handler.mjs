@@ -0,0 +1,20 @@
+import { onInvoke, onShutdown } from "llrt:lambda";
+
+let invocations = 0;
+
+onInvoke(async (event) => {
+  if (event.eventType !== "INVOKE") {
+    throw new Error(`Unexpected event type: ${event.eventType}`);
+  }
+  await new Promise((res) => setTimeout(res, 0));
+  invocations++;
+});
+
+onShutdown(() => {
+  console.log(`Flushed after ${invocations} invocations`);
+});
+
+export const handler = async () => ({
+  statusCode: 200,
+  body: "Hello world!",
+});
@@ -0,0 +1,4 @@
+import { greeting } from "./greeting.js";
+import { platform } from "os";
+
+export const greet = (name) => `${greeting()} ${name} from ${platform()}`;
@@ -0,0 +1,10 @@
+import { onShutdown } from "llrt:lambda";
+
+onShutdown(() => {
+  console.log("Shutting down");
+});
+
+export const handler = async () => ({
+  statusCode: 200,
+  body: "Hello world!",
+});
@@ -0,0 +1,5 @@
+export function fail({ message }) {
+    throw new Error(message);
+}
+fail({ message: "kaboom" });
+//# sourceMappingURL=data:application/json;base64,eyJ2ZXJzaW9uIjozLCJmaWxlIjoidGhyb3cuanMiLCJzb3VyY2VSb290IjoiIiwic291cmNlcyI6WyJzcmMvdGhyb3cudHMiXSwibmFtZXMiOltdLCJtYXBwaW5ncyI6IkFBSUE7QUFDQSxJQUFFO0FBQ0Y7QUFFQSJ9
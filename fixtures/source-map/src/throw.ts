@@ -0,0 +1,9 @@
+interface Options {
+  message: string;
+}
+
+export function fail({ message }: Options): never {
+  throw new Error(message);
+}
+
+fail({ message: "kaboom" });
@@ -0,0 +1,5 @@
+export function fail({ message }) {
+    throw new Error(message);
+}
+fail({ message: "kaboom" });
+//# sourceMappingURL=throw.js.map
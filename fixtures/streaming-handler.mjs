@@ -0,0 +1,12 @@
+export const handler = awslambda.streamifyResponse(
+  async (event, responseStream) => {
+    responseStream = awslambda.HttpResponseStream.from(responseStream, {
+      statusCode: 200,
+      headers: { "Content-Type": "text/plain" },
+    });
+    responseStream.write("Hello ");
+    await new Promise((res) => setTimeout(res, 0));
+    responseStream.write("world!");
+    responseStream.end();
+  }
+);
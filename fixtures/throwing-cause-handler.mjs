@@ -0,0 +1,7 @@
+export const handler = async () => {
+  const cause = new AggregateError(
+    [new TypeError("invalid input"), new RangeError("out of range")],
+    "validation failed"
+  );
+  throw new Error("kaboom", { cause });
+};
@@ -0,0 +1,6 @@
+export const handler = awslambda.streamifyResponse(
+  async (event, responseStream) => {
+    responseStream.write("partial");
+    throw new Error("kaboom");
+  }
+);
@@ -0,0 +1,8 @@
+export const handler = (event, context) =>
+  new Promise((resolve) => {
+    context.signal.addEventListener("abort", () =>
+      resolve(context.signal.reason.name)
+    );
+  });
+
+export const hanging = () => new Promise(() => {});
@@ -1,17 +1,84 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 use std::future::Future;
-use std::sync::OnceLock;
+use std::sync::{Arc, Mutex, OnceLock};
 
 use llrt_utils::primordials::{BasePrimordials, Primordial};
-use rquickjs::{atom::PredefinedAtom, CatchResultExt, CaughtError, Ctx, Object, Result};
-use tokio::sync::oneshot::{self, Receiver};
+use rquickjs::{atom::PredefinedAtom, qjs, CatchResultExt, CaughtError, Ctx, Object, Result};
+use tokio::sync::{
+    oneshot::{self, Receiver},
+    Notify,
+};
 use tracing::trace;
 
 #[allow(clippy::type_complexity)]
 static ERROR_HANDLER: OnceLock<Box<dyn for<'js> Fn(&Ctx<'js>, CaughtError<'js>) + Sync + Send>> =
     OnceLock::new();
 
+static PENDING_TASKS: Mutex<Vec<PendingTasks>> = Mutex::new(Vec::new());
+
+struct PendingTasks {
+    rt: usize,
+    count: usize,
+    idle: Arc<Notify>,
+}
+
+/// Counts a spawned future as pending work of its runtime until it is dropped.
+struct PendingTask(usize);
+
+impl PendingTask {
+    fn new(ctx: &Ctx<'_>) -> Self {
+        let rt = runtime_id(ctx);
+        let mut pending = PENDING_TASKS.lock().unwrap();
+        match pending.iter_mut().find(|tasks| tasks.rt == rt) {
+            Some(tasks) => tasks.count += 1,
+            None => pending.push(PendingTasks {
+                rt,
+                count: 1,
+                idle: Default::default(),
+            }),
+        }
+        Self(rt)
+    }
+}
+
+impl Drop for PendingTask {
+    fn drop(&mut self) {
+        let mut pending = PENDING_TASKS.lock().unwrap();
+        if let Some(index) = pending.iter().position(|tasks| tasks.rt == self.0) {
+            pending[index].count -= 1;
+            if pending[index].count == 0 {
+                pending.swap_remove(index).idle.notify_waiters();
+            }
+        }
+    }
+}
+
+fn runtime_id(ctx: &Ctx<'_>) -> usize {
+    unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) as usize }
+}
+
+/// Resolves once the job queue is empty and no futures spawned through [`CtxExtension`],
+/// including timers, are pending in the runtime of `ctx`.
+pub async fn idle(ctx: &Ctx<'_>) {
+    let rt = runtime_id(ctx);
+    loop {
+        while ctx.execute_pending_job() {}
+        let idle;
+        let notified;
+        {
+            let pending = PENDING_TASKS.lock().unwrap();
+            let Some(tasks) = pending.iter().find(|tasks| tasks.rt == rt) else {
+                return;
+            };
+            idle = tasks.idle.clone();
+            // Created while locked so the last task finishing can't be missed
+            notified = idle.notified();
+        }
+        notified.await;
+    }
+}
+
 pub trait CtxExtension<'js> {
     /// Despite naming, this will not necessarily exit the parent process.
     /// It depends on the handler set by `set_spawn_error_handler`.
@@ -39,7 +106,9 @@ impl<'js> CtxExtension<'js> for Ctx<'js> {
 
         let (join_channel_tx, join_channel_rx) = oneshot::channel();
 
+        let pending = PendingTask::new(self);
         self.spawn(async move {
+            let _pending = pending;
             match future.await.catch(&ctx) {
                 Ok(res) => {
                     //result here doesn't matter if receiver has dropped
@@ -57,7 +126,9 @@ impl<'js> CtxExtension<'js> for Ctx<'js> {
         F: Future<Output = Result<()>> + 'js,
     {
         let ctx = self.clone();
+        let pending = PendingTask::new(self);
         self.spawn(async move {
+            let _pending = pending;
             if let Err(err) = future.await.catch(&ctx) {
                 handle_spawn_error(&ctx, err, None)
             }
@@ -0,0 +1,125 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::{
+    env, fs,
+    io::{stdin, stdout, Read, Write},
+    process::exit,
+};
+
+use crate::base::{
+    libs::logging::print_error_and_exit,
+    runtime_client::{
+        self,
+        emulator::{Emulator, InvocationResult, INVOCATIONS_PATH},
+    },
+    vm::Vm,
+};
+// rquickjs components
+use crate::base::{async_with, CatchResultExt};
+
+const DEFAULT_PORT: u16 = 8080;
+const ENV_UNDERSCORE_HANDLER: &str = "_HANDLER";
+
+/// `llrt invoke <handler> [--event <file|->]`
+pub async fn run_invoke(vm: &Vm, args: &[String]) -> Result<(), String> {
+    let mut handler = None;
+    let mut event_file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--event" | "-e" => event_file = args.next(),
+            _ if handler.is_none() && !arg.starts_with('-') => handler = Some(arg.clone()),
+            _ => return Err(["invoke: unknown argument \"", arg, "\""].concat()),
+        }
+    }
+
+    let handler = handler.ok_or("invoke: handler is required, e.g. index.handler")?;
+
+    let event = match event_file.map(|file| file.as_str()) {
+        Some("-") => {
+            let mut event = Vec::new();
+            stdin().read_to_end(&mut event).map_err(|e| e.to_string())?;
+            event
+        },
+        Some(file) => fs::read(file).map_err(|e| [file, ": ", &e.to_string()].concat())?,
+        None => b"{}".to_vec(),
+    };
+
+    let emulator = Emulator::bind(0).await.map_err(|e| e.to_string())?;
+
+    let (_, result) = tokio::join!(
+        start(vm, emulator.runtime_api(), handler, 1),
+        emulator.invoke(event)
+    );
+
+    print_result(&result);
+    if result.is_error() {
+        exit(1);
+    }
+    Ok(())
+}
+
+/// `llrt serve-lambda [handler] [--port <port>]`
+pub async fn run_serve(vm: &Vm, args: &[String]) -> Result<(), String> {
+    let mut handler = None;
+    let mut port = DEFAULT_PORT;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" | "-p" => {
+                port = args
+                    .next()
+                    .and_then(|port| port.parse().ok())
+                    .ok_or("serve-lambda: --port requires a valid port number")?;
+            },
+            _ if handler.is_none() && !arg.starts_with('-') => handler = Some(arg.clone()),
+            _ => return Err(["serve-lambda: unknown argument \"", arg, "\""].concat()),
+        }
+    }
+
+    let handler = handler
+        .or_else(|| env::var(ENV_UNDERSCORE_HANDLER).ok())
+        .ok_or("serve-lambda: handler is required, e.g. index.handler")?;
+
+    let emulator = Emulator::bind(port).await.map_err(|e| e.to_string())?;
+    println!(
+        "Serving {} on http://{}{}",
+        handler,
+        emulator.runtime_api(),
+        INVOCATIONS_PATH
+    );
+
+    start(vm, emulator.runtime_api(), handler, 0).await;
+
+    if let Some(init_error) = emulator.init_error() {
+        print_result(&init_error);
+        exit(1);
+    }
+    Ok(())
+}
+
+async fn start(vm: &Vm, runtime_api: String, handler: String, iterations: usize) {
+    async_with!(vm.ctx => |ctx|{
+        if let Err(err) =
+            runtime_client::start_with_runtime_api(&ctx, runtime_api, handler, iterations)
+                .await
+                .catch(&ctx)
+        {
+            print_error_and_exit(&ctx, err)
+        }
+    })
+    .await;
+}
+
+fn print_result(result: &InvocationResult) {
+    if result.is_error() {
+        let _ = std::io::stderr().write_all(&result.body);
+        eprintln!();
+    } else {
+        let mut stdout = stdout();
+        let _ = stdout.write_all(&result.body);
+        let _ = stdout.write_all(b"\n");
+    }
+}
@@ -13,9 +13,13 @@ use std::{
 };
 
 mod base;
+#[cfg(not(feature = "lambda"))]
+mod emulator;
 mod minimal_tracer;
 #[cfg(not(feature = "lambda"))]
 mod repl;
+#[cfg(not(feature = "lambda"))]
+mod watch;
 
 use constcat::concat;
 use llrt_core::modules::process::EXIT_CODE;
@@ -23,9 +27,10 @@ use minimal_tracer::MinimalTracer;
 use tracing::trace;
 
 #[cfg(not(feature = "lambda"))]
-use crate::base::compiler::compile_file;
+use crate::base::compiler::{compile_file, CompileOptions};
 use crate::base::{
     bytecode::BYTECODE_EXT,
+    coverage,
     libs::{
         logging::print_error_and_exit,
         utils::{
@@ -34,6 +39,7 @@ use crate::base::{
         },
     },
     modules::path::name_extname,
+    profiler::{self, ProfilerOptions},
     runtime_client,
     utils::io::{is_supported_ext, SUPPORTED_EXTENSIONS},
     vm::Vm,
@@ -41,7 +47,7 @@ use crate::base::{
 };
 
 // rquickjs components
-use crate::base::{async_with, CatchResultExt};
+use crate::base::{async_with, CatchResultExt, Object};
 
 #[cfg(not(target_os = "windows"))]
 #[global_allocator]
@@ -58,11 +64,14 @@ async fn main() -> Result<ExitCode, Box<dyn Error + Send + Sync>> {
     trace!("Initialized VM in {}ms", now.elapsed().as_millis());
 
     if env::var("AWS_LAMBDA_RUNTIME_API").is_ok() && env::var("_HANDLER").is_ok() {
+        start_profiler(&vm, ProfilerOptions::from_env()).await;
         start_runtime(&vm).await
     } else {
         start_cli(&vm).await;
     }
 
+    vm.runtime.idle().await;
+    coverage::finish(&vm).await;
     vm.idle().await?;
 
     Ok(ExitCode::from(EXIT_CODE.load(Ordering::Relaxed)))
@@ -81,28 +90,80 @@ fn usage() {
 
 Usage:
   llrt <filename>
+  llrt --cpu-prof [--cpu-prof-dir=<dir>] [--cpu-prof-interval=<us>] <filename>
+  llrt --watch | --watch-clear <filename> | test <test_args>
   llrt -v | --version
   llrt -h | --help
   llrt -e | --eval <source>
   llrt compile input.js [output.lrt]
   llrt test <test_args>
+  llrt invoke <handler> [--event <file>]
+  llrt serve-lambda [handler] [--port <port>]
 
 Options:
   -v, --version     Print version information
   -h, --help        Print this help message
   -e, --eval        Evaluate the provided source code
+  --cpu-prof        Sample the call stack and write a Chrome DevTools
+                      compatible CPU.<date>.<time>.<pid>.cpuprofile on exit
+  --cpu-prof-dir    Directory to write the CPU profile to, defaults to the
+                      working directory
+  --cpu-prof-interval
+                    Sampling interval in microseconds, defaults to 1000
+  --watch           Restart the program when a file it loaded changes, tests
+                      depending on the changed file are run again
+  --watch-clear     Same as --watch, but clear the screen before restarting
   compile           Compile JS to bytecode and compress it with zstd:
                       if [output.lrt] is omitted, <input>.lrt is used.
                       lrt file is expected to be executed by the llrt version
                       that created it
                       --executable      Create a self-contained executable that includes
                                         the LLRT runtime
+                      --bundle          Follow static imports and compile the input
+                                        with its dependencies into a single archive
+                      --asset <path>    Embed a file or directory in the executable,
+                                        readable with fs and require at its path
+                                        relative to the input, next to the executable
+                                        (repeatable)
+                      --sign <key.pem>  Sign the bytecode with an Ed25519 private key,
+                                        verified when LLRT_BYTECODE_PUBLIC_KEY is set
   test              Run tests with provided arguments:
                       <test_args> -d <directory> <test-filter>
+                      --coverage        Write lcov.info and coverage-final.json
+                                        reports of the code run by the tests
+                      --coverage-dir <dir>
+                                        Directory to write the coverage reports
+                                        to, defaults to coverage
+                      --reporter <junit|tap|json>
+                                        Write a machine-readable report of the
+                                        results instead of the progress output
+                      --output <file>   File to write the report to, the progress
+                                        output is kept when it is set
+                      -u, --update-snapshots
+                                        Overwrite snapshots that do not match
+  invoke            Invoke a Lambda handler once against a local Runtime API
+                      emulator and print the response, or the error to stderr:
+                      --event <file>    JSON event to send, "-" reads stdin,
+                                        defaults to {}
+  serve-lambda      Serve a Lambda handler from a local Runtime API emulator,
+                      events are POSTed to
+                      /2015-03-31/functions/function/invocations
+                      --port <port>     Port to listen on, defaults to 8080
 "#
     );
 }
 
+async fn start_profiler(vm: &Vm, options: Result<Option<ProfilerOptions>, String>) {
+    match options {
+        Ok(Some(options)) => profiler::start(vm, options).await,
+        Ok(None) => {},
+        Err(error) => {
+            eprintln!("{error}");
+            exit(1);
+        },
+    }
+}
+
 async fn start_runtime(vm: &Vm) {
     async_with!(vm.ctx => |ctx|{
         if let Err(err) = runtime_client::start(&ctx).await.catch(&ctx) {
@@ -115,7 +176,10 @@ async fn start_runtime(vm: &Vm) {
 async fn start_cli(vm: &Vm) {
     #[cfg(not(feature = "lambda"))]
     {
-        use crate::base::bytecode::BYTECODE_SELF_CONTAINED_EXECUTABLE_MARKER;
+        use crate::base::bytecode::{
+            BYTECODE_EMBEDDED_ASSETS_MARKER, BYTECODE_SELF_CONTAINED_EXECUTABLE_MARKER,
+        };
+        use crate::base::modules::fs::vfs;
         use std::io::{Read, Seek, SeekFrom};
 
         let executable_path = env::current_exe()
@@ -125,7 +189,7 @@ async fn start_cli(vm: &Vm) {
             executable_path.display()
         );
 
-        if let Ok(mut f) = std::fs::File::open(executable_path) {
+        if let Ok(mut f) = std::fs::File::open(&executable_path) {
             let size_bytes_length: usize = size_of::<u64>();
             let marker_length: usize = BYTECODE_SELF_CONTAINED_EXECUTABLE_MARKER.len();
             let offset: usize = marker_length + size_bytes_length;
@@ -157,12 +221,61 @@ async fn start_cli(vm: &Vm) {
                     eprintln!("Failed to read embedded module: {error:?}");
                     exit(1);
                 });
+
+                // Assets are stored before the module, followed by their size and marker
+                let assets_marker_length = BYTECODE_EMBEDDED_ASSETS_MARKER.len();
+                let assets_offset = (assets_marker_length + size_bytes_length) as u64;
+                if start >= assets_offset {
+                    let _ = f.seek(SeekFrom::Start(start - assets_offset));
+                    let mut assets_end = vec![0; assets_offset as usize];
+                    f.read_exact(&mut assets_end).unwrap_or_else(|error| {
+                        eprintln!("Failed to read embedded assets: {error:?}");
+                        exit(1);
+                    });
+                    if &assets_end[size_bytes_length..] == BYTECODE_EMBEDDED_ASSETS_MARKER {
+                        let size_bytes: [u8; size_of::<u64>()] =
+                            assets_end[..size_bytes_length].try_into().unwrap();
+                        let assets_size = u64::from_le_bytes(size_bytes);
+                        let _ = f.seek(SeekFrom::Start(start - assets_offset - assets_size));
+                        let mut assets = vec![0; assets_size as usize];
+                        let assets = f
+                            .read_exact(&mut assets)
+                            .and_then(|_| vfs::read_assets(&assets))
+                            .unwrap_or_else(|error| {
+                                eprintln!("Failed to read embedded assets: {error:?}");
+                                exit(1);
+                            });
+                        trace!("Mounting {} embedded assets", assets.len());
+                        if let Err(error) = vfs::mount(assets, &executable_path) {
+                            eprintln!("Failed to mount embedded assets: {error:?}");
+                            exit(1);
+                        }
+                    }
+                }
                 return vm.run_bytecode(&module).await;
             }
         }
     }
 
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+
+    // The program runs in a child process restarted on changes, see watch.rs
+    #[cfg(not(feature = "lambda"))]
+    if let Some(options) = watch::WatchOptions::from_args(&mut args) {
+        if let Err(error) = watch::run(args.split_off(1), options).await {
+            eprintln!("{error}");
+            exit(1);
+        }
+        return;
+    }
+
+    // Runtime flags come before the command or filename and override the environment
+    start_profiler(
+        vm,
+        ProfilerOptions::from_env()
+            .and_then(|options| ProfilerOptions::from_args(&mut args, options)),
+    )
+    .await;
 
     if args.len() > 1 {
         for (i, arg) in args.iter().enumerate() {
@@ -190,18 +303,51 @@ async fn start_cli(vm: &Vm) {
                         }
                         return;
                     },
+                    "invoke" | "serve-lambda" => {
+                        #[cfg(not(feature = "lambda"))]
+                        {
+                            let result = if arg == "invoke" {
+                                emulator::run_invoke(vm, &args[i + 1..]).await
+                            } else {
+                                emulator::run_serve(vm, &args[i + 1..]).await
+                            };
+                            if let Err(error) = result {
+                                eprintln!("{error}");
+                                exit(1);
+                            }
+                            return;
+                        }
+                        #[cfg(feature = "lambda")]
+                        {
+                            eprintln!("Not supported in \"lambda\" version.");
+                            exit(1);
+                        }
+                    },
                     "compile" => {
                         #[cfg(not(feature = "lambda"))]
                         {
                             if let Some(filename) = args.get(i + 1) {
                                 // Parse args for output_filename and --executable
                                 let mut output_filename = String::new();
-                                let mut create_executable = false;
+                                let mut options = CompileOptions::default();
 
                                 // Parse remaining arguments
-                                for arg in args.iter().skip(i + 2) {
+                                let mut remaining = args.iter().skip(i + 2);
+                                while let Some(arg) = remaining.next() {
                                     if arg == "--executable" {
-                                        create_executable = true;
+                                        options.create_executable = true;
+                                    } else if arg == "--bundle" {
+                                        options.bundle = true;
+                                    } else if arg == "--asset" || arg == "--sign" {
+                                        let Some(path) = remaining.next() else {
+                                            eprintln!("compile: {arg} requires a path.");
+                                            exit(1);
+                                        };
+                                        if arg == "--asset" {
+                                            options.assets.push(PathBuf::from(path));
+                                        } else {
+                                            options.signing_key = Some(PathBuf::from(path));
+                                        }
                                     } else if output_filename.is_empty() && !arg.starts_with("--") {
                                         output_filename = arg.clone();
                                     }
@@ -217,7 +363,7 @@ async fn start_cli(vm: &Vm) {
                                 let filename = Path::new(filename);
                                 let output_filename = Path::new(&output_filename);
                                 if let Err(error) =
-                                    compile_file(filename, output_filename, create_executable).await
+                                    compile_file(filename, output_filename, options).await
                                 {
                                     eprintln!("{error}");
                                     exit(1);
@@ -275,11 +421,21 @@ async fn start_cli(vm: &Vm) {
     }
 }
 
+const TEST_REPORTERS: &[&str] = &["junit", "tap", "json"];
+
 async fn run_tests(vm: &Vm, args: &[std::string::String]) -> Result<(), String> {
     let mut filters: Vec<&str> = Vec::with_capacity(args.len());
 
     let mut root = ".";
 
+    let mut coverage_dir = None;
+
+    let mut reporter = None;
+
+    let mut output = None;
+
+    let mut update_snapshots = false;
+
     let mut skip_next = false;
 
     for (i, arg) in args.iter().enumerate() {
@@ -295,6 +451,37 @@ async fn run_tests(vm: &Vm, args: &[std::string::String]) -> Result<(), String>
                 root = dir;
                 skip_next = true;
             }
+        } else if arg == "--coverage" {
+            coverage_dir.get_or_insert("coverage");
+        } else if arg == "--coverage-dir" {
+            if let Some(dir) = args.get(i + 1) {
+                coverage_dir = Some(dir.as_str());
+                skip_next = true;
+            }
+        } else if arg == "--reporter" {
+            let name = args
+                .get(i + 1)
+                .map(|name| name.as_str())
+                .unwrap_or_default();
+            if !TEST_REPORTERS.contains(&name) {
+                return Err([
+                    "--reporter must be one of ",
+                    &TEST_REPORTERS.join(", "),
+                    ", got \"",
+                    name,
+                    "\"",
+                ]
+                .concat());
+            }
+            reporter = Some(name);
+            skip_next = true;
+        } else if arg == "-u" || arg == "--update-snapshots" {
+            update_snapshots = true;
+        } else if arg == "--output" {
+            if let Some(file) = args.get(i + 1) {
+                output = Some(file.as_str());
+                skip_next = true;
+            }
         } else {
             filters.push(arg)
         }
@@ -338,12 +525,47 @@ async fn run_tests(vm: &Vm, args: &[std::string::String]) -> Result<(), String>
         };
     }
 
+    // Only the tests affected by a change are run again in watch mode
+    #[cfg(not(feature = "lambda"))]
+    if let Ok(tests) = env::var(watch::ENV_LLRT_WATCH_TESTS) {
+        let tests: std::collections::HashSet<PathBuf> = tests.lines().map(PathBuf::from).collect();
+        entries.retain(|entry| {
+            std::fs::canonicalize(entry)
+                .map(|entry| tests.contains(&entry))
+                .unwrap_or_default()
+        });
+    }
+
     entries.sort_unstable();
 
     trace!("Found tests in {}ms", now.elapsed().as_millis());
 
+    // Workers instrument the files they load and write their counts to the reports directory
+    let coverage_counts_dir = match coverage_dir {
+        Some(dir) => Some(
+            coverage::prepare(Path::new(dir))
+                .map_err(|e| ["Failed to create \"", dir, "\": ", &e.to_string()].concat())?
+                .to_string_lossy()
+                .into_owned(),
+        ),
+        None => None,
+    };
+
+    if output.is_some() && reporter.is_none() {
+        return Err("--output requires --reporter".into());
+    }
+
     vm.run_with(|ctx| {
-        ctx.globals().set("__testEntries", entries)?;
+        let globals = ctx.globals();
+        globals.set("__testEntries", entries)?;
+        globals.set("__testReporter", reporter)?;
+        globals.set("__testOutput", output)?;
+        globals.set("__testUpdateSnapshots", update_snapshots)?;
+        if let Some(dir) = coverage_counts_dir {
+            let process: Object = globals.get("process")?;
+            let env: Object = process.get("env")?;
+            env.set(coverage::ENV_LLRT_COVERAGE_DIR, dir)?;
+        }
         Ok(())
     })
     .await;
@@ -3,7 +3,7 @@
 #![allow(clippy::uninlined_format_args)]
 
 use std::{
-    collections::VecDeque,
+    collections::{BTreeSet, VecDeque},
     env, fs,
     io::{stdout, IsTerminal},
     path::{Path, PathBuf},
@@ -23,12 +23,20 @@ use crate::base::libs::{
 };
 // rquickjs components
 use crate::base::{
-    async_with, AsyncContext, CatchResultExt, Ctx, Error, EvalOptions, Object, PredefinedAtom,
-    Promise, Rest,
+    async_with, AsyncContext, CatchResultExt, Ctx, Error, EvalOptions, Filter, Function, Object,
+    PredefinedAtom, Promise, Rest, Value,
 };
 
 use crate::VERSION_STRING;
 
+const HELP: &str = r#".editor  Enter editor mode to evaluate multiple lines at once
+.exit    Exit the REPL
+.help    Print this help message
+.load    Load JS from a file into the REPL session
+.save    Save all evaluated commands in this REPL session to a file
+
+Press Ctrl+C to abort the current expression, Ctrl+D to exit the REPL"#;
+
 async fn process_input(ctx: &Ctx<'_>, input: &str, tty: bool) -> String {
     // First try to evaluate and format the input
 
@@ -38,7 +46,9 @@ async fn process_input(ctx: &Ctx<'_>, input: &str, tty: bool) -> String {
     match async {
         let promise = ctx.eval_with_options::<Promise, _>(input.as_bytes(), options)?;
         let future = promise.into_future::<Object>();
-        let value = future.await?.get(PredefinedAtom::Value)?;
+        let value: Value = future.await?.get(PredefinedAtom::Value)?;
+        // Like in Node.js, the last result is available as `_`
+        ctx.globals().set("_", value.clone())?;
         format_values(ctx, Rest(vec![value]), tty, true)
     }
     .await
@@ -48,6 +58,7 @@ async fn process_input(ctx: &Ctx<'_>, input: &str, tty: bool) -> String {
         Err(error) => {
             match (|| {
                 let error_value = error.into_value(ctx)?;
+                ctx.globals().set("_error", error_value.clone())?;
                 format_values(ctx, Rest(vec![error_value]), tty, true)
             })() {
                 Ok(s) => s,
@@ -57,11 +68,243 @@ async fn process_input(ctx: &Ctx<'_>, input: &str, tty: bool) -> String {
     }
 }
 
+async fn print_input(ctx: &Ctx<'_>, input: &str, tty: bool) -> Result<(), Error> {
+    execute!(stdout(), cursor::MoveToColumn(0))?;
+    disable_raw_mode()?;
+    let output = process_input(ctx, input, tty).await;
+    println!("{output}");
+    enable_raw_mode()?;
+    Ok(())
+}
+
+// Prints lines while in raw mode
+fn print_lines(text: &str) -> Result<(), Error> {
+    execute!(stdout(), cursor::MoveToColumn(0))?;
+    disable_raw_mode()?;
+    println!("{text}");
+    enable_raw_mode()?;
+    Ok(())
+}
+
+/// Whether the input ends inside brackets, a template literal or a comment, in which case the
+/// REPL waits for more lines before evaluating it.
+fn is_incomplete(input: &str) -> bool {
+    // Open brackets, with '`' for template literals and '$' for their substitutions
+    let mut stack = Vec::new();
+    let mut previous = None;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if stack.last() == Some(&'`') {
+            match c {
+                '\\' => {
+                    chars.next();
+                },
+                '`' => {
+                    stack.pop();
+                    previous = Some(c);
+                },
+                '$' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    stack.push('$');
+                    previous = Some('{');
+                },
+                _ => {},
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => loop {
+                match chars.next() {
+                    // Continues on the next line
+                    Some('\\') => {
+                        if chars.next().is_none() {
+                            return true;
+                        }
+                    },
+                    Some(quote) if quote == c => break,
+                    // Unterminated strings are syntax errors
+                    Some('\n') | None => return false,
+                    _ => {},
+                }
+            },
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                continue;
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut star = false;
+                loop {
+                    match chars.next() {
+                        Some('/') if star => break,
+                        Some(c) => star = c == '*',
+                        None => return true,
+                    }
+                }
+                continue;
+            },
+            // A slash after an operand is a division, otherwise it starts a regular expression
+            '/' if previous.is_none_or(|previous| "(,=:[!&|?{};+-*%<>~^".contains(previous)) => {
+                let mut class = false;
+                loop {
+                    match chars.next() {
+                        Some('\\') => {
+                            chars.next();
+                        },
+                        Some('[') => class = true,
+                        Some(']') => class = false,
+                        Some('/') if !class => break,
+                        Some('\n') | None => return false,
+                        _ => {},
+                    }
+                }
+            },
+            '(' | '[' | '{' | '`' => stack.push(c),
+            ')' | ']' | '}' => {
+                let open = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                match stack.pop() {
+                    Some(bracket) if bracket == open => {},
+                    // Back in the template literal
+                    Some('$') if c == '}' => {},
+                    // Let the evaluation report the syntax error
+                    _ => return false,
+                }
+            },
+            _ => {},
+        }
+        if !c.is_whitespace() {
+            previous = Some(c);
+        }
+    }
+
+    !stack.is_empty()
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Completes the global or property name before the end of the line by introspecting the live
+/// context. Returns the position of the name and the sorted candidates.
+fn complete(ctx: &Ctx<'_>, line: &str) -> (usize, Vec<String>) {
+    let start = line
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !is_identifier_char(*c) && *c != '.')
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(line.len());
+    let expression = &line[start..];
+    if expression.is_empty() || expression.starts_with(|c: char| c.is_ascii_digit()) {
+        return (line.len(), Vec::new());
+    }
+
+    let (object, prefix, position) = match expression.rfind('.') {
+        Some(dot) => {
+            let path = &expression[..dot];
+            if path.is_empty() || path.contains("..") || path.ends_with('.') {
+                return (line.len(), Vec::new());
+            }
+            // Only names and property accesses get here, so evaluating has no side effects
+            // other than getters
+            let object = ctx
+                .eval::<Value, _>(path)
+                .catch(ctx)
+                .ok()
+                .and_then(|value| {
+                    if value.is_null() || value.is_undefined() {
+                        return None;
+                    }
+                    // Complete the methods of primitives from their wrapper object
+                    let object: Function = ctx.globals().get(PredefinedAtom::Object).ok()?;
+                    object.call::<_, Object>((value,)).ok()
+                });
+            let Some(object) = object else {
+                return (line.len(), Vec::new());
+            };
+            (object, &expression[dot + 1..], start + dot + 1)
+        },
+        None => (ctx.globals(), expression, start),
+    };
+
+    let mut candidates = BTreeSet::new();
+    let mut current = Some(object);
+    while let Some(object) = current {
+        for key in object.own_keys::<String>(Filter::new().string()).flatten() {
+            if key.starts_with(prefix)
+                && !key.starts_with(|c: char| c.is_ascii_digit())
+                && key.chars().all(is_identifier_char)
+            {
+                candidates.insert(key);
+            }
+        }
+        current = object.get_prototype();
+    }
+
+    (position, candidates.into_iter().collect())
+}
+
+fn common_prefix(candidates: &[String]) -> &str {
+    let Some(first) = candidates.first() else {
+        return "";
+    };
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first
+            .bytes()
+            .zip(candidate.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count();
+    }
+    while !first.is_char_boundary(len) {
+        len -= 1;
+    }
+    &first[..len]
+}
+
+/// Completes the name before the cursor. Returns the text to insert, which is empty when the
+/// candidates have nothing in common beyond what was typed, and the candidates.
+fn complete_at(ctx: &Ctx<'_>, line: &str, cursor_pos: usize) -> (String, Vec<String>) {
+    let (position, candidates) = complete(ctx, &line[..cursor_pos]);
+    if candidates.is_empty() {
+        return (String::new(), candidates);
+    }
+    let completion = common_prefix(&candidates)
+        .get(cursor_pos - position..)
+        .unwrap_or_default()
+        .to_string();
+    (completion, candidates)
+}
+
+// The cursor is a byte index into the input, moved by whole characters
+fn previous_char_boundary(input: &str, cursor_pos: usize) -> usize {
+    input[..cursor_pos]
+        .char_indices()
+        .next_back()
+        .map_or(0, |(i, _)| i)
+}
+
+fn next_char_boundary(input: &str, cursor_pos: usize) -> usize {
+    input[cursor_pos..]
+        .chars()
+        .next()
+        .map_or(cursor_pos, |c| cursor_pos + c.len_utf8())
+}
+
 pub(crate) async fn run_repl(ctx: &AsyncContext) {
     let is_tty = stdout().is_terminal();
     async_with!(ctx => |ctx| {
 
-        println!("Welcome to {}\nType \".exit\" or Ctrl+C or Ctrl+D to exit", VERSION_STRING);
+        println!("Welcome to {}\nType \".help\" for more information, \".exit\" or Ctrl+C or Ctrl+D to exit", VERSION_STRING);
 
         let history_file = if cfg!(windows) {
             env::var("APPDATA")
@@ -98,6 +341,12 @@ pub(crate) async fn run_repl(ctx: &AsyncContext) {
         let mut history_index = history.len();
         let mut cursor_pos = 0;
 
+        // Lines of an incomplete expression, or of the editor mode
+        let mut pending_input = String::new();
+        let mut editor_mode = false;
+        // Evaluated commands, for .save
+        let mut session: Vec<String> = Vec::new();
+
         println!("");
 
         let exit_repl = || {
@@ -113,12 +362,19 @@ pub(crate) async fn run_repl(ctx: &AsyncContext) {
         let mut added_input_chars = false;
 
         loop {
+            let prompt = if editor_mode {
+                ""
+            } else if pending_input.is_empty() {
+                "> "
+            } else {
+                "... "
+            };
             execute!(
                 stdout(),
                 cursor::MoveToColumn(0),
                 Clear(ClearType::CurrentLine),
-                Print(format!("> {}", current_input)),
-                cursor::MoveToColumn(cursor_pos as u16 + 2)
+                Print(format!("{}{}", prompt, current_input)),
+                cursor::MoveToColumn((current_input[..cursor_pos].chars().count() + prompt.len()) as u16)
             )?;
 
             if let Event::Key(KeyEvent {
@@ -136,33 +392,80 @@ pub(crate) async fn run_repl(ctx: &AsyncContext) {
                 match code {
                     KeyCode::Enter => {
                         println!();
-                        let cmd: String = current_input.trim().into();
-                        if !cmd.is_empty() {
-                            if cmd == ".exit" {
-                                exit_repl()?;
-                                break;
-                            }
-                            execute!(stdout(), cursor::MoveToColumn(0))?;
-                            disable_raw_mode()?;
-                            let output = process_input(&ctx, &cmd,is_tty).await;
-                            println!("{output}");
-                            enable_raw_mode()?;
+                        let line = std::mem::take(&mut current_input);
+                        cursor_pos = 0;
 
+                        let cmd = line.trim();
+                        if !cmd.is_empty() {
                             //only push to history if we're not reusing the same command
                             if added_input_chars {
-                                history.push_back(cmd);
+                                history.push_back(cmd.into());
                                 if history.len() > 100 {
                                     history.pop_front();
                                 }
                             }
-
-                            history_index = history.len();
-                            current_input.clear();
-                            cursor_pos = 0;
                             if persist_history {
                                 write_history(&history, history_file.as_deref());
                             }
-                            added_input_chars = false;
+                        }
+                        history_index = history.len();
+                        added_input_chars = false;
+
+                        if editor_mode {
+                            pending_input.push_str(&line);
+                            pending_input.push('\n');
+                            continue;
+                        }
+
+                        if pending_input.is_empty() && cmd.starts_with('.') {
+                            let (command, argument) = cmd.split_once(' ').unwrap_or((cmd, ""));
+                            let argument = argument.trim();
+                            match command {
+                                ".exit" => {
+                                    exit_repl()?;
+                                    break;
+                                },
+                                ".help" => print_lines(HELP)?,
+                                ".editor" => {
+                                    editor_mode = true;
+                                    print_lines("// Entering editor mode (Ctrl+D to finish, Ctrl+C to cancel)")?;
+                                },
+                                ".load" => match fs::read_to_string(argument) {
+                                    Ok(source) => {
+                                        print_input(&ctx, &source, is_tty).await?;
+                                        session.push(source);
+                                    },
+                                    Err(err) => print_lines(&format!("Failed to load {}: {}", argument, err))?,
+                                },
+                                ".save" => match fs::write(argument, session.join("\n")) {
+                                    Ok(_) => print_lines(&format!("Session saved to: {}", argument))?,
+                                    Err(err) => print_lines(&format!("Failed to save {}: {}", argument, err))?,
+                                },
+                                _ => print_lines("Invalid REPL keyword")?,
+                            }
+                            continue;
+                        }
+
+                        pending_input.push_str(&line);
+                        if is_incomplete(&pending_input) {
+                            pending_input.push('\n');
+                            continue;
+                        }
+                        let input = std::mem::take(&mut pending_input);
+                        if !input.trim().is_empty() {
+                            print_input(&ctx, &input, is_tty).await?;
+                            session.push(input);
+                        }
+                    },
+                    KeyCode::Tab => {
+                        let (completion, candidates) = complete_at(&ctx, &current_input, cursor_pos);
+                        if !completion.is_empty() {
+                            added_input_chars = true;
+                            current_input.insert_str(cursor_pos, &completion);
+                            cursor_pos += completion.len();
+                        } else if candidates.len() > 1 {
+                            println!();
+                            print_lines(&candidates.join("  "))?;
                         }
                     },
                     KeyCode::Up => {
@@ -194,27 +497,46 @@ pub(crate) async fn run_repl(ctx: &AsyncContext) {
                         }
                     },
                     KeyCode::Left => {
-                        cursor_pos = cursor_pos.saturating_sub(1);
+                        cursor_pos = previous_char_boundary(&current_input, cursor_pos);
                     },
                     KeyCode::Right => {
-                        if cursor_pos < current_input.len() {
-                            cursor_pos += 1;
-                        }
+                        cursor_pos = next_char_boundary(&current_input, cursor_pos);
                     },
                     KeyCode::Backspace => {
                         if cursor_pos > 0 {
-                            current_input.remove(cursor_pos - 1);
-                            cursor_pos -= 1;
+                            cursor_pos = previous_char_boundary(&current_input, cursor_pos);
+                            current_input.remove(cursor_pos);
                         }
                     },
                     KeyCode::Char(c) => {
+                        if modifiers == KeyModifiers::CONTROL && c == 'd' && editor_mode {
+                            println!();
+                            editor_mode = false;
+                            pending_input.push_str(&std::mem::take(&mut current_input));
+                            cursor_pos = 0;
+                            let input = std::mem::take(&mut pending_input);
+                            if !input.trim().is_empty() {
+                                print_input(&ctx, &input, is_tty).await?;
+                                session.push(input);
+                            }
+                            continue;
+                        }
+                        // Abort the current expression rather than exiting
+                        if modifiers == KeyModifiers::CONTROL && c == 'c' && (editor_mode || !pending_input.is_empty()) {
+                            println!();
+                            editor_mode = false;
+                            pending_input.clear();
+                            current_input.clear();
+                            cursor_pos = 0;
+                            continue;
+                        }
                         if modifiers == KeyModifiers::CONTROL && (c == 'c' || c == 'd') {
                             exit_repl()?;
                             break;
                         }
                         added_input_chars = true;
                         current_input.insert(cursor_pos, c);
-                        cursor_pos += 1;
+                        cursor_pos += c.len_utf8();
                     },
                     _ => {},
                 }
@@ -247,7 +569,10 @@ mod tests {
 
     use llrt_test::test_async_with;
 
-    use crate::repl::process_input;
+    use crate::repl::{
+        common_prefix, complete, complete_at, is_incomplete, next_char_boundary,
+        previous_char_boundary, process_input,
+    };
 
     #[tokio::test]
     async fn test_process_input() {
@@ -271,8 +596,93 @@ mod tests {
                     output,
                     "ReferenceError: a is not defined\n  at <eval> (eval_script:1:1)"
                 );
+
+                let output = process_input(&ctx, "await Promise.resolve(3)", false).await;
+
+                assert_eq!(output, "3");
+
+                let output = process_input(&ctx, "_ * 2", false).await;
+
+                assert_eq!(output, "6");
+
+                process_input(&ctx, "throw 42", false).await;
+                let output = process_input(&ctx, "_error + 1", false).await;
+
+                assert_eq!(output, "43");
+            })
+        })
+        .await;
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(is_incomplete("function a() {"));
+        assert!(is_incomplete("[1,\n2"));
+        assert!(is_incomplete("`a${"));
+        assert!(is_incomplete("`a\n"));
+        assert!(is_incomplete("/* comment"));
+        assert!(is_incomplete("'a\\"));
+        assert!(is_incomplete("if (a) { `${b}`"));
+
+        assert!(!is_incomplete("1+1"));
+        assert!(!is_incomplete("function a() {}"));
+        assert!(!is_incomplete("'{'"));
+        assert!(!is_incomplete("`${'}'}`"));
+        assert!(!is_incomplete("/[{]/.test(a) // {"));
+        assert!(!is_incomplete("a / (b) / c"));
+        assert!(!is_incomplete("a)"));
+        assert!(!is_incomplete("'unterminated"));
+    }
+
+    #[tokio::test]
+    async fn test_complete() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                process_input(
+                    &ctx,
+                    "globalThis.someObject = { first: 1, firstName: 'a' }",
+                    false,
+                )
+                .await;
+
+                let (position, candidates) = complete(&ctx, "x = someOb");
+                assert_eq!(position, 4);
+                assert_eq!(candidates, ["someObject"]);
+
+                let (position, candidates) = complete(&ctx, "someObject.fi");
+                assert_eq!(position, 11);
+                assert_eq!(candidates, ["first", "firstName"]);
+                assert_eq!(common_prefix(&candidates), "first");
+
+                let (_, candidates) = complete(&ctx, "someObject.hasOwn");
+                assert_eq!(candidates, ["hasOwnProperty"]);
+
+                let (_, candidates) = complete(&ctx, "someObject.firstName.toUpper");
+                assert_eq!(candidates, ["toUpperCase"]);
+
+                let (_, candidates) = complete(&ctx, "missing.a");
+                assert!(candidates.is_empty());
+
+                let (completion, candidates) = complete_at(&ctx, "noSuchGlobal", 12);
+                assert_eq!(completion, "");
+                assert!(candidates.is_empty());
+
+                let input = "é = someObject.firstN";
+                let (completion, _) = complete_at(&ctx, input, input.len());
+                assert_eq!(completion, "ame");
             })
         })
         .await;
     }
+
+    #[test]
+    fn test_char_boundaries() {
+        let input = "aé€b";
+        assert_eq!(next_char_boundary(input, 1), 3);
+        assert_eq!(next_char_boundary(input, 3), 6);
+        assert_eq!(next_char_boundary(input, input.len()), input.len());
+        assert_eq!(previous_char_boundary(input, 6), 3);
+        assert_eq!(previous_char_boundary(input, 3), 1);
+        assert_eq!(previous_char_boundary(input, 0), 0);
+    }
 }
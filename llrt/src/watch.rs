@@ -0,0 +1,315 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// `llrt --watch` runs the program in a child process and restarts it when a file it loaded
+// changes. Children report the files resolved by the module loader to a file that is watched
+// like the others, see `llrt_core::watch`. For `llrt test`, only the test files depending on
+// the changed files are run again.
+//
+// Directories are watched rather than files, so that files replaced by editors with a rename
+// keep being tracked.
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    env, fs,
+    io::stdout,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor, execute,
+    terminal::{Clear, ClearType},
+};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use tokio::{process::Command, sync::mpsc, time};
+
+use crate::base::watch::ENV_LLRT_WATCH_REPORT;
+
+/// Test files to run, one per line, set when only some tests are affected by a change.
+pub const ENV_LLRT_WATCH_TESTS: &str = "__LLRT_WATCH_TESTS";
+
+// Editors often save files in several steps
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+pub struct WatchOptions {
+    pub clear: bool,
+}
+
+impl WatchOptions {
+    /// Removes `--watch` and `--watch-clear` from the leading flags of the arguments, leaving
+    /// other runtime flags for the child process. `--watch-clear` implies `--watch`.
+    pub fn from_args(args: &mut Vec<String>) -> Option<Self> {
+        let mut options = None;
+        let mut i = 1;
+        while let Some(arg) = args.get(i) {
+            match arg.as_str() {
+                "--watch" => {
+                    options.get_or_insert(Self { clear: false });
+                },
+                "--watch-clear" => options = Some(Self { clear: true }),
+                _ if arg.starts_with("--") => {
+                    i += 1;
+                    continue;
+                },
+                _ => break,
+            }
+            args.remove(i);
+        }
+        options
+    }
+}
+
+/// Runs `llrt <args>` until interrupted, restarting it whenever one of its files changes.
+pub async fn run(args: Vec<String>, options: WatchOptions) -> Result<(), String> {
+    if args.iter().all(|arg| arg.starts_with("--")) {
+        return Err("--watch requires a file or the test command".into());
+    }
+    let executable = env::current_exe().map_err(|err| err.to_string())?;
+    let is_test = args.iter().any(|arg| arg == "test");
+    let name = args.join(" ");
+
+    let report_dir = env::temp_dir().join(format!("llrt-watch-{}", process::id()));
+    fs::create_dir_all(&report_dir).map_err(|err| err.to_string())?;
+    let report_dir = fs::canonicalize(&report_dir).map_err(|err| err.to_string())?;
+    let report = report_dir.join("dependencies");
+
+    let (watcher, mut changes) = Watcher::new().map_err(|err| err.to_string())?;
+    let mut graph = DependencyGraph::default();
+    let mut tests = None;
+
+    loop {
+        fs::write(&report, "").map_err(|err| err.to_string())?;
+        watcher.watch(&report);
+        let mut report_offset = 0;
+
+        if options.clear {
+            let _ = execute!(
+                stdout(),
+                Clear(ClearType::All),
+                Clear(ClearType::Purge),
+                cursor::MoveTo(0, 0)
+            );
+        }
+
+        let mut command = Command::new(&executable);
+        command
+            .args(&args)
+            .env(ENV_LLRT_WATCH_REPORT, &report)
+            .kill_on_drop(true);
+        if let Some(tests) = tests.take() {
+            command.env(ENV_LLRT_WATCH_TESTS, tests);
+        }
+        let mut child = command.spawn().map_err(|err| err.to_string())?;
+        let mut running = true;
+
+        let changed = loop {
+            tokio::select! {
+                status = child.wait(), if running => {
+                    running = false;
+                    match status {
+                        Ok(status) if status.success() => eprintln!("Completed running '{name}'"),
+                        _ => eprintln!("Failed running '{name}'"),
+                    }
+                    eprintln!("Waiting for file changes before restarting...");
+                },
+                Some(path) = changes.recv() => {
+                    let mut paths = vec![path];
+                    while let Ok(Some(path)) = time::timeout(DEBOUNCE, changes.recv()).await {
+                        paths.push(path);
+                    }
+                    if paths.contains(&report) {
+                        report_offset = read_report(&report, report_offset, &mut graph, &watcher);
+                        paths.retain(|path| path != &report);
+                    }
+                    if !paths.is_empty() {
+                        break paths;
+                    }
+                },
+                _ = tokio::signal::ctrl_c() => {
+                    let _ = child.kill().await;
+                    let _ = fs::remove_dir_all(&report_dir);
+                    process::exit(130);
+                },
+            }
+        };
+
+        if running {
+            let _ = child.kill().await;
+        }
+        if is_test {
+            let affected = graph.roots(&changed);
+            if !affected.is_empty() {
+                tests = Some(affected.join("\n"));
+            }
+        }
+        eprintln!("Restarting '{name}'");
+    }
+}
+
+// Reads the lines appended since the last read, returning the new offset
+fn read_report(
+    report: &Path,
+    offset: usize,
+    graph: &mut DependencyGraph,
+    watcher: &Watcher,
+) -> usize {
+    let Ok(content) = fs::read_to_string(report) else {
+        return offset;
+    };
+    let Some(content) = content.get(offset..) else {
+        return offset;
+    };
+    // Ignore a partially written last line
+    let Some(end) = content.rfind('\n') else {
+        return offset;
+    };
+    for line in content[..end].lines() {
+        if let Some((importer, path)) = line.split_once('\t') {
+            let path = PathBuf::from(path);
+            watcher.watch(&path);
+            graph.add(importer, path);
+        }
+    }
+    offset + end + 1
+}
+
+/// Files by the files that imported them.
+#[derive(Default)]
+struct DependencyGraph {
+    importers: HashMap<PathBuf, HashSet<PathBuf>>,
+}
+
+impl DependencyGraph {
+    fn add(&mut self, importer: &str, path: PathBuf) {
+        let importers = self.importers.entry(path).or_default();
+        // Entry points are imported from scripts or the test runner
+        if Path::new(importer).is_absolute() {
+            importers.insert(PathBuf::from(importer));
+        }
+    }
+
+    /// Entry points depending on any of the paths, such as test files.
+    fn roots(&self, paths: &[PathBuf]) -> Vec<String> {
+        let mut roots = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+        while let Some(path) = stack.pop() {
+            if !visited.insert(path) {
+                continue;
+            }
+            match self.importers.get(path) {
+                Some(importers) if !importers.is_empty() => {
+                    stack.extend(importers.iter().map(PathBuf::as_path))
+                },
+                Some(_) => roots.push(path.to_string_lossy().into_owned()),
+                None => {},
+            }
+        }
+        roots.sort_unstable();
+        roots
+    }
+}
+
+/// Notifies changes to individual files.
+struct Watcher {
+    files: Arc<Mutex<HashSet<PathBuf>>>,
+    directories: RefCell<HashSet<PathBuf>>,
+    watcher: RefCell<RecommendedWatcher>,
+}
+
+impl Watcher {
+    fn new() -> notify::Result<(Self, mpsc::UnboundedReceiver<PathBuf>)> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let files = Arc::new(Mutex::new(HashSet::new()));
+
+        let watched = files.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            let files = watched.lock().unwrap();
+            for path in event.paths {
+                if files.contains(&path) {
+                    //the receiver is only dropped when exiting
+                    let _ = sender.send(path);
+                }
+            }
+        })?;
+
+        let watcher = Self {
+            files,
+            directories: RefCell::default(),
+            watcher: RefCell::new(watcher),
+        };
+        Ok((watcher, receiver))
+    }
+
+    fn watch(&self, path: &Path) {
+        if !self.files.lock().unwrap().insert(path.to_path_buf()) {
+            return;
+        }
+        let Some(dir) = path.parent() else {
+            return;
+        };
+        if !self.directories.borrow_mut().insert(dir.to_path_buf()) {
+            return;
+        }
+        if let Err(err) = self
+            .watcher
+            .borrow_mut()
+            .watch(dir, RecursiveMode::NonRecursive)
+        {
+            eprintln!("Failed to watch {}: {}", dir.display(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_watch_flags() {
+        let mut parsed = args(&["llrt", "--cpu-prof", "--watch", "index.js", "--watch"]);
+        let options = WatchOptions::from_args(&mut parsed).unwrap();
+        assert!(!options.clear);
+        assert_eq!(parsed, args(&["llrt", "--cpu-prof", "index.js", "--watch"]));
+
+        let mut parsed = args(&["llrt", "--watch-clear", "test"]);
+        assert!(WatchOptions::from_args(&mut parsed).unwrap().clear);
+        assert_eq!(parsed, args(&["llrt", "test"]));
+
+        let mut parsed = args(&["llrt", "index.js", "--watch"]);
+        assert!(WatchOptions::from_args(&mut parsed).is_none());
+    }
+
+    #[test]
+    fn finds_affected_entry_points() {
+        let mut graph = DependencyGraph::default();
+        graph.add("llrt:test/worker", PathBuf::from("/app/a.test.js"));
+        graph.add("llrt:test/worker", PathBuf::from("/app/b.test.js"));
+        graph.add("/app/a.test.js", PathBuf::from("/app/util.js"));
+        graph.add("/app/b.test.js", PathBuf::from("/app/other.js"));
+        graph.add("/app/other.js", PathBuf::from("/app/util.js"));
+
+        assert_eq!(
+            graph.roots(&[PathBuf::from("/app/util.js")]),
+            ["/app/a.test.js", "/app/b.test.js"]
+        );
+        assert_eq!(
+            graph.roots(&[PathBuf::from("/app/a.test.js")]),
+            ["/app/a.test.js"]
+        );
+        assert!(graph.roots(&[PathBuf::from("/app/unknown.js")]).is_empty());
+    }
+}
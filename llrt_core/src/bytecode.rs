@@ -1,10 +1,18 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 
-pub const BYTECODE_VERSION: &str = "lrt01";
+pub const BYTECODE_VERSION: &str = "lrt02";
 pub const BYTECODE_COMPRESSED: u8 = b'c';
 pub const BYTECODE_UNCOMPRESSED: u8 = b'u';
+pub const BYTECODE_ARCHIVE: u8 = b'a';
+pub const BYTECODE_FLAG_HASH: u8 = 1;
+pub const BYTECODE_FLAG_SIGNATURE: u8 = 2;
+pub const BYTECODE_HASH_LENGTH: usize = 32;
+pub const BYTECODE_SIGNATURE_LENGTH: usize = 64;
+/// Version of llrt recorded in the header, bytecode only runs on the version that created it
+pub const BYTECODE_RUNTIME_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const BYTECODE_SELF_CONTAINED_EXECUTABLE_MARKER: &[u8] = "lrtx".as_bytes();
+pub const BYTECODE_EMBEDDED_ASSETS_MARKER: &[u8] = "lrta".as_bytes();
 
 macro_rules! define_extension {
     ($base:ident, $file:ident, $ext:expr) => {
@@ -18,16 +26,30 @@ macro_rules! define_extension {
 define_extension!(BYTECODE_EXT, BYTECODE_FILE_EXT, "lrt");
 pub const SIGNATURE_LENGTH: usize = BYTECODE_VERSION.len() + 1;
 
+// The header is the bytecode version, the kind of payload, the flags, the length-prefixed
+// runtime version and, when flagged, the SHA-256 hash of the payload and the Ed25519
+// signature of everything before it. Compressed payloads start with their uncompressed size.
 #[allow(dead_code)]
 pub fn add_bytecode_header(bytes: Vec<u8>, file_size: Option<u32>) -> Vec<u8> {
-    let mut compressed_bytes = Vec::with_capacity(bytes.len());
-    compressed_bytes.extend_from_slice(BYTECODE_VERSION.as_bytes());
+    let kind = if file_size.is_some() {
+        BYTECODE_COMPRESSED
+    } else {
+        BYTECODE_UNCOMPRESSED
+    };
+    let mut compressed_bytes = Vec::with_capacity(bytes.len() + 32);
+    write_bytecode_header(&mut compressed_bytes, kind, 0);
     if let Some(file_size) = file_size {
-        compressed_bytes.push(BYTECODE_COMPRESSED);
         compressed_bytes.extend_from_slice(&file_size.to_le_bytes());
-    } else {
-        compressed_bytes.push(BYTECODE_UNCOMPRESSED)
     }
     compressed_bytes.extend_from_slice(&bytes);
     compressed_bytes
 }
+
+#[allow(dead_code)]
+pub fn write_bytecode_header(bytes: &mut Vec<u8>, kind: u8, flags: u8) {
+    bytes.extend_from_slice(BYTECODE_VERSION.as_bytes());
+    bytes.push(kind);
+    bytes.push(flags);
+    bytes.push(BYTECODE_RUNTIME_VERSION.len() as u8);
+    bytes.extend_from_slice(BYTECODE_RUNTIME_VERSION.as_bytes());
+}
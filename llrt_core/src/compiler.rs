@@ -1,65 +1,383 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
 use std::{
-    env, fs, io,
+    collections::HashMap,
+    env,
+    ffi::CString,
+    fs, io,
     path::{Path, PathBuf},
+    result::Result as StdResult,
+    sync::{Arc, Mutex},
 };
 
-use rquickjs::{CatchResultExt, Context, Module, Runtime, WriteOptions};
+use rquickjs::{
+    loader::{Loader, Resolver},
+    qjs, CatchResultExt, Context, Ctx, Error, Module, Result, Runtime, WriteOptions,
+};
 use tracing::trace;
 use zstd::bulk::Compressor;
 
-use crate::bytecode::{add_bytecode_header, BYTECODE_SELF_CONTAINED_EXECUTABLE_MARKER};
+use crate::bytecode::{
+    add_bytecode_header, BYTECODE_ARCHIVE, BYTECODE_COMPRESSED, BYTECODE_EMBEDDED_ASSETS_MARKER,
+    BYTECODE_SELF_CONTAINED_EXECUTABLE_MARKER,
+};
 use crate::compiler_common::{human_file_size, DummyLoader, DummyResolver};
 use crate::libs::{logging::print_error_and_exit, utils::result::ResultExt};
-use crate::modules::embedded::COMPRESSION_DICT;
+use crate::modules::{
+    embedded::{
+        archive::{write_archive, ArchiveModule},
+        integrity::{read_signing_key, seal},
+        resolver::embedded_resolve,
+        COMPRESSION_DICT,
+    },
+    fs::vfs::write_assets,
+    module_builder::ModuleResolver,
+    require::{resolver::require_resolve, CJS_LOADER_PREFIX},
+};
+use crate::source_map::read_embeddable_source_map;
+use crate::vm::VmOptions;
+
+// Name of the module importing the entry point when bundling
+const BUNDLE_ROOT: &str = "<bundle>";
+
+#[derive(Default)]
+pub struct CompileOptions {
+    /// Prepend the runtime to the bytecode to create a self-contained executable
+    pub create_executable: bool,
+    /// Compile the module graph of the input into an archive
+    pub bundle: bool,
+    /// Files and directories to embed in the executable
+    pub assets: Vec<PathBuf>,
+    /// PEM file with the Ed25519 private key to sign the bytecode with
+    pub signing_key: Option<PathBuf>,
+}
 
-fn compress_module(bytes: &[u8]) -> io::Result<Vec<u8>> {
+fn compress_module(bytes: &[u8]) -> io::Result<(u32, Vec<u8>)> {
     let mut compressor = Compressor::with_dictionary(22, COMPRESSION_DICT)?;
     let compressed_bytes = compressor.compress(bytes)?;
     let uncompressed_len = bytes.len() as u32;
+    Ok((uncompressed_len, compressed_bytes))
+}
+
+#[derive(Default)]
+struct CompileStats {
+    js_bytes: usize,
+    total_bytes: usize,
+    compressed_bytes: usize,
+}
+
+impl CompileStats {
+    fn add_module(&mut self, source: &[u8], bytecode: &[u8], compressed: &[u8]) {
+        self.js_bytes += source.len();
+        self.total_bytes += bytecode.len();
+        self.compressed_bytes += compressed.len();
+    }
+}
+
+#[derive(Default)]
+struct BundleState {
+    root: PathBuf,
+    prefix: String,
+    paths: HashMap<String, PathBuf>,
+    modules: Vec<ArchiveModule>,
+    stats: CompileStats,
+}
+
+impl BundleState {
+    fn module_name(&self, path: &Path) -> String {
+        let path = path.strip_prefix(&self.root).unwrap_or(path);
+        let path = path.to_string_lossy().replace('\\', "/");
+        [self.prefix.as_str(), "/", path.trim_start_matches('/')].concat()
+    }
+}
+
+/// Resolves imports like the runtime does, naming modules found on disk after their path
+/// relative to the entry point. Builtin and embedded modules are left to the runtime.
+struct BundleResolver {
+    state: Arc<Mutex<BundleState>>,
+    builtins: ModuleResolver,
+}
+
+impl Resolver for BundleResolver {
+    fn resolve(&mut self, ctx: &Ctx<'_>, base: &str, name: &str) -> Result<String> {
+        if base == BUNDLE_ROOT {
+            return Ok(name.into());
+        }
+        if self.builtins.resolve(ctx, base, name).is_ok() || embedded_resolve(name, base).is_ok() {
+            trace!("Not bundling builtin module: {}", name);
+            return Ok(name.into());
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let base_path = state
+            .paths
+            .get(base)
+            .map(|path| path.to_string_lossy().to_string())
+            .ok_or_else(|| Error::new_resolving(base, name))?;
+
+        let resolved = require_resolve(ctx, name, &base_path, None, true)?;
+        if resolved.starts_with(CJS_LOADER_PREFIX) || resolved.ends_with(".cjs") {
+            return Err(Error::new_resolving_message(
+                base,
+                name,
+                "CommonJS modules can't be bundled",
+            ));
+        }
+        let path = fs::canonicalize(resolved.as_ref()).unwrap_or_else(|_| resolved.as_ref().into());
+        let module_name = state.module_name(&path);
+
+        if let Some(module) = state.modules.iter_mut().find(|module| module.name == base) {
+            module.imports.insert(name.into(), module_name.clone());
+        }
+        state.paths.insert(module_name.clone(), path);
+        Ok(module_name)
+    }
+}
 
-    let compressed = add_bytecode_header(compressed_bytes, Some(uncompressed_len));
-    Ok(compressed)
+struct BundleLoader {
+    state: Arc<Mutex<BundleState>>,
+}
+
+impl Loader for BundleLoader {
+    fn load<'js>(&mut self, ctx: &Ctx<'js>, name: &str) -> Result<Module<'js>> {
+        let mut state = self.state.lock().unwrap();
+        let Some(path) = state.paths.get(name).cloned() else {
+            // Builtin modules are only needed to resolve the graph
+            return Module::declare(ctx.clone(), name, "");
+        };
+
+        trace!("Bundling module: {}", name);
+
+        let mut source = fs::read(&path)?;
+        let is_json = path.extension().is_some_and(|ext| ext == "json");
+        if is_json {
+            let mut module = b"export default JSON.parse(`".to_vec();
+            module.append(&mut source);
+            module.extend_from_slice(b"`);");
+            source = module;
+        } else if source.starts_with(b"#!") {
+            let end = source
+                .iter()
+                .position(|&c| c == b'\n')
+                .unwrap_or(source.len());
+            source.drain(..end);
+        }
+        let source_map = if is_json {
+            None
+        } else {
+            read_embeddable_source_map(&path, &source)
+        };
+
+        let module = Module::declare(ctx.clone(), name, source.clone())?;
+        let bytecode = module.write(WriteOptions::default())?;
+        let (uncompressed_len, compressed) = compress_module(&bytecode)?;
+        let compressed = add_bytecode_header(compressed, Some(uncompressed_len));
+        state.stats.add_module(&source, &bytecode, &compressed);
+        state.modules.push(ArchiveModule {
+            name: name.into(),
+            bytecode: compressed,
+            imports: HashMap::new(),
+            source_map,
+        });
+
+        Ok(module)
+    }
+}
+
+/// Reads the asset files and directories to embed in an executable, naming each asset
+/// after its path relative to `root`, the directory of the compiled entry file.
+fn collect_assets(root: &Path, paths: &[PathBuf]) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let root = root.canonicalize()?;
+    let mut files = Vec::new();
+    let mut pending: Vec<PathBuf> = paths.iter().rev().cloned().collect();
+    while let Some(path) = pending.pop() {
+        let path = fs::canonicalize(&path).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                ["Unable to read asset: ", &path.to_string_lossy()].concat(),
+            )
+        })?;
+        if path.is_dir() {
+            let mut entries = fs::read_dir(&path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort_unstable_by(|a, b| b.cmp(a));
+            pending.append(&mut entries);
+            continue;
+        }
+        let Ok(name) = path.strip_prefix(&root) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                [
+                    "Asset is outside of the directory of the compiled file: ",
+                    &path.to_string_lossy(),
+                ]
+                .concat(),
+            ));
+        };
+        let name = name.to_string_lossy().replace('\\', "/");
+        if files.iter().any(|(existing, _)| *existing == name) {
+            continue;
+        }
+        trace!("Embedding asset: {}", name);
+        let bytes = fs::read(&path)?;
+        files.push((name, bytes));
+    }
+    Ok(files)
+}
+
+/// Loads the module graph of `entry` through the runtime loader, without evaluating it.
+fn load_module_graph(ctx: &Ctx<'_>, entry: &str) -> Result<()> {
+    let source = CString::new(["import \"", entry, "\";"].concat())?;
+    let name = CString::new(BUNDLE_ROOT)?;
+    let flags =
+        qjs::JS_EVAL_TYPE_MODULE | qjs::JS_EVAL_FLAG_STRICT | qjs::JS_EVAL_FLAG_COMPILE_ONLY;
+    unsafe {
+        let ctx_ptr = ctx.as_raw().as_ptr();
+        let module = qjs::JS_Eval(
+            ctx_ptr,
+            source.as_ptr(),
+            source.as_bytes().len() as _,
+            name.as_ptr(),
+            flags as i32,
+        );
+        if qjs::JS_IsException(module) {
+            return Err(Error::Exception);
+        }
+        let result = qjs::JS_ResolveModule(ctx_ptr, module);
+        qjs::JS_FreeValue(ctx_ptr, module);
+        if result < 0 {
+            return Err(Error::Exception);
+        }
+    }
+    Ok(())
 }
 
 pub async fn compile_file(
     input_filename: &Path,
     output_filename: &Path,
-    create_executable: bool,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let resolver = (DummyResolver,);
-    let loader = (DummyLoader,);
+    options: CompileOptions,
+) -> StdResult<(), Box<dyn std::error::Error + Send + Sync>> {
+    let CompileOptions {
+        create_executable,
+        bundle,
+        assets,
+        signing_key,
+    } = options;
+
+    if !assets.is_empty() && !create_executable {
+        return Err("Assets can only be embedded in executables, use --executable".into());
+    }
+    let assets = match input_filename.parent() {
+        _ if assets.is_empty() => Vec::new(),
+        Some(root) if !root.as_os_str().is_empty() => collect_assets(root, &assets)?,
+        _ => collect_assets(Path::new("."), &assets)?,
+    };
+    let signing_key = signing_key
+        .map(|path| {
+            read_signing_key(&path).map_err(|err| {
+                [
+                    "Unable to read signing key ",
+                    &path.to_string_lossy(),
+                    ": ",
+                    &err.to_string(),
+                ]
+                .concat()
+            })
+        })
+        .transpose()?;
 
     let rt = Runtime::new()?;
-    rt.set_loader(resolver, loader);
+
+    let bundle_state = Arc::new(Mutex::new(BundleState::default()));
+    if bundle {
+        let (builtins, _, _) = VmOptions::default().module_builder.build();
+        let resolver = BundleResolver {
+            state: bundle_state.clone(),
+            builtins,
+        };
+        let loader = BundleLoader {
+            state: bundle_state.clone(),
+        };
+        rt.set_loader(resolver, loader);
+    } else {
+        rt.set_loader((DummyResolver,), (DummyLoader,));
+    }
     let ctx = Context::full(&rt)?;
 
-    let mut total_bytes: usize = 0;
-    let mut compressed_bytes: usize = 0;
-    let mut js_bytes: usize = 0;
+    let mut stats = CompileStats::default();
 
     ctx.with(|ctx| {
         (|| {
-            let source = fs::read_to_string(input_filename).or_throw_msg(
-                &ctx,
-                &["Unable to load: ", &input_filename.to_string_lossy()].concat(),
-            )?;
-            js_bytes = source.len();
+            let mut compressed = if bundle {
+                let entry = fs::canonicalize(input_filename).or_throw_msg(
+                    &ctx,
+                    &["Unable to load: ", &input_filename.to_string_lossy()].concat(),
+                )?;
+                let entry_name = {
+                    let mut state = bundle_state.lock().unwrap();
+                    state.root = entry.parent().map(Path::to_path_buf).unwrap_or_default();
+                    state.prefix = output_filename
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let entry_name = state.module_name(&entry);
+                    state.paths.insert(entry_name.clone(), entry);
+                    entry_name
+                };
+
+                load_module_graph(&ctx, &entry_name)?;
+
+                let mut state = bundle_state.lock().unwrap();
+                stats = std::mem::take(&mut state.stats);
+                trace!("Bundled {} modules", state.modules.len());
+                seal(
+                    BYTECODE_ARCHIVE,
+                    &write_archive(&state.modules),
+                    signing_key.as_ref(),
+                )
+            } else {
+                let source = fs::read_to_string(input_filename).or_throw_msg(
+                    &ctx,
+                    &["Unable to load: ", &input_filename.to_string_lossy()].concat(),
+                )?;
 
-            let module_name = input_filename
-                .with_extension("")
-                .to_string_lossy()
-                .to_string();
+                let module_name = input_filename
+                    .with_extension("")
+                    .to_string_lossy()
+                    .to_string();
 
-            trace!("Compiling module: {}", module_name);
+                trace!("Compiling module: {}", module_name);
 
-            let module = Module::declare(ctx.clone(), module_name, source)?;
-            let bytes = module.write(WriteOptions::default())?;
-            let mut compressed = compress_module(&bytes)?;
+                let module = Module::declare(ctx.clone(), module_name.clone(), source.clone())?;
+                let bytes = module.write(WriteOptions::default())?;
+                let (uncompressed_len, compressed) = compress_module(&bytes)?;
 
-            total_bytes += bytes.len();
-            compressed_bytes += compressed.len();
+                // Modules with a source map are compiled to an archive to embed it
+                let compressed = if let Some(source_map) =
+                    read_embeddable_source_map(input_filename, source.as_bytes())
+                {
+                    trace!("Embedding source map of: {}", module_name);
+                    let module = ArchiveModule {
+                        name: module_name,
+                        bytecode: add_bytecode_header(compressed, Some(uncompressed_len)),
+                        imports: HashMap::new(),
+                        source_map: Some(source_map),
+                    };
+                    seal(
+                        BYTECODE_ARCHIVE,
+                        &write_archive(&[module]),
+                        signing_key.as_ref(),
+                    )
+                } else {
+                    let mut payload = Vec::with_capacity(compressed.len() + 4);
+                    payload.extend_from_slice(&uncompressed_len.to_le_bytes());
+                    payload.extend_from_slice(&compressed);
+                    seal(BYTECODE_COMPRESSED, &payload, signing_key.as_ref())
+                };
+                stats.add_module(source.as_bytes(), &bytes, &compressed);
+                compressed
+            };
 
             if create_executable {
                 // Create the executable by prepending the LLRT runtime to the bytecode
@@ -70,9 +388,17 @@ pub async fn compile_file(
                 let mut content = fs::read(executable_path)?;
                 exe_content.append(&mut content);
 
+                if !assets.is_empty() {
+                    let mut assets = write_assets(&assets);
+                    let size = u64::try_from(assets.len()).unwrap();
+                    exe_content.append(&mut assets);
+                    exe_content.extend_from_slice(&size.to_le_bytes());
+                    exe_content.extend_from_slice(BYTECODE_EMBEDDED_ASSETS_MARKER);
+                }
+
+                let size = u64::try_from(compressed.len()).unwrap();
                 exe_content.append(&mut compressed);
 
-                let size = u64::try_from(compressed_bytes).unwrap();
                 let size_bytes = size.to_le_bytes();
                 exe_content.extend_from_slice(&size_bytes);
 
@@ -98,11 +424,15 @@ pub async fn compile_file(
         .unwrap_or_else(|err| print_error_and_exit(&ctx, err))
     });
 
-    trace!("JS size: {}", human_file_size(js_bytes));
-    trace!("Bytecode size: {}", human_file_size(total_bytes));
+    trace!("JS size: {}", human_file_size(stats.js_bytes));
+    trace!(
+        "Assets size: {}",
+        human_file_size(assets.iter().map(|(_, bytes)| bytes.len()).sum())
+    );
+    trace!("Bytecode size: {}", human_file_size(stats.total_bytes));
     trace!(
         "Compressed bytecode size: {}",
-        human_file_size(compressed_bytes)
+        human_file_size(stats.compressed_bytes)
     );
 
     Ok(())
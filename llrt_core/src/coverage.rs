@@ -0,0 +1,784 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// Code coverage for `llrt test --coverage`.
+//
+// QuickJS has no coverage or debugger hooks, so test workers parse the files they load and insert
+// counters incremented at the start of statements and function bodies. Workers write their counts
+// when they exit, then the test runner merges them into lcov and Istanbul JSON reports, mapping
+// locations through source maps.
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    env,
+    fmt::Write as _,
+    fs, mem,
+    path::{Path, PathBuf},
+    process,
+    sync::Mutex,
+};
+
+use once_cell::sync::Lazy;
+use oxc_allocator::{Allocator, Vec as ArenaVec};
+use oxc_ast::ast::{
+    ArrowFunctionExpression, AssignmentExpression, BindingPattern, Expression, Function,
+    FunctionBody, LabeledStatement, MethodDefinition, ObjectProperty, PropertyDefinition,
+    Statement, VariableDeclarator,
+};
+use oxc_ast_visit::{walk, Visit};
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, SourceType, Span};
+use oxc_syntax::scope::ScopeFlags;
+use rquickjs::{Array, Ctx, Result, TypedArray};
+use simd_json::{json, prelude::*, OwnedValue};
+
+use crate::source_map;
+use crate::vm::Vm;
+
+/// Directory test workers write their counts to, set by the test runner.
+pub const ENV_LLRT_COVERAGE_DIR: &str = "__LLRT_COVERAGE_DIR";
+
+const COUNTERS_GLOBAL: &str = "__llrt_cov";
+const COUNTS_DIR: &str = ".tmp";
+
+static COUNTS_DIR_PATH: Lazy<Option<PathBuf>> =
+    Lazy::new(|| env::var_os(ENV_LLRT_COVERAGE_DIR).map(PathBuf::from));
+
+static CWD: Lazy<Option<String>> = Lazy::new(|| {
+    env::current_dir()
+        .ok()
+        .map(|dir| dir.to_string_lossy().into_owned())
+});
+
+/// Files instrumented by this process, their index is the index of their counters.
+static FILES: Mutex<Vec<InstrumentedFile>> = Mutex::new(Vec::new());
+
+static REPORT_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// A location with a 1-based line and a 0-based column, like Istanbul.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct Location {
+    line: u32,
+    column: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Counter {
+    /// Functions have a name, statements don't
+    name: Option<String>,
+    start: Location,
+    end: Location,
+}
+
+struct InstrumentedFile {
+    path: String,
+    counters: Vec<Counter>,
+}
+
+/// Prepares `dir` for the reports of a test run and returns the directory workers write their
+/// counts to. The reports are written by [`finish`].
+pub fn prepare(dir: &Path) -> std::io::Result<PathBuf> {
+    let counts_dir = dir.join(COUNTS_DIR);
+    if counts_dir.exists() {
+        fs::remove_dir_all(&counts_dir)?;
+    }
+    fs::create_dir_all(&counts_dir)?;
+    *REPORT_DIR.lock().unwrap() = Some(dir.to_path_buf());
+    Ok(fs::canonicalize(counts_dir)?)
+}
+
+/// Adds counters to the source of a module when coverage is collected. Only files in the
+/// working directory outside of `node_modules` are instrumented.
+pub fn instrument<'a>(ctx: &Ctx<'_>, path: &str, source: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    if COUNTS_DIR_PATH.is_none() || !should_instrument(path) {
+        return Ok(Cow::Borrowed(source));
+    }
+    let Ok(source) = std::str::from_utf8(source) else {
+        return Ok(Cow::Borrowed(source.as_bytes()));
+    };
+
+    let mut files = FILES.lock().unwrap();
+    let id = files.len();
+    let Some((instrumented, counters)) = instrument_source(
+        source,
+        &[COUNTERS_GLOBAL, "[", &id.to_string(), "]"].concat(),
+    ) else {
+        return Ok(Cow::Borrowed(source.as_bytes()));
+    };
+
+    let globals = ctx.globals();
+    let all_counters = match globals.get::<_, Option<Array>>(COUNTERS_GLOBAL)? {
+        Some(all_counters) => all_counters,
+        None => {
+            let all_counters = Array::new(ctx.clone())?;
+            globals.set(COUNTERS_GLOBAL, all_counters.clone())?;
+            all_counters
+        },
+    };
+    all_counters.set(
+        id,
+        TypedArray::<u32>::new(ctx.clone(), vec![0; counters.len()])?,
+    )?;
+    files.push(InstrumentedFile {
+        path: path.into(),
+        counters,
+    });
+
+    Ok(Cow::Owned(instrumented.into_bytes()))
+}
+
+fn should_instrument(path: &str) -> bool {
+    let in_cwd = CWD
+        .as_deref()
+        .is_some_and(|cwd| path.starts_with(cwd) && path[cwd.len()..].starts_with('/'));
+    in_cwd
+        && !path.contains("/node_modules/")
+        && [".js", ".mjs", ".cjs"]
+            .iter()
+            .any(|ext| path.ends_with(ext))
+}
+
+fn is_reported_source(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    !path.contains("node_modules/")
+        && !path.contains("://")
+        && !name.contains(".test.")
+        && !name.contains(".spec.")
+}
+
+/// Writes the counts of this process when it instrumented files, and the reports when it ran
+/// tests with coverage.
+pub async fn finish(vm: &Vm) {
+    let files = std::mem::take(&mut *FILES.lock().unwrap());
+    if let (Some(dir), false) = (COUNTS_DIR_PATH.as_ref(), files.is_empty()) {
+        let len = files.len();
+        let counts = vm.ctx.with(move |ctx| read_counts(&ctx, len)).await;
+        let path = dir.join([&process::id().to_string(), ".json"].concat());
+        if let Err(err) = fs::write(&path, counts_to_json(&files, &counts).encode()) {
+            eprintln!("Failed to write coverage {}: {}", path.display(), err);
+        }
+    }
+
+    let report_dir = REPORT_DIR.lock().unwrap().take();
+    if let Some(dir) = report_dir {
+        match report(&dir) {
+            // Written to stderr, stdout may carry the output of a reporter
+            Ok(summary) => eprintln!("{}", summary),
+            Err(err) => eprintln!("Failed to write coverage reports: {}", err),
+        }
+    }
+}
+
+fn read_counts(ctx: &Ctx<'_>, len: usize) -> Vec<Vec<u32>> {
+    let all_counters: Option<Array> = ctx.globals().get(COUNTERS_GLOBAL).ok();
+    (0..len)
+        .map(|id| {
+            all_counters
+                .as_ref()
+                .and_then(|all_counters| all_counters.get::<TypedArray<u32>>(id).ok())
+                .map(|counters| AsRef::<[u32]>::as_ref(&counters).to_vec())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn counts_to_json(files: &[InstrumentedFile], counts: &[Vec<u32>]) -> OwnedValue {
+    let files: Vec<OwnedValue> = files
+        .iter()
+        .zip(counts)
+        .map(|(file, counts)| {
+            let counters: Vec<OwnedValue> = file
+                .counters
+                .iter()
+                .map(|counter| {
+                    json!([
+                        counter.start.line,
+                        counter.start.column,
+                        counter.end.line,
+                        counter.end.column,
+                        counter.name.clone()
+                    ])
+                })
+                .collect();
+            json!({
+                "path": file.path.as_str(),
+                "counters": counters,
+                "counts": counts.clone()
+            })
+        })
+        .collect();
+    OwnedValue::from(files)
+}
+
+/// Coverage of an original source file. Counters are keyed by location, so that a source
+/// bundled into several files, or loaded by several workers, is only reported once.
+#[derive(Default)]
+struct SourceCoverage {
+    statements: BTreeMap<(Location, Location), u64>,
+    functions: BTreeMap<(Location, String), (Location, u64)>,
+}
+
+impl SourceCoverage {
+    fn lines(&self) -> BTreeMap<u32, u64> {
+        let mut lines = BTreeMap::new();
+        for ((start, _), count) in &self.statements {
+            let hits: &mut u64 = lines.entry(start.line).or_default();
+            *hits = (*hits).max(*count);
+        }
+        lines
+    }
+}
+
+fn report(dir: &Path) -> std::result::Result<String, String> {
+    let counts_dir = dir.join(COUNTS_DIR);
+    let mut sources: BTreeMap<String, SourceCoverage> = BTreeMap::new();
+
+    for entry in fs::read_dir(&counts_dir).map_err(|err| err.to_string())? {
+        let path = entry.map_err(|err| err.to_string())?.path();
+        let Ok(mut json) = fs::read(&path) else {
+            continue;
+        };
+        let Ok(OwnedValue::Array(files)) = simd_json::to_owned_value(&mut json) else {
+            continue;
+        };
+        for file in files.iter() {
+            add_file_counts(&mut sources, file);
+        }
+    }
+    let _ = fs::remove_dir_all(&counts_dir);
+
+    let cwd = CWD.as_deref().unwrap_or_default();
+    let lcov_path = dir.join("lcov.info");
+    let json_path = dir.join("coverage-final.json");
+    fs::write(&lcov_path, to_lcov(&sources)).map_err(|err| err.to_string())?;
+    fs::write(&json_path, to_istanbul(&sources).encode()).map_err(|err| err.to_string())?;
+
+    let mut summary = String::with_capacity(256 + sources.len() * 64);
+    let mut totals = [(0, 0); 3];
+    let _ = writeln!(summary, "\n{:<40} | % Stmts | % Funcs | % Lines", "File");
+    for (path, source) in &sources {
+        let lines = source.lines();
+        let counts = [
+            covered(source.statements.values()),
+            covered(source.functions.values().map(|(_, count)| count)),
+            covered(lines.values()),
+        ];
+        for (total, count) in totals.iter_mut().zip(counts) {
+            total.0 += count.0;
+            total.1 += count.1;
+        }
+        let name = path
+            .strip_prefix(cwd)
+            .map(|path| path.trim_start_matches('/'))
+            .unwrap_or(path);
+        let _ = writeln!(
+            summary,
+            "{:<40} | {:>7} | {:>7} | {:>7}",
+            name,
+            percent(counts[0]),
+            percent(counts[1]),
+            percent(counts[2])
+        );
+    }
+    let _ = writeln!(
+        summary,
+        "{:<40} | {:>7} | {:>7} | {:>7}",
+        "All files",
+        percent(totals[0]),
+        percent(totals[1]),
+        percent(totals[2])
+    );
+    let _ = write!(
+        summary,
+        "\nCoverage written to {} and {}",
+        lcov_path.display(),
+        json_path.display()
+    );
+    Ok(summary)
+}
+
+fn add_file_counts(sources: &mut BTreeMap<String, SourceCoverage>, file: &OwnedValue) {
+    let (Some(path), Some(counters), Some(counts)) = (
+        file.get_str("path"),
+        file.get_array("counters"),
+        file.get_array("counts"),
+    ) else {
+        return;
+    };
+    for (counter, count) in counters.iter().zip(counts.iter()) {
+        let count = count.as_u64().unwrap_or_default();
+        let Some(counter) = counter.as_array() else {
+            continue;
+        };
+        let field = |index: usize| {
+            counter
+                .get(index)
+                .and_then(|value| value.as_u32())
+                .unwrap_or_default()
+        };
+        let start = Location {
+            line: field(0),
+            column: field(1),
+        };
+        let end = Location {
+            line: field(2),
+            column: field(3),
+        };
+        let name = counter.get(4).and_then(|name| name.as_str());
+
+        let (source, start, end) = map_range(path, start, end);
+        if !is_reported_source(&source) {
+            continue;
+        }
+        let coverage = sources.entry(source).or_default();
+        match name {
+            Some(name) => {
+                let function = coverage
+                    .functions
+                    .entry((start, name.into()))
+                    .or_insert((end, 0));
+                function.1 += count;
+            },
+            None => *coverage.statements.entry((start, end)).or_default() += count,
+        }
+    }
+}
+
+fn map_range(path: &str, start: Location, end: Location) -> (String, Location, Location) {
+    let map = |location: Location| {
+        source_map::lookup_location(path, location.line, location.column + 1).map(
+            |(source, line, column)| {
+                (
+                    source,
+                    Location {
+                        line,
+                        column: column - 1,
+                    },
+                )
+            },
+        )
+    };
+    match map(start) {
+        Some((source, start)) => {
+            let end = match map(end) {
+                Some((end_source, end)) if end_source == source && end >= start => end,
+                _ => start,
+            };
+            (source, start, end)
+        },
+        None => (path.into(), start, end),
+    }
+}
+
+fn covered<'a>(counts: impl Iterator<Item = &'a u64>) -> (usize, usize) {
+    counts.fold((0, 0), |(hit, total), count| {
+        (hit + (*count > 0) as usize, total + 1)
+    })
+}
+
+fn percent((hit, total): (usize, usize)) -> String {
+    if total == 0 {
+        return "100".into();
+    }
+    format!("{:.2}", hit as f64 * 100.0 / total as f64)
+}
+
+fn to_lcov(sources: &BTreeMap<String, SourceCoverage>) -> String {
+    let mut lcov = String::with_capacity(sources.len() * 1024);
+    for (path, source) in sources {
+        let _ = writeln!(lcov, "TN:\nSF:{}", path);
+        for ((start, name), _) in &source.functions {
+            let _ = writeln!(lcov, "FN:{},{}", start.line, name);
+        }
+        for ((_, name), (_, count)) in &source.functions {
+            let _ = writeln!(lcov, "FNDA:{},{}", count, name);
+        }
+        let functions = covered(source.functions.values().map(|(_, count)| count));
+        let _ = writeln!(lcov, "FNF:{}\nFNH:{}", functions.1, functions.0);
+
+        let lines = source.lines();
+        for (line, count) in &lines {
+            let _ = writeln!(lcov, "DA:{},{}", line, count);
+        }
+        let lines = covered(lines.values());
+        let _ = writeln!(lcov, "LF:{}\nLH:{}", lines.1, lines.0);
+        lcov.push_str("BRF:0\nBRH:0\nend_of_record\n");
+    }
+    lcov
+}
+
+fn to_istanbul(sources: &BTreeMap<String, SourceCoverage>) -> OwnedValue {
+    let location =
+        |location: &Location| json!({ "line": location.line, "column": location.column });
+    let range = |start: &Location, end: &Location| json!({ "start": location(start), "end": location(end) });
+
+    let mut files = simd_json::owned::Object::default();
+    for (path, source) in sources {
+        let mut statement_map = simd_json::owned::Object::default();
+        let mut statement_counts = simd_json::owned::Object::default();
+        for (index, ((start, end), count)) in source.statements.iter().enumerate() {
+            statement_map.insert(index.to_string(), range(start, end));
+            statement_counts.insert(index.to_string(), OwnedValue::from(*count));
+        }
+
+        let mut function_map = simd_json::owned::Object::default();
+        let mut function_counts = simd_json::owned::Object::default();
+        for (index, ((start, name), (end, count))) in source.functions.iter().enumerate() {
+            function_map.insert(
+                index.to_string(),
+                json!({
+                    "name": name.as_str(),
+                    "decl": range(start, start),
+                    "loc": range(start, end),
+                    "line": start.line
+                }),
+            );
+            function_counts.insert(index.to_string(), OwnedValue::from(*count));
+        }
+
+        files.insert(
+            path.clone(),
+            json!({
+                "path": path.as_str(),
+                "statementMap": statement_map,
+                "fnMap": function_map,
+                "branchMap": {},
+                "s": statement_counts,
+                "f": function_counts,
+                "b": {}
+            }),
+        );
+    }
+    OwnedValue::from(files)
+}
+
+/// Text inserted into an instrumented source.
+#[derive(Debug, Clone, Copy)]
+enum Insertion {
+    /// `<counters>[index]++;`
+    Counter(usize),
+    Text(&'static str),
+}
+
+/// Where the statement being visited is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Position {
+    /// In a statement list, such as a block or the program
+    List,
+    /// The body of a control statement, which needs a block to be counted
+    Body,
+    /// The body of a labeled statement, which can't be wrapped in a block without breaking
+    /// `continue` statements
+    Labeled,
+}
+
+struct Instrumenter<'s> {
+    source: &'s str,
+    line_starts: Vec<usize>,
+    position: Position,
+    /// Name of the function expression visited next, from the binding or property it's assigned to
+    name: Option<String>,
+    anonymous: usize,
+    counters: Vec<Counter>,
+    insertions: Vec<(usize, Insertion)>,
+}
+
+/// Inserts `<counters>[index]++;` at the start of statements and function bodies. Returns
+/// `None` when the source can't be parsed, in which case it shouldn't be instrumented.
+fn instrument_source(source: &str, counters: &str) -> Option<(String, Vec<Counter>)> {
+    if source.contains(COUNTERS_GLOBAL) {
+        return None;
+    }
+    let allocator = Allocator::default();
+    // Sources are always declared as ES modules, CommonJS included
+    let parsed = Parser::new(&allocator, source, SourceType::mjs()).parse();
+    if parsed.panicked || !parsed.errors.is_empty() {
+        return None;
+    }
+
+    let mut instrumenter = Instrumenter {
+        source,
+        line_starts: std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect(),
+        position: Position::Body,
+        name: None,
+        anonymous: 0,
+        counters: Vec::new(),
+        insertions: Vec::new(),
+    };
+    instrumenter.visit_program(&parsed.program);
+    // Insertions at the same offset keep the order they were visited in
+    instrumenter.insertions.sort_by_key(|(offset, _)| *offset);
+
+    let mut output =
+        String::with_capacity(source.len() + instrumenter.insertions.len() * (counters.len() + 8));
+    let mut last = 0;
+    for (offset, insertion) in instrumenter.insertions {
+        output.push_str(&source[last..offset]);
+        match insertion {
+            Insertion::Counter(index) => {
+                output.push_str(counters);
+                output.push('[');
+                output.push_str(&index.to_string());
+                output.push_str("]++;");
+            },
+            Insertion::Text(text) => output.push_str(text),
+        }
+        last = offset;
+    }
+    output.push_str(&source[last..]);
+    Some((output, instrumenter.counters))
+}
+
+impl Instrumenter<'_> {
+    fn location(&self, offset: u32) -> Location {
+        let offset = offset as usize;
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        Location {
+            line: line as u32,
+            column: (offset - self.line_starts[line - 1]) as u32,
+        }
+    }
+
+    fn insert(&mut self, offset: u32, insertion: Insertion) {
+        self.insertions.push((offset as usize, insertion));
+    }
+
+    fn add_counter(&mut self, name: Option<String>, start: u32, end: u32) -> usize {
+        let index = self.counters.len();
+        self.counters.push(Counter {
+            name,
+            start: self.location(start),
+            end: self.location(end),
+        });
+        index
+    }
+
+    fn insert_statement(&mut self, span: Span) {
+        let index = self.add_counter(None, span.start, span.end);
+        self.insert(span.start, Insertion::Counter(index));
+    }
+
+    fn open_function(&mut self, name: Option<&str>, span: Span) -> usize {
+        let name = match name.map(Into::into).or_else(|| self.name.take()) {
+            Some(name) => name,
+            None => {
+                let name = ["(anonymous_", &self.anonymous.to_string(), ")"].concat();
+                self.anonymous += 1;
+                name
+            },
+        };
+        self.name = None;
+        self.add_counter(Some(name), span.start, span.end)
+    }
+
+    fn insert_function(&mut self, index: usize, body: &FunctionBody) {
+        // Counters can't come before a directive prologue, such as "use strict"
+        match body.directives.last() {
+            Some(directive) => {
+                if !self.source[..directive.span.end as usize].ends_with(';') {
+                    self.insert(directive.span.end, Insertion::Text(";"));
+                }
+                self.insert(directive.span.end, Insertion::Counter(index));
+            },
+            None => self.insert(body.span.start + 1, Insertion::Counter(index)),
+        }
+    }
+
+    fn name_function(&mut self, name: Option<&str>, value: Option<&Expression>) {
+        if value.is_some_and(Expression::is_function) {
+            self.name = name.map(Into::into);
+        }
+    }
+}
+
+impl<'a> Visit<'a> for Instrumenter<'_> {
+    fn visit_statements(&mut self, statements: &ArenaVec<'a, Statement<'a>>) {
+        for statement in statements {
+            self.position = Position::List;
+            self.visit_statement(statement);
+        }
+    }
+
+    fn visit_statement(&mut self, statement: &Statement<'a>) {
+        let position = mem::replace(&mut self.position, Position::Body);
+        // Function declarations are hoisted, they are counted when called
+        if matches!(
+            statement,
+            Statement::EmptyStatement(_) | Statement::FunctionDeclaration(_)
+        ) {
+            return walk::walk_statement(self, statement);
+        }
+        let span = statement.span();
+        match position {
+            Position::List => self.insert_statement(span),
+            Position::Body if !matches!(statement, Statement::BlockStatement(_)) => {
+                self.insert(span.start, Insertion::Text("{"));
+                self.insert_statement(span);
+                walk::walk_statement(self, statement);
+                return self.insert(span.end, Insertion::Text("}"));
+            },
+            _ => {},
+        }
+        walk::walk_statement(self, statement);
+    }
+
+    fn visit_labeled_statement(&mut self, statement: &LabeledStatement<'a>) {
+        self.visit_label_identifier(&statement.label);
+        self.position = Position::Labeled;
+        self.visit_statement(&statement.body);
+    }
+
+    fn visit_function(&mut self, function: &Function<'a>, flags: ScopeFlags) {
+        let name = function.id.as_ref().map(|id| id.name.as_str());
+        let index = self.open_function(name, function.span);
+        if let Some(body) = &function.body {
+            self.insert_function(index, body);
+        }
+        walk::walk_function(self, function, flags);
+    }
+
+    fn visit_arrow_function_expression(&mut self, arrow: &ArrowFunctionExpression<'a>) {
+        let index = self.open_function(None, arrow.span);
+        let Some(expression) = arrow.get_expression().filter(|_| arrow.expression) else {
+            self.insert_function(index, &arrow.body);
+            return walk::walk_arrow_function_expression(self, arrow);
+        };
+        // Expression bodies are turned into blocks returning the expression
+        let span = expression.span();
+        self.insert(span.start, Insertion::Text("{"));
+        self.insert(span.start, Insertion::Counter(index));
+        self.insert_statement(span);
+        self.insert(span.start, Insertion::Text("return "));
+        self.visit_formal_parameters(&arrow.params);
+        self.visit_expression(expression);
+        self.insert(span.end, Insertion::Text("}"));
+    }
+
+    fn visit_variable_declarator(&mut self, declarator: &VariableDeclarator<'a>) {
+        if let BindingPattern::BindingIdentifier(id) = &declarator.id {
+            self.name_function(Some(id.name.as_str()), declarator.init.as_ref());
+        }
+        walk::walk_variable_declarator(self, declarator);
+    }
+
+    fn visit_assignment_expression(&mut self, assignment: &AssignmentExpression<'a>) {
+        let name = assignment
+            .left
+            .as_simple_assignment_target()
+            .and_then(|target| target.get_identifier_name());
+        self.name_function(name, Some(&assignment.right));
+        walk::walk_assignment_expression(self, assignment);
+    }
+
+    fn visit_object_property(&mut self, property: &ObjectProperty<'a>) {
+        if !property.computed {
+            self.name_function(property.key.static_name().as_deref(), Some(&property.value));
+        }
+        walk::walk_object_property(self, property);
+    }
+
+    fn visit_method_definition(&mut self, method: &MethodDefinition<'a>) {
+        if !method.computed {
+            self.name = method.key.static_name().map(Into::into);
+        }
+        walk::walk_method_definition(self, method);
+    }
+
+    fn visit_property_definition(&mut self, property: &PropertyDefinition<'a>) {
+        if !property.computed {
+            self.name_function(
+                property.key.static_name().as_deref(),
+                property.value.as_ref(),
+            );
+        }
+        walk::walk_property_definition(self, property);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instrument(source: &str) -> (String, Vec<Counter>) {
+        instrument_source(source, "c").unwrap()
+    }
+
+    #[test]
+    fn instruments_statements_and_functions() {
+        let (output, counters) = instrument(
+            "import a from 'a';\nfunction add(a, b) {\n  return a + b;\n}\nconst x = add(1, 2);\n",
+        );
+        assert_eq!(
+            output,
+            "c[0]++;import a from 'a';\nfunction add(a, b) {c[1]++;\n  c[2]++;return a + b;\n}\nc[3]++;const x = add(1, 2);\n"
+        );
+        assert_eq!(counters[1].name.as_deref(), Some("add"));
+        assert_eq!(counters[1].start, Location { line: 2, column: 0 });
+        assert_eq!(counters[1].end, Location { line: 4, column: 1 });
+        assert_eq!(counters[2].start, Location { line: 3, column: 2 });
+        assert_eq!(
+            counters[2].end,
+            Location {
+                line: 3,
+                column: 15
+            }
+        );
+        assert_eq!(counters.len(), 4);
+    }
+
+    #[test]
+    fn names_functions() {
+        let (output, counters) = instrument(
+            "const f = async (x) => { return x; };\nfoo(function () {});\nconst o = { m() {}, p: () => {} };\nexports.e = function () {};\n",
+        );
+        assert_eq!(
+            output,
+            "c[0]++;const f = async (x) => {c[1]++; c[2]++;return x; };\nc[3]++;foo(function () {c[4]++;});\nc[5]++;const o = { m() {c[6]++;}, p: () => {c[7]++;} };\nc[8]++;exports.e = function () {c[9]++;};\n"
+        );
+        let names: Vec<_> = counters
+            .iter()
+            .filter_map(|counter| counter.name.as_deref())
+            .collect();
+        assert_eq!(names, ["f", "(anonymous_0)", "m", "p", "e"]);
+    }
+
+    #[test]
+    fn wraps_control_statement_bodies() {
+        let (output, _) = instrument(
+            "if (a) b(); else if (c) d();\nfor (;;) e()\nwhile (a) { f(); }\nouter: for (const i of a) continue outer;",
+        );
+        assert_eq!(
+            output,
+            "c[0]++;if (a) {c[1]++;b();} else {c[2]++;if (c) {c[3]++;d();}}\nc[4]++;for (;;) {c[5]++;e()}\nc[6]++;while (a) { c[7]++;f(); }\nc[8]++;outer: for (const i of a) {c[9]++;continue outer;}"
+        );
+    }
+
+    #[test]
+    fn turns_expression_bodies_into_blocks() {
+        let (output, counters) = instrument("const f = (x) => ({ x });");
+        assert_eq!(
+            output,
+            "c[0]++;const f = (x) => {c[1]++;c[2]++;return ({ x })};"
+        );
+        assert_eq!(counters[1].name.as_deref(), Some("f"));
+    }
+
+    #[test]
+    fn keeps_directives_first() {
+        let (output, _) = instrument(
+            "function f() {\n  \"use strict\"\n  return 1;\n}\nconst g = () => { 'use strict'; };",
+        );
+        assert_eq!(
+            output,
+            "function f() {\n  \"use strict\";c[0]++;\n  c[1]++;return 1;\n}\nc[2]++;const g = () => { 'use strict';c[3]++; };"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_sources() {
+        assert!(instrument_source("function f() {", "c").is_none());
+        assert!(instrument_source("const s = 'unterminated", "c").is_none());
+    }
+}
@@ -14,9 +14,15 @@ pub const ENV_LLRT_LOG: &str = "LLRT_LOG";
 
 //module
 pub const ENV_LLRT_PLATFORM: &str = "LLRT_PLATFORM";
+pub const ENV_LLRT_BYTECODE_PUBLIC_KEY: &str = "LLRT_BYTECODE_PUBLIC_KEY";
 
 //vm
+pub const ENV_LLRT_CPU_PROF: &str = "LLRT_CPU_PROF";
+pub const ENV_LLRT_CPU_PROF_DIR: &str = "LLRT_CPU_PROF_DIR";
+pub const ENV_LLRT_CPU_PROF_INTERVAL: &str = "LLRT_CPU_PROF_INTERVAL";
 pub const ENV_LLRT_GC_THRESHOLD_MB: &str = "LLRT_GC_THRESHOLD_MB";
+pub const ENV_LLRT_SOURCE_MAPS: &str = "LLRT_SOURCE_MAPS";
 
 //runtime client
 pub const ENV_LLRT_SDK_CONNECTION_WARMUP: &str = "LLRT_SDK_CONNECTION_WARMUP";
+pub const ENV_LLRT_LAMBDA_CONCURRENCY: &str = "LLRT_LAMBDA_CONCURRENCY";
@@ -8,14 +8,18 @@
 pub mod bytecode;
 pub mod compiler;
 mod compiler_common;
+pub mod coverage;
 pub mod environment;
 mod http;
 pub mod libs;
 pub mod modules;
+pub mod profiler;
 pub mod runtime_client;
 mod security;
+pub mod source_map;
 pub mod utils;
 pub mod vm;
+pub mod watch;
 
 pub use llrt_modules::VERSION;
 
@@ -27,7 +27,7 @@ use crate::libs::{
         module::{export_default, ModuleInfo},
     },
 };
-use crate::runtime_client;
+use crate::runtime_client::invocation_context;
 
 static AWS_LAMBDA_MODE: AtomicBool = AtomicBool::new(false);
 static AWS_LAMBDA_JSON_LOG_FORMAT: AtomicBool = AtomicBool::new(false);
@@ -194,7 +194,8 @@ fn write_lambda_log<'js>(
 
     let current_time: DateTime<Utc> = Utc::now();
     let formatted_time = current_time.format(time_format);
-    let request_id = runtime_client::LAMBDA_REQUEST_ID.read().unwrap();
+    let invocation = invocation_context::current(ctx);
+    let request_id = invocation.as_ref().map(|invocation| &invocation.request_id);
 
     if is_json_log_format {
         result.push('{');
@@ -204,7 +205,7 @@ fn write_lambda_log<'js>(
         result.push_str("\",");
 
         //request id
-        if let Some(id) = request_id.as_ref() {
+        if let Some(id) = request_id {
             result.push_str("\"requestId\":\"");
             result.push_str(id);
             result.push_str("\",");
@@ -218,7 +219,7 @@ fn write_lambda_log<'js>(
         write!(result, "{}", formatted_time).unwrap();
         result.push('\t');
 
-        match request_id.as_ref() {
+        match request_id {
             Some(id) => result.push_str(id),
             None => result.push_str("n/a"),
         }
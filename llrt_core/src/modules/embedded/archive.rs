@@ -0,0 +1,175 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+// Multi-module bytecode archives created by `llrt compile --bundle`.
+//
+// The payload of an archive is the module count followed by, for each module, its name,
+// its compressed bytecode, how each of its imports resolves to another module of the
+// archive and its source map, if any. The first module is the entry point.
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex},
+};
+
+use once_cell::sync::Lazy;
+use rquickjs::{loader::Resolver, Ctx, Error, Result};
+use tracing::trace;
+
+use crate::source_map;
+
+/// Modules of every archive loaded so far, by name.
+static ARCHIVE_MODULES: Lazy<Mutex<HashMap<String, Arc<ArchiveModule>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Default)]
+pub struct ArchiveModule {
+    pub name: String,
+    pub bytecode: Vec<u8>,
+    /// Import specifiers of the module and the archive module each resolves to
+    pub imports: HashMap<String, String>,
+    pub source_map: Option<Vec<u8>>,
+}
+
+pub fn write_archive(modules: &[ArchiveModule]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(modules.iter().map(|m| m.bytecode.len() + 64).sum());
+    write_u32(&mut bytes, modules.len());
+    for module in modules {
+        write_bytes(&mut bytes, module.name.as_bytes());
+        write_bytes(&mut bytes, &module.bytecode);
+        write_u32(&mut bytes, module.imports.len());
+        for (specifier, name) in &module.imports {
+            write_bytes(&mut bytes, specifier.as_bytes());
+            write_bytes(&mut bytes, name.as_bytes());
+        }
+        write_bytes(&mut bytes, module.source_map.as_deref().unwrap_or_default());
+    }
+    bytes
+}
+
+pub fn read_archive(mut input: &[u8]) -> io::Result<Vec<ArchiveModule>> {
+    let count = read_u32(&mut input)?;
+    let mut modules = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        let name = read_string(&mut input)?;
+        let bytecode = read_bytes(&mut input)?.to_vec();
+        let import_count = read_u32(&mut input)?;
+        let mut imports = HashMap::with_capacity(import_count.min(1024));
+        for _ in 0..import_count {
+            let specifier = read_string(&mut input)?;
+            imports.insert(specifier, read_string(&mut input)?);
+        }
+        let source_map = read_bytes(&mut input)?;
+        modules.push(ArchiveModule {
+            name,
+            bytecode,
+            imports,
+            source_map: (!source_map.is_empty()).then(|| source_map.to_vec()),
+        });
+    }
+    Ok(modules)
+}
+
+/// Makes the modules of an archive available to [`ArchiveResolver`] and the embedded
+/// loader, returning the entry module.
+pub fn register(bytes: &[u8]) -> io::Result<Arc<ArchiveModule>> {
+    let modules = read_archive(bytes)?;
+    let mut registered = ARCHIVE_MODULES.lock().unwrap();
+    let mut entry = None;
+    for mut module in modules {
+        trace!("Registering archive module: {}", module.name);
+        if let Some(source_map) = module.source_map.take() {
+            source_map::register(&module.name, source_map);
+        }
+        let module = Arc::new(module);
+        entry.get_or_insert_with(|| module.clone());
+        registered.insert(module.name.clone(), module);
+    }
+    entry.ok_or_else(invalid_archive)
+}
+
+pub fn get_module(name: &str) -> Option<Arc<ArchiveModule>> {
+    ARCHIVE_MODULES.lock().unwrap().get(name).cloned()
+}
+
+/// Resolves imports of archive modules as they were resolved when compiling the archive.
+#[derive(Debug, Default)]
+pub struct ArchiveResolver;
+
+impl Resolver for ArchiveResolver {
+    fn resolve(&mut self, _ctx: &Ctx, base: &str, name: &str) -> Result<String> {
+        get_module(base)
+            .and_then(|module| module.imports.get(name).cloned())
+            .ok_or_else(|| Error::new_resolving(base, name))
+    }
+}
+
+fn invalid_archive() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Invalid bytecode archive")
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    bytes.extend_from_slice(&(value as u32).to_le_bytes());
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, value: &[u8]) {
+    write_u32(bytes, value.len());
+    bytes.extend_from_slice(value);
+}
+
+fn read_u32(input: &mut &[u8]) -> io::Result<usize> {
+    let (value, rest) = input.split_first_chunk::<4>().ok_or_else(invalid_archive)?;
+    *input = rest;
+    Ok(u32::from_le_bytes(*value) as usize)
+}
+
+fn read_bytes<'a>(input: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    let len = read_u32(input)?;
+    if input.len() < len {
+        return Err(invalid_archive());
+    }
+    let (value, rest) = input.split_at(len);
+    *input = rest;
+    Ok(value)
+}
+
+fn read_string(input: &mut &[u8]) -> io::Result<String> {
+    String::from_utf8(read_bytes(input)?.to_vec()).map_err(|_| invalid_archive())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{read_archive, write_archive, ArchiveModule};
+
+    #[test]
+    fn roundtrips_archive() {
+        let modules = vec![
+            ArchiveModule {
+                name: "app.lrt/index.mjs".into(),
+                bytecode: vec![1, 2, 3],
+                imports: HashMap::from([("./util.mjs".into(), "app.lrt/util.mjs".into())]),
+                source_map: Some(b"{}".to_vec()),
+            },
+            ArchiveModule {
+                name: "app.lrt/util.mjs".into(),
+                bytecode: vec![4, 5],
+                imports: HashMap::new(),
+                source_map: None,
+            },
+        ];
+
+        let bytes = write_archive(&modules);
+
+        let read = read_archive(&bytes).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].name, "app.lrt/index.mjs");
+        assert_eq!(read[0].bytecode, vec![1, 2, 3]);
+        assert_eq!(read[0].imports["./util.mjs"], "app.lrt/util.mjs");
+        assert_eq!(read[0].source_map.as_deref(), Some(&b"{}"[..]));
+        assert_eq!(read[1].name, "app.lrt/util.mjs");
+        assert!(read[1].source_map.is_none());
+
+        assert!(read_archive(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::{env, fs, io, path::Path};
+
+use once_cell::sync::Lazy;
+use ring::{
+    digest::{digest, SHA256},
+    signature::{Ed25519KeyPair, UnparsedPublicKey, ED25519},
+};
+use tracing::trace;
+
+use crate::bytecode::{
+    write_bytecode_header, BYTECODE_ARCHIVE, BYTECODE_COMPRESSED, BYTECODE_FLAG_HASH,
+    BYTECODE_FLAG_SIGNATURE, BYTECODE_HASH_LENGTH, BYTECODE_RUNTIME_VERSION,
+    BYTECODE_SIGNATURE_LENGTH, BYTECODE_UNCOMPRESSED, BYTECODE_VERSION, SIGNATURE_LENGTH,
+};
+use crate::environment::ENV_LLRT_BYTECODE_PUBLIC_KEY;
+
+// DER prefix of the SubjectPublicKeyInfo of an Ed25519 public key
+const ED25519_SPKI_PREFIX: &[u8] = &[
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// Key that bytecode loaded from outside of the runtime must be signed with, if any
+static PUBLIC_KEY: Lazy<Result<Option<Vec<u8>>, String>> = Lazy::new(|| {
+    let Ok(path) = env::var(ENV_LLRT_BYTECODE_PUBLIC_KEY) else {
+        return Ok(None);
+    };
+    trace!("Loading bytecode public key: {}", path);
+    read_public_key(Path::new(&path)).map(Some).map_err(|err| {
+        [
+            "Invalid ",
+            ENV_LLRT_BYTECODE_PUBLIC_KEY,
+            " \"",
+            &path,
+            "\": ",
+            &err.to_string(),
+        ]
+        .concat()
+    })
+});
+
+pub struct Bytecode<'a> {
+    pub kind: u8,
+    signature: Option<&'a [u8]>,
+    /// Header bytes covered by the signature
+    signed: &'a [u8],
+    pub payload: &'a [u8],
+}
+
+/// Parses the header of `input`, checking that it was created by this version of llrt and
+/// that its payload matches the hash recorded in the header.
+pub fn parse(input: &[u8]) -> io::Result<Bytecode<'_>> {
+    if input.len() < SIGNATURE_LENGTH + 2 || !input.starts_with(b"lrt") {
+        return Err(invalid_data("Invalid bytecode signature"));
+    }
+    if !input.starts_with(BYTECODE_VERSION.as_bytes()) {
+        return Err(invalid_data(
+            &[
+                "Bytecode was compiled by an incompatible version of llrt, recompile it with llrt ",
+                BYTECODE_RUNTIME_VERSION,
+            ]
+            .concat(),
+        ));
+    }
+
+    let kind = input[SIGNATURE_LENGTH - 1];
+    if ![BYTECODE_COMPRESSED, BYTECODE_UNCOMPRESSED, BYTECODE_ARCHIVE].contains(&kind) {
+        return Err(invalid_data("Invalid bytecode signature"));
+    }
+    let flags = input[SIGNATURE_LENGTH];
+    if flags & !(BYTECODE_FLAG_HASH | BYTECODE_FLAG_SIGNATURE) != 0 {
+        return Err(invalid_data("Unsupported bytecode flags"));
+    }
+
+    let mut rest = &input[SIGNATURE_LENGTH + 1..];
+    let version_length = take(&mut rest, 1)?[0] as usize;
+    let runtime_version = String::from_utf8_lossy(take(&mut rest, version_length)?);
+    if runtime_version != BYTECODE_RUNTIME_VERSION {
+        return Err(invalid_data(
+            &[
+                "Bytecode was compiled by llrt ",
+                &runtime_version,
+                " and can't run on llrt ",
+                BYTECODE_RUNTIME_VERSION,
+                ", recompile it with this version",
+            ]
+            .concat(),
+        ));
+    }
+
+    let hash = if flags & BYTECODE_FLAG_HASH != 0 {
+        Some(take(&mut rest, BYTECODE_HASH_LENGTH)?)
+    } else {
+        None
+    };
+    let signed = &input[..input.len() - rest.len()];
+    let signature = if flags & BYTECODE_FLAG_SIGNATURE != 0 {
+        Some(take(&mut rest, BYTECODE_SIGNATURE_LENGTH)?)
+    } else {
+        None
+    };
+
+    if let Some(hash) = hash {
+        if digest(&SHA256, rest).as_ref() != hash {
+            return Err(invalid_data(
+                "Bytecode integrity check failed, the file is corrupted or has been modified",
+            ));
+        }
+    }
+
+    Ok(Bytecode {
+        kind,
+        signature,
+        signed,
+        payload: rest,
+    })
+}
+
+/// Parses bytecode loaded from outside of the runtime, also verifying its signature when
+/// `LLRT_BYTECODE_PUBLIC_KEY` is set.
+pub fn verify(input: &[u8]) -> io::Result<Bytecode<'_>> {
+    let public_key = PUBLIC_KEY.as_ref().map_err(|err| invalid_data(err))?;
+    let bytecode = parse(input)?;
+    verify_signature(&bytecode, public_key.as_deref())?;
+    Ok(bytecode)
+}
+
+fn verify_signature(bytecode: &Bytecode, public_key: Option<&[u8]>) -> io::Result<()> {
+    let Some(public_key) = public_key else {
+        return Ok(());
+    };
+    let Some(signature) = bytecode.signature else {
+        return Err(invalid_data(
+            &[
+                "Bytecode is not signed, but ",
+                ENV_LLRT_BYTECODE_PUBLIC_KEY,
+                " requires a signature",
+            ]
+            .concat(),
+        ));
+    };
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(bytecode.signed, signature)
+        .map_err(|_| {
+            invalid_data(
+                "Bytecode signature verification failed, the file has been modified or was signed with another key",
+            )
+        })
+}
+
+/// Adds a header with the hash of `payload` and, given a key, its signature.
+pub fn seal(kind: u8, payload: &[u8], key: Option<&Ed25519KeyPair>) -> Vec<u8> {
+    let mut flags = BYTECODE_FLAG_HASH;
+    if key.is_some() {
+        flags |= BYTECODE_FLAG_SIGNATURE;
+    }
+
+    let mut bytes = Vec::with_capacity(payload.len() + 128);
+    write_bytecode_header(&mut bytes, kind, flags);
+    bytes.extend_from_slice(digest(&SHA256, payload).as_ref());
+    if let Some(key) = key {
+        let signature = key.sign(&bytes);
+        bytes.extend_from_slice(signature.as_ref());
+    }
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// Reads an Ed25519 private key from a PKCS#8 PEM file, as created by
+/// `openssl genpkey -algorithm ed25519`.
+pub fn read_signing_key(path: &Path) -> io::Result<Ed25519KeyPair> {
+    let pem = fs::read(path)?;
+    let key = rustls_pemfile::private_key(&mut pem.as_slice())?
+        .ok_or_else(|| invalid_data("No private key found"))?;
+    Ed25519KeyPair::from_pkcs8_maybe_unchecked(key.secret_der())
+        .map_err(|_| invalid_data("Not an Ed25519 private key"))
+}
+
+/// Reads an Ed25519 public key from a PEM file, as created by `openssl pkey -pubout`.
+fn read_public_key(path: &Path) -> io::Result<Vec<u8>> {
+    let pem = fs::read(path)?;
+    let key = rustls_pemfile::public_keys(&mut pem.as_slice())
+        .next()
+        .ok_or_else(|| invalid_data("No public key found"))??;
+    match key.as_ref().strip_prefix(ED25519_SPKI_PREFIX) {
+        Some(key) if key.len() == 32 => Ok(key.to_vec()),
+        _ => Err(invalid_data("Not an Ed25519 public key")),
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+    if input.len() < len {
+        return Err(invalid_data("Invalid bytecode header"));
+    }
+    let (value, rest) = input.split_at(len);
+    *input = rest;
+    Ok(value)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::{
+        rand::SystemRandom,
+        signature::{Ed25519KeyPair, KeyPair},
+    };
+
+    use crate::bytecode::{
+        add_bytecode_header, BYTECODE_COMPRESSED, BYTECODE_RUNTIME_VERSION, BYTECODE_UNCOMPRESSED,
+    };
+
+    use super::{parse, seal, verify_signature};
+
+    fn generate_key() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    #[test]
+    fn parses_sealed_bytecode() {
+        let bytes = seal(BYTECODE_COMPRESSED, &[1, 2, 3], None);
+        let bytecode = parse(&bytes).unwrap();
+        assert_eq!(bytecode.kind, BYTECODE_COMPRESSED);
+        assert_eq!(bytecode.payload, &[1, 2, 3]);
+
+        let bytes = add_bytecode_header(vec![4, 5], None);
+        let bytecode = parse(&bytes).unwrap();
+        assert_eq!(bytecode.kind, BYTECODE_UNCOMPRESSED);
+        assert_eq!(bytecode.payload, &[4, 5]);
+    }
+
+    #[test]
+    fn rejects_modified_bytecode() {
+        let mut bytes = seal(BYTECODE_COMPRESSED, &[1, 2, 3], None);
+        *bytes.last_mut().unwrap() = 4;
+        let err = parse(&bytes).err().unwrap();
+        assert!(err.to_string().contains("integrity check failed"));
+    }
+
+    #[test]
+    fn rejects_incompatible_bytecode() {
+        let err = parse(b"lrt01c\x03\x00\x00\x00abc").err().unwrap();
+        assert!(err.to_string().contains("incompatible version"));
+
+        let mut bytes = seal(BYTECODE_COMPRESSED, &[1, 2, 3], None);
+        let version = bytes
+            .windows(BYTECODE_RUNTIME_VERSION.len())
+            .position(|window| window == BYTECODE_RUNTIME_VERSION.as_bytes())
+            .unwrap();
+        bytes[version] = b'9';
+        let err = parse(&bytes).err().unwrap();
+        assert!(err.to_string().contains("recompile it"));
+    }
+
+    #[test]
+    fn verifies_signatures() {
+        let key = generate_key();
+        let public_key = key.public_key().as_ref();
+
+        let bytes = seal(BYTECODE_COMPRESSED, &[1, 2, 3], Some(&key));
+        let bytecode = parse(&bytes).unwrap();
+        assert!(verify_signature(&bytecode, None).is_ok());
+        assert!(verify_signature(&bytecode, Some(public_key)).is_ok());
+
+        let other_key = generate_key();
+        let bytes = seal(BYTECODE_COMPRESSED, &[1, 2, 3], Some(&other_key));
+        let bytecode = parse(&bytes).unwrap();
+        assert!(verify_signature(&bytecode, Some(public_key)).is_err());
+
+        let bytes = seal(BYTECODE_COMPRESSED, &[1, 2, 3], None);
+        let bytecode = parse(&bytes).unwrap();
+        let err = verify_signature(&bytecode, Some(public_key)).err().unwrap();
+        assert!(err.to_string().contains("not signed"));
+    }
+}
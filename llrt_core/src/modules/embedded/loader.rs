@@ -7,12 +7,10 @@ use rquickjs::{loader::Loader, Ctx, Error, Module, Object, Result};
 use tracing::trace;
 use zstd::{bulk::Decompressor, dict::DecoderDictionary};
 
-use crate::bytecode::{
-    BYTECODE_COMPRESSED, BYTECODE_FILE_EXT, BYTECODE_UNCOMPRESSED, BYTECODE_VERSION,
-    SIGNATURE_LENGTH,
-};
+use crate::bytecode::{BYTECODE_ARCHIVE, BYTECODE_COMPRESSED, BYTECODE_FILE_EXT};
 
-use super::{BYTECODE_CACHE, CJS_IMPORT_PREFIX, CJS_LOADER_PREFIX, COMPRESSION_DICT};
+use super::integrity::{self, Bytecode};
+use super::{archive, BYTECODE_CACHE, CJS_IMPORT_PREFIX, CJS_LOADER_PREFIX, COMPRESSION_DICT};
 
 static DECOMPRESSOR_DICT: Lazy<DecoderDictionary> =
     Lazy::new(|| DecoderDictionary::copy(COMPRESSION_DICT));
@@ -24,8 +22,24 @@ include!(concat!(env!("OUT_DIR"), "/sdk_client_endpoints.rs"));
 pub struct EmbeddedLoader;
 
 impl EmbeddedLoader {
+    /// Loads bytecode from outside of the runtime, verifying its header first.
     pub fn load_bytecode_module<'js>(ctx: Ctx<'js>, buf: &[u8]) -> Result<Module<'js>> {
-        let bytes = Self::get_module_bytecode(buf)?;
+        let bytecode = integrity::verify(buf)?;
+        Self::load_parsed_module(ctx, bytecode)
+    }
+
+    /// Loads bytecode embedded in the runtime or in a verified archive.
+    fn load_trusted_module<'js>(ctx: Ctx<'js>, buf: &[u8]) -> Result<Module<'js>> {
+        let bytecode = integrity::parse(buf)?;
+        Self::load_parsed_module(ctx, bytecode)
+    }
+
+    fn load_parsed_module<'js>(ctx: Ctx<'js>, bytecode: Bytecode<'_>) -> Result<Module<'js>> {
+        if bytecode.kind == BYTECODE_ARCHIVE {
+            let entry = archive::register(bytecode.payload)?;
+            return Self::load_trusted_module(ctx, &entry.bytecode);
+        }
+        let bytes = Self::get_module_bytecode(bytecode)?;
         unsafe { Module::load(ctx, &bytes) }
     }
 
@@ -38,53 +52,16 @@ impl EmbeddedLoader {
         Ok((uncompressed_size, rest))
     }
 
-    fn get_module_bytecode(input: &[u8]) -> Result<Vec<u8>> {
-        let (_, compressed, input) = Self::get_bytecode_signature(input)?;
-
-        if compressed {
-            let (size, input) = Self::uncompressed_size(input)?;
+    fn get_module_bytecode(bytecode: Bytecode<'_>) -> Result<Vec<u8>> {
+        if bytecode.kind == BYTECODE_COMPRESSED {
+            let (size, input) = Self::uncompressed_size(bytecode.payload)?;
             let mut buf = Vec::with_capacity(size);
             let mut decompressor = Decompressor::with_prepared_dictionary(&DECOMPRESSOR_DICT)?;
             decompressor.decompress_to_buffer(input, &mut buf)?;
             return Ok(buf);
         }
 
-        Ok(input.to_vec())
-    }
-
-    fn get_bytecode_signature(input: &[u8]) -> StdResult<(&[u8], bool, &[u8]), io::Error> {
-        let raw_signature = input
-            .get(..SIGNATURE_LENGTH)
-            .ok_or(io::Error::new::<String>(
-                io::ErrorKind::InvalidInput,
-                "Invalid bytecode signature length".into(),
-            ))?;
-
-        let (last, signature) = raw_signature.split_last().unwrap();
-
-        if signature != BYTECODE_VERSION.as_bytes() {
-            return Err(io::Error::new::<String>(
-                io::ErrorKind::InvalidInput,
-                "Invalid bytecode version".into(),
-            ));
-        }
-
-        let mut compressed = None;
-        if *last == BYTECODE_COMPRESSED {
-            compressed = Some(true)
-        } else if *last == BYTECODE_UNCOMPRESSED {
-            compressed = Some(false)
-        }
-
-        let rest = &input[SIGNATURE_LENGTH..];
-        Ok((
-            signature,
-            compressed.ok_or(io::Error::new::<String>(
-                io::ErrorKind::InvalidInput,
-                "Invalid bytecode signature".into(),
-            ))?,
-            rest,
-        ))
+        Ok(bytecode.payload.to_vec())
     }
 
     fn normalize_name(name: &str) -> (bool, bool, &str, &str) {
@@ -123,7 +100,12 @@ impl EmbeddedLoader {
 
             trace!("Loading embedded module: {}\n", path);
 
-            return Ok((Self::load_bytecode_module(ctx, bytes)?, Some(path.into())));
+            return Ok((Self::load_trusted_module(ctx, bytes)?, Some(path.into())));
+        }
+
+        if let Some(module) = archive::get_module(path) {
+            trace!("Loading archive module: {}\n", path);
+            return Ok((Self::load_trusted_module(ctx, &module.bytecode)?, None));
         }
 
         let bytes = std::fs::read(path)?;
@@ -131,7 +113,9 @@ impl EmbeddedLoader {
 
         if normalized_name.ends_with(BYTECODE_FILE_EXT) {
             trace!("Loading binary module: {}\n", path);
-            return Ok((Self::load_bytecode_module(ctx, bytes)?, Some(path.into())));
+            let module = Self::load_bytecode_module(ctx, bytes)
+                .map_err(|err| Error::new_loading_message(path, err.to_string()))?;
+            return Ok((module, Some(path.into())));
         }
 
         Err(Error::new_loading_message(path, "unable to load"))
@@ -6,6 +6,8 @@ use rquickjs::{Ctx, Function, Result};
 
 use self::resolver::embedded_resolve;
 
+pub mod archive;
+pub mod integrity;
 pub mod loader;
 pub mod resolver;
 
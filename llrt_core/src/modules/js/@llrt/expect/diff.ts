@@ -0,0 +1,44 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// Line based diff of two serialized values, in the format Jest prints
+// them in: removed lines are prefixed with "-", added lines with "+".
+export function diffLines(
+  expected: string,
+  received: string,
+  expectedLabel = "Expected",
+  receivedLabel = "Received"
+) {
+  const a = expected.split("\n");
+  const b = received.split("\n");
+  // Length of the longest common subsequence of a[i..] and b[j..]
+  const lengths: number[][] = Array.from({ length: a.length + 1 }, () =>
+    new Array(b.length + 1).fill(0)
+  );
+  for (let i = a.length - 1; i >= 0; i--) {
+    for (let j = b.length - 1; j >= 0; j--) {
+      lengths[i][j] =
+        a[i] === b[j]
+          ? lengths[i + 1][j + 1] + 1
+          : Math.max(lengths[i + 1][j], lengths[i][j + 1]);
+    }
+  }
+
+  const lines: string[] = [`- ${expectedLabel}`, `+ ${receivedLabel}`, ""];
+  let i = 0;
+  let j = 0;
+  while (i < a.length || j < b.length) {
+    if (i < a.length && j < b.length && a[i] === b[j]) {
+      lines.push(`  ${a[i++]}`);
+      j++;
+    } else if (
+      i < a.length &&
+      (j === b.length || lengths[i + 1][j] >= lengths[i][j + 1])
+    ) {
+      lines.push(`- ${a[i++]}`);
+    } else {
+      lines.push(`+ ${b[j++]}`);
+    }
+  }
+  return lines.join("\n");
+}
@@ -25,11 +25,13 @@ SOFTWARE.
 
 // Extracted and modified from Vitest:  https://github.com/vitest-dev/vitest/blob/a199ac2dd1322d7839d4d1350c983070da546805/packages/expect/src/jest-expect.ts
 
+import { AssertionError } from "chai";
 import {
   arrayBufferEquality,
   equals as jestEquals,
   generateToBeMessage,
   iterableEquality,
+  pluralize,
   sparseArrayEquality,
   subsetEquality,
   typeEquality,
@@ -37,6 +39,13 @@ import {
 import ChaiPlugin = Chai.ChaiPlugin;
 import Assertion = Chai.Assertion;
 import { AsymmetricMatcher } from "./jest-asymmetric-matchers";
+import { isMockFunction, Mock } from "./jest-mock";
+import {
+  diffSnapshot,
+  matchInlineSnapshot,
+  matchSnapshot,
+  SnapshotResult,
+} from "./jest-snapshot";
 
 // Jest Expect Compact
 export const JestChaiExpect: ChaiPlugin = (chai, utils) => {
@@ -54,9 +63,14 @@ export const JestChaiExpect: ChaiPlugin = (chai, utils) => {
     name: string | string[],
     fn: (this: Chai.AssertionStatic & Assertion, ...args: any[]) => any
   ) {
+    // Like Jest, expect.assertions counts matcher calls rather than expect calls
+    const matcher = function (this: any, ...args: any[]) {
+      countAssertion();
+      return fn.apply(this, args);
+    };
     const addMethod = (n: string) => {
       // const softWrapper = wrapSoft(utils, fn)
-      utils.addMethod(chai.Assertion.prototype, n, fn);
+      utils.addMethod(chai.Assertion.prototype, n, matcher);
       // utils.addMethod((globalThis as any)[JEST_MATCHERS_OBJECT].matchers, n, softWrapper)
     };
 
@@ -415,6 +429,188 @@ export const JestChaiExpect: ChaiPlugin = (chai, utils) => {
     return `${i}th`;
   };
 
+  const getSpy = (assertion: any): Mock => {
+    const spy = utils.flag(assertion, "object");
+    if (!isMockFunction(spy))
+      throw new TypeError(
+        `${utils.inspect(spy)} is not a spy or a call to a spy!`
+      );
+    return spy;
+  };
+
+  const formatCalls = (spy: Mock, message: string) => {
+    const { calls } = spy.mock;
+    if (!calls.length) return `${message}\n\nNumber of calls: 0`;
+    return `${message}\n\nReceived:\n${calls
+      .map((args, i) => `  ${ordinalOf(i + 1)} call: ${utils.inspect(args)}`)
+      .join("\n")}\n\nNumber of calls: ${calls.length}`;
+  };
+
+  const formatReturns = (spy: Mock, message: string) => {
+    const { results } = spy.mock;
+    return `${message}\n\nReceived:\n${results
+      .map(
+        (result, i) =>
+          `  ${ordinalOf(i + 1)} call: ${result.type} ${utils.inspect(
+            result.value
+          )}`
+      )
+      .join("\n")}\n\nNumber of calls: ${results.length}`;
+  };
+
+  const equalsArgs = (actual: any[] | undefined, expected: any[]) =>
+    !!actual &&
+    jestEquals(actual, expected, [...customTesters, iterableEquality]);
+
+  def(["toHaveBeenCalled", "toBeCalled"], function () {
+    const spy = getSpy(this);
+    const name = spy.getMockName();
+    const count = spy.mock.calls.length;
+    return this.assert(
+      count > 0,
+      `expected "${name}" to be called at least once`,
+      formatCalls(spy, `expected "${name}" to not be called at all`),
+      true,
+      count > 0
+    );
+  });
+  def(["toHaveBeenCalledTimes", "toBeCalledTimes"], function (times: number) {
+    const spy = getSpy(this);
+    const name = spy.getMockName();
+    const count = spy.mock.calls.length;
+    return this.assert(
+      count === times,
+      `expected "${name}" to be called #{exp} times, but got ${count} times`,
+      `expected "${name}" to not be called #{exp} times`,
+      times,
+      count,
+      false
+    );
+  });
+  def(["toHaveBeenCalledWith", "toBeCalledWith"], function (...args: any[]) {
+    const spy = getSpy(this);
+    const name = spy.getMockName();
+    const pass = spy.mock.calls.some((call) => equalsArgs(call, args));
+    return this.assert(
+      pass,
+      formatCalls(
+        spy,
+        `expected "${name}" to be called with arguments: #{exp}`
+      ),
+      formatCalls(
+        spy,
+        `expected "${name}" to not be called with arguments: #{exp}`
+      ),
+      args,
+      spy.mock.calls,
+      false
+    );
+  });
+  def(
+    ["toHaveBeenLastCalledWith", "lastCalledWith"],
+    function (...args: any[]) {
+      const spy = getSpy(this);
+      const name = spy.getMockName();
+      const lastCall = spy.mock.calls[spy.mock.calls.length - 1];
+      return this.assert(
+        equalsArgs(lastCall, args),
+        `expected last "${name}" call to have been called with #{exp}`,
+        `expected last "${name}" call to not have been called with #{exp}`,
+        args,
+        lastCall
+      );
+    }
+  );
+  def(
+    ["toHaveBeenNthCalledWith", "nthCalledWith"],
+    function (nth: number, ...args: any[]) {
+      const spy = getSpy(this);
+      const name = spy.getMockName();
+      const call = spy.mock.calls[nth - 1];
+      return this.assert(
+        equalsArgs(call, args),
+        `expected ${ordinalOf(nth)} "${name}" call to have been called with #{exp}`,
+        `expected ${ordinalOf(nth)} "${name}" call to not have been called with #{exp}`,
+        args,
+        call
+      );
+    }
+  );
+  def(["toHaveReturned", "toReturn"], function () {
+    const spy = getSpy(this);
+    const name = spy.getMockName();
+    const pass = spy.mock.results.some(({ type }) => type === "return");
+    return this.assert(
+      pass,
+      formatReturns(
+        spy,
+        `expected "${name}" to be successfully called at least once`
+      ),
+      formatReturns(spy, `expected "${name}" to not be successfully called`),
+      true,
+      pass
+    );
+  });
+  def(["toHaveReturnedTimes", "toReturnTimes"], function (times: number) {
+    const spy = getSpy(this);
+    const name = spy.getMockName();
+    const count = spy.mock.results.filter(
+      ({ type }) => type === "return"
+    ).length;
+    return this.assert(
+      count === times,
+      `expected "${name}" to be successfully called #{exp} times, but got ${count} times`,
+      `expected "${name}" to not be successfully called #{exp} times`,
+      times,
+      count,
+      false
+    );
+  });
+  def(["toHaveReturnedWith", "toReturnWith"], function (value: any) {
+    const spy = getSpy(this);
+    const name = spy.getMockName();
+    const pass = spy.mock.results.some(
+      (result) =>
+        result.type === "return" &&
+        jestEquals(result.value, value, customTesters)
+    );
+    return this.assert(
+      pass,
+      formatReturns(spy, `expected "${name}" to return #{exp}`),
+      formatReturns(spy, `expected "${name}" to not return #{exp}`),
+      value
+    );
+  });
+  def(["toHaveLastReturnedWith", "lastReturnedWith"], function (value: any) {
+    const spy = getSpy(this);
+    const name = spy.getMockName();
+    const result = spy.mock.results[spy.mock.results.length - 1];
+    return this.assert(
+      result?.type === "return" &&
+        jestEquals(result.value, value, customTesters),
+      `expected last "${name}" call to return #{exp}`,
+      `expected last "${name}" call to not return #{exp}`,
+      value,
+      result?.value
+    );
+  });
+  def(
+    ["toHaveNthReturnedWith", "nthReturnedWith"],
+    function (nth: number, value: any) {
+      const spy = getSpy(this);
+      const name = spy.getMockName();
+      const result = spy.mock.results[nth - 1];
+      return this.assert(
+        result?.type === "return" &&
+          jestEquals(result.value, value, customTesters),
+        `expected ${ordinalOf(nth)} "${name}" call to return #{exp}`,
+        `expected ${ordinalOf(nth)} "${name}" call to not return #{exp}`,
+        value,
+        result?.value
+      );
+    }
+  );
+
   def(
     ["toThrow", "toThrowError"],
     function (expected?: string | RegExp | Error) {
@@ -515,6 +711,85 @@ export const JestChaiExpect: ChaiPlugin = (chai, utils) => {
     return this.be.satisfy(matcher, message);
   });
 
+  function assertSnapshot(
+    assertion: Chai.AssertionStatic,
+    { pass, key, expected, received }: SnapshotResult
+  ) {
+    if (!pass) {
+      throw new AssertionError(
+        `Snapshot \`${key}\` mismatched, run \`llrt test -u\` to update it\n\n${diffSnapshot(expected!, received)}`,
+        { showDiff: false },
+        utils.flag(assertion, "ssfi")
+      );
+    }
+  }
+
+  def("toMatchSnapshot", function (hint?: string) {
+    if (utils.flag(this, "negate")) {
+      throw new Error("toMatchSnapshot cannot be used with .not");
+    }
+    assertSnapshot(this, matchSnapshot(this._obj, hint));
+  });
+  def("toMatchInlineSnapshot", function (inlineSnapshot?: string) {
+    if (utils.flag(this, "negate")) {
+      throw new Error("toMatchInlineSnapshot cannot be used with .not");
+    }
+    // The error recorded by .resolves and .rejects is created at the call site
+    const error: Error = utils.flag(this, "error") || new Error();
+    assertSnapshot(
+      this,
+      matchInlineSnapshot(this._obj, inlineSnapshot, error.stack || "")
+    );
+  });
+
+  // The message of the error thrown by the function, or the rejection reason
+  function thrownMessage(assertion: Chai.AssertionStatic & Assertion) {
+    const obj = assertion._obj;
+    const promise = utils.flag(assertion, "promise");
+    if (promise === "rejects") {
+      return obj?.message ?? obj;
+    }
+    if (promise !== "resolves" && typeof obj === "function") {
+      try {
+        obj();
+      } catch (err: any) {
+        return err?.message ?? err;
+      }
+    }
+    throw new AssertionError(
+      utils.flag(assertion, "message") ||
+        "expected function to throw an error, but it didn't",
+      { showDiff: false },
+      utils.flag(assertion, "ssfi")
+    );
+  }
+
+  def("toThrowErrorMatchingSnapshot", function (hint?: string) {
+    if (utils.flag(this, "negate")) {
+      throw new Error("toThrowErrorMatchingSnapshot cannot be used with .not");
+    }
+    assertSnapshot(this, matchSnapshot(thrownMessage(this), hint));
+  });
+  def(
+    "toThrowErrorMatchingInlineSnapshot",
+    function (inlineSnapshot?: string) {
+      if (utils.flag(this, "negate")) {
+        throw new Error(
+          "toThrowErrorMatchingInlineSnapshot cannot be used with .not"
+        );
+      }
+      const error: Error = utils.flag(this, "error") || new Error();
+      assertSnapshot(
+        this,
+        matchInlineSnapshot(
+          thrownMessage(this),
+          inlineSnapshot,
+          error.stack || ""
+        )
+      );
+    }
+  );
+
   utils.addProperty(
     chai.Assertion.prototype,
     "resolves",
@@ -621,8 +896,48 @@ export const JestChaiExpect: ChaiPlugin = (chai, utils) => {
       return proxy;
     }
   );
+
+  (chai.expect as any).assertions = (count: number) => {
+    assertionState.expected = count;
+  };
+  (chai.expect as any).hasAssertions = () => {
+    assertionState.hasAssertions = true;
+  };
 };
 
+// Counts the matcher calls of the running test for expect.assertions and
+// expect.hasAssertions
+const assertionState = {
+  calls: 0,
+  expected: null as number | null,
+  hasAssertions: false,
+};
+
+export function resetAssertions() {
+  assertionState.calls = 0;
+  assertionState.expected = null;
+  assertionState.hasAssertions = false;
+}
+
+export function countAssertion() {
+  assertionState.calls++;
+}
+
+export function verifyAssertions() {
+  const { calls, expected, hasAssertions } = assertionState;
+  const received = pluralize("assertion call", calls);
+  if (expected !== null && calls !== expected) {
+    throw new AssertionError(
+      `expect.assertions(${expected})\n\nExpected ${pluralize("assertion", expected)} to be called but received ${received}.`
+    );
+  }
+  if (hasAssertions && calls === 0) {
+    throw new AssertionError(
+      "expect.hasAssertions()\n\nExpected at least one assertion to be called but received none."
+    );
+  }
+}
+
 export function assertTypes(
   value: unknown,
   name: string,
@@ -0,0 +1,316 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// Jest compatible mock functions and spies, exposed to tests as the `jest`
+// global. Module mocks are implemented natively by llrt:mock, so that both
+// `import` and `require` of a mocked specifier are intercepted. Fake timers
+// are driven by the native timer queue, only `Date` is replaced here.
+
+import {
+  mock as mockModule,
+  unmock as unmockModule,
+  requireActual,
+  reset as resetModuleMocks,
+  useFakeTimers,
+  useRealTimers,
+  isUsingFakeTimers,
+  advanceTimersByTime,
+  runAllTimers,
+  runOnlyPendingTimers,
+  getTimerCount,
+  setSystemTime,
+  now,
+} from "llrt:mock";
+
+type Procedure = (...args: any[]) => any;
+
+type MockResult =
+  | { type: "return"; value: any }
+  | { type: "throw"; value: any }
+  | { type: "incomplete"; value: undefined };
+
+export type MockContext = {
+  calls: any[][];
+  contexts: any[];
+  instances: any[];
+  invocationCallOrder: number[];
+  results: MockResult[];
+  lastCall?: any[];
+};
+
+export type Mock<T extends Procedure = Procedure> = T & {
+  _isMockFunction: true;
+  mock: MockContext;
+  getMockName(): string;
+  mockName(name: string): Mock<T>;
+  getMockImplementation(): T | undefined;
+  mockImplementation(fn: T): Mock<T>;
+  mockImplementationOnce(fn: T): Mock<T>;
+  mockReturnThis(): Mock<T>;
+  mockReturnValue(value: ReturnType<T>): Mock<T>;
+  mockReturnValueOnce(value: ReturnType<T>): Mock<T>;
+  mockResolvedValue(value: Awaited<ReturnType<T>>): Mock<T>;
+  mockResolvedValueOnce(value: Awaited<ReturnType<T>>): Mock<T>;
+  mockRejectedValue(value: any): Mock<T>;
+  mockRejectedValueOnce(value: any): Mock<T>;
+  mockClear(): Mock<T>;
+  mockReset(): Mock<T>;
+  mockRestore(): void;
+};
+
+const MOCKS = new Set<Mock>();
+
+let invocationCallOrder = 0;
+
+const createContext = (): MockContext => ({
+  calls: [],
+  contexts: [],
+  instances: [],
+  invocationCallOrder: [],
+  results: [],
+  lastCall: undefined,
+});
+
+export function isMockFunction(value: any): value is Mock {
+  return typeof value === "function" && value._isMockFunction === true;
+}
+
+export function fn<T extends Procedure = Procedure>(
+  implementation?: T,
+  restore?: () => void
+): Mock<T> {
+  let defaultImplementation = implementation;
+  let onceImplementations: T[] = [];
+  let name = "jest.fn()";
+
+  const mock = function (this: any, ...args: any[]) {
+    const context = mock.mock;
+    context.calls.push(args);
+    context.contexts.push(this);
+    context.invocationCallOrder.push(++invocationCallOrder);
+    context.lastCall = args;
+    if (new.target) {
+      context.instances.push(this);
+    }
+
+    const result: MockResult = { type: "incomplete", value: undefined };
+    context.results.push(result);
+
+    const impl = onceImplementations.shift() ?? defaultImplementation;
+    try {
+      const value = impl?.apply(this, args);
+      Object.assign(result, { type: "return", value });
+      return value;
+    } catch (error) {
+      Object.assign(result, { type: "throw", value: error });
+      throw error;
+    }
+  } as Mock<T>;
+
+  Object.defineProperty(mock, "name", { value: implementation?.name || "" });
+
+  const api: Omit<Mock<T>, keyof T | "mock"> = {
+    _isMockFunction: true,
+    getMockName: () => name,
+    mockName: (value) => {
+      name = value;
+      return mock;
+    },
+    getMockImplementation: () => defaultImplementation,
+    mockImplementation: (fn) => {
+      defaultImplementation = fn;
+      return mock;
+    },
+    mockImplementationOnce: (fn) => {
+      onceImplementations.push(fn);
+      return mock;
+    },
+    mockReturnThis: () =>
+      mock.mockImplementation(function (this: any) {
+        return this;
+      } as T),
+    mockReturnValue: (value) => mock.mockImplementation((() => value) as T),
+    mockReturnValueOnce: (value) =>
+      mock.mockImplementationOnce((() => value) as T),
+    mockResolvedValue: (value) =>
+      mock.mockImplementation((() => Promise.resolve(value)) as T),
+    mockResolvedValueOnce: (value) =>
+      mock.mockImplementationOnce((() => Promise.resolve(value)) as T),
+    mockRejectedValue: (value) =>
+      mock.mockImplementation((() => Promise.reject(value)) as T),
+    mockRejectedValueOnce: (value) =>
+      mock.mockImplementationOnce((() => Promise.reject(value)) as T),
+    mockClear: () => {
+      mock.mock = createContext();
+      return mock;
+    },
+    mockReset: () => {
+      mock.mockClear();
+      defaultImplementation = undefined;
+      onceImplementations = [];
+      return mock;
+    },
+    mockRestore: () => {
+      mock.mockReset();
+      MOCKS.delete(mock);
+      restore?.();
+    },
+  };
+  Object.assign(mock, api);
+  mock.mock = createContext();
+
+  MOCKS.add(mock);
+  return mock;
+}
+
+const findDescriptor = (object: any, key: PropertyKey) => {
+  let current = object;
+  while (current) {
+    const descriptor = Object.getOwnPropertyDescriptor(current, key);
+    if (descriptor) {
+      return descriptor;
+    }
+    current = Object.getPrototypeOf(current);
+  }
+};
+
+export function spyOn<T extends object>(
+  object: T,
+  key: keyof T,
+  accessType?: "get" | "set"
+): Mock {
+  if (
+    object === null ||
+    (typeof object !== "object" && typeof object !== "function")
+  ) {
+    throw new TypeError("Cannot spy on a primitive value");
+  }
+
+  const descriptor = findDescriptor(object, key);
+  if (!descriptor) {
+    throw new TypeError(`${String(key)} property does not exist`);
+  }
+  const isOwn = Object.prototype.hasOwnProperty.call(object, key);
+  const restore = () => {
+    if (isOwn) {
+      Object.defineProperty(object, key, descriptor);
+    } else {
+      delete (object as any)[key];
+    }
+  };
+
+  if (accessType) {
+    const original = descriptor[accessType];
+    if (typeof original !== "function") {
+      throw new TypeError(
+        `${String(key)} property does not have a ${accessType}ter`
+      );
+    }
+    if (isMockFunction(original)) {
+      return original;
+    }
+    const spy = fn(original, restore).mockName(String(key));
+    Object.defineProperty(object, key, {
+      ...descriptor,
+      configurable: true,
+      [accessType]: spy,
+    });
+    return spy;
+  }
+
+  const original = object[key];
+  if (typeof original !== "function") {
+    throw new TypeError(
+      `Cannot spy on the ${String(key)} property because it is not a function; ${typeof original} given instead`
+    );
+  }
+  if (isMockFunction(original)) {
+    return original;
+  }
+  const spy = fn(original as Procedure, restore).mockName(String(key));
+  Object.defineProperty(object, key, {
+    configurable: true,
+    enumerable: descriptor.enumerable ?? true,
+    writable: true,
+    value: spy,
+  });
+  return spy;
+}
+
+const RealDate = Date;
+
+// Date without arguments and Date.now() follow the fake clock
+const FakeDate = function (this: any, ...args: any[]) {
+  if (!new.target) {
+    return new RealDate(now()).toString();
+  }
+  return Reflect.construct(RealDate, args.length ? args : [now()], new.target);
+} as unknown as DateConstructor;
+FakeDate.prototype = RealDate.prototype;
+FakeDate.now = now;
+FakeDate.parse = RealDate.parse;
+FakeDate.UTC = RealDate.UTC;
+
+const toMillis = (value?: number | Date) =>
+  value instanceof RealDate ? value.getTime() : value;
+
+// Mocks, module mocks and fake timers only live for the duration of a test file
+export function resetMocks() {
+  for (const mock of [...MOCKS]) {
+    mock.mockRestore();
+  }
+  resetModuleMocks();
+  jest.useRealTimers();
+}
+
+export const jest = {
+  fn,
+  spyOn,
+  isMockFunction,
+  mocked: <T>(value: T) => value,
+  // Not wrapped, so that relative specifiers are resolved from the test file
+  mock: mockModule,
+  unmock: unmockModule,
+  requireActual,
+  clearAllMocks() {
+    MOCKS.forEach((mock) => mock.mockClear());
+    return jest;
+  },
+  resetAllMocks() {
+    MOCKS.forEach((mock) => mock.mockReset());
+    return jest;
+  },
+  restoreAllMocks() {
+    [...MOCKS].forEach((mock) => mock.mockRestore());
+    return jest;
+  },
+  useFakeTimers(config?: { now?: number | Date }) {
+    useFakeTimers(toMillis(config?.now));
+    globalThis.Date = FakeDate;
+    return jest;
+  },
+  useRealTimers() {
+    useRealTimers();
+    globalThis.Date = RealDate;
+    return jest;
+  },
+  isFakeTimers: isUsingFakeTimers,
+  advanceTimersByTime(ms: number) {
+    advanceTimersByTime(ms);
+    return jest;
+  },
+  runAllTimers() {
+    runAllTimers();
+    return jest;
+  },
+  runOnlyPendingTimers() {
+    runOnlyPendingTimers();
+    return jest;
+  },
+  getTimerCount,
+  setSystemTime(now?: number | Date) {
+    setSystemTime(toMillis(now) ?? RealDate.now());
+    return jest;
+  },
+  now,
+};
@@ -0,0 +1,360 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// Snapshots for toMatchSnapshot and toMatchInlineSnapshot. Values are
+// serialized with the same formatter as console.log, without colors. File
+// snapshots are stored in __snapshots__/<test file>.snap next to the test
+// file, inline snapshots are written into the test file itself. New snapshots
+// are written when a test file completes, mismatching ones are only
+// overwritten when updating is enabled with `llrt test -u`.
+
+import fs from "node:fs";
+import path from "node:path";
+import { format } from "node:util";
+import { diffLines } from "./diff";
+
+type SnapshotFile = {
+  path: string;
+  snapshots: Record<string, string>;
+  dirty: boolean;
+};
+
+type InlineSnapshot = {
+  file: string;
+  line: number;
+  column: number;
+  snapshot: string;
+};
+
+export type SnapshotResult = {
+  pass: boolean;
+  key: string;
+  expected?: string;
+  received: string;
+};
+
+const HEADER = "// LLRT Snapshot v1";
+
+const STACK_FRAME_REGEX = /\(?([^()\s]+):(\d+):(\d+)\)?$/;
+
+let testFile: string | null = null;
+let update = false;
+let snapshotFile: SnapshotFile | null = null;
+let currentTest = "";
+let counters = new Map<string, number>();
+let inlineSnapshots: InlineSnapshot[] = [];
+
+export function startSnapshots(file: string, updateSnapshots: boolean) {
+  testFile = file;
+  update = updateSnapshots;
+  snapshotFile = null;
+  inlineSnapshots = [];
+  counters = new Map();
+  currentTest = "";
+}
+
+export function setSnapshotTest(name: string) {
+  currentTest = name;
+}
+
+// Writes the snapshots added or updated while running the current test file
+export function saveSnapshots() {
+  if (snapshotFile?.dirty) {
+    const { snapshots } = snapshotFile;
+    let output = `${HEADER}\n`;
+    for (const key of Object.keys(snapshots).sort(naturalCompare)) {
+      output += `\nexports[${printBacktickString(key)}] = ${printBacktickString(
+        addExtraLineBreaks(snapshots[key])
+      )};\n`;
+    }
+    fs.mkdirSync(path.dirname(snapshotFile.path), { recursive: true });
+    fs.writeFileSync(snapshotFile.path, output);
+    snapshotFile.dirty = false;
+  }
+
+  const byFile = new Map<string, InlineSnapshot[]>();
+  for (const snapshot of inlineSnapshots) {
+    const snapshots = byFile.get(snapshot.file) || [];
+    snapshots.push(snapshot);
+    byFile.set(snapshot.file, snapshots);
+  }
+  for (const [file, snapshots] of byFile) {
+    writeInlineSnapshots(file, snapshots);
+  }
+  inlineSnapshots = [];
+}
+
+export function serialize(value: any): string {
+  if (typeof value === "string") {
+    return `"${value}"`;
+  }
+  return format(value);
+}
+
+export function diffSnapshot(expected: string, received: string) {
+  return diffLines(expected, received, "Snapshot", "Received");
+}
+
+export function matchSnapshot(value: any, hint?: string): SnapshotResult {
+  const key = nextKey(hint);
+  const received = serialize(value);
+  const file = loadSnapshotFile();
+  const expected = file.snapshots[key];
+
+  if (expected === undefined || (update && expected !== received)) {
+    file.snapshots[key] = received;
+    file.dirty = true;
+    return { pass: true, key, received };
+  }
+  return { pass: expected === received, key, expected, received };
+}
+
+export function matchInlineSnapshot(
+  value: any,
+  inline: string | undefined,
+  stack: string
+): SnapshotResult {
+  const key = nextKey();
+  const received = serialize(value);
+  const expected = inline === undefined ? undefined : stripIndentation(inline);
+
+  if (expected === undefined || (update && expected !== received)) {
+    const location = findTestLocation(stack);
+    if (!location) {
+      throw new Error(
+        "Could not find the location of toMatchInlineSnapshot in the test file"
+      );
+    }
+    const pending = inlineSnapshots.find(
+      ({ file, line, column }) =>
+        file === location.file &&
+        line === location.line &&
+        column === location.column
+    );
+    if (pending) {
+      // The same call ran more than once, e.g. in a loop
+      return {
+        pass: pending.snapshot === received,
+        key,
+        expected: pending.snapshot,
+        received,
+      };
+    }
+    inlineSnapshots.push({ ...location, snapshot: received });
+    return { pass: true, key, received };
+  }
+  return { pass: expected === received, key, expected, received };
+}
+
+function nextKey(hint?: string) {
+  const name = hint ? `${currentTest}: ${hint}` : currentTest;
+  const count = (counters.get(name) || 0) + 1;
+  counters.set(name, count);
+  return `${name} ${count}`;
+}
+
+function loadSnapshotFile(): SnapshotFile {
+  if (snapshotFile) {
+    return snapshotFile;
+  }
+  if (!testFile) {
+    throw new Error("Snapshots can only be used while running tests");
+  }
+  const file = path.join(
+    path.dirname(testFile),
+    "__snapshots__",
+    `${path.basename(testFile)}.snap`
+  );
+  const snapshots: Record<string, string> = {};
+  let source: string | null = null;
+  try {
+    source = fs.readFileSync(file, "utf8") as string;
+  } catch {}
+  if (source) {
+    const data: Record<string, string> = {};
+    new Function("exports", source)(data);
+    for (const key in data) {
+      snapshots[key] = removeExtraLineBreaks(data[key]);
+    }
+  }
+  snapshotFile = { path: file, snapshots, dirty: false };
+  return snapshotFile;
+}
+
+function findTestLocation(stack: string) {
+  for (const frame of stack.split("\n")) {
+    const match = STACK_FRAME_REGEX.exec(frame.trim());
+    if (!match) {
+      continue;
+    }
+    const [, file, line, column] = match;
+    if (file.startsWith("llrt:") || file.startsWith("@llrt/")) {
+      continue;
+    }
+    try {
+      fs.statSync(file);
+    } catch {
+      continue;
+    }
+    return { file, line: parseInt(line), column: parseInt(column) };
+  }
+  return null;
+}
+
+function writeInlineSnapshots(file: string, snapshots: InlineSnapshot[]) {
+  let source = fs.readFileSync(file, "utf8") as string;
+
+  const replacements = snapshots.map((snapshot) =>
+    inlineSnapshotReplacement(source, snapshot)
+  );
+  // Replace from the end of the file so that earlier offsets stay valid
+  replacements.sort((a, b) => b.start - a.start);
+  for (const { start, end, text } of replacements) {
+    source = source.slice(0, start) + text + source.slice(end);
+  }
+  fs.writeFileSync(file, source);
+}
+
+function inlineSnapshotReplacement(
+  source: string,
+  { file, line, column, snapshot }: InlineSnapshot
+) {
+  const lineStart = lineOffset(source, line);
+  const lineEnd = source.indexOf("\n", lineStart);
+  const lineText = source.slice(
+    lineStart,
+    lineEnd === -1 ? source.length : lineEnd
+  );
+
+  // The reported column depends on how the call was compiled, use the closest
+  // matcher call on the line
+  const name = "toMatchInlineSnapshot";
+  let index = -1;
+  for (
+    let i = lineText.indexOf(name);
+    i !== -1;
+    i = lineText.indexOf(name, i + 1)
+  ) {
+    if (index === -1 || Math.abs(i - column) < Math.abs(index - column)) {
+      index = i;
+    }
+  }
+  if (index === -1) {
+    throw new Error(`Could not find ${name} at ${file}:${line}`);
+  }
+
+  let start = lineStart + index + name.length;
+  start = skipWhitespace(source, start);
+  if (source[start] !== "(") {
+    throw new Error(
+      `Could not find the arguments of ${name} at ${file}:${line}`
+    );
+  }
+  start = skipWhitespace(source, start + 1);
+  let end = start;
+  const quote = source[start];
+  if (quote === "`" || quote === '"' || quote === "'") {
+    end = skipString(source, start);
+  } else if (quote !== ")") {
+    throw new Error(
+      `${name} at ${file}:${line} only supports a string literal argument`
+    );
+  }
+
+  const indentation = /^\s*/.exec(lineText)![0];
+  return { start, end, text: printInlineSnapshot(snapshot, indentation) };
+}
+
+function lineOffset(source: string, line: number) {
+  let offset = 0;
+  for (let i = 1; i < line; i++) {
+    offset = source.indexOf("\n", offset) + 1;
+  }
+  return offset;
+}
+
+function skipWhitespace(source: string, index: number) {
+  while (index < source.length && /\s/.test(source[index])) {
+    index++;
+  }
+  return index;
+}
+
+function skipString(source: string, start: number) {
+  const quote = source[start];
+  let index = start + 1;
+  while (index < source.length && source[index] !== quote) {
+    index += source[index] === "\\" ? 2 : 1;
+  }
+  return index + 1;
+}
+
+function printInlineSnapshot(snapshot: string, indentation: string) {
+  if (!snapshot.includes("\n")) {
+    return printBacktickString(snapshot);
+  }
+  const lines = escapeBacktickString(snapshot)
+    .split("\n")
+    .map((line) => (line ? `${indentation}  ${line}` : line));
+  return `\`\n${lines.join("\n")}\n${indentation}\``;
+}
+
+// Removes the indentation added by printInlineSnapshot
+function stripIndentation(snapshot: string) {
+  const lines = snapshot.split("\n");
+  if (
+    lines.length < 3 ||
+    lines[0].trim() !== "" ||
+    lines[lines.length - 1].trim() !== ""
+  ) {
+    return snapshot;
+  }
+  const content = lines.slice(1, -1);
+  let indentation = Infinity;
+  for (const line of content) {
+    if (line.trim()) {
+      indentation = Math.min(indentation, /^ */.exec(line)![0].length);
+    }
+  }
+  if (indentation === Infinity) {
+    indentation = 0;
+  }
+  return content.map((line) => line.slice(indentation)).join("\n");
+}
+
+function addExtraLineBreaks(snapshot: string) {
+  return snapshot.includes("\n") ? `\n${snapshot}\n` : snapshot;
+}
+
+function removeExtraLineBreaks(snapshot: string) {
+  return snapshot.length > 2 &&
+    snapshot.startsWith("\n") &&
+    snapshot.endsWith("\n")
+    ? snapshot.slice(1, -1)
+    : snapshot;
+}
+
+function escapeBacktickString(text: string) {
+  return text.replace(/\\|`|\$\{/g, (match) => `\\${match}`);
+}
+
+function printBacktickString(text: string) {
+  return `\`${escapeBacktickString(text)}\``;
+}
+
+// Orders "test 2" before "test 10"
+function naturalCompare(a: string, b: string) {
+  const chunksA = a.split(/(\d+)/);
+  const chunksB = b.split(/(\d+)/);
+  for (let i = 0; i < Math.min(chunksA.length, chunksB.length); i++) {
+    if (chunksA[i] === chunksB[i]) {
+      continue;
+    }
+    // Odd chunks are the numbers
+    if (i % 2 === 1) {
+      return parseInt(chunksA[i]) - parseInt(chunksB[i]);
+    }
+    return chunksA[i] < chunksB[i] ? -1 : 1;
+  }
+  return chunksA.length - chunksB.length;
+}
@@ -1,5 +1,6 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
+import fs from "node:fs";
 import net from "node:net";
 import os from "node:os";
 import { spawn, ChildProcess } from "node:child_process";
@@ -9,33 +10,13 @@ import { platform } from "node:os";
 const IS_WINDOWS = platform() === "win32";
 import CircularBuffer from "./CircularBuffer";
 import { dimensions } from "llrt:util";
+import { REPORTERS, RootSuite, SuiteResult, TestResult } from "./reporters";
 
 type TestOptions = {
   workerCount?: number;
-};
-
-type TestProps = {
-  success: boolean;
-  started: number;
-  ended: number;
-};
-
-type TestResult = TestProps & {
-  desc: string;
-  error: Error | null;
-};
-
-type SuiteResult = TestProps & {
-  desc: string;
-  tests: TestResult[];
-  children: SuiteResult[];
-  parent: SuiteResult | null;
-};
-
-type RootSuite = TestProps & {
-  results: SuiteResult[];
-  name: string;
-  printed: boolean;
+  reporter?: string;
+  output?: string;
+  updateSnapshots?: boolean;
 };
 
 type WorkerData = {
@@ -117,10 +98,21 @@ class TestServer {
   private spinnerFrameIndex = 0;
   private started = 0;
   private shutdownPending = false;
+  private reporter?: string;
+  private output?: string;
+  private updateSnapshots: boolean;
+  // Without an output file, the report replaces the progress and summary
+  // on stdout
+  private quiet: boolean;
 
   constructor(
     testFiles: string[],
-    { workerCount = os.availableParallelism() }: TestOptions = {}
+    {
+      workerCount = os.availableParallelism(),
+      reporter,
+      output,
+      updateSnapshots = false,
+    }: TestOptions = {}
   ) {
     this.fileQueue = [...testFiles];
     this.testFiles = [...testFiles];
@@ -128,11 +120,16 @@ class TestServer {
     this.filesFailed = new Map();
     this.filesCompleted = new Set();
     this.workerCount = Math.min(workerCount, testFiles.length);
+    this.reporter = reporter;
+    this.output = output;
+    this.quiet = !!reporter && !output;
+    this.updateSnapshots = updateSnapshots;
   }
 
   public async start() {
     if (this.testFiles.length === 0) {
       this.printResults();
+      this.writeReport();
       this.shutdown();
       return;
     }
@@ -229,6 +226,7 @@ class TestServer {
       ...process.env,
       __LLRT_TEST_SERVER_PORT: (this.server?.address() as any).port,
       __LLRT_TEST_WORKER_ID: id.toString(),
+      __LLRT_TEST_UPDATE_SNAPSHOTS: this.updateSnapshots ? "1" : "0",
     };
     delete env.LLRT_LOG;
     const proc = spawn(
@@ -320,6 +318,7 @@ class TestServer {
         //clear current path
 
         workerData.currentPath.length = 0;
+        workerData.currentTest = null;
 
         if (nextFile) {
           this.results.set(nextFile, {
@@ -329,6 +328,7 @@ class TestServer {
             started: 0,
             ended: 0,
             printed: false,
+            errors: [],
           });
           workerData.currentFile = nextFile;
           this.workerDataFileInProgress.set(nextFile, workerData);
@@ -400,12 +400,25 @@ class TestServer {
           const test = workerData.currentTest!;
           test.ended = ended;
           test.success = true;
+          workerData.currentTest = null;
         }
 
         workerData.currentPath.pop();
 
         break;
       }
+      case "skip": {
+        const { desc } = message;
+        this.workerData[workerId].currentResult!.tests.push({
+          desc,
+          success: true,
+          skipped: true,
+          started: 0,
+          ended: 0,
+          error: null,
+        });
+        break;
+      }
       case "error": {
         const { error, ended } = message;
         this.handleTestError(workerId, error, ended);
@@ -428,6 +441,7 @@ class TestServer {
       clearInterval(this.updateInterval!);
       this.tick();
       this.printResults();
+      this.writeReport();
       if (shutdownOnComplete) {
         this.shutdown();
       } else {
@@ -459,6 +473,9 @@ class TestServer {
     if (results) {
       results.success = false;
     }
+    if (results && !workerData.currentTest) {
+      results.errors.push(error);
+    }
     const testFailures = this.filesFailed.get(workerData.currentFile!) || [];
     testFailures.push({
       desc: workerData.currentPath.slice(1),
@@ -473,6 +490,7 @@ class TestServer {
     test.ended = ended;
     test.error = error;
     test.success = false;
+    workerData.currentTest = null;
     workerData.currentPath.pop();
   }
 
@@ -503,7 +521,7 @@ class TestServer {
       }
     }
 
-    if (this.completedWorkers != this.workerCount) {
+    if (this.completedWorkers != this.workerCount && !this.quiet) {
       let [terminalWidth] = dimensions();
       let message = "";
 
@@ -637,7 +655,9 @@ class TestServer {
     output += ` ${Color.DIM(TestServer.elapsed({ started: this.started, ended }))}\n`;
     output += `${this.totalSuccess} passed, ${this.totalFailed} failed, ${this.totalSkipped} skipped, ${this.totalTests} tests\n`;
 
-    console.log(output);
+    if (!this.quiet) {
+      console.log(output);
+    }
 
     if (this.totalFailed > 0) {
       output = "";
@@ -670,6 +690,9 @@ class TestServer {
     let output = "";
     const indent = "  ".repeat(depth);
     for (let test of result.tests) {
+      if (test.skipped) {
+        continue;
+      }
       const icon = test.success
         ? Color.GREEN(TestServer.CHECKMARK)
         : Color.RED(TestServer.CROSS);
@@ -685,6 +708,25 @@ class TestServer {
     }
     return output;
   }
+  private writeReport() {
+    if (!this.reporter) {
+      return;
+    }
+    const report = REPORTERS[this.reporter]({
+      files: this.testFiles
+        .filter((file) => this.results.has(file))
+        .map((file) => ({ file, suite: this.results.get(file)! })),
+      started: this.started,
+      ended: performance.now(),
+    });
+    if (this.output) {
+      fs.mkdirSync(path.dirname(this.output), { recursive: true });
+      fs.writeFileSync(this.output, report);
+    } else {
+      console.log(report);
+    }
+  }
+
   private formattedError(error: Error, indent: string = ""): string {
     let stack = error.stack || "";
 
@@ -713,5 +755,8 @@ class TestServer {
 
 const testServer = new TestServer((globalThis as any).__testEntries, {
   workerCount: undefined,
+  reporter: (globalThis as any).__testReporter,
+  output: (globalThis as any).__testOutput,
+  updateSnapshots: (globalThis as any).__testUpdateSnapshots,
 });
 await testServer.start();
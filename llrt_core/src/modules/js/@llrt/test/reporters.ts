@@ -0,0 +1,260 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+export type TestProps = {
+  success: boolean;
+  started: number;
+  ended: number;
+};
+
+export type TestResult = TestProps & {
+  desc: string;
+  error: Error | null;
+  skipped?: boolean;
+};
+
+export type SuiteResult = TestProps & {
+  desc: string;
+  tests: TestResult[];
+  children: SuiteResult[];
+  parent: SuiteResult | null;
+};
+
+export type RootSuite = TestProps & {
+  results: SuiteResult[];
+  name: string;
+  printed: boolean;
+  // Errors raised outside of a test, e.g. while importing the file or in a hook
+  errors: Error[];
+};
+
+export type ReportFile = {
+  file: string;
+  suite: RootSuite;
+};
+
+export type Report = {
+  files: ReportFile[];
+  started: number;
+  ended: number;
+};
+
+type ReportedTest = {
+  ancestors: string[];
+  test: TestResult;
+};
+
+type Reporter = (report: Report) => string;
+
+export const REPORTERS: Record<string, Reporter> = {
+  junit: junitReport,
+  tap: tapReport,
+  json: jsonReport,
+};
+
+const ANSI_ESCAPE_REGEX = /\x1b\[[0-9;]*m/g;
+
+const XML_INVALID_CHARS_REGEX = /[\x00-\x08\x0b\x0c\x0e-\x1f]/g;
+
+const XML_ESCAPES: Record<string, string> = {
+  "&": "&amp;",
+  "<": "&lt;",
+  ">": "&gt;",
+  '"': "&quot;",
+  "'": "&apos;",
+};
+
+function stripAnsi(text: string) {
+  return text.replace(ANSI_ESCAPE_REGEX, "");
+}
+
+function escapeXml(text: string) {
+  return stripAnsi(text)
+    .replace(XML_INVALID_CHARS_REGEX, "")
+    .replace(/[&<>"']/g, (char) => XML_ESCAPES[char]);
+}
+
+type Timing = Pick<TestProps, "started" | "ended">;
+
+function duration({ started, ended }: Timing) {
+  return Math.max(0, ended - started);
+}
+
+function seconds(props: Timing) {
+  return (duration(props) / 1000).toFixed(3);
+}
+
+function errorMessage(error: Error) {
+  return stripAnsi(`${error.name || "Error"}: ${error.message}`);
+}
+
+// QuickJS stacks only contain the frames
+function errorDetails(error: Error) {
+  const message = errorMessage(error);
+  return error.stack ? `${message}\n${stripAnsi(error.stack)}` : message;
+}
+
+// Tests in the order they ran, with the describe blocks they are nested in.
+// The outermost suite is the test file itself and is not an ancestor.
+function collectTests(suite: RootSuite): ReportedTest[] {
+  const tests: ReportedTest[] = [];
+  const visit = (result: SuiteResult, ancestors: string[]) => {
+    for (const test of result.tests) {
+      tests.push({ ancestors, test });
+    }
+    for (const child of result.children) {
+      visit(child, [...ancestors, child.desc]);
+    }
+  };
+  for (const result of suite.results) {
+    visit(result, []);
+  }
+  return tests;
+}
+
+function count(tests: ReportedTest[]) {
+  let failed = 0;
+  let skipped = 0;
+  for (const { test } of tests) {
+    if (test.skipped) {
+      skipped++;
+    } else if (!test.success) {
+      failed++;
+    }
+  }
+  return {
+    total: tests.length,
+    failed,
+    skipped,
+    passed: tests.length - failed - skipped,
+  };
+}
+
+function junitReport({ files, started, ended }: Report) {
+  const totals = { total: 0, failed: 0, skipped: 0, errors: 0 };
+  let output = "";
+
+  for (const { file, suite } of files) {
+    const tests = collectTests(suite);
+    const { total, failed, skipped } = count(tests);
+    const errors = suite.errors.length;
+    totals.total += total + errors;
+    totals.failed += failed;
+    totals.skipped += skipped;
+    totals.errors += errors;
+
+    output += `  <testsuite name="${escapeXml(suite.name)}" file="${escapeXml(file)}" tests="${total + errors}" failures="${failed}" errors="${errors}" skipped="${skipped}" time="${seconds(suite)}">\n`;
+    for (const { ancestors, test } of tests) {
+      const classname = [suite.name, ...ancestors].join(" > ");
+      output += `    <testcase classname="${escapeXml(classname)}" name="${escapeXml(test.desc)}" time="${seconds(test)}"`;
+      if (test.skipped) {
+        output += ">\n      <skipped/>\n    </testcase>\n";
+      } else if (test.error) {
+        output += `>\n      <failure message="${escapeXml(test.error.message)}" type="${escapeXml(test.error.name || "Error")}">${escapeXml(errorDetails(test.error))}</failure>\n    </testcase>\n`;
+      } else {
+        output += "/>\n";
+      }
+    }
+    for (const error of suite.errors) {
+      output += `    <testcase classname="${escapeXml(suite.name)}" name="Test suite failed to run" time="0.000">\n`;
+      output += `      <error message="${escapeXml(error.message)}" type="${escapeXml(error.name || "Error")}">${escapeXml(errorDetails(error))}</error>\n`;
+      output += "    </testcase>\n";
+    }
+    output += "  </testsuite>\n";
+  }
+
+  return (
+    `<?xml version="1.0" encoding="UTF-8"?>\n` +
+    `<testsuites name="llrt test" tests="${totals.total}" failures="${totals.failed}" errors="${totals.errors}" skipped="${totals.skipped}" time="${seconds({ started, ended })}">\n` +
+    output +
+    "</testsuites>\n"
+  );
+}
+
+function tapYaml(error: Error) {
+  let output = "  ---\n";
+  output += `  message: ${JSON.stringify(stripAnsi(error.message))}\n`;
+  output += `  name: ${JSON.stringify(error.name || "Error")}\n`;
+  if (error.stack) {
+    output += "  stack: |-\n";
+    for (const line of stripAnsi(error.stack).split("\n")) {
+      output += `    ${line}\n`;
+    }
+  }
+  output += "  ...\n";
+  return output;
+}
+
+function tapDescription(parts: string[]) {
+  // "#" starts a directive in TAP
+  return parts.join(" > ").replace(/#/g, "\\#");
+}
+
+function tapReport({ files }: Report) {
+  let output = "";
+  let index = 0;
+
+  for (const { suite } of files) {
+    for (const { ancestors, test } of collectTests(suite)) {
+      index++;
+      const description = tapDescription([suite.name, ...ancestors, test.desc]);
+      if (test.skipped) {
+        output += `ok ${index} - ${description} # SKIP\n`;
+      } else if (test.error) {
+        output += `not ok ${index} - ${description} # time=${duration(test).toFixed(3)}ms\n`;
+        output += tapYaml(test.error);
+      } else {
+        output += `ok ${index} - ${description} # time=${duration(test).toFixed(3)}ms\n`;
+      }
+    }
+    for (const error of suite.errors) {
+      index++;
+      output += `not ok ${index} - ${tapDescription([suite.name])}\n`;
+      output += tapYaml(error);
+    }
+  }
+
+  return `TAP version 13\n1..${index}\n${output}`;
+}
+
+function jsonReport({ files, started, ended }: Report) {
+  const totals = { total: 0, passed: 0, failed: 0, skipped: 0 };
+
+  const testResults = files.map(({ file, suite }) => {
+    const tests = collectTests(suite);
+    const { total, passed, failed, skipped } = count(tests);
+    totals.total += total;
+    totals.passed += passed;
+    totals.failed += failed;
+    totals.skipped += skipped;
+
+    return {
+      name: file,
+      status: suite.success ? "passed" : "failed",
+      duration: duration(suite),
+      failureMessages: suite.errors.map(errorDetails),
+      assertionResults: tests.map(({ ancestors, test }) => ({
+        ancestorTitles: ancestors,
+        title: test.desc,
+        fullName: [...ancestors, test.desc].join(" "),
+        status: test.skipped ? "skipped" : test.success ? "passed" : "failed",
+        duration: test.skipped ? 0 : duration(test),
+        failureMessages: test.error ? [errorDetails(test.error)] : [],
+      })),
+    };
+  });
+
+  return JSON.stringify(
+    {
+      success: files.every(({ suite }) => suite.success),
+      numTotalTests: totals.total,
+      numPassedTests: totals.passed,
+      numFailedTests: totals.failed,
+      numSkippedTests: totals.skipped,
+      duration: duration({ started, ended }),
+      testResults,
+    },
+    null,
+    2
+  );
+}
@@ -4,6 +4,7 @@ export type SocketReqMsg =
   | ModuleReqMsg
   | EndReqMsg
   | StartReqMsg
+  | SkipReqMsg
   | CompletedReqMsg
   | ErrorReqMsg;
 
@@ -49,6 +50,11 @@ export type StartReqMsg = {
   timeout?: number;
 };
 
+export type SkipReqMsg = {
+  type: "skip";
+  desc: string;
+};
+
 export type SocketResponseMap = {
   next: {
     nextFile: string | null;
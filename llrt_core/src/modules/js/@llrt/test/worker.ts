@@ -1,6 +1,18 @@
 import * as chai from "chai";
-import { JestChaiExpect } from "../expect/jest-expect";
+import {
+  JestChaiExpect,
+  resetAssertions,
+  verifyAssertions,
+} from "../expect/jest-expect";
 import { JestAsymmetricMatchers } from "../expect/jest-asymmetric-matchers";
+import { jest, resetMocks } from "../expect/jest-mock";
+import {
+  saveSnapshots,
+  serialize,
+  setSnapshotTest,
+  startSnapshots,
+} from "../expect/jest-snapshot";
+import { diffLines } from "../expect/diff";
 import { SocketReqMsg, SocketResponseMap } from "./shared";
 import SocketClient from "./SocketClient";
 
@@ -70,6 +82,9 @@ class TestAgent {
   private static DEFAULT_TIMEOUT_MS =
     parseInt((process.env as any).TEST_TIMEOUT) || 5000;
 
+  private static UPDATE_SNAPSHOTS =
+    (process.env as any).__LLRT_TEST_UPDATE_SNAPSHOTS === "1";
+
   private static EMPTY_ARROW_FN_REGEX = /^(async)?\s*\(\s*\)\s*=>/m;
   private static EMPTY_FN_REGEX =
     /^(async)?\s*function\s*[a-zA-Z0-9_-]*\s*\(\s*\)\s*\{/m;
@@ -77,8 +92,7 @@ class TestAgent {
   private static EXPECT = (() => {
     chai.use(JestChaiExpect);
     chai.use(JestAsymmetricMatchers);
-    const expect = chai.expect as (value: any, message?: string) => any;
-    return Object.assign(expect, chai.expect);
+    return chai.expect;
   })();
 
   private workerId: number;
@@ -136,7 +150,7 @@ class TestAgent {
           suites: [],
           parent,
           only: only || parent.only,
-          skip,
+          skip: skip || parent.skip,
           desc,
           timeout: timeout || parent.timeout,
         };
@@ -168,6 +182,7 @@ class TestAgent {
       this.rootSuite.testCount++;
       if (skip || suite?.skip) {
         this.rootSuite.skipCount++;
+        suite?.tests?.push({ desc, fn, skip: true });
         return;
       }
       let onlyValue = only || suite.only;
@@ -266,7 +281,7 @@ class TestAgent {
         };
       } else {
         errorData.error = {
-          message: errorData.error.message,
+          message: TestAgent.errorMessage(errorData.error),
           stack: errorData.error.stack,
           name: errorData.error.name,
         };
@@ -281,9 +296,46 @@ class TestAgent {
     return JSON.parse(response) as SocketReturnType<T>;
   }
 
+  // Appends the expected and received values of failed assertions to the
+  // message, as a diff when they span multiple lines
+  private static errorMessage(error: any) {
+    const { message } = error;
+    if (
+      error.name !== "AssertionError" ||
+      !("expected" in error) ||
+      !("actual" in error)
+    ) {
+      return message;
+    }
+    let expected: string;
+    let received: string;
+    try {
+      expected = serialize(error.expected);
+      received = serialize(error.actual);
+    } catch {
+      return message;
+    }
+    if (expected === received) {
+      return message;
+    }
+    if (expected.includes("\n") || received.includes("\n")) {
+      return `${message}\n\n${diffLines(expected, received)}`;
+    }
+    return `${message}\n\nExpected: ${expected}\nReceived: ${received}`;
+  }
+
   private async runTests(testSuite: TestSuite, tests: Test[] = []) {
+    const ancestors: string[] = [];
+    for (let suite = testSuite; suite.parent; suite = suite.parent) {
+      ancestors.unshift(suite.desc);
+    }
+
     for (const test of tests) {
-      if (test.skip || (this.onlyCount > 0 && !test.only)) {
+      if (test.skip) {
+        await this.sendMessage("skip", { desc: test.desc });
+        continue;
+      }
+      if (this.onlyCount > 0 && !test.only) {
         continue;
       }
 
@@ -297,12 +349,14 @@ class TestAgent {
           timeout: test.timeout,
         });
 
+        resetAssertions();
         if (testSuite.beforeEach) {
           await this.executeAsyncOrCallbackFn(testSuite.beforeEach);
         }
 
         started = performance.now();
 
+        setSnapshotTest([...ancestors, test.desc].join(" "));
         await this.executeAsyncOrCallbackFn(test.fn, test.timeout);
 
         const end = performance.now();
@@ -310,6 +364,7 @@ class TestAgent {
         if (testSuite.afterEach) {
           await this.executeAsyncOrCallbackFn(testSuite.afterEach);
         }
+        verifyAssertions();
 
         await this.sendMessage("end", {
           ended: end,
@@ -358,6 +413,9 @@ class TestAgent {
         global.test = this.testFunction;
         global.describe = this.describe;
         global.expect = TestAgent.EXPECT;
+        global.jest = jest;
+
+        startSnapshots(entry, TestAgent.UPDATE_SNAPSHOTS);
 
         global.beforeEach = (cb: MaybeAsyncFunction) => {
           this.currentSuite.beforeEach = cb;
@@ -390,8 +448,12 @@ class TestAgent {
 
         await this.runRootSuite();
 
+        saveSnapshots();
+        resetMocks();
+
         delete global.it;
         delete global.expect;
+        delete global.jest;
         delete global.test;
         delete global.describe;
         delete global.beforeEach;
@@ -435,10 +497,7 @@ class TestAgent {
       const stack = [...testSuite.suites];
       while (stack.length > 0) {
         const suite = stack.shift()!;
-        if (
-          suite.skip ||
-          (this.onlyCount > 0 && !suite.only && !suite.containsOnly)
-        ) {
+        if (this.onlyCount > 0 && !suite.only && !suite.containsOnly) {
           continue;
         }
         const suiteStarted = performance.now();
@@ -450,11 +509,12 @@ class TestAgent {
         });
 
         try {
-          if (suite.beforeAll) {
+          // Skipped suites only report their tests as skipped
+          if (suite.beforeAll && !suite.skip) {
             await this.executeAsyncOrCallbackFn(suite.beforeAll);
           }
           await this.runTests(suite, suite.tests);
-          if (suite.afterAll) {
+          if (suite.afterAll && !suite.skip) {
             await this.executeAsyncOrCallbackFn(suite.afterAll);
           }
           await this.sendMessage("end", {
@@ -8,6 +8,22 @@ declare module "hex" {
   export const encode: (bytes: Uint8Array) => string;
 }
 
+declare module "llrt:mock" {
+  export const mock: (specifier: string, factory: () => unknown) => void;
+  export const unmock: (specifier: string) => void;
+  export const requireActual: (specifier: string) => any;
+  export const reset: () => void;
+  export const useFakeTimers: (now?: number) => void;
+  export const useRealTimers: () => void;
+  export const isUsingFakeTimers: () => boolean;
+  export const advanceTimersByTime: (ms: number) => void;
+  export const runAllTimers: () => void;
+  export const runOnlyPendingTimers: () => void;
+  export const getTimerCount: () => number;
+  export const setSystemTime: (now: number) => void;
+  export const now: () => number;
+}
+
 declare module "xml" {
   export class XMLParser {
     constructor(options?: {
@@ -0,0 +1,423 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// Heap statistics and snapshots for chasing leaks.
+//
+// QuickJS doesn't expose its object list, so snapshots walk the object graph from globalThis
+// with the same reflection JavaScript code has: own properties including accessors, prototypes
+// and the contents of Maps and Sets. Objects only reachable from closures, module scopes or
+// pending jobs are not visited, they are reported as the difference with the total object
+// count of the runtime. Proxies are counted but not traversed, to avoid running their traps.
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
+    fs,
+    hash::{Hash, Hasher},
+    process,
+    rc::Rc,
+    sync::atomic::{AtomicI64, Ordering},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Utc};
+use rquickjs::{
+    function::{Constructor, This},
+    module::{Declarations, Exports, ModuleDef},
+    object::Filter,
+    prelude::{Func, Opt},
+    qjs, Array, Atom, Ctx, Function, Object, Result, Value,
+};
+
+use crate::libs::{
+    json::stringify::json_stringify_replacer_space,
+    utils::{
+        module::{export_default, ModuleInfo},
+        result::ResultExt,
+    },
+};
+use crate::modules::process::memory::compute_memory_usage;
+
+const MAX_RETAINERS: usize = 10;
+
+static PEAK_MALLOCED_MEMORY: AtomicI64 = AtomicI64::new(0);
+
+fn gc(ctx: Ctx<'_>) {
+    ctx.run_gc();
+}
+
+fn get_heap_statistics(ctx: Ctx<'_>) -> Result<Object<'_>> {
+    let usage = compute_memory_usage(&ctx);
+    let peak = PEAK_MALLOCED_MEMORY
+        .fetch_max(usage.malloc_size, Ordering::Relaxed)
+        .max(usage.malloc_size);
+    // A limit of 0 means the heap is unlimited
+    let limit = usage.malloc_limit;
+    let available = if limit > 0 {
+        (limit - usage.malloc_size).max(0)
+    } else {
+        0
+    };
+
+    let statistics = Object::new(ctx)?;
+    statistics.set("total_heap_size", usage.malloc_size)?;
+    statistics.set("total_heap_size_executable", usage.js_func_code_size)?;
+    statistics.set("total_physical_size", usage.malloc_size)?;
+    statistics.set("total_available_size", available)?;
+    statistics.set("used_heap_size", usage.memory_used_size)?;
+    statistics.set("heap_size_limit", limit)?;
+    statistics.set("malloced_memory", usage.malloc_size)?;
+    statistics.set("peak_malloced_memory", peak)?;
+    statistics.set("does_zap_garbage", 0)?;
+    statistics.set("number_of_native_contexts", 1)?;
+    statistics.set("number_of_detached_contexts", 0)?;
+    statistics.set("total_global_handles_size", 0)?;
+    statistics.set("used_global_handles_size", 0)?;
+    statistics.set("external_memory", 0)?;
+    Ok(statistics)
+}
+
+fn get_heap_snapshot(ctx: Ctx<'_>) -> Result<Object<'_>> {
+    // Like V8, only count objects that survive a collection
+    ctx.run_gc();
+    let usage = compute_memory_usage(&ctx);
+
+    let mut walker = HeapWalker::new(&ctx)?;
+    walker.walk()?;
+
+    let snapshot = Object::new(ctx.clone())?;
+    snapshot.set("timestamp", unix_millis())?;
+    snapshot.set("statistics", get_heap_statistics(ctx.clone())?)?;
+
+    let counts = Object::new(ctx.clone())?;
+    for (name, count, size) in [
+        ("objects", usage.obj_count, usage.obj_size),
+        ("properties", usage.prop_count, usage.prop_size),
+        ("shapes", usage.shape_count, usage.shape_size),
+        ("strings", usage.str_count, usage.str_size),
+        ("atoms", usage.atom_count, usage.atom_size),
+        ("functions", usage.js_func_count, usage.js_func_size),
+        ("arrays", usage.array_count, 0),
+    ] {
+        let entry = Object::new(ctx.clone())?;
+        entry.set("count", count)?;
+        entry.set("size", size)?;
+        counts.set(name, entry)?;
+    }
+    snapshot.set("counts", counts)?;
+
+    let reachable = walker.nodes.len() as i64;
+    let objects = Object::new(ctx.clone())?;
+    objects.set("total", usage.obj_count)?;
+    objects.set("reachable", reachable)?;
+    objects.set("unreachable", (usage.obj_count - reachable).max(0))?;
+    snapshot.set("objects", objects)?;
+
+    snapshot.set("classes", walker.classes()?)?;
+    Ok(snapshot)
+}
+
+fn write_heap_snapshot(ctx: Ctx<'_>, filename: Opt<String>) -> Result<String> {
+    let filename = filename.0.unwrap_or_else(|| {
+        let timestamp = DateTime::<Utc>::from(SystemTime::now()).format("%Y%m%d.%H%M%S");
+        format!("Heap.{}.{}.json", timestamp, process::id())
+    });
+    let snapshot = get_heap_snapshot(ctx.clone())?;
+    let json = json_stringify_replacer_space(&ctx, snapshot.into_value(), None, Some("  ".into()))?
+        .unwrap_or_default();
+    fs::write(&filename, json).or_throw_msg(&ctx, &["Can't write ", &filename].concat())?;
+    Ok(filename)
+}
+
+fn unix_millis() -> f64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as f64
+}
+
+fn object_id(object: &Object<'_>) -> usize {
+    unsafe { qjs::JS_VALUE_GET_PTR(object.as_raw()) as usize }
+}
+
+fn class_id(object: &Object<'_>) -> qjs::JSClassID {
+    unsafe { qjs::JS_GetClassID(object.as_raw()) }
+}
+
+#[derive(Default)]
+struct ClassSummary {
+    count: u64,
+    shapes: HashSet<u64>,
+    // Retainer edge name, with its count and the first retained node
+    retainers: HashMap<String, (u64, usize)>,
+}
+
+struct HeapWalker<'js> {
+    ctx: Ctx<'js>,
+    get_own_property_descriptor: Function<'js>,
+    map_for_each: Function<'js>,
+    set_for_each: Function<'js>,
+    proxy_class: qjs::JSClassID,
+    map_class: qjs::JSClassID,
+    set_class: qjs::JSClassID,
+    visited: HashSet<usize>,
+    // Parent and path segment of each visited object, to rebuild retaining paths
+    nodes: Vec<(Option<usize>, String)>,
+    queue: VecDeque<(Object<'js>, usize, String)>,
+    class_names: HashMap<usize, String>,
+    summaries: HashMap<String, ClassSummary>,
+}
+
+impl<'js> HeapWalker<'js> {
+    fn new(ctx: &Ctx<'js>) -> Result<Self> {
+        let globals = ctx.globals();
+        let object: Object = globals.get("Object")?;
+        let proxy: Constructor = globals.get("Proxy")?;
+        let map: Constructor = globals.get("Map")?;
+        let set: Constructor = globals.get("Set")?;
+
+        // Class ids are not public, compare with instances instead
+        let target = Object::new(ctx.clone())?;
+        let proxy: Object = proxy.construct((target.clone(), target))?;
+        let map_instance: Object = map.construct(())?;
+        let set_instance: Object = set.construct(())?;
+        let map_prototype: Object = map.get("prototype")?;
+        let set_prototype: Object = set.get("prototype")?;
+
+        Ok(Self {
+            ctx: ctx.clone(),
+            get_own_property_descriptor: object.get("getOwnPropertyDescriptor")?,
+            map_for_each: map_prototype.get("forEach")?,
+            set_for_each: set_prototype.get("forEach")?,
+            proxy_class: class_id(&proxy),
+            map_class: class_id(&map_instance),
+            set_class: class_id(&set_instance),
+            visited: HashSet::new(),
+            nodes: Vec::new(),
+            queue: VecDeque::new(),
+            class_names: HashMap::new(),
+            summaries: HashMap::new(),
+        })
+    }
+
+    fn walk(&mut self) -> Result<()> {
+        let globals = self.ctx.globals();
+        self.push(globals, None, "globalThis".into(), "(global)".into());
+        while let Some((object, node, retainer)) = self.queue.pop_front() {
+            self.visit(object, node, retainer)?;
+        }
+        Ok(())
+    }
+
+    fn push(
+        &mut self,
+        object: Object<'js>,
+        parent: Option<usize>,
+        segment: String,
+        retainer: String,
+    ) {
+        if !self.visited.insert(object_id(&object)) {
+            return;
+        }
+        let node = self.nodes.len();
+        self.nodes.push((parent, segment));
+        self.queue.push_back((object, node, retainer));
+    }
+
+    fn visit(&mut self, object: Object<'js>, node: usize, retainer: String) -> Result<()> {
+        let class = class_id(&object);
+        if class == self.proxy_class {
+            self.count("Proxy".into(), 0, node, retainer);
+            return Ok(());
+        }
+
+        let prototype = object.get_prototype();
+        let class_name = self.class_name(prototype.as_ref())?;
+
+        let keys: Vec<Atom> = object
+            .own_keys(Filter::new().string().symbol())
+            .collect::<Result<_>>()?;
+        let mut hasher = DefaultHasher::new();
+        prototype.as_ref().map(object_id).hash(&mut hasher);
+        for key in &keys {
+            key.to_string()?.hash(&mut hasher);
+        }
+        self.count(class_name.clone(), hasher.finish(), node, retainer);
+
+        for key in keys {
+            let name = key.to_string()?;
+            let (segment, edge) = match name.parse::<u32>() {
+                Ok(_) => (["[", &name, "]"].concat(), "[]".to_string()),
+                Err(_) => ([".", &name].concat(), [".", &name].concat()),
+            };
+            let descriptor: Option<Object> = self
+                .get_own_property_descriptor
+                .call((object.clone(), key.to_value()?))?;
+            let Some(descriptor) = descriptor else {
+                continue;
+            };
+            for (field, suffix) in [("value", ""), ("get", " (get)"), ("set", " (set)")] {
+                if let Some(child) = descriptor.get::<_, Value>(field)?.into_object() {
+                    let retainer = [&class_name, edge.as_str(), suffix].concat();
+                    self.push(child, Some(node), [&segment, suffix].concat(), retainer);
+                }
+            }
+        }
+
+        if let Some(prototype) = prototype {
+            let retainer = [&class_name, ".__proto__"].concat();
+            self.push(prototype, Some(node), ".__proto__".into(), retainer);
+        }
+
+        if class == self.map_class || class == self.set_class {
+            let for_each = if class == self.map_class {
+                &self.map_for_each
+            } else {
+                &self.set_for_each
+            };
+            let entries = Rc::new(RefCell::new(Vec::new()));
+            let callback = Function::new(self.ctx.clone(), {
+                let entries = entries.clone();
+                move |value: Value<'js>, key: Value<'js>| {
+                    entries.borrow_mut().push((key, value));
+                }
+            })?;
+            for_each.call::<_, ()>((This(object.clone()), callback))?;
+
+            let is_map = class == self.map_class;
+            for (key, value) in entries.take() {
+                if is_map {
+                    if let Some(key) = key.into_object() {
+                        let retainer = [&class_name, "[key]"].concat();
+                        self.push(key, Some(node), "[key]".into(), retainer);
+                    }
+                }
+                if let Some(value) = value.into_object() {
+                    let retainer = [&class_name, "[value]"].concat();
+                    self.push(value, Some(node), "[value]".into(), retainer);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn count(&mut self, class_name: String, shape: u64, node: usize, retainer: String) {
+        let summary = self.summaries.entry(class_name).or_default();
+        summary.count += 1;
+        summary.shapes.insert(shape);
+        summary.retainers.entry(retainer).or_insert((0, node)).0 += 1;
+    }
+
+    // Names objects after the constructor of their prototype, without running getters
+    fn class_name(&mut self, prototype: Option<&Object<'js>>) -> Result<String> {
+        let Some(prototype) = prototype else {
+            return Ok("Object".into());
+        };
+        let id = object_id(prototype);
+        if let Some(name) = self.class_names.get(&id) {
+            return Ok(name.clone());
+        }
+
+        let name = if class_id(prototype) == self.proxy_class {
+            "Proxy".into()
+        } else {
+            match self.own_value(prototype, "constructor")?.into_object() {
+                Some(constructor) => self
+                    .own_value(&constructor, "name")?
+                    .as_string()
+                    .and_then(|name| name.to_string().ok())
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| "(anonymous)".into()),
+                None => "Object".into(),
+            }
+        };
+        self.class_names.insert(id, name.clone());
+        Ok(name)
+    }
+
+    fn own_value(&self, object: &Object<'js>, key: &str) -> Result<Value<'js>> {
+        let descriptor: Option<Object> = self
+            .get_own_property_descriptor
+            .call((object.clone(), key))?;
+        match descriptor {
+            Some(descriptor) => descriptor.get("value"),
+            None => Ok(Value::new_undefined(self.ctx.clone())),
+        }
+    }
+
+    fn path(&self, mut node: usize) -> String {
+        let mut segments = Vec::new();
+        loop {
+            let (parent, segment) = &self.nodes[node];
+            segments.push(segment.as_str());
+            match parent {
+                Some(parent) => node = *parent,
+                None => break,
+            }
+        }
+        segments.reverse();
+        segments.concat()
+    }
+
+    fn classes(&self) -> Result<Array<'js>> {
+        let mut summaries: Vec<_> = self.summaries.iter().collect();
+        summaries
+            .sort_by(|(a_name, a), (b_name, b)| b.count.cmp(&a.count).then(a_name.cmp(b_name)));
+
+        let classes = Array::new(self.ctx.clone())?;
+        for (index, (name, summary)) in summaries.into_iter().enumerate() {
+            let mut retainers: Vec<_> = summary.retainers.iter().collect();
+            retainers.sort_by(|(a_name, a), (b_name, b)| b.0.cmp(&a.0).then(a_name.cmp(b_name)));
+
+            let retainer_array = Array::new(self.ctx.clone())?;
+            for (index, (name, (count, node))) in
+                retainers.into_iter().take(MAX_RETAINERS).enumerate()
+            {
+                let retainer = Object::new(self.ctx.clone())?;
+                retainer.set("name", name.as_str())?;
+                retainer.set("count", *count)?;
+                retainer.set("path", self.path(*node))?;
+                retainer_array.set(index, retainer)?;
+            }
+
+            let class = Object::new(self.ctx.clone())?;
+            class.set("name", name.as_str())?;
+            class.set("count", summary.count)?;
+            class.set("shapes", summary.shapes.len())?;
+            class.set("retainers", retainer_array)?;
+            classes.set(index, class)?;
+        }
+        Ok(classes)
+    }
+}
+
+pub struct LlrtHeapModule;
+
+impl ModuleDef for LlrtHeapModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare("gc")?;
+        declare.declare("getHeapStatistics")?;
+        declare.declare("getHeapSnapshot")?;
+        declare.declare("writeHeapSnapshot")?;
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        export_default(ctx, exports, |default| {
+            default.set("gc", Func::from(gc))?;
+            default.set("getHeapStatistics", Func::from(get_heap_statistics))?;
+            default.set("getHeapSnapshot", Func::from(get_heap_snapshot))?;
+            default.set("writeHeapSnapshot", Func::from(write_heap_snapshot))?;
+            Ok(())
+        })
+    }
+}
+
+impl From<LlrtHeapModule> for ModuleInfo<LlrtHeapModule> {
+    fn from(val: LlrtHeapModule) -> Self {
+        ModuleInfo {
+            name: "llrt:heap",
+            module: val,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::cell::RefCell;
+
+use rquickjs::{
+    module::{Declarations, Exports, ModuleDef},
+    prelude::Func,
+    Ctx, Function, JsLifetime, Result,
+};
+
+use crate::libs::utils::{
+    module::{export_default, ModuleInfo},
+    result::ResultExt,
+};
+
+/// Listeners registered from JS through `llrt:lambda`. The runtime client
+/// registers an internal extension with the Lambda Extensions API only when
+/// at least one listener is present after the handler module is initialized.
+#[derive(Default)]
+pub struct ExtensionHooks<'js> {
+    pub invoke: Vec<Function<'js>>,
+    pub shutdown: Vec<Function<'js>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for ExtensionHooks<'js> {
+    type Changed<'to> = ExtensionHooks<'to>;
+}
+
+impl ExtensionHooks<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.invoke.is_empty() && self.shutdown.is_empty()
+    }
+}
+
+fn add_hook<'js>(
+    ctx: &Ctx<'js>,
+    listener: Function<'js>,
+    add: fn(&mut ExtensionHooks<'js>, Function<'js>),
+) -> Result<()> {
+    if ctx.userdata::<RefCell<ExtensionHooks>>().is_none() {
+        let _ = ctx.store_userdata(RefCell::new(ExtensionHooks::default()));
+    }
+    let hooks = ctx.userdata::<RefCell<ExtensionHooks>>().or_throw(ctx)?;
+    add(&mut hooks.borrow_mut(), listener);
+    Ok(())
+}
+
+fn on_invoke<'js>(ctx: Ctx<'js>, listener: Function<'js>) -> Result<()> {
+    add_hook(&ctx, listener, |hooks, listener| {
+        hooks.invoke.push(listener)
+    })
+}
+
+fn on_shutdown<'js>(ctx: Ctx<'js>, listener: Function<'js>) -> Result<()> {
+    add_hook(&ctx, listener, |hooks, listener| {
+        hooks.shutdown.push(listener)
+    })
+}
+
+pub struct LlrtLambdaModule;
+
+impl ModuleDef for LlrtLambdaModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare("onInvoke")?;
+        declare.declare("onShutdown")?;
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        export_default(ctx, exports, |default| {
+            default.set("onInvoke", Func::from(on_invoke))?;
+            default.set("onShutdown", Func::from(on_shutdown))?;
+            Ok(())
+        })
+    }
+}
+
+impl From<LlrtLambdaModule> for ModuleInfo<LlrtLambdaModule> {
+    fn from(val: LlrtLambdaModule) -> Self {
+        ModuleInfo {
+            name: "llrt:lambda",
+            module: val,
+        }
+    }
+}
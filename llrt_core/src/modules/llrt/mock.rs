@@ -0,0 +1,368 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// Module mocks for the test runner.
+//
+// Mocked specifiers are resolved to `__mock:<id>` modules by `MockResolver`, which comes before
+// the other resolvers, and by `require`. Their exports are the value returned by the factory,
+// which is called once per mock. Relative specifiers are keyed by their resolved path, so that
+// a mock applies to a file however it is imported, packages and builtins by their name.
+//
+// QuickJS caches modules by name, so modules that imported a specifier before its mock changed
+// would keep the previous exports. The resolver records the importers of every specifier, and
+// when a mock is added or removed, its importers, direct or not, are loaded again under a new
+// `__reload:<version>:<path>` name by later imports and requires. Static imports of a test file
+// are resolved before it runs, so they can only see mocks through a dynamic import.
+//
+// Fake timers are implemented by the timer queue and exposed here for the `jest` global.
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
+
+use rquickjs::{
+    loader::{Loader, Resolver},
+    module::{Declarations, Exports, ModuleDef},
+    prelude::{Func, Opt},
+    Ctx, Error, Function, JsLifetime, Module, Result, Value,
+};
+
+use crate::libs::utils::{
+    module::{export_default, ModuleInfo},
+    result::ResultExt,
+};
+use crate::modules::{
+    path::{is_absolute, resolve_path},
+    require::{resolver::require_resolve, RequireState, CJS_IMPORT_PREFIX},
+    timers::fake,
+};
+use crate::utils::ctx::CtxExt;
+
+pub const MOCK_PREFIX: &str = "__mock:";
+pub const RELOAD_PREFIX: &str = "__reload:";
+
+#[derive(Default)]
+struct MockState<'js> {
+    next_id: usize,
+    ids: HashMap<String, usize>,
+    factories: HashMap<usize, Function<'js>>,
+    exports: HashMap<usize, Value<'js>>,
+    // Set by requireActual for the next require
+    bypass: bool,
+    // Modules importing each key, by path
+    importers: HashMap<String, HashSet<String>>,
+    next_version: usize,
+    versions: HashMap<String, usize>,
+}
+
+enum Import {
+    Mock(usize),
+    Module(String),
+}
+
+unsafe impl<'js> JsLifetime<'js> for MockState<'js> {
+    type Changed<'to> = MockState<'to>;
+}
+
+fn mock_key(ctx: &Ctx<'_>, specifier: &str, base: &str) -> String {
+    if specifier.starts_with('.') || is_absolute(specifier) {
+        if let Ok(path) = require_resolve(ctx, specifier, base, None, true) {
+            return path.into_owned();
+        }
+    }
+    specifier.trim_start_matches("node:").into()
+}
+
+/// Returns the path of a module name, without the version of reloaded modules.
+pub fn module_path(name: &str) -> &str {
+    name.strip_prefix(RELOAD_PREFIX)
+        .and_then(|name| name.split_once(':'))
+        .map_or(name, |(_, path)| path)
+}
+
+/// Returns the name to load the module at `path` with, which is versioned once a module it
+/// imports is mocked or unmocked.
+pub fn module_name<'a>(ctx: &Ctx<'_>, path: &'a str) -> Cow<'a, str> {
+    let version = ctx
+        .userdata::<RefCell<MockState>>()
+        .and_then(|state| state.borrow().versions.get(path).copied());
+    match version {
+        Some(version) => [RELOAD_PREFIX, &version.to_string(), ":", path]
+            .concat()
+            .into(),
+        None => path.into(),
+    }
+}
+
+// The module calling into native code, like for require
+fn caller_path(ctx: &Ctx<'_>) -> Result<String> {
+    let name = ctx.get_script_or_module_name()?;
+    resolve_path([module_path(name.trim_start_matches(CJS_IMPORT_PREFIX))].iter())
+}
+
+// Records `base` as an importer of `specifier` and returns its mock, if it is mocked
+fn record_import(ctx: &Ctx<'_>, specifier: &str, base: &str) -> Option<Import> {
+    let state = ctx.userdata::<RefCell<MockState>>()?;
+    let key = mock_key(ctx, specifier, base);
+    let mut state = state.borrow_mut();
+    state
+        .importers
+        .entry(key.clone())
+        .or_default()
+        .insert(base.into());
+    Some(match state.ids.get(&key) {
+        Some(id) => Import::Mock(*id),
+        None => Import::Module(key),
+    })
+}
+
+// Gives the modules importing `key`, directly or not, a new version so that later imports load
+// them again
+fn invalidate_importers(ctx: &Ctx<'_>, state: &mut MockState, key: &str) {
+    let mut pending = vec![key];
+    let mut stale = HashSet::new();
+    while let Some(key) = pending.pop() {
+        for importer in state.importers.get(key).into_iter().flatten() {
+            if stale.insert(importer.as_str()) {
+                pending.push(importer);
+            }
+        }
+    }
+    let stale: Vec<String> = stale.into_iter().map(String::from).collect();
+    if let Some(require_state) = ctx.userdata::<RefCell<RequireState>>() {
+        let mut require_state = require_state.borrow_mut();
+        for path in &stale {
+            require_state.cache.remove(path.as_str());
+            require_state.exports.remove(path.as_str());
+        }
+    }
+    for path in stale {
+        state.next_version += 1;
+        let version = state.next_version;
+        state.versions.insert(path, version);
+    }
+}
+
+fn mock_exports<'js>(ctx: &Ctx<'js>, id: usize) -> Result<Value<'js>> {
+    let state = ctx.userdata::<RefCell<MockState>>().or_throw(ctx)?;
+    if let Some(exports) = state.borrow().exports.get(&id) {
+        return Ok(exports.clone());
+    }
+    // The factory may import other modules, so the state is not borrowed while it runs
+    let factory = state
+        .borrow()
+        .factories
+        .get(&id)
+        .cloned()
+        .or_throw_msg(ctx, "Mock factory not found")?;
+    let exports: Value = factory.call(())?;
+    state.borrow_mut().exports.insert(id, exports.clone());
+    Ok(exports)
+}
+
+/// Returns the exports of the mock for a `require` of `specifier`, if it is mocked.
+pub fn require_mock<'js>(ctx: &Ctx<'js>, specifier: &str) -> Result<Option<Value<'js>>> {
+    if let Some(id) = specifier.strip_prefix(MOCK_PREFIX) {
+        let id = id.parse().or_throw(ctx)?;
+        return mock_exports(ctx, id).map(Some);
+    }
+    let Some(state) = ctx.userdata::<RefCell<MockState>>() else {
+        return Ok(None);
+    };
+    if std::mem::take(&mut state.borrow_mut().bypass) {
+        return Ok(None);
+    }
+    drop(state);
+    match record_import(ctx, specifier, &caller_path(ctx)?) {
+        Some(Import::Mock(id)) => mock_exports(ctx, id).map(Some),
+        _ => Ok(None),
+    }
+}
+
+fn mock<'js>(ctx: Ctx<'js>, specifier: String, factory: Function<'js>) -> Result<()> {
+    let key = mock_key(&ctx, &specifier, &caller_path(&ctx)?);
+    let state = ctx.userdata::<RefCell<MockState>>().or_throw(&ctx)?;
+    let mut state = state.borrow_mut();
+    // Modules are cached by name, a new id makes imports load the new mock
+    let id = state.next_id;
+    state.next_id += 1;
+    invalidate_importers(&ctx, &mut state, &key);
+    state.ids.insert(key, id);
+    state.factories.insert(id, factory);
+    Ok(())
+}
+
+fn unmock(ctx: Ctx<'_>, specifier: String) -> Result<()> {
+    let key = mock_key(&ctx, &specifier, &caller_path(&ctx)?);
+    if let Some(state) = ctx.userdata::<RefCell<MockState>>() {
+        let mut state = state.borrow_mut();
+        if state.ids.remove(&key).is_some() {
+            invalidate_importers(&ctx, &mut state, &key);
+        }
+    }
+    Ok(())
+}
+
+fn require_actual<'js>(ctx: Ctx<'js>, specifier: String) -> Result<Value<'js>> {
+    let require: Function = ctx.globals().get("require")?;
+    if let Some(state) = ctx.userdata::<RefCell<MockState>>() {
+        state.borrow_mut().bypass = true;
+    }
+    let result = require.call((specifier,));
+    if let Some(state) = ctx.userdata::<RefCell<MockState>>() {
+        state.borrow_mut().bypass = false;
+    }
+    result
+}
+
+fn reset(ctx: Ctx<'_>) {
+    if let Some(state) = ctx.userdata::<RefCell<MockState>>() {
+        let mut state = state.borrow_mut();
+        let keys: Vec<String> = state.ids.drain().map(|(key, _)| key).collect();
+        for key in keys {
+            invalidate_importers(&ctx, &mut state, &key);
+        }
+        state.factories.clear();
+        state.exports.clear();
+    }
+}
+
+/// Resolves mocked specifiers to their mock module, and the importers of changed mocks to a new
+/// version.
+#[derive(Debug, Default)]
+pub struct MockResolver;
+
+impl Resolver for MockResolver {
+    fn resolve(&mut self, ctx: &Ctx, base: &str, name: &str) -> Result<String> {
+        if !name.starts_with(CJS_IMPORT_PREFIX) {
+            let base = module_path(base.trim_start_matches(CJS_IMPORT_PREFIX));
+            match record_import(ctx, name, base) {
+                Some(Import::Mock(id)) => return Ok([MOCK_PREFIX, &id.to_string()].concat()),
+                Some(Import::Module(key)) => {
+                    if let Cow::Owned(name) = module_name(ctx, &key) {
+                        return Ok(name);
+                    }
+                },
+                None => {},
+            }
+        }
+        Err(Error::new_resolving(base, name))
+    }
+}
+
+/// Declares mock modules, exporting the value returned by the factory as default export and
+/// its properties as named exports.
+#[derive(Debug, Default)]
+pub struct MockLoader;
+
+impl Loader for MockLoader {
+    fn load<'js>(&mut self, ctx: &Ctx<'js>, name: &str) -> Result<Module<'js>> {
+        if !name.starts_with(MOCK_PREFIX) {
+            return Err(Error::new_loading(name));
+        }
+        let Some(exports) = require_mock(ctx, name)? else {
+            return Err(Error::new_loading(name));
+        };
+
+        let mut module = String::with_capacity(512);
+        module.push_str("const value = require(\"");
+        module.push_str(name);
+        module.push_str("\");export default value?.default ?? value;");
+        if let Some(object) = exports.as_object() {
+            for (i, key) in object.keys::<String>().flatten().enumerate() {
+                let is_name = !key.starts_with(|c: char| c.is_ascii_digit())
+                    && key
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+                if key == "default" || !is_name {
+                    continue;
+                }
+                let i = i.to_string();
+                module.push_str(&["const v", &i, "=value.", &key, ";"].concat());
+                module.push_str(&["export{v", &i, " as ", &key, "};"].concat());
+            }
+        }
+        Module::declare(ctx.clone(), name, module)
+    }
+}
+
+pub struct LlrtMockModule;
+
+impl ModuleDef for LlrtMockModule {
+    fn declare(declare: &Declarations) -> Result<()> {
+        declare.declare("mock")?;
+        declare.declare("unmock")?;
+        declare.declare("requireActual")?;
+        declare.declare("reset")?;
+        declare.declare("useFakeTimers")?;
+        declare.declare("useRealTimers")?;
+        declare.declare("isUsingFakeTimers")?;
+        declare.declare("advanceTimersByTime")?;
+        declare.declare("runAllTimers")?;
+        declare.declare("runOnlyPendingTimers")?;
+        declare.declare("getTimerCount")?;
+        declare.declare("setSystemTime")?;
+        declare.declare("now")?;
+        declare.declare("default")?;
+        Ok(())
+    }
+
+    fn evaluate<'js>(ctx: &Ctx<'js>, exports: &Exports<'js>) -> Result<()> {
+        // Importers are recorded from here on, so that mocks reload modules imported before them
+        if ctx.userdata::<RefCell<MockState>>().is_none() {
+            let _ = ctx.store_userdata(RefCell::new(MockState::default()));
+        }
+        export_default(ctx, exports, |default| {
+            default.set("mock", Func::from(mock))?;
+            default.set("unmock", Func::from(unmock))?;
+            default.set("requireActual", Func::from(require_actual))?;
+            default.set("reset", Func::from(reset))?;
+            default.set(
+                "useFakeTimers",
+                Func::from(|ctx: Ctx<'_>, now: Opt<f64>| fake::use_fake_timers(&ctx, now.0)),
+            )?;
+            default.set(
+                "useRealTimers",
+                Func::from(|ctx: Ctx<'_>| fake::use_real_timers(&ctx)),
+            )?;
+            default.set(
+                "isUsingFakeTimers",
+                Func::from(|ctx: Ctx<'_>| fake::is_using_fake_timers(&ctx)),
+            )?;
+            default.set(
+                "advanceTimersByTime",
+                Func::from(|ctx: Ctx<'_>, ms: f64| {
+                    fake::advance_timers_by_time(&ctx, ms.max(0.) as u64)
+                }),
+            )?;
+            default.set(
+                "runAllTimers",
+                Func::from(|ctx: Ctx<'_>| fake::run_all_timers(&ctx)),
+            )?;
+            default.set(
+                "runOnlyPendingTimers",
+                Func::from(|ctx: Ctx<'_>| fake::run_only_pending_timers(&ctx)),
+            )?;
+            default.set(
+                "getTimerCount",
+                Func::from(|ctx: Ctx<'_>| fake::get_timer_count(&ctx)),
+            )?;
+            default.set(
+                "setSystemTime",
+                Func::from(|ctx: Ctx<'_>, now: f64| fake::set_system_time(&ctx, now)),
+            )?;
+            default.set("now", Func::from(|ctx: Ctx<'_>| fake::now(&ctx)))?;
+            Ok(())
+        })
+    }
+}
+
+impl From<LlrtMockModule> for ModuleInfo<LlrtMockModule> {
+    fn from(val: LlrtMockModule) -> Self {
+        ModuleInfo {
+            name: "llrt:mock",
+            module: val,
+        }
+    }
+}
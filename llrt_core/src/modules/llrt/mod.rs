@@ -1,5 +1,8 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
+pub mod heap;
 pub mod hex;
+pub mod lambda;
+pub mod mock;
 pub mod util;
 pub mod xml;
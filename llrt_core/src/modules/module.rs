@@ -11,6 +11,7 @@ use rquickjs::{
 
 use crate::libs::utils::module::{export_default, ModuleInfo};
 use crate::modules::{
+    llrt::mock,
     require::{require, RequireState, CJS_IMPORT_PREFIX},
     ModuleNames,
 };
@@ -80,7 +81,7 @@ pub fn init(ctx: &Ctx) -> Result<()> {
             struct Args<'js>(Ctx<'js>);
             let Args(ctx) = Args(ctx);
             let name = ctx.get_script_or_module_name()?;
-            let name = name.trim_start_matches(CJS_IMPORT_PREFIX);
+            let name = mock::module_path(name.trim_start_matches(CJS_IMPORT_PREFIX));
 
             let binding = ctx.userdata::<RefCell<RequireState>>().unwrap();
             let mut state = binding.borrow_mut();
@@ -97,7 +98,7 @@ pub fn init(ctx: &Ctx) -> Result<()> {
             struct Args<'js>(Ctx<'js>, Value<'js>);
             let Args(ctx, exports) = Args(ctx, exports);
             let name = ctx.get_script_or_module_name()?;
-            let name = name.trim_start_matches(CJS_IMPORT_PREFIX);
+            let name = mock::module_path(name.trim_start_matches(CJS_IMPORT_PREFIX));
             let binding = ctx.userdata::<RefCell<RequireState>>().unwrap();
             let mut state = binding.borrow_mut();
             state.exports.insert(name.into(), exports);
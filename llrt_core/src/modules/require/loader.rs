@@ -5,20 +5,26 @@ use std::{fs::File, io::Read};
 use rquickjs::{loader::Loader, Ctx, Function, Module, Object, Result, Value};
 use tracing::trace;
 
+use crate::coverage;
+use crate::modules::{
+    fs::vfs,
+    llrt::mock::{self, RELOAD_PREFIX},
+};
+
 use super::{CJS_IMPORT_PREFIX, CJS_LOADER_PREFIX};
 
 #[derive(Debug, Default)]
 pub struct NpmJsLoader;
 
 impl NpmJsLoader {
-    fn load_cjs_module<'js>(name: &str, ctx: Ctx<'js>) -> Result<Module<'js>> {
+    fn load_cjs_module<'js>(name: &str, path: &str, ctx: Ctx<'js>) -> Result<Module<'js>> {
         let cjs_specifier = [CJS_IMPORT_PREFIX, name].concat();
         let require: Function = ctx.globals().get("require")?;
         let export_object: Value = require.call((&cjs_specifier,))?;
-        let mut module = String::with_capacity(name.len() + 512);
+        let mut module = String::with_capacity(path.len() + 512);
         module.push_str("const value = require(\"");
 
-        module.push_str(name);
+        module.push_str(path);
         module.push_str("\");export default value.default||value;");
         if let Some(obj) = export_object.as_object() {
             let keys: Result<Vec<String>> = obj.keys().collect();
@@ -59,7 +65,7 @@ impl NpmJsLoader {
 
         if let Some(cjs_path) = name.strip_prefix(CJS_IMPORT_PREFIX) {
             // If it starts with CJS_IMPORT_PREFIX, mark as from_cjs_import
-            return (true, false, name, cjs_path);
+            return (true, false, name, mock::module_path(cjs_path));
         }
 
         if let Some(cjs_path) = name.strip_prefix(CJS_LOADER_PREFIX) {
@@ -67,6 +73,11 @@ impl NpmJsLoader {
             return (false, true, cjs_path, cjs_path);
         }
 
+        if name.starts_with(RELOAD_PREFIX) {
+            // Modules loaded again after a mock changed keep their versioned name
+            return (false, false, name, mock::module_path(name));
+        }
+
         // Default return if no prefixes match
         (false, false, name, name)
     }
@@ -81,23 +92,34 @@ impl NpmJsLoader {
         //json files can never be from CJS imports as they are handled by require
         if !from_cjs_import {
             if normalized_name.ends_with(".json") {
-                let mut file = File::open(path)?;
                 let prefix = "export default JSON.parse(`";
                 let suffix = "`);";
                 let mut json = String::with_capacity(prefix.len() + suffix.len());
                 json.push_str(prefix);
-                file.read_to_string(&mut json)?;
+                match vfs::read(path) {
+                    Some(bytes) => json.push_str(&String::from_utf8_lossy(bytes)),
+                    None => {
+                        File::open(path)?.read_to_string(&mut json)?;
+                    },
+                }
                 json.push_str(suffix);
 
                 return Ok((Module::declare(ctx, path, json)?, None));
             }
             if is_cjs || normalized_name.ends_with(".cjs") {
                 let url = ["file://", path].concat();
-                return Ok((Self::load_cjs_module(path, ctx)?, Some(url)));
+                return Ok((
+                    Self::load_cjs_module(normalized_name, path, ctx)?,
+                    Some(url),
+                ));
             }
         }
 
-        let bytes = std::fs::read(path)?;
+        let source = match vfs::read(path) {
+            Some(bytes) => bytes.to_vec(),
+            None => std::fs::read(path)?,
+        };
+        let bytes = coverage::instrument(&ctx, path, &source)?;
         let mut bytes: &[u8] = &bytes;
 
         if !from_cjs_import && bytes.starts_with(b"#!") {
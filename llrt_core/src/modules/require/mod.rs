@@ -19,10 +19,12 @@ use crate::bytecode::BYTECODE_FILE_EXT;
 use crate::environment;
 use crate::libs::{json::parse::json_parse, utils::provider::ProviderType};
 use crate::modules::{
+    llrt::mock,
     ModuleNames,
-    {path::resolve_path, timers::poll_timers},
+    {fs::vfs, path::resolve_path, timers::poll_timers},
 };
 use crate::utils::ctx::CtxExt;
+use crate::watch;
 
 use self::resolver::require_resolve;
 
@@ -53,6 +55,10 @@ unsafe impl<'js> JsLifetime<'js> for RequireState<'js> {
 }
 
 pub fn require(ctx: Ctx<'_>, specifier: String) -> Result<Value<'_>> {
+    if let Some(value) = mock::require_mock(&ctx, &specifier)? {
+        return Ok(value);
+    }
+
     let globals = ctx.globals();
     let embedded_fn: Option<Function> = globals.get("__embedded_hook").ok();
 
@@ -88,20 +94,23 @@ pub fn require(ctx: Ctx<'_>, specifier: String) -> Result<Value<'_>> {
             import_name.clone()
         } else {
             let module_name = ctx.get_script_or_module_name()?;
-            let module_name = module_name.trim_start_matches(CJS_IMPORT_PREFIX);
+            let module_name = mock::module_path(module_name.trim_start_matches(CJS_IMPORT_PREFIX));
             let abs_path = resolve_path([module_name].iter())?;
 
             let resolved_path =
                 require_resolve(&ctx, &specifier, &abs_path, embedded_fn, false)?.into_owned();
+            watch::record_dependency(&abs_path, &resolved_path);
             import_name = resolved_path.into();
             if is_bytecode_or_json {
                 import_name.clone()
             } else {
-                [CJS_IMPORT_PREFIX, &import_name].concat().into()
+                [CJS_IMPORT_PREFIX, &mock::module_name(&ctx, &import_name)]
+                    .concat()
+                    .into()
             }
         }
     } else {
-        import_name = specifier[CJS_IMPORT_PREFIX.len()..].into();
+        import_name = mock::module_path(&specifier[CJS_IMPORT_PREFIX.len()..]).into();
         specifier.into()
     };
 
@@ -115,7 +124,10 @@ pub fn require(ctx: Ctx<'_>, specifier: String) -> Result<Value<'_>> {
     }
 
     if is_json {
-        let json = fs::read_to_string(import_name.as_ref())?;
+        let json = match vfs::read(import_name.as_ref()) {
+            Some(bytes) => bytes.to_vec(),
+            None => fs::read(import_name.as_ref())?,
+        };
         let json = json_parse(&ctx, json)?;
         state.cache.insert(import_name, json.clone());
         return Ok(json);
@@ -16,10 +16,13 @@ use simd_json::{derived::ValueObjectAccessAsScalar, BorrowedValue};
 use tracing::trace;
 
 use crate::libs::utils::result::ResultExt;
+use crate::modules::fs::vfs;
+use crate::modules::llrt::mock;
 use crate::modules::path::{
     self, is_absolute, name_extname, replace_backslash, resolve_path_with_separator,
 };
 use crate::utils::io::{is_supported_ext, JS_EXTENSIONS, SUPPORTED_EXTENSIONS};
+use crate::watch;
 
 use super::{CJS_IMPORT_PREFIX, CJS_LOADER_PREFIX, LLRT_PLATFORM};
 
@@ -96,11 +99,13 @@ impl Resolver for NpmJsResolver {
             return Ok(name.to_string());
         }
 
-        let base = base.trim_start_matches(CJS_IMPORT_PREFIX);
+        let base = mock::module_path(base.trim_start_matches(CJS_IMPORT_PREFIX));
 
         trace!("Try resolve '{}' from '{}'", name, base);
 
-        require_resolve(ctx, name, base, None, true).map(|name| name.into_owned())
+        let path = require_resolve(ctx, name, base, None, true)?.into_owned();
+        watch::record_dependency(base, &path);
+        Ok(path)
     }
 }
 
@@ -146,12 +151,12 @@ pub fn require_resolve<'a>(
     let x_starts_with_current_dir = x.starts_with("./");
     let x_starts_with_parent_dir = x.starts_with("../");
 
-    if is_supported_ext && Path::new(x).is_file() {
+    if is_supported_ext && is_file(x) {
         return resolved_by_file_exists(x.into());
     }
 
     let x_normalized = path::normalize(x);
-    if !x_starts_with_parent_dir && is_supported_ext && Path::new(&x_normalized).is_file() {
+    if !x_starts_with_parent_dir && is_supported_ext && is_file(&x_normalized) {
         return resolved_by_file_exists(x_normalized.into());
     }
 
@@ -230,6 +235,10 @@ pub fn require_resolve<'a>(
     Err(Error::new_resolving(y.to_string(), x.to_string()))
 }
 
+fn is_file(path: &str) -> bool {
+    vfs::is_file(path) || Path::new(path).is_file()
+}
+
 fn resolved_by_file_exists(path: Cow<'_, str>) -> Result<Cow<'_, str>> {
     trace!("+- Resolved by `FILE`: {}", path);
     to_abs_path(path)
@@ -250,7 +259,7 @@ fn load_as_file<'a>(ctx: &Ctx<'_>, x: Rc<String>) -> Result<Option<Cow<'a, str>>
     trace!("|  load_as_file(x): {}", x);
 
     // 1. If X is a file, load X as its file extension format. STOP
-    if Path::new(x.as_ref()).is_file() {
+    if is_file(x.as_ref()) {
         trace!("|  load_as_file(1): {}", x);
         return Ok(Some(rc_string_to_cow(x)));
     }
@@ -267,7 +276,7 @@ fn load_as_file<'a>(ctx: &Ctx<'_>, x: Rc<String>) -> Result<Option<Cow<'a, str>>
             current_file.truncate(base_file_length);
             current_file.push_str(extension);
 
-            if Path::new(&current_file).is_file() {
+            if is_file(&current_file) {
                 // a. Find the closest package scope SCOPE to X.
                 match find_the_closest_package_scope(&x) {
                     // b. If no scope was found
@@ -303,7 +312,7 @@ fn load_as_file<'a>(ctx: &Ctx<'_>, x: Rc<String>) -> Result<Option<Cow<'a, str>>
     if let Some(mut current_file) = base_file.take() {
         current_file.truncate(base_file_length);
         current_file.push_str(".json");
-        if Path::new(&current_file).is_file() {
+        if is_file(&current_file) {
             trace!("|  load_as_file(3): {}", current_file);
             return Ok(Some(current_file.into()));
         }
@@ -330,7 +339,7 @@ fn load_index<'a>(ctx: &Ctx<'_>, x: Rc<String>) -> Result<Option<Cow<'a, str>>>
         if let Some(mut file) = base_file.take() {
             file.truncate(base_file_length);
             file.push_str(extension);
-            if Path::new(&file).is_file() {
+            if is_file(&file) {
                 // a. Find the closest package scope SCOPE to X.
                 match find_the_closest_package_scope(&x) {
                     // b. If no scope was found, load X/index.js as a CommonJS module. STOP.
@@ -365,7 +374,7 @@ fn load_index<'a>(ctx: &Ctx<'_>, x: Rc<String>) -> Result<Option<Cow<'a, str>>>
     if let Some(mut file) = base_file.take() {
         file.truncate(base_file_length);
         file.push_str(".json");
-        if Path::new(&file).is_file() {
+        if is_file(&file) {
             trace!("|  load_index(2): {}", file);
             return Ok(Some(file.into()));
         }
@@ -948,7 +957,7 @@ fn correct_extensions<'a>(x: String) -> Cow<'a, str> {
         if let Some(mut current_path) = path.take() {
             current_path.truncate(base_path_length);
             current_path.push_str(extension);
-            if Path::new(&current_path).is_file() {
+            if is_file(&current_path) {
                 return current_path.into();
             }
             path = Some(current_path);
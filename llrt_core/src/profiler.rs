@@ -0,0 +1,484 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// Sampling CPU profiler writing Chrome DevTools compatible .cpuprofile files.
+//
+// QuickJS has no sampling API, so stacks are captured from the runtime interrupt handler, which
+// the engine calls on function calls and loop back-edges every 10000 ticks. The only frame
+// information QuickJS exposes without creating an error is the script of each frame, so the
+// profile is a tree of scripts rather than functions. Time spent outside of JavaScript, such as
+// waiting for I/O, is attributed to "(program)".
+use std::{
+    collections::HashMap,
+    env,
+    ffi::CStr,
+    fs,
+    path::PathBuf,
+    process,
+    ptr::NonNull,
+    sync::{Mutex, Once},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rquickjs::qjs;
+use simd_json::{json, prelude::Writable, OwnedValue};
+use tracing::trace;
+
+use crate::environment::{ENV_LLRT_CPU_PROF, ENV_LLRT_CPU_PROF_DIR, ENV_LLRT_CPU_PROF_INTERVAL};
+use crate::vm::Vm;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_micros(1000);
+/// Deeper frames are not sampled
+const MAX_FRAMES: i32 = 64;
+
+const ROOT_NODE: usize = 0;
+const PROGRAM_NODE: usize = 1;
+
+static PROFILE: Lazy<Mutex<Option<Profile>>> = Lazy::new(|| Mutex::new(None));
+static STARTED: Once = Once::new();
+
+pub struct ProfilerOptions {
+    pub dir: PathBuf,
+    pub interval: Duration,
+}
+
+impl Default for ProfilerOptions {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("."),
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+}
+
+impl ProfilerOptions {
+    /// Reads `LLRT_CPU_PROF`, `LLRT_CPU_PROF_DIR` and `LLRT_CPU_PROF_INTERVAL`.
+    pub fn from_env() -> std::result::Result<Option<Self>, String> {
+        let enabled = env::var(ENV_LLRT_CPU_PROF).unwrap_or_default();
+        if enabled != "1" && enabled != "true" {
+            return Ok(None);
+        }
+        let mut options = Self::default();
+        if let Ok(dir) = env::var(ENV_LLRT_CPU_PROF_DIR) {
+            options.dir = PathBuf::from(dir);
+        }
+        if let Ok(interval) = env::var(ENV_LLRT_CPU_PROF_INTERVAL) {
+            options.interval = parse_interval(&interval)?;
+        }
+        Ok(Some(options))
+    }
+
+    /// Removes leading `--cpu-prof`, `--cpu-prof-dir=<dir>` and `--cpu-prof-interval=<us>`
+    /// flags from the arguments, overriding `options`. The directory and interval flags imply
+    /// `--cpu-prof`.
+    pub fn from_args(
+        args: &mut Vec<String>,
+        mut options: Option<Self>,
+    ) -> std::result::Result<Option<Self>, String> {
+        while let Some(arg) = args.get(1) {
+            if arg == "--cpu-prof" {
+                options.get_or_insert_with(Self::default);
+            } else if let Some(dir) = arg.strip_prefix("--cpu-prof-dir=") {
+                options.get_or_insert_with(Self::default).dir = PathBuf::from(dir);
+            } else if let Some(interval) = arg.strip_prefix("--cpu-prof-interval=") {
+                options.get_or_insert_with(Self::default).interval = parse_interval(interval)?;
+            } else {
+                break;
+            }
+            args.remove(1);
+        }
+        Ok(options)
+    }
+}
+
+fn parse_interval(interval: &str) -> std::result::Result<Duration, String> {
+    match interval.parse::<u64>() {
+        Ok(micros) if micros > 0 => Ok(Duration::from_micros(micros)),
+        _ => Err(["Invalid CPU profile interval: ", interval].concat()),
+    }
+}
+
+/// Starts sampling the call stack of the VM. The profile is written when the process exits, and
+/// by `flush` after each Lambda invocation. Only the first call has an effect.
+pub async fn start(vm: &Vm, options: ProfilerOptions) {
+    let mut started = false;
+    STARTED.call_once(|| {
+        let start_time = SystemTime::now();
+        let timestamp = DateTime::<Utc>::from(start_time).format("%Y%m%d.%H%M%S");
+        let path = options
+            .dir
+            .join(format!("CPU.{}.{}.cpuprofile", timestamp, process::id()));
+        trace!("Writing CPU profile to {}", path.display());
+
+        *PROFILE.lock().unwrap() = Some(Profile::new(path, options.interval, start_time));
+
+        // Safety: the handler only locks PROFILE, it never touches the runtime
+        unsafe { libc::atexit(write_on_exit) };
+        started = true;
+    });
+    if !started {
+        return;
+    }
+
+    let ctx = vm.ctx.with(|ctx| RawContext(ctx.as_raw())).await;
+    vm.runtime
+        .set_interrupt_handler(Some(Box::new(move || {
+            sample(&ctx);
+            false
+        })))
+        .await;
+}
+
+/// Writes the profile collected so far, replacing the previous file.
+pub fn flush() {
+    // Never block, the profile may be locked by a sample when exiting from JavaScript
+    let Ok(profile) = PROFILE.try_lock() else {
+        return;
+    };
+    let Some(profile) = profile.as_ref() else {
+        return;
+    };
+    let json = profile.to_json(Instant::now()).encode();
+    if let Some(dir) = profile.path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Err(err) = fs::write(&profile.path, json) {
+        eprintln!(
+            "Failed to write CPU profile {}: {}",
+            profile.path.display(),
+            err
+        );
+    }
+}
+
+extern "C" fn write_on_exit() {
+    flush();
+}
+
+struct RawContext(NonNull<qjs::JSContext>);
+
+// Safety: the pointer is only dereferenced by the interrupt handler, which QuickJS calls on the
+// thread holding the runtime lock
+unsafe impl Send for RawContext {}
+
+fn sample(ctx: &RawContext) {
+    let now = Instant::now();
+    // Never runs JavaScript, so the profile can stay locked while capturing the stack
+    let mut profile = PROFILE.lock().unwrap();
+    let Some(profile) = profile.as_mut() else {
+        return;
+    };
+    if now < profile.next_sample {
+        return;
+    }
+    profile.next_sample = now + profile.interval;
+
+    // Safety: the interrupt handler is called while a function of this context is running
+    let stack = unsafe { capture_stack(ctx.0, &mut profile.scripts) };
+    profile.add_sample(&stack, now);
+}
+
+// Returns the scripts of the frames, innermost first, without allocating objects or running
+// JavaScript, unlike creating an error, which would call Error.prepareStackTrace. Frames of
+// native functions have no script and are left out, consecutive frames of the same script are
+// merged.
+unsafe fn capture_stack(ctx: NonNull<qjs::JSContext>, scripts: &mut Scripts) -> Vec<usize> {
+    let mut stack = Vec::new();
+    // There is no way to tell the end of the stack apart from a native frame
+    for level in 0..MAX_FRAMES {
+        let atom = qjs::JS_GetScriptOrModuleName(ctx.as_ptr(), level);
+        if atom == qjs::JS_ATOM_NULL {
+            continue;
+        }
+        let script = scripts.get(ctx, atom);
+        qjs::JS_FreeAtom(ctx.as_ptr(), atom);
+        if stack.last() != Some(&script) {
+            stack.push(script);
+        }
+    }
+    stack
+}
+
+/// File names of the sampled scripts, converted once per script.
+#[derive(Default)]
+struct Scripts {
+    ids: HashMap<qjs::JSAtom, usize>,
+    file_names: Vec<String>,
+}
+
+impl Scripts {
+    unsafe fn get(&mut self, ctx: NonNull<qjs::JSContext>, atom: qjs::JSAtom) -> usize {
+        if let Some(id) = self.ids.get(&atom) {
+            return *id;
+        }
+        // File names are ASCII in practice, which QuickJS converts without copying. The atom is
+        // kept alive so it can't be reused for another name.
+        let file_name = qjs::JS_AtomToCString(ctx.as_ptr(), atom);
+        let id = self.file_names.len();
+        self.file_names.push(if file_name.is_null() {
+            String::new()
+        } else {
+            let name = CStr::from_ptr(file_name).to_string_lossy().into_owned();
+            qjs::JS_FreeCString(ctx.as_ptr(), file_name);
+            name
+        });
+        self.ids.insert(qjs::JS_DupAtom(ctx.as_ptr(), atom), id);
+        id
+    }
+}
+
+/// The name and file of a node. Sampled nodes are named after their script.
+#[derive(Clone, Default)]
+struct CallFrame {
+    function_name: String,
+    file_name: String,
+}
+
+struct Node {
+    call_frame: CallFrame,
+    children: Vec<usize>,
+    hit_count: u64,
+}
+
+impl Node {
+    fn new(call_frame: CallFrame) -> Self {
+        Self {
+            call_frame,
+            children: Vec::new(),
+            hit_count: 0,
+        }
+    }
+}
+
+struct Profile {
+    path: PathBuf,
+    interval: Duration,
+    start_time: SystemTime,
+    start: Instant,
+    next_sample: Instant,
+    last_sample: Instant,
+    scripts: Scripts,
+    nodes: Vec<Node>,
+    node_ids: HashMap<(usize, usize), usize>,
+    samples: Vec<usize>,
+    time_deltas: Vec<u64>,
+}
+
+impl Profile {
+    fn new(path: PathBuf, interval: Duration, start_time: SystemTime) -> Self {
+        let start = Instant::now();
+        let named = |name: &str| CallFrame {
+            function_name: name.into(),
+            ..Default::default()
+        };
+        let mut root = Node::new(named("(root)"));
+        root.children.push(PROGRAM_NODE);
+        Self {
+            path,
+            interval,
+            start_time,
+            start,
+            next_sample: start,
+            last_sample: start,
+            scripts: Scripts::default(),
+            nodes: vec![root, Node::new(named("(program)"))],
+            node_ids: HashMap::new(),
+            samples: Vec::new(),
+            time_deltas: Vec::new(),
+        }
+    }
+
+    /// Adds a sample of the scripts on the stack, innermost first.
+    fn add_sample(&mut self, stack: &[usize], now: Instant) {
+        // Samples are only taken while JavaScript runs, the gaps are spent elsewhere
+        if now.duration_since(self.last_sample) >= self.interval * 2 {
+            self.push_sample(PROGRAM_NODE, self.last_sample + self.interval);
+        }
+
+        let mut node = ROOT_NODE;
+        for script in stack.iter().rev() {
+            node = self.child(node, *script);
+        }
+        if stack.is_empty() {
+            node = PROGRAM_NODE;
+        }
+        self.push_sample(node, now);
+    }
+
+    fn child(&mut self, parent: usize, script: usize) -> usize {
+        if let Some(id) = self.node_ids.get(&(parent, script)) {
+            return *id;
+        }
+        let file_name = &self.scripts.file_names[script];
+        let id = self.nodes.len();
+        self.nodes.push(Node::new(CallFrame {
+            function_name: file_name.rsplit('/').next().unwrap_or_default().into(),
+            file_name: file_name.clone(),
+        }));
+        self.nodes[parent].children.push(id);
+        self.node_ids.insert((parent, script), id);
+        id
+    }
+
+    fn push_sample(&mut self, node: usize, time: Instant) {
+        self.nodes[node].hit_count += 1;
+        self.samples.push(node);
+        self.time_deltas
+            .push(time.duration_since(self.last_sample).as_micros() as u64);
+        self.last_sample = time;
+    }
+
+    fn to_json(&self, end: Instant) -> OwnedValue {
+        let start_time = self
+            .start_time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let end_time = start_time + end.duration_since(self.start).as_micros() as u64;
+
+        let mut script_ids: HashMap<String, u64> = HashMap::new();
+        let nodes: Vec<OwnedValue> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(id, node)| {
+                let frame = &node.call_frame;
+                let file_name = frame.file_name.as_str();
+                let url = if file_name.starts_with('/') {
+                    ["file://", file_name].concat()
+                } else {
+                    file_name.to_string()
+                };
+                let script_id = if url.is_empty() {
+                    0
+                } else {
+                    let next_id = script_ids.len() as u64 + 1;
+                    *script_ids.entry(url.clone()).or_insert(next_id)
+                };
+
+                json!({
+                    "id": id as u64 + 1,
+                    "callFrame": {
+                        "functionName": frame.function_name.as_str(),
+                        "scriptId": script_id.to_string(),
+                        "url": url,
+                        "lineNumber": -1,
+                        "columnNumber": -1
+                    },
+                    "hitCount": node.hit_count,
+                    "children": node.children.iter().map(|id| *id as u64 + 1).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        json!({
+            "nodes": nodes,
+            "startTime": start_time,
+            "endTime": end_time,
+            "samples": self.samples.iter().map(|id| *id as u64 + 1).collect::<Vec<_>>(),
+            "timeDeltas": self.time_deltas.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use simd_json::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn parses_cpu_prof_flags() {
+        let mut args: Vec<String> = ["llrt", "--cpu-prof-interval=500", "index.js"]
+            .map(String::from)
+            .to_vec();
+        let options = ProfilerOptions::from_args(&mut args, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.interval, Duration::from_micros(500));
+        assert_eq!(options.dir, PathBuf::from("."));
+        assert_eq!(args, ["llrt", "index.js"]);
+
+        let mut args: Vec<String> = ["llrt", "index.js", "--cpu-prof"]
+            .map(String::from)
+            .to_vec();
+        assert!(ProfilerOptions::from_args(&mut args, None)
+            .unwrap()
+            .is_none());
+
+        let mut args: Vec<String> = ["llrt", "--cpu-prof-interval=0"].map(String::from).to_vec();
+        assert!(ProfilerOptions::from_args(&mut args, None).is_err());
+
+        // Flags override the options read from the environment
+        let env_options = ProfilerOptions {
+            dir: PathBuf::from("/tmp"),
+            interval: Duration::from_micros(2000),
+        };
+        let mut args: Vec<String> = ["llrt", "--cpu-prof-interval=500", "index.js"]
+            .map(String::from)
+            .to_vec();
+        let options = ProfilerOptions::from_args(&mut args, Some(env_options))
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.interval, Duration::from_micros(500));
+        assert_eq!(options.dir, PathBuf::from("/tmp"));
+    }
+
+    #[test]
+    fn builds_call_tree() {
+        let interval = Duration::from_millis(1);
+        let mut profile = Profile::new(PathBuf::new(), interval, UNIX_EPOCH);
+        let start = profile.start;
+        profile.scripts.file_names = vec!["/app/index.js".into(), "/app/lib.js".into()];
+        let (index, lib) = (0, 1);
+
+        profile.add_sample(&[lib, index], start + interval);
+        profile.add_sample(&[index], start + interval * 2);
+        profile.add_sample(&[lib, index], start + interval * 5);
+
+        let json = profile.to_json(start + interval * 5);
+        let nodes = json.get("nodes").unwrap().as_array().unwrap();
+        let name = |node: &OwnedValue| {
+            node.get("callFrame")
+                .unwrap()
+                .get("functionName")
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        let names: Vec<String> = nodes.iter().map(name).collect();
+        assert_eq!(names, ["(root)", "(program)", "index.js", "lib.js"]);
+
+        let index = &nodes[2];
+        assert_eq!(index.get("children").unwrap().as_array().unwrap().len(), 1);
+        assert_eq!(index.get("hitCount").unwrap().as_u64(), Some(1));
+        assert_eq!(
+            index.get("callFrame").unwrap().get("url").unwrap().as_str(),
+            Some("file:///app/index.js")
+        );
+        assert_eq!(nodes[3].get("hitCount").unwrap().as_u64(), Some(2));
+
+        // The gap before the last sample is attributed to (program)
+        let samples: Vec<u64> = json
+            .get("samples")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|id| id.as_u64())
+            .collect();
+        assert_eq!(samples, [4, 3, 2, 4]);
+        let time_deltas: Vec<u64> = json
+            .get("timeDeltas")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|delta| delta.as_u64())
+            .collect();
+        assert_eq!(time_deltas, [1000, 1000, 1000, 2000]);
+    }
+}
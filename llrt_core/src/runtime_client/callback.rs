@@ -0,0 +1,159 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::{cell::RefCell, rc::Rc};
+
+use rquickjs::{
+    atom::PredefinedAtom, function::Opt, promise::Promise, Ctx, Exception, Function, Object,
+    Result, Value,
+};
+use tokio::sync::oneshot;
+
+use crate::libs::context;
+
+use super::invocation_context;
+
+const CALLBACK_WAITS_FOR_EMPTY_EVENT_LOOP: &str = "callbackWaitsForEmptyEventLoop";
+
+struct Completion<'js> {
+    error: Option<Value<'js>>,
+    result: Option<Value<'js>>,
+    wait_for_empty_event_loop: bool,
+}
+
+type CompletionSender<'js> = Rc<RefCell<Option<oneshot::Sender<Completion<'js>>>>>;
+
+/// Calls a handler, passing a callback when it declares the `(event, context, callback)`
+/// signature, and resolves with whichever comes first: the returned promise, the
+/// callback or `context.done`, `context.succeed` and `context.fail`. Handlers returning
+/// `undefined` without ever completing resolve with `undefined` once the event loop is
+/// empty, i.e. no jobs, timers or I/O are pending.
+///
+/// The event loop is shared by concurrent invocations, so when processing invocations
+/// concurrently it is never waited for: `callbackWaitsForEmptyEventLoop` defaults to
+/// `false` and enabling it fails the invocation, while handlers returning `undefined`
+/// resolve right away unless they declare the callback.
+pub async fn call_handler<'js>(
+    ctx: &Ctx<'js>,
+    handler: &Function<'js>,
+    event: Value<'js>,
+    context: Object<'js>,
+    promise_constructor: &Value<'js>,
+) -> Result<Value<'js>> {
+    let concurrent = invocation_context::is_concurrent(ctx);
+    let (tx, rx) = oneshot::channel();
+    let tx: CompletionSender = Rc::new(RefCell::new(Some(tx)));
+
+    let callback = {
+        let tx = tx.clone();
+        let context = context.clone();
+        Function::new(
+            ctx.clone(),
+            move |error: Opt<Value<'js>>, result: Opt<Value<'js>>| {
+                let wait_for_empty_event_loop = context
+                    .get::<_, Option<bool>>(CALLBACK_WAITS_FOR_EMPTY_EVENT_LOOP)
+                    .ok()
+                    .flatten()
+                    .unwrap_or(!concurrent);
+                complete(&tx, error.0, result.0, wait_for_empty_event_loop);
+            },
+        )?
+    };
+
+    // Unlike the callback, these complete the invocation without waiting for the event loop
+    {
+        let tx = tx.clone();
+        context.set(
+            "done",
+            Function::new(
+                ctx.clone(),
+                move |error: Opt<Value<'js>>, result: Opt<Value<'js>>| {
+                    complete(&tx, error.0, result.0, false);
+                },
+            )?,
+        )?;
+    }
+    {
+        let tx = tx.clone();
+        context.set(
+            "succeed",
+            Function::new(ctx.clone(), move |result: Opt<Value<'js>>| {
+                complete(&tx, None, result.0, false);
+            })?,
+        )?;
+    }
+    {
+        let tx = tx.clone();
+        context.set(
+            "fail",
+            Function::new(ctx.clone(), move |error: Opt<Value<'js>>| {
+                complete(&tx, error.0, None, false);
+            })?,
+        )?;
+    }
+
+    // Like Node.js, the callback is only passed to handlers declaring it
+    let arity: usize = handler.get(PredefinedAtom::Length).unwrap_or_default();
+    let handler_result: Value = if arity >= 3 {
+        handler.call((event, context, callback))?
+    } else {
+        handler.call((event, context))?
+    };
+
+    let completion = match handler_result.as_object() {
+        Some(obj) if obj.is_instance_of(promise_constructor) => {
+            let promise: Promise = handler_result.get()?;
+            tokio::select! {
+                result = promise.into_future::<Value>() => return result,
+                completion = rx => completion,
+            }
+        },
+        _ if !handler_result.is_undefined() => return Ok(handler_result),
+        _ if concurrent && arity < 3 => return Ok(handler_result),
+        _ if concurrent => rx.await,
+        _ => {
+            tokio::select! {
+                _ = context::idle(ctx) => return Ok(handler_result),
+                completion = rx => completion,
+            }
+        },
+    };
+
+    // The sender is kept alive by the context functions, so the receiver can't be cancelled
+    let Ok(completion) = completion else {
+        return Ok(Value::new_undefined(ctx.clone()));
+    };
+
+    if completion.wait_for_empty_event_loop {
+        if concurrent {
+            return Err(Exception::throw_message(
+                ctx,
+                "callbackWaitsForEmptyEventLoop is not supported when processing invocations concurrently",
+            ));
+        }
+        context::idle(ctx).await;
+    }
+
+    match completion.error {
+        Some(error) => Err(ctx.throw(error)),
+        None => Ok(completion
+            .result
+            .unwrap_or_else(|| Value::new_undefined(ctx.clone()))),
+    }
+}
+
+fn complete<'js>(
+    tx: &CompletionSender<'js>,
+    error: Option<Value<'js>>,
+    result: Option<Value<'js>>,
+    wait_for_empty_event_loop: bool,
+) {
+    // Only the first completion counts, like in Node.js
+    if let Some(tx) = tx.borrow_mut().take() {
+        let error = error.filter(|error| !error.is_null() && !error.is_undefined());
+        let _ = tx.send(Completion {
+            error,
+            result,
+            wait_for_empty_event_loop,
+        });
+    }
+}
@@ -0,0 +1,482 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    env, io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    body::Incoming,
+    header::{HeaderName, HeaderValue, CONTENT_TYPE},
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc, oneshot},
+};
+use tracing::trace;
+
+use crate::libs::encoding::bytes_from_b64;
+
+use super::{
+    response_stream::HEADER_ERROR_BODY, ENV_AWS_LAMBDA_FUNCTION_NAME, ENV_RUNTIME_PATH,
+    HEADER_DEADLINE_MS, HEADER_ERROR_TYPE, HEADER_INVOKED_FUNCTION_ARN, HEADER_REQUEST_ID,
+    HEADER_TRACE_ID,
+};
+
+const ENV_AWS_LAMBDA_FUNCTION_TIMEOUT: &str = "AWS_LAMBDA_FUNCTION_TIMEOUT";
+const ENV_AWS_REGION: &str = "AWS_REGION";
+const DEFAULT_TIMEOUT_SECS: u64 = 3;
+pub const INVOCATIONS_PATH: &str = "/2015-03-31/functions/function/invocations";
+const EXTENSION_PATH: &str = "/2020-01-01/extension";
+const HEADER_FUNCTION_ERROR: &str = "x-amz-function-error";
+
+/// Outcome of a single invocation, as posted by the runtime client.
+#[derive(Clone, Debug)]
+pub struct InvocationResult {
+    pub content_type: Option<String>,
+    pub error_type: Option<String>,
+    pub body: Bytes,
+}
+
+impl InvocationResult {
+    pub fn is_error(&self) -> bool {
+        self.error_type.is_some()
+    }
+}
+
+struct Invocation {
+    request_id: String,
+    event: Bytes,
+    result_tx: oneshot::Sender<InvocationResult>,
+}
+
+struct State {
+    queue_tx: mpsc::UnboundedSender<Invocation>,
+    queue_rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<Invocation>>,
+    pending: Mutex<HashMap<String, oneshot::Sender<InvocationResult>>>,
+    init_error: Mutex<Option<InvocationResult>>,
+    timeout: Duration,
+    function_arn: String,
+}
+
+/// In-process stand-in for the Lambda Runtime API. Events are queued with
+/// [`Emulator::invoke`] or by POSTing them to
+/// `/2015-03-31/functions/function/invocations`, the same path used by the
+/// Lambda Runtime Interface Emulator.
+pub struct Emulator {
+    addr: SocketAddr,
+    state: Arc<State>,
+}
+
+impl Emulator {
+    pub async fn bind(port: u16) -> io::Result<Self> {
+        let timeout = env::var(ENV_AWS_LAMBDA_FUNCTION_TIMEOUT)
+            .ok()
+            .and_then(|timeout| timeout.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+        Self::bind_with_timeout(port, Duration::from_secs(timeout)).await
+    }
+
+    /// Binds the emulator with a function timeout other than `AWS_LAMBDA_FUNCTION_TIMEOUT`.
+    pub async fn bind_with_timeout(port: u16, timeout: Duration) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+        let addr = listener.local_addr()?;
+
+        let (queue_tx, queue_rx) = mpsc::unbounded_channel();
+        let function_name =
+            env::var(ENV_AWS_LAMBDA_FUNCTION_NAME).unwrap_or_else(|_| String::from("function"));
+        let region = env::var(ENV_AWS_REGION).unwrap_or_else(|_| String::from("us-east-1"));
+
+        let state = Arc::new(State {
+            queue_tx,
+            queue_rx: tokio::sync::Mutex::new(queue_rx),
+            pending: Mutex::new(HashMap::new()),
+            init_error: Mutex::new(None),
+            timeout,
+            function_arn: [
+                "arn:aws:lambda:",
+                &region,
+                ":000000000000:function:",
+                &function_name,
+            ]
+            .concat(),
+        });
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| handle(state.clone(), req));
+                    if let Err(err) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        trace!("Emulator connection error: {}", err);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { addr, state })
+    }
+
+    /// The `host:port` to use as `AWS_LAMBDA_RUNTIME_API`.
+    pub fn runtime_api(&self) -> String {
+        self.addr.to_string()
+    }
+
+    pub async fn invoke(&self, event: impl Into<Bytes>) -> InvocationResult {
+        invoke(&self.state, event.into()).await
+    }
+
+    pub fn init_error(&self) -> Option<InvocationResult> {
+        self.state.init_error.lock().unwrap().clone()
+    }
+}
+
+async fn invoke(state: &State, event: Bytes) -> InvocationResult {
+    let (result_tx, result_rx) = oneshot::channel();
+    {
+        // Hold the lock while queueing so a concurrent init error can't miss this invocation
+        let init_error = state.init_error.lock().unwrap();
+        if let Some(init_error) = init_error.as_ref() {
+            return init_error.clone();
+        }
+        let invocation = Invocation {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            event,
+            result_tx,
+        };
+        let _ = state.queue_tx.send(invocation);
+    }
+
+    match result_rx.await {
+        Ok(result) => result,
+        Err(_) => state
+            .init_error
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| InvocationResult {
+                content_type: None,
+                error_type: Some(String::from("Runtime.ExitError")),
+                body: Bytes::from_static(b"{\"errorMessage\":\"Runtime exited\"}"),
+            }),
+    }
+}
+
+async fn handle(
+    state: Arc<State>,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let path = req.uri().path().to_string();
+    let method = req.method().clone();
+
+    if let Some(path) = path
+        .strip_prefix('/')
+        .and_then(|path| path.strip_prefix(ENV_RUNTIME_PATH))
+    {
+        return Ok(match (method, path) {
+            (Method::GET, "/invocation/next") => next_invocation(&state).await,
+            (Method::POST, "/init/error") => {
+                let result = collect_result(req, true).await;
+                init_error(&state, result);
+                accepted()
+            },
+            (Method::POST, path) if path.starts_with("/invocation/") => {
+                let path = &path["/invocation/".len()..];
+                let (request_id, is_error) = match path.rsplit_once('/') {
+                    Some((request_id, "response")) => (request_id.to_string(), false),
+                    Some((request_id, "error")) => (request_id.to_string(), true),
+                    _ => return Ok(not_found()),
+                };
+                let Some(result_tx) = state.pending.lock().unwrap().remove(&request_id) else {
+                    return Ok(not_found());
+                };
+                let result = collect_result(req, is_error).await;
+                let _ = result_tx.send(result);
+                accepted()
+            },
+            _ => not_found(),
+        });
+    }
+
+    Ok(match (method, path.as_str()) {
+        (Method::POST, INVOCATIONS_PATH) => {
+            let event = match req.into_body().collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(_) => return Ok(bad_request()),
+            };
+            let result = invoke(&state, event).await;
+            let mut res = Response::new(Full::new(result.body));
+            if let Some(content_type) = result.content_type.as_deref().or(
+                // Errors are always JSON
+                result.error_type.as_ref().map(|_| "application/json"),
+            ) {
+                if let Ok(value) = HeaderValue::from_str(content_type) {
+                    res.headers_mut().insert(CONTENT_TYPE, value);
+                }
+            }
+            if result.error_type.is_some() {
+                res.headers_mut()
+                    .insert(HEADER_FUNCTION_ERROR, HeaderValue::from_static("Unhandled"));
+            }
+            res
+        },
+        (Method::POST, path) if path == [EXTENSION_PATH, "/register"].concat() => {
+            let mut res = Response::new(Full::new(Bytes::from_static(b"{}")));
+            if let Ok(value) = HeaderValue::from_str(&uuid::Uuid::new_v4().to_string()) {
+                res.headers_mut()
+                    .insert("lambda-extension-identifier", value);
+            }
+            res
+        },
+        // Internal extensions are kept waiting as the emulator has no extension events to deliver
+        (Method::GET, path) if path == [EXTENSION_PATH, "/event/next"].concat() => {
+            std::future::pending::<()>().await;
+            not_found()
+        },
+        _ => not_found(),
+    })
+}
+
+async fn next_invocation(state: &State) -> Response<Full<Bytes>> {
+    let Some(invocation) = state.queue_rx.lock().await.recv().await else {
+        return not_found();
+    };
+
+    let deadline = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        + state.timeout;
+    let trace_id = [
+        "Root=1-",
+        &format!("{:08x}", deadline.as_secs()),
+        "-",
+        &uuid::Uuid::new_v4().simple().to_string()[..24],
+        ";Sampled=0",
+    ]
+    .concat();
+
+    let mut res = Response::new(Full::new(invocation.event));
+    let headers = res.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    if let Ok(value) = HeaderValue::from_str(&invocation.request_id) {
+        headers.insert(&HEADER_REQUEST_ID, value);
+    }
+    headers.insert(
+        &HEADER_DEADLINE_MS,
+        HeaderValue::from(deadline.as_millis() as u64),
+    );
+    if let Ok(value) = HeaderValue::from_str(&state.function_arn) {
+        headers.insert(&HEADER_INVOKED_FUNCTION_ARN, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&trace_id) {
+        headers.insert(&HEADER_TRACE_ID, value);
+    }
+
+    state
+        .pending
+        .lock()
+        .unwrap()
+        .insert(invocation.request_id, invocation.result_tx);
+
+    res
+}
+
+async fn collect_result(req: Request<Incoming>, is_error: bool) -> InvocationResult {
+    let header = |name: &HeaderName| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    let content_type = header(&CONTENT_TYPE);
+    let mut error_type = header(&HEADER_ERROR_TYPE);
+    if is_error && error_type.is_none() {
+        error_type = Some(String::from("Unhandled"));
+    }
+
+    let collected = match req.into_body().collect().await {
+        Ok(collected) => collected,
+        Err(err) => {
+            return InvocationResult {
+                content_type: None,
+                error_type: Some(String::from("Runtime.ResponseError")),
+                body: Bytes::from(err.to_string()),
+            }
+        },
+    };
+
+    // Streamed responses report errors mid-stream through trailers
+    if let Some(trailers) = collected.trailers() {
+        if let Some(trailer_error_type) = trailers
+            .get(&HEADER_ERROR_TYPE)
+            .and_then(|value| value.to_str().ok())
+        {
+            let body = trailers
+                .get(&HEADER_ERROR_BODY)
+                .and_then(|value| bytes_from_b64(value.as_bytes()).ok())
+                .unwrap_or_default();
+            return InvocationResult {
+                content_type: Some(String::from("application/json")),
+                error_type: Some(trailer_error_type.to_string()),
+                body: Bytes::from(body),
+            };
+        }
+    }
+
+    InvocationResult {
+        content_type,
+        error_type,
+        body: collected.to_bytes(),
+    }
+}
+
+fn init_error(state: &State, result: InvocationResult) {
+    *state.init_error.lock().unwrap() = Some(result.clone());
+
+    for (_, result_tx) in state.pending.lock().unwrap().drain() {
+        let _ = result_tx.send(result.clone());
+    }
+    if let Ok(mut queue_rx) = state.queue_rx.try_lock() {
+        while let Ok(invocation) = queue_rx.try_recv() {
+            let _ = invocation.result_tx.send(result.clone());
+        }
+    }
+}
+
+fn accepted() -> Response<Full<Bytes>> {
+    status_response(StatusCode::ACCEPTED)
+}
+
+fn not_found() -> Response<Full<Bytes>> {
+    status_response(StatusCode::NOT_FOUND)
+}
+
+fn bad_request() -> Response<Full<Bytes>> {
+    status_response(StatusCode::BAD_REQUEST)
+}
+
+fn status_response(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut res = Response::new(Full::default());
+    *res.status_mut() = status;
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rquickjs::{async_with, CatchResultExt};
+
+    use crate::runtime_client::{self, emulator::Emulator};
+    use crate::vm::Vm;
+
+    #[tokio::test]
+    async fn invoke() {
+        let vm = Vm::new().await.unwrap();
+
+        async fn invoke_handler(vm: &Vm, handler: &str) -> super::InvocationResult {
+            invoke_handler_with_timeout(vm, handler, Duration::from_secs(3)).await
+        }
+
+        async fn invoke_handler_with_timeout(
+            vm: &Vm,
+            handler: &str,
+            timeout: Duration,
+        ) -> super::InvocationResult {
+            let emulator = Emulator::bind_with_timeout(0, timeout).await.unwrap();
+            let runtime_api = emulator.runtime_api();
+            let handler = handler.to_string();
+
+            let (_, result) = tokio::join!(
+                async_with!(vm.ctx => |ctx|{
+                    runtime_client::start_with_runtime_api(&ctx, runtime_api, handler, 1)
+                        .await
+                        .catch(&ctx)
+                        .unwrap()
+                }),
+                emulator.invoke(r#"{"hello": "world"}"#)
+            );
+            result
+        }
+
+        let result = invoke_handler(&vm, "../fixtures/handler.handler").await;
+        assert!(!result.is_error());
+        assert_eq!(
+            result.body.as_ref(),
+            br#"{"statusCode":200,"body":"Hello world!"}"#
+        );
+
+        let result = invoke_handler(&vm, "../fixtures/throwing-handler.handler").await;
+        assert!(result.is_error());
+
+        let result = invoke_handler(&vm, "../fixtures/throwing-cause-handler.handler").await;
+        assert!(result.is_error());
+        let body = String::from_utf8_lossy(&result.body);
+        assert!(body.contains(r#""cause":{"errorType":"AggregateError""#));
+        assert!(body.contains(r#""errorMessage":"TypeError: invalid input""#));
+        assert!(body.contains(r#""errorMessage":"RangeError: out of range""#));
+
+        let result = invoke_handler(&vm, "../fixtures/streaming-handler.handler").await;
+        assert!(!result.is_error());
+        assert!(result.body.ends_with(b"Hello world!"));
+
+        let result = invoke_handler(&vm, "../fixtures/throwing-streaming-handler.handler").await;
+        assert!(result.is_error());
+
+        let result = invoke_handler(&vm, "../fixtures/callback-handler.handler").await;
+        assert!(!result.is_error());
+        assert_eq!(
+            result.body.as_ref(),
+            br#"{"statusCode":200,"body":"Hello world!"}"#
+        );
+
+        let result = invoke_handler(&vm, "../fixtures/callback-handler.succeed").await;
+        assert!(!result.is_error());
+        assert_eq!(
+            result.body.as_ref(),
+            br#"{"statusCode":200,"body":"Hello world!"}"#
+        );
+
+        let result = invoke_handler(&vm, "../fixtures/callback-handler.fail").await;
+        assert!(result.is_error());
+
+        let result = invoke_handler(&vm, "../fixtures/callback-handler.error").await;
+        assert!(result.is_error());
+
+        let result = invoke_handler_with_timeout(
+            &vm,
+            "../fixtures/timeout-handler.handler",
+            Duration::from_secs(1),
+        )
+        .await;
+        assert!(!result.is_error());
+        assert_eq!(result.body.as_ref(), br#""TimeoutError""#);
+
+        let result = invoke_handler_with_timeout(
+            &vm,
+            "../fixtures/timeout-handler.hanging",
+            Duration::from_secs(1),
+        )
+        .await;
+        assert_eq!(result.error_type.as_deref(), Some("Runtime.Timeout"));
+
+        vm.runtime.idle().await;
+    }
+}
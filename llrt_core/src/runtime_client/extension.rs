@@ -0,0 +1,205 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::cell::RefCell;
+
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::{header::CONTENT_TYPE, http::header::HeaderName, Request, StatusCode};
+#[cfg(not(test))]
+use rquickjs::function::Rest;
+use rquickjs::{
+    atom::PredefinedAtom, CatchResultExt, CaughtError, Ctx, Exception, Function, Object, Result,
+};
+use tokio::sync::watch;
+
+use crate::libs::{json::parse::json_parse, utils::result::ResultExt};
+#[cfg(not(test))]
+use crate::modules::console::log_error;
+use crate::modules::{fetch::HyperClient, llrt::lambda::ExtensionHooks};
+
+use super::await_handler_result;
+
+const EXTENSION_PATH: &str = "2020-01-01/extension";
+const EXTENSION_NAME: &str = "llrt";
+const EVENT_INVOKE: &str = "INVOKE";
+const EVENT_SHUTDOWN: &str = "SHUTDOWN";
+
+static HEADER_EXTENSION_NAME: HeaderName = HeaderName::from_static("lambda-extension-name");
+static HEADER_EXTENSION_IDENTIFIER: HeaderName =
+    HeaderName::from_static("lambda-extension-identifier");
+
+/// Handle to a registered internal extension. Dropping it stops polling
+/// `/extension/event/next` and listening for SIGTERM.
+pub struct Extension {
+    _stop: watch::Sender<()>,
+}
+
+/// Registers an internal extension when the handler module added listeners through
+/// `llrt:lambda`. Must be called after init and before the first `/invocation/next`.
+///
+/// Internal extensions can only subscribe to INVOKE events. Once any extension is
+/// registered Lambda sends SIGTERM to the runtime before shutting down the sandbox,
+/// which is when shutdown listeners are called.
+///
+/// `/extension/event/next` is polled even without an INVOKE subscription, since
+/// Lambda doesn't finish init until every registered extension has called it.
+pub async fn register<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    runtime_api: &str,
+) -> Result<Option<Extension>> {
+    let subscribe_invoke = match ctx.userdata::<RefCell<ExtensionHooks>>() {
+        Some(hooks) if !hooks.borrow().is_empty() => !hooks.borrow().invoke.is_empty(),
+        _ => return Ok(None),
+    };
+
+    let base_url = ["http://", runtime_api, "/", EXTENSION_PATH].concat();
+    let events = if subscribe_invoke {
+        ["{\"events\":[\"", EVENT_INVOKE, "\"]}"].concat()
+    } else {
+        String::from("{\"events\":[]}")
+    };
+
+    let req = Request::builder()
+        .method("POST")
+        .uri([base_url.as_str(), "/register"].concat())
+        .header(&HEADER_EXTENSION_NAME, EXTENSION_NAME)
+        .header(CONTENT_TYPE, "application/json")
+        .body(BoxBody::new(Full::from(events)))
+        .or_throw(ctx)?;
+
+    let res = client.request(req).await.or_throw(ctx)?;
+
+    if res.status() != StatusCode::OK {
+        let res_bytes = res.collect().await.or_throw(ctx)?.to_bytes();
+        let res_str = String::from_utf8_lossy(&res_bytes);
+        return Err(Exception::throw_message(
+            ctx,
+            &["Unexpected /extension/register response: ", &res_str].concat(),
+        ));
+    }
+
+    let extension_id = res
+        .headers()
+        .get(&HEADER_EXTENSION_IDENTIFIER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+        .or_throw_msg(ctx, "Missing Lambda-Extension-Identifier header")?;
+
+    let (stop_tx, stop_rx) = watch::channel(());
+
+    let ctx2 = ctx.clone();
+    let client = client.clone();
+    let mut stop = stop_rx.clone();
+    ctx.spawn(async move {
+        let next_url = [base_url.as_str(), "/event/next"].concat();
+        loop {
+            let event = tokio::select! {
+                _ = stop.changed() => break,
+                event = next_event(&ctx2, &client, &next_url, &extension_id) => event,
+            };
+            let event = match event.catch(&ctx2) {
+                Ok(event) => event,
+                Err(err) => {
+                    report_hook_error(&ctx2, Err(err));
+                    break;
+                },
+            };
+            let event_type: Option<String> = event.get("eventType").unwrap_or_default();
+            let is_shutdown = event_type.as_deref() == Some(EVENT_SHUTDOWN);
+            let result = call_hooks(&ctx2, event, is_shutdown).await;
+            report_hook_error(&ctx2, result.catch(&ctx2));
+            if is_shutdown {
+                break;
+            }
+        }
+    });
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigterm = signal(SignalKind::terminate()).or_throw(ctx)?;
+        let ctx2 = ctx.clone();
+        let mut stop = stop_rx;
+        ctx.spawn(async move {
+            tokio::select! {
+                _ = stop.changed() => {},
+                _ = sigterm.recv() => {
+                    let result = async {
+                        let event = Object::new(ctx2.clone())?;
+                        event.set("eventType", EVENT_SHUTDOWN)?;
+                        call_hooks(&ctx2, event, true).await
+                    }
+                    .await;
+                    report_hook_error(&ctx2, result.catch(&ctx2));
+                    std::process::exit(0);
+                },
+            }
+        });
+    }
+
+    Ok(Some(Extension { _stop: stop_tx }))
+}
+
+async fn next_event<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    uri: &str,
+    extension_id: &str,
+) -> Result<Object<'js>> {
+    let req = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .header(&HEADER_EXTENSION_IDENTIFIER, extension_id)
+        .body(BoxBody::new(Full::default()))
+        .or_throw(ctx)?;
+
+    let res = client.request(req).await.or_throw(ctx)?;
+    let status = res.status();
+    let res_bytes = res.collect().await.or_throw(ctx)?.to_bytes();
+
+    if status != StatusCode::OK {
+        let res_str = String::from_utf8_lossy(&res_bytes);
+        return Err(Exception::throw_message(
+            ctx,
+            &["Unexpected /extension/event/next response: ", &res_str].concat(),
+        ));
+    }
+
+    json_parse(ctx, res_bytes.to_vec())?.get()
+}
+
+async fn call_hooks<'js>(ctx: &Ctx<'js>, event: Object<'js>, shutdown: bool) -> Result<()> {
+    let listeners: Vec<Function> = match ctx.userdata::<RefCell<ExtensionHooks>>() {
+        Some(hooks) => {
+            let hooks = hooks.borrow();
+            if shutdown {
+                hooks.shutdown.clone()
+            } else {
+                hooks.invoke.clone()
+            }
+        },
+        None => return Ok(()),
+    };
+    let promise_ctor = ctx.globals().get(PredefinedAtom::Promise)?;
+    for listener in listeners {
+        let result = listener.call((event.clone(),))?;
+        await_handler_result(result, &promise_ctor).await?;
+    }
+    Ok(())
+}
+
+fn report_hook_error<'js>(ctx: &Ctx<'js>, result: std::result::Result<(), CaughtError<'js>>) {
+    if let Err(err) = result {
+        #[cfg(not(test))]
+        {
+            if let Ok((_, error_object)) = super::build_error_object(ctx, &err, None) {
+                let _ = log_error(ctx.clone(), Rest(vec![error_object]));
+            }
+        }
+        #[cfg(test)]
+        {
+            let _ = (ctx, err);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use once_cell::sync::Lazy;
+use rquickjs::{
+    atom::PredefinedAtom,
+    object::{Accessor, Property},
+    promise::PromiseHookType,
+    runtime::PromiseHook,
+    Ctx, Error, Function, JsLifetime, Object, Result, Value,
+};
+
+use crate::environment;
+
+use super::ENV_X_AMZN_TRACE_ID;
+
+/// Number of invocations polled and processed concurrently, set through
+/// `LLRT_LAMBDA_CONCURRENCY`. Defaults to 1, processing one invocation at a time.
+pub static LAMBDA_CONCURRENCY: Lazy<usize> = Lazy::new(|| {
+    env::var(environment::ENV_LLRT_LAMBDA_CONCURRENCY)
+        .ok()
+        .and_then(|concurrency| concurrency.parse().ok())
+        .unwrap_or(1)
+        .max(1)
+});
+
+pub struct InvocationContext {
+    pub request_id: String,
+    pub trace_id: Option<String>,
+}
+
+/// Tracks which invocation the code currently executing belongs to. When invocations
+/// are processed concurrently, promises are tagged with the invocation that created
+/// them so request ids and trace ids follow the handler across `await`.
+pub struct InvocationContexts<'js> {
+    concurrent: bool,
+    symbol: Value<'js>,
+    next_id: u32,
+    current: u32,
+    stack: Vec<u32>,
+    active: HashMap<u32, Rc<InvocationContext>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for InvocationContexts<'js> {
+    type Changed<'to> = InvocationContexts<'to>;
+}
+
+impl InvocationContexts<'_> {
+    fn switch(&mut self, id: u32) {
+        self.current = id;
+    }
+
+    fn get(&self, id: u32) -> Option<&Rc<InvocationContext>> {
+        if id == 0 {
+            return None;
+        }
+        self.active.get(&id)
+    }
+}
+
+pub fn init(ctx: &Ctx<'_>, concurrent: bool) -> Result<()> {
+    let symbol_ctor: Function = ctx.globals().get(PredefinedAtom::Symbol)?;
+    let symbol: Value = symbol_ctor.call(("invocationContext",))?;
+    let _ = ctx.store_userdata(RefCell::new(InvocationContexts {
+        concurrent,
+        symbol,
+        next_id: 0,
+        current: 0,
+        stack: Vec::new(),
+        active: HashMap::new(),
+    }));
+
+    // The trace id is read from the current invocation rather than written to the
+    // process environment, which is shared by all invocations
+    let env: Object = ctx.globals().get::<_, Object>("process")?.get("env")?;
+    env.prop(
+        ENV_X_AMZN_TRACE_ID,
+        Accessor::new_get(|ctx| {
+            struct Args<'js>(Ctx<'js>);
+            let Args(ctx) = Args(ctx);
+            Ok::<_, Error>(current(&ctx).and_then(|context| context.trace_id.clone()))
+        })
+        .configurable()
+        .enumerable(),
+    )?;
+    Ok(())
+}
+
+/// Returns true when invocations are processed concurrently.
+pub fn is_concurrent(ctx: &Ctx<'_>) -> bool {
+    ctx.userdata::<RefCell<InvocationContexts>>()
+        .is_some_and(|contexts| contexts.borrow().concurrent)
+}
+
+/// Returns the context of the invocation currently executing, if any.
+pub fn current(ctx: &Ctx<'_>) -> Option<Rc<InvocationContext>> {
+    let contexts = ctx.userdata::<RefCell<InvocationContexts>>()?;
+    let contexts = contexts.borrow();
+    contexts.get(contexts.current).cloned()
+}
+
+/// Registers a new invocation and returns its id. When processing one invocation at
+/// a time it replaces the previous invocation, which stays current until then.
+pub fn enter(ctx: &Ctx<'_>, context: InvocationContext) -> u32 {
+    let Some(contexts) = ctx.userdata::<RefCell<InvocationContexts>>() else {
+        return 0;
+    };
+    let mut contexts = contexts.borrow_mut();
+    contexts.next_id = contexts.next_id.checked_add(1).unwrap_or(1);
+    let id = contexts.next_id;
+    contexts.active.insert(id, Rc::new(context));
+    if !contexts.concurrent {
+        contexts.switch(id);
+        contexts.active.retain(|active_id, _| *active_id == id);
+    }
+    id
+}
+
+/// Runs `f` as invocation `id`, so promises created by it belong to the invocation.
+pub fn scope<R>(ctx: &Ctx<'_>, id: u32, f: impl FnOnce() -> R) -> R {
+    // Guards are not held while running `f` as they prevent storing other userdata
+    let previous = match ctx.userdata::<RefCell<InvocationContexts>>() {
+        Some(contexts) => {
+            let mut contexts = contexts.borrow_mut();
+            let previous = contexts.current;
+            contexts.switch(id);
+            previous
+        },
+        None => return f(),
+    };
+    let result = f();
+    if let Some(contexts) = ctx.userdata::<RefCell<InvocationContexts>>() {
+        let mut contexts = contexts.borrow_mut();
+        if contexts.concurrent {
+            contexts.switch(previous);
+        }
+    }
+    result
+}
+
+/// Polls `future` as invocation `id`, see [`scope`].
+pub fn scoped<'js, F: Future>(ctx: &Ctx<'js>, id: u32, future: F) -> Scoped<'js, F> {
+    Scoped {
+        ctx: ctx.clone(),
+        id,
+        future: Box::pin(future),
+    }
+}
+
+pub struct Scoped<'js, F> {
+    ctx: Ctx<'js>,
+    id: u32,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Scoped<'_, F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        scope(&this.ctx, this.id, || this.future.as_mut().poll(cx))
+    }
+}
+
+/// Removes a finished invocation. Only applies to concurrent processing as
+/// otherwise the invocation is replaced by the next one.
+pub fn exit(ctx: &Ctx<'_>, id: u32) {
+    if let Some(contexts) = ctx.userdata::<RefCell<InvocationContexts>>() {
+        let mut contexts = contexts.borrow_mut();
+        if contexts.concurrent {
+            if contexts.current == id {
+                contexts.switch(0);
+            }
+            contexts.active.remove(&id);
+        }
+    }
+}
+
+pub fn promise_hook() -> PromiseHook {
+    Box::new(
+        |ctx: Ctx<'_>, type_: PromiseHookType, promise: Value<'_>, parent: Value<'_>| {
+            let Some(contexts) = ctx.userdata::<RefCell<InvocationContexts>>() else {
+                return;
+            };
+            let Some(promise) = promise.into_object() else {
+                return;
+            };
+            let (symbol, current) = {
+                let contexts = contexts.borrow();
+                (contexts.symbol.clone(), contexts.current)
+            };
+
+            match type_ {
+                PromiseHookType::Init => {
+                    let id = if current != 0 {
+                        current
+                    } else {
+                        parent
+                            .as_object()
+                            .and_then(|parent| parent.get::<_, Option<u32>>(symbol.clone()).ok())
+                            .flatten()
+                            .unwrap_or_default()
+                    };
+                    if id != 0 {
+                        let _ = promise.prop(symbol, Property::from(id));
+                    }
+                },
+                PromiseHookType::Before => {
+                    let id: u32 = promise
+                        .get::<_, Option<u32>>(symbol)
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default();
+                    let mut contexts = contexts.borrow_mut();
+                    contexts.stack.push(current);
+                    contexts.switch(id);
+                },
+                PromiseHookType::After => {
+                    let mut contexts = contexts.borrow_mut();
+                    let previous = contexts.stack.pop().unwrap_or_default();
+                    contexts.switch(previous);
+                },
+                PromiseHookType::Resolve => {},
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use llrt_test::given_runtime;
+    use rquickjs::{async_with, prelude::Func, Ctx, Function, Promise};
+
+    use super::{enter, init, promise_hook, scoped, InvocationContext};
+
+    #[tokio::test]
+    async fn keeps_request_id_across_await() {
+        let (rt, ctx) = given_runtime().await;
+        rt.set_promise_hook(Some(promise_hook())).await;
+
+        async_with!(ctx => |ctx| {
+            ctx.eval::<(), _>("globalThis.process = { env: {} }").unwrap();
+            init(&ctx, true).unwrap();
+            ctx.globals()
+                .set(
+                    "requestId",
+                    Func::from(|ctx: Ctx<'_>| {
+                        super::current(&ctx)
+                            .map(|context| context.request_id.clone())
+                            .unwrap_or_else(|| String::from("n/a"))
+                    }),
+                )
+                .unwrap();
+
+            let handler: Function = ctx
+                .eval(
+                    r#"
+                    (async () => {
+                        const id = () => `${requestId()}/${process.env._X_AMZN_TRACE_ID}`;
+                        const ids = [id()];
+                        for (let i = 0; i < 3; i++) {
+                            await Promise.resolve();
+                            ids.push(id());
+                        }
+                        return ids.join(",");
+                    })
+                    "#,
+                )
+                .unwrap();
+
+            let invoke = |request_id: &str| {
+                let id = enter(
+                    &ctx,
+                    InvocationContext {
+                        request_id: request_id.into(),
+                        trace_id: Some(format!("trace-{request_id}")),
+                    },
+                );
+                let handler = handler.clone();
+                scoped(&ctx, id, async move {
+                    let promise: Promise = handler.call(()).unwrap();
+                    promise.into_future::<String>().await.unwrap()
+                })
+            };
+
+            let (a, b) = tokio::join!(invoke("a"), invoke("b"));
+            assert_eq!(a, "a/trace-a,a/trace-a,a/trace-a,a/trace-a");
+            assert_eq!(b, "b/trace-b,b/trace-b,b/trace-b,b/trace-b");
+            assert_eq!(super::current(&ctx).map(|c| c.request_id.clone()), None);
+        })
+        .await;
+    }
+}
@@ -0,0 +1,1240 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+#![allow(clippy::uninlined_format_args)]
+
+use std::{
+    env,
+    future::Future,
+    result::Result as StdResult,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::{
+    header::{HeaderMap, CONTENT_TYPE},
+    http::header::HeaderName,
+    Request, StatusCode,
+};
+use once_cell::sync::Lazy;
+use rquickjs::{
+    atom::PredefinedAtom,
+    function::Rest,
+    prelude::{Func, Opt, This},
+    promise::Promise,
+    qjs, Array, CatchResultExt, CaughtError, Class, Ctx, Exception, Function, IntoJs, Module,
+    Object, Result, Value,
+};
+use tokio::sync::mpsc;
+use tracing::info;
+use zstd::zstd_safe::WriteBuf;
+
+use crate::libs::{
+    json::{
+        parse::json_parse,
+        stringify::{self, json_stringify},
+    },
+    logging::{format_values, replace_newline_with_carriage_return},
+    utils::{class::get_class_name, result::ResultExt},
+};
+
+#[cfg(not(test))]
+use crate::modules::console::log_error;
+use crate::modules::{
+    abort::AbortSignal,
+    exceptions::{DOMException, DOMExceptionName},
+    fetch::{HyperClient, HTTP_CLIENT},
+};
+use crate::profiler;
+use crate::utils::latch::Latch;
+
+use self::invocation_context::{InvocationContext, LAMBDA_CONCURRENCY};
+
+mod callback;
+#[cfg(not(feature = "lambda"))]
+pub mod emulator;
+mod extension;
+pub mod invocation_context;
+mod response_stream;
+mod xray;
+
+const ENV_AWS_LAMBDA_FUNCTION_NAME: &str = "AWS_LAMBDA_FUNCTION_NAME";
+const ENV_AWS_LAMBDA_FUNCTION_VERSION: &str = "AWS_LAMBDA_FUNCTION_VERSION";
+const ENV_AWS_LAMBDA_FUNCTION_MEMORY_SIZE: &str = "AWS_LAMBDA_FUNCTION_MEMORY_SIZE";
+const ENV_AWS_LAMBDA_LOG_GROUP_NAME: &str = "AWS_LAMBDA_LOG_GROUP_NAME";
+const ENV_AWS_LAMBDA_LOG_STREAM_NAME: &str = "AWS_LAMBDA_LOG_STREAM_NAME";
+const ENV_LAMBDA_TASK_ROOT: &str = "LAMBDA_TASK_ROOT";
+const ENV_UNDERSCORE_HANDLER: &str = "_HANDLER";
+const ENV_LAMBDA_HANDLER: &str = "LAMBDA_HANDLER";
+const AWS_LAMBDA_RUNTIME_API: &str = "AWS_LAMBDA_RUNTIME_API";
+const ENV_UNDERSCORE_EXIT_ITERATIONS: &str = "_EXIT_ITERATIONS";
+const ENV_RUNTIME_PATH: &str = "2018-06-01/runtime";
+const ENV_X_AMZN_TRACE_ID: &str = "_X_AMZN_TRACE_ID";
+const ERROR_TYPE_TIMEOUT: &str = "Runtime.Timeout";
+/// Maximum nesting of `cause` and `AggregateError.errors` included when reporting errors
+const MAX_ERROR_DEPTH: usize = 8;
+
+/// How long before the deadline `context.signal` is aborted, leaving the handler time to clean up
+const DEADLINE_ABORT_MARGIN: Duration = Duration::from_millis(500);
+/// How long before the deadline a handler that hasn't settled is reported as timed out
+const DEADLINE_TIMEOUT_MARGIN: Duration = Duration::from_millis(50);
+
+static HEADER_TRACE_ID: HeaderName = HeaderName::from_static("lambda-runtime-trace-id");
+static HEADER_DEADLINE_MS: HeaderName = HeaderName::from_static("lambda-runtime-deadline-ms");
+static HEADER_REQUEST_ID: HeaderName = HeaderName::from_static("lambda-runtime-aws-request-id");
+static HEADER_ERROR_TYPE: HeaderName =
+    HeaderName::from_static("lambda-runtime-function-error-type");
+static HEADER_INVOKED_FUNCTION_ARN: HeaderName =
+    HeaderName::from_static("lambda-runtime-invoked-function-arn");
+static HEADER_CLIENT_CONTEXT: HeaderName = HeaderName::from_static("lambda-runtime-client-context");
+static HEADER_COGNITO_IDENTITY: HeaderName =
+    HeaderName::from_static("lambda-runtime-cognito-identity");
+
+pub struct SdkClientInitState {
+    rt: *mut qjs::JSRuntime,
+    latch: Arc<Latch>,
+    endpoints: Vec<Box<str>>, //we're likely to have a small number of clients
+}
+impl SdkClientInitState {
+    fn new(rt: *mut qjs::JSRuntime) -> Self {
+        Self {
+            rt,
+            latch: Latch::default().into(),
+            endpoints: Vec::new(),
+        }
+    }
+}
+
+unsafe impl Sync for SdkClientInitState {}
+unsafe impl Send for SdkClientInitState {}
+
+fn get_sdk_client_init_state_mut<'a>(
+    guard: &'a mut RwLockWriteGuard<Vec<SdkClientInitState>>,
+    rt: *mut qjs::JSRuntime,
+) -> &'a mut SdkClientInitState {
+    let state = guard.iter_mut().find(|state| state.rt == rt);
+
+    //save a branch
+    unsafe { state.unwrap_unchecked() }
+}
+
+fn get_sdk_client_init_state<'a>(
+    guard: &'a RwLockReadGuard<Vec<SdkClientInitState>>,
+    rt: *mut qjs::JSRuntime,
+) -> &'a SdkClientInitState {
+    let state = guard.iter().find(|state| state.rt == rt);
+
+    //save a branch
+    unsafe { state.unwrap_unchecked() }
+}
+
+static SDK_CONNECTION_INIT_LATCH: Lazy<RwLock<Vec<SdkClientInitState>>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+pub fn check_client_inited(rt: *mut qjs::JSRuntime, endpoint: &str) -> bool {
+    let mut write = SDK_CONNECTION_INIT_LATCH.write().unwrap();
+    let state = get_sdk_client_init_state_mut(&mut write, rt);
+    if state.endpoints.iter().any(|s| s.as_ref() == endpoint) {
+        return true;
+    }
+    state.endpoints.push(endpoint.into());
+    state.latch.increment();
+    false
+}
+
+pub fn mark_client_inited(rt: *mut qjs::JSRuntime) {
+    let mut write = SDK_CONNECTION_INIT_LATCH.write().unwrap();
+    let state = get_sdk_client_init_state_mut(&mut write, rt);
+    state.latch.decrement();
+}
+
+#[derive(Clone)]
+struct LambdaContext<'js, 'a> {
+    pub aws_request_id: String,
+    pub invoked_function_arn: String,
+    pub trace_id: Option<String>,
+    pub deadline_ms: i64,
+    pub signal: Class<'js, AbortSignal<'js>>,
+    pub callback_waits_for_empty_event_loop: bool,
+    pub get_remaining_time_in_millis: Function<'js>,
+    pub client_context: Value<'js>,
+    pub cognito_identity_json: Value<'js>,
+    pub lambda_environment: &'a LambdaEnvironment,
+}
+
+impl<'js> IntoJs<'js> for LambdaContext<'js, '_> {
+    fn into_js(self, ctx: &Ctx<'js>) -> Result<Value<'js>> {
+        let obj = Object::new(ctx.clone())?;
+        obj.set("awsRequestId", self.aws_request_id)?;
+        obj.set("invokedFunctionArn", self.invoked_function_arn)?;
+        obj.set("logGroupName", &self.lambda_environment.log_group_name)?;
+        obj.set("logStreamName", &self.lambda_environment.log_stream_name)?;
+        obj.set("functionName", &self.lambda_environment.function_name)?;
+        obj.set("functionVersion", &self.lambda_environment.function_version)?;
+        obj.set(
+            "memoryLimitInMB",
+            self.lambda_environment.memory_limit_in_mb,
+        )?;
+        obj.set(
+            "callbackWaitsForEmptyEventLoop",
+            self.callback_waits_for_empty_event_loop,
+        )?;
+        obj.set(
+            "getRemainingTimeInMillis",
+            self.get_remaining_time_in_millis,
+        )?;
+        obj.set("clientContext", self.client_context)?;
+        obj.set("cognitoIdentityJson", self.cognito_identity_json)?;
+        obj.set("signal", self.signal)?;
+        Ok(obj.into_value())
+    }
+}
+
+#[derive(Clone)]
+struct LambdaEnvironment {
+    pub log_group_name: String,
+    pub log_stream_name: String,
+    pub function_name: String,
+    pub function_version: String,
+    pub memory_limit_in_mb: usize,
+}
+
+impl LambdaEnvironment {
+    fn new() -> Self {
+        Self {
+            log_group_name: env::var(ENV_AWS_LAMBDA_LOG_GROUP_NAME).unwrap_or_default(),
+            log_stream_name: env::var(ENV_AWS_LAMBDA_LOG_STREAM_NAME).unwrap_or_default(),
+            function_name: env::var(ENV_AWS_LAMBDA_FUNCTION_NAME).unwrap_or_default(),
+            function_version: env::var(ENV_AWS_LAMBDA_FUNCTION_VERSION).unwrap_or_default(),
+            memory_limit_in_mb: env::var(ENV_AWS_LAMBDA_FUNCTION_MEMORY_SIZE)
+                .unwrap_or("128".into())
+                .parse()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+struct NextInvocationResponse<'js, 'a> {
+    event: Value<'js>,
+    context: LambdaContext<'js, 'a>,
+}
+
+struct RuntimeConfig {
+    runtime_api: String,
+    handler: String,
+    iterations: usize,
+    concurrency: usize,
+}
+
+impl RuntimeConfig {
+    fn default(ctx: &Ctx) -> Result<Self> {
+        Ok(Self {
+            runtime_api: env::var(AWS_LAMBDA_RUNTIME_API).map_err(|_| {
+                Exception::throw_message(
+                    ctx,
+                    concat!(
+                        "Environment variable ",
+                        stringify!(AWS_LAMBDA_RUNTIME_API),
+                        " is not defined.",
+                    ),
+                )
+            })?,
+            handler: env::var(ENV_LAMBDA_HANDLER)
+                .or_else(|_| env::var(ENV_UNDERSCORE_HANDLER))
+                .map_err(|_| {
+                    Exception::throw_message(
+                        ctx,
+                        concat!(
+                            "Environment variable ",
+                            stringify!(ENV_LAMBDA_HANDLER),
+                            " or ",
+                            stringify!(ENV_UNDERSCORE_HANDLER),
+                            " is not defined.",
+                        ),
+                    )
+                })?,
+            iterations: env::var(ENV_UNDERSCORE_EXIT_ITERATIONS)
+                .ok()
+                .and_then(|i| i.parse().ok())
+                .unwrap_or_default(),
+            concurrency: *LAMBDA_CONCURRENCY,
+        })
+    }
+}
+
+pub async fn start(ctx: &Ctx<'_>) -> Result<()> {
+    start_with_cfg(ctx, RuntimeConfig::default(ctx)?).await
+}
+
+/// Starts the runtime client against the given Runtime API endpoint instead of the
+/// one in the environment, stopping after `iterations` invocations (0 runs forever).
+pub async fn start_with_runtime_api(
+    ctx: &Ctx<'_>,
+    runtime_api: String,
+    handler: String,
+    iterations: usize,
+) -> Result<()> {
+    start_with_cfg(
+        ctx,
+        RuntimeConfig {
+            runtime_api,
+            handler,
+            iterations,
+            concurrency: *LAMBDA_CONCURRENCY,
+        },
+    )
+    .await
+}
+
+async fn start_with_cfg(ctx: &Ctx<'_>, config: RuntimeConfig) -> Result<()> {
+    let (module_name, handler_name) = get_module_and_handler_name(ctx, &config.handler)?;
+    let task_root = get_task_root();
+
+    let rt = unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) };
+    {
+        let mut state_ref = SDK_CONNECTION_INIT_LATCH.write().unwrap();
+        state_ref.push(SdkClientInitState::new(rt));
+    }
+
+    response_stream::init(ctx)?;
+    invocation_context::init(ctx, config.concurrency > 1)?;
+
+    let specifier: String = [task_root.as_str(), module_name].join("/");
+
+    let import_promise = Module::import(ctx, specifier.as_bytes())?;
+
+    let latch = {
+        let state_ref = SDK_CONNECTION_INIT_LATCH.read().unwrap();
+        get_sdk_client_init_state(&state_ref, rt).latch.clone()
+    };
+    latch.wait().await;
+
+    let js_handler_module = import_promise.into_future::<Object>().await?;
+
+    let handler: Value = js_handler_module.get(handler_name)?;
+
+    if !handler.is_function() {
+        return Err(Exception::throw_message(
+            ctx,
+            &[
+                "\"",
+                handler_name,
+                "\" is not a function in \"",
+                module_name,
+                "\"",
+            ]
+            .concat(),
+        ));
+    }
+
+    let client = HTTP_CLIENT.as_ref().or_throw(ctx)?.clone();
+
+    let base_url = ["http://", &config.runtime_api, "/", ENV_RUNTIME_PATH].concat();
+    let handler = handler.as_function().unwrap();
+    if let Err(err) = async {
+        let _extension = extension::register(ctx, &client, &config.runtime_api).await?;
+        start_process_events(ctx, &client, handler, base_url.as_str(), &config).await
+    }
+    .await
+    .catch(ctx)
+    {
+        post_error(ctx, &client, &base_url, "/init/error", &err, None).await?;
+    }
+    Ok(())
+}
+
+async fn next_invocation<'js, 'a>(
+    ctx: &Ctx<'js>,
+    client: &'a HyperClient,
+    uri: &str,
+    lambda_environment: &'a LambdaEnvironment,
+) -> Result<NextInvocationResponse<'js, 'a>> {
+    let req = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .header(CONTENT_TYPE, "application/json")
+        .body(BoxBody::new(Full::default()))
+        .or_throw(ctx)?;
+
+    let res = client.request(req).await.or_throw(ctx)?;
+
+    if res.status() != StatusCode::OK {
+        let res_bytes = res.collect().await.or_throw(ctx)?.to_bytes();
+        let res_str = String::from_utf8_lossy(res_bytes.as_slice());
+        return Err(Exception::throw_message(
+            ctx,
+            &["Unexpected /invocation/next response: ", &res_str].concat(),
+        ));
+    }
+
+    let headers = res.headers();
+
+    let trace_id = headers
+        .get(&HEADER_TRACE_ID)
+        .map(|trace_id| String::from_utf8_lossy(trace_id.as_bytes()).to_string());
+
+    let deadline_ms = get_header_value(headers, &HEADER_DEADLINE_MS)
+        .unwrap_or("0".into())
+        .parse::<i64>()
+        .or_throw(ctx)?;
+
+    let get_remaining_time_in_millis = Func::from(move || {
+        let now = Utc::now();
+        deadline_ms - now.timestamp_millis()
+    });
+    let get_remaining_time_in_millis = get_remaining_time_in_millis
+        .into_js(ctx)?
+        .into_function()
+        .unwrap();
+
+    let client_context = if let Some(json) = headers.get(&HEADER_CLIENT_CONTEXT) {
+        json_parse(ctx, json.as_bytes())
+    } else {
+        rquickjs::Undefined.into_js(ctx)
+    }?;
+    let cognito_identity_json = if let Some(json) = headers.get(&HEADER_COGNITO_IDENTITY) {
+        json_parse(ctx, json.as_bytes())
+    } else {
+        rquickjs::Undefined.into_js(ctx)
+    }?;
+    let context = LambdaContext {
+        aws_request_id: get_header_value(headers, &HEADER_REQUEST_ID).or_throw(ctx)?,
+        invoked_function_arn: get_header_value(headers, &HEADER_INVOKED_FUNCTION_ARN)
+            .unwrap_or("n/a".into()),
+        trace_id,
+        deadline_ms,
+        signal: Class::instance(ctx.clone(), AbortSignal::new())?,
+        callback_waits_for_empty_event_loop: true,
+        get_remaining_time_in_millis,
+        client_context,
+        cognito_identity_json,
+        lambda_environment,
+    };
+    let bytes = res.collect().await.or_throw(ctx)?.to_bytes();
+    let event: Value<'js> = json_parse(ctx, bytes)?;
+
+    Ok(NextInvocationResponse { event, context })
+}
+
+async fn invoke_response<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    base_url: &str,
+    request_id: &str,
+    result_json: Option<String>,
+) -> Result<()> {
+    let req = Request::builder()
+        .method("POST")
+        .uri([base_url, "/invocation/", request_id, "/response"].concat())
+        .header(CONTENT_TYPE, "application/json")
+        .body(BoxBody::new(Full::from(bytes::Bytes::from(
+            result_json.unwrap_or_default(),
+        ))))
+        .or_throw(ctx)?;
+
+    let res = client.request(req).await.or_throw(ctx)?;
+    match res.status() {
+        StatusCode::ACCEPTED => Ok(()),
+        _ => {
+            let res_bytes = res.collect().await.or_throw(ctx)?.to_bytes();
+            let res_str = String::from_utf8_lossy(res_bytes.as_slice());
+            Err(Exception::throw_message(
+                ctx,
+                &["Unexpected /invocation/response response: ", &res_str].concat(),
+            ))
+        },
+    }
+}
+
+// handler: (event: any, context: Context) => Promise<any>
+async fn start_process_events<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    handler: &Function<'js>,
+    base_url: &str,
+    config: &RuntimeConfig,
+) -> Result<()> {
+    if config.concurrency <= 1 {
+        return process_events(ctx, client, handler, base_url, config.iterations).await;
+    }
+
+    // Each worker polls /invocation/next on its own, handlers are interleaved on the event loop.
+    // Workers hold the context so they can't be joined as tokio tasks, they report through a
+    // channel instead so that the first failing worker stops the runtime client.
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    for _ in 0..config.concurrency {
+        let tx = tx.clone();
+        let ctx2 = ctx.clone();
+        let client = client.clone();
+        let handler = handler.clone();
+        let base_url = base_url.to_string();
+        let iterations = config.iterations;
+        ctx.spawn(async move {
+            let result = process_events(&ctx2, &client, &handler, &base_url, iterations).await;
+            let _ = tx.send(result);
+        });
+    }
+    drop(tx);
+    for _ in 0..config.concurrency {
+        match rx.recv().await {
+            Some(result) => result?,
+            None => {
+                return Err(Exception::throw_message(
+                    ctx,
+                    "Invocation worker stopped without reporting a result",
+                ))
+            },
+        }
+    }
+    Ok(())
+}
+
+async fn process_events<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    handler: &Function<'js>,
+    base_url: &str,
+    max_iterations: usize,
+) -> Result<()> {
+    let mut iterations = 0;
+    let next_invocation_url = [base_url, "/invocation/next"].concat();
+
+    let mut request_id = String::with_capacity(36); //length of uuid
+
+    let lambda_environment = LambdaEnvironment::new();
+
+    let promise_ctor: Value = ctx.globals().get(PredefinedAtom::Promise)?;
+
+    loop {
+        let now = Instant::now();
+
+        process_event(
+            ctx,
+            client,
+            handler,
+            base_url,
+            &next_invocation_url,
+            &mut request_id,
+            &lambda_environment,
+            &promise_ctor,
+        )
+        .await?;
+
+        if max_iterations > 0 {
+            if iterations >= max_iterations - 1 {
+                info!("Done in {:?}", now.elapsed().as_millis());
+                break;
+            }
+            iterations += 1;
+        }
+        request_id.clear();
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn process_event<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    handler: &Function<'js>,
+    base_url: &str,
+    next_invocation_url: &str,
+    request_id: &mut String,
+    lambda_environment: &LambdaEnvironment,
+    promise_constructor: &Value<'js>,
+) -> Result<()> {
+    let NextInvocationResponse { event, context } =
+        next_invocation(ctx, client, next_invocation_url, lambda_environment).await?;
+    request_id.clear();
+    request_id.push_str(&context.aws_request_id);
+
+    let invocation_id = invocation_context::enter(
+        ctx,
+        InvocationContext {
+            request_id: context.aws_request_id.clone(),
+            trace_id: context.trace_id.clone(),
+        },
+    );
+
+    let deadline = Deadline {
+        signal: context.signal.clone(),
+        deadline_ms: context.deadline_ms,
+    };
+    let result = invocation_context::scoped(
+        ctx,
+        invocation_id,
+        invoke_handler(
+            ctx,
+            client,
+            handler,
+            base_url,
+            request_id,
+            event,
+            context,
+            &deadline,
+            promise_constructor,
+        ),
+    )
+    .await;
+
+    invocation_context::exit(ctx, invocation_id);
+    // The execution environment may be frozen or shut down before the process exits
+    profiler::flush();
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn invoke_handler<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    handler: &Function<'js>,
+    base_url: &str,
+    request_id: &String,
+    event: Value<'js>,
+    context: LambdaContext<'js, '_>,
+    deadline: &Deadline<'js>,
+    promise_constructor: &Value<'js>,
+) -> Result<()> {
+    let js_context = context.into_js(ctx)?;
+
+    if response_stream::is_streaming_handler(ctx, handler)? {
+        return response_stream::invoke_streaming_response(
+            ctx,
+            client,
+            handler,
+            base_url,
+            request_id,
+            event,
+            js_context,
+            deadline,
+            promise_constructor,
+        )
+        .await;
+    }
+
+    let error_path = ["/invocation/", request_id, "/error"].concat();
+    let settled = deadline
+        .settle(
+            ctx,
+            callback::call_handler(
+                ctx,
+                handler,
+                event,
+                js_context.into_object().or_throw(ctx)?,
+                promise_constructor,
+            ),
+        )
+        .await?;
+
+    let err = match settled {
+        Settled::Ok(result) => match stringify::json_stringify(ctx, result) {
+            // Once the response has been sent, failures can't be reported as an invocation error
+            Ok(result_json) => {
+                return invoke_response(ctx, client, base_url, request_id, result_json).await
+            },
+            Err(err) => err,
+        },
+        Settled::Err(err) => err,
+        Settled::TimedOut(elapsed) => {
+            return post_timeout_error(ctx, client, base_url, &error_path, request_id, elapsed)
+                .await;
+        },
+    };
+    let err = CaughtError::from_error(ctx, err);
+    post_error(ctx, client, base_url, &error_path, &err, Some(request_id)).await
+}
+
+/// How a handler settled, see [`Deadline::settle`].
+enum Settled<'js> {
+    Ok(Value<'js>),
+    Err(rquickjs::Error),
+    TimedOut(Duration),
+}
+
+struct Deadline<'js> {
+    signal: Class<'js, AbortSignal<'js>>,
+    deadline_ms: i64,
+}
+
+impl<'js> Deadline<'js> {
+    /// Races the settlement of a handler against the deadline. Only the handler is raced,
+    /// sending its response is not subject to the deadline. Without a deadline, e.g. when
+    /// invoked locally, the handler may run indefinitely.
+    async fn settle(
+        &self,
+        ctx: &Ctx<'js>,
+        handler: impl Future<Output = Result<Value<'js>>>,
+    ) -> Result<Settled<'js>> {
+        let result = if self.deadline_ms > 0 {
+            tokio::select! {
+                result = handler => result,
+                timeout = await_deadline(ctx, &self.signal, self.deadline_ms) => {
+                    return Ok(Settled::TimedOut(timeout?));
+                },
+            }
+        } else {
+            handler.await
+        };
+        Ok(match result {
+            Ok(value) => Settled::Ok(value),
+            Err(err) => Settled::Err(err),
+        })
+    }
+}
+
+/// Aborts `signal` shortly before the deadline and resolves once the handler should be
+/// reported as timed out, with the time elapsed since the invocation started.
+async fn await_deadline<'js>(
+    ctx: &Ctx<'js>,
+    signal: &Class<'js, AbortSignal<'js>>,
+    deadline_ms: i64,
+) -> Result<Duration> {
+    let start = Instant::now();
+    let remaining =
+        Duration::from_millis((deadline_ms - Utc::now().timestamp_millis()).max(0) as u64);
+
+    tokio::time::sleep(remaining.saturating_sub(DEADLINE_ABORT_MARGIN)).await;
+    let reason = DOMException::new_with_name(
+        ctx,
+        DOMExceptionName::TimeoutError,
+        String::from("The invocation is about to time out"),
+    )?;
+    signal.borrow_mut().set_reason(Opt(Some(
+        Class::instance(ctx.clone(), reason)?.into_value(),
+    )));
+    AbortSignal::send_aborted(This(signal.clone()), ctx.clone())?;
+
+    let timeout = remaining.saturating_sub(DEADLINE_TIMEOUT_MARGIN);
+    tokio::time::sleep(timeout.saturating_sub(start.elapsed())).await;
+    Ok(start.elapsed())
+}
+
+async fn await_handler_result<'js>(
+    handler_result: Value<'js>,
+    promise_constructor: &Value<'js>,
+) -> Result<Value<'js>> {
+    match handler_result.as_object() {
+        Some(obj) if obj.is_instance_of(promise_constructor) => {
+            handler_result
+                .get::<Promise>()?
+                .into_future::<Value>()
+                .await
+        },
+        _ => Ok(handler_result),
+    }
+}
+
+async fn post_error<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    base_url: &str,
+    path: &str,
+    error: &CaughtError<'js>,
+    request_id: Option<&String>,
+) -> Result<()> {
+    let (error_type, error_object) = build_error_object(ctx, error, request_id)?;
+    let error_cause = xray::error_cause(ctx, error)?;
+    post_error_object(
+        ctx,
+        client,
+        base_url,
+        path,
+        &error_type,
+        error_object,
+        error_cause,
+    )
+    .await
+}
+
+async fn post_timeout_error<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    base_url: &str,
+    path: &str,
+    request_id: &str,
+    elapsed: Duration,
+) -> Result<()> {
+    let error_object = timeout_error_object(ctx, request_id, elapsed)?;
+    post_error_object(
+        ctx,
+        client,
+        base_url,
+        path,
+        ERROR_TYPE_TIMEOUT,
+        error_object,
+        None,
+    )
+    .await
+}
+
+fn timeout_error_object<'js>(
+    ctx: &Ctx<'js>,
+    request_id: &str,
+    elapsed: Duration,
+) -> Result<Value<'js>> {
+    let error_object = Object::new(ctx.clone())?;
+    error_object.set("errorType", ERROR_TYPE_TIMEOUT)?;
+    error_object.set(
+        "errorMessage",
+        format!("Task timed out after {:.2} seconds", elapsed.as_secs_f64()),
+    )?;
+    error_object.set("stackTrace", "")?;
+    error_object.set("requestId", request_id)?;
+    Ok(error_object.into_value())
+}
+
+async fn post_error_object<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    base_url: &str,
+    path: &str,
+    error_type: &str,
+    error_object: Value<'js>,
+    error_cause: Option<String>,
+) -> Result<()> {
+    #[cfg(not(test))]
+    {
+        log_error(ctx.clone(), Rest(vec![error_object.clone()]))?;
+    }
+
+    let error_body = json_stringify(ctx, error_object)?.unwrap_or_default();
+
+    let url = [base_url, path].concat();
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri(url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(&HEADER_ERROR_TYPE, error_type);
+    if let Some(error_cause) = error_cause {
+        req = req.header(&xray::HEADER_XRAY_ERROR_CAUSE, error_cause);
+    }
+    let req = req
+        .body(BoxBody::new(Full::from(bytes::Bytes::from(error_body))))
+        .or_throw(ctx)?;
+    let res = client.request(req).await.or_throw(ctx)?;
+    if res.status() != StatusCode::ACCEPTED {
+        let res_bytes = res.collect().await.or_throw(ctx)?.to_bytes();
+        let res_str = String::from_utf8_lossy(res_bytes.as_slice());
+        return Err(Exception::throw_message(
+            ctx,
+            &["Unexpected ", path, " response: ", &res_str].concat(),
+        ));
+    }
+    Ok(())
+}
+
+fn build_error_object<'js>(
+    ctx: &Ctx<'js>,
+    error: &CaughtError<'js>,
+    request_id: Option<&String>,
+) -> Result<(String, Value<'js>)> {
+    let error_object = Object::new(ctx.clone())?;
+    let error_type = match error {
+        CaughtError::Exception(ex) => set_exception_fields(ctx, &error_object, ex, 0)?,
+        CaughtError::Error(err) => {
+            set_error_fields(&error_object, "Error", format!("Error: {:?}", &err), None)?
+        },
+        CaughtError::Value(value) => {
+            let log_msg = format_values(ctx, Rest(vec![value.clone()]), false, true)
+                .unwrap_or(String::from("{unknown value}"));
+            set_error_fields(&error_object, "Error", ["Error: ", &log_msg].concat(), None)?
+        },
+    };
+    error_object.set("requestId", request_id.unwrap_or(&String::from("n/a")))?;
+
+    Ok((error_type, error_object.into_value()))
+}
+
+fn set_error_fields<'js>(
+    target: &Object<'js>,
+    error_type: &str,
+    error_msg: String,
+    error_stack: Option<String>,
+) -> Result<String> {
+    target.set("errorType", error_type)?;
+    target.set("errorMessage", error_msg)?;
+    target.set("stackTrace", error_stack.unwrap_or_default())?;
+    Ok(error_type.into())
+}
+
+/// Sets the fields of an error object for `ex`, including its `cause` and the `errors` of
+/// an `AggregateError`, nested up to [`MAX_ERROR_DEPTH`] levels deep.
+fn set_exception_fields<'js>(
+    ctx: &Ctx<'js>,
+    target: &Object<'js>,
+    ex: &Exception<'js>,
+    depth: usize,
+) -> Result<String> {
+    let error_name = exception_type(ex);
+    let error_msg = [&error_name, ": ", &ex.message().unwrap_or_default()].concat();
+    let error_stack = ex.stack().map(|mut stack| {
+        replace_newline_with_carriage_return(&mut stack);
+        stack
+    });
+    set_error_fields(target, &error_name, error_msg, error_stack)?;
+
+    if depth + 1 >= MAX_ERROR_DEPTH {
+        return Ok(error_name);
+    }
+
+    let cause: Value = ex.get("cause")?;
+    if !cause.is_undefined() {
+        target.set("cause", build_nested_error(ctx, cause, depth + 1)?)?;
+    }
+    if let Some(errors) = ex.get::<_, Value>(PredefinedAtom::Errors)?.into_array() {
+        let nested_errors = Array::new(ctx.clone())?;
+        for (index, error) in errors.iter::<Value>().enumerate() {
+            nested_errors.set(index, build_nested_error(ctx, error?, depth + 1)?)?;
+        }
+        target.set("errors", nested_errors)?;
+    }
+    Ok(error_name)
+}
+
+fn build_nested_error<'js>(ctx: &Ctx<'js>, value: Value<'js>, depth: usize) -> Result<Value<'js>> {
+    if let Some(ex) = value.as_exception() {
+        let error_object = Object::new(ctx.clone())?;
+        set_exception_fields(ctx, &error_object, ex, depth)?;
+        return Ok(error_object.into_value());
+    }
+    if value.is_object() {
+        // Arbitrary objects may not be serializable, e.g. when they are circular
+        let formatted = format_values(ctx, Rest(vec![value]), false, true)
+            .unwrap_or(String::from("{unknown value}"));
+        return formatted.into_js(ctx);
+    }
+    Ok(value)
+}
+
+fn exception_type(ex: &Exception<'_>) -> String {
+    get_class_name(ex)
+        .unwrap_or(None)
+        .unwrap_or(String::from("Error"))
+}
+
+fn get_module_and_handler_name<'a>(ctx: &Ctx, handler: &'a str) -> Result<(&'a str, &'a str)> {
+    handler
+        .rfind('.')
+        .and_then(|pos| {
+            let (module_name, handler_name) = handler.split_at(pos);
+            if !module_name.is_empty() && handler_name.len() > 1 {
+                //removes the dot and make sure the length is greater than 0
+                Some((module_name, &handler_name[1..]))
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| {
+            Exception::throw_message(
+                ctx,
+                &[
+                    "Invalid handler name or LAMBDA_HANDLER env value: \"",
+                    handler,
+                    "\": Should be in format {{filepath}}.{{method_name}}",
+                ]
+                .concat(),
+            )
+        })
+}
+
+fn get_task_root() -> String {
+    env::var(ENV_LAMBDA_TASK_ROOT).unwrap_or_else(|_| {
+        env::current_dir()
+            .ok()
+            .and_then(|path| path.into_os_string().into_string().ok())
+            .unwrap_or_else(|| "/".to_string())
+    })
+}
+
+fn get_header_value(headers: &HeaderMap, header: &HeaderName) -> StdResult<String, String> {
+    headers
+        .get(header)
+        .map(|h| String::from_utf8_lossy(h.as_bytes()).to_string())
+        .ok_or_else(|| ["Missing or invalid header: ", header.as_str()].concat())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{convert::Infallible, time::Duration};
+
+    use bytes::Bytes;
+    use http_body_util::{BodyExt, Full};
+    use hyper::{
+        body::Incoming,
+        header::{HeaderValue, CONTENT_TYPE},
+        server::conn::http1,
+        service::service_fn,
+        Request, Response, StatusCode,
+    };
+    use hyper_util::rt::TokioIo;
+    use rquickjs::{async_with, CatchResultExt};
+    use tokio::{net::TcpListener, sync::mpsc};
+    use wiremock::{matchers, Mock, MockServer, ResponseTemplate};
+
+    use crate::libs::encoding::bytes_from_b64;
+    use crate::runtime_client::{
+        self, response_stream::HEADER_ERROR_BODY, RuntimeConfig, ENV_RUNTIME_PATH,
+        HEADER_ERROR_TYPE, HEADER_INVOKED_FUNCTION_ARN, HEADER_REQUEST_ID,
+    };
+    use crate::vm::Vm;
+
+    #[tokio::test]
+    async fn runtime() {
+        let mock_server = MockServer::start().await;
+
+        fn uuid_v4() -> String {
+            let mut bytes = [0u8; 8];
+            for b in bytes.iter_mut() {
+                *b = rand::random();
+            }
+
+            format!(
+                "{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+                bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]
+            )
+        }
+
+        Mock::given(matchers::method("GET"))
+            .and(matchers::path("/"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(matchers::method("GET"))
+            .and(matchers::path(format!(
+                "{}/invocation/next",
+                ENV_RUNTIME_PATH
+            )))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header(&HEADER_REQUEST_ID, uuid_v4())
+                    .insert_header(&HEADER_INVOKED_FUNCTION_ARN, "n/a")
+                    .set_body_string(r#"{"hello": "world"}"#),
+            )
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path_regex(
+                r#"invocation/[A-z0-9-]{1,}/response$"#,
+            ))
+            .and(matchers::header(&CONTENT_TYPE, "application/json"))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path_regex(
+                r#"invocation/[A-z0-9-]{1,}/response$"#,
+            ))
+            .and(matchers::header(
+                "lambda-runtime-function-response-mode",
+                "streaming",
+            ))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path_regex(r#"invocation/[A-z0-9-]{1,}/error$"#))
+            .and(matchers::header(&CONTENT_TYPE, "application/json"))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/2020-01-01/extension/register"))
+            .and(matchers::header("lambda-extension-name", "llrt"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("lambda-extension-identifier", uuid_v4()),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(matchers::method("GET"))
+            .and(matchers::path("/2020-01-01/extension/event/next"))
+            .and(matchers::header_exists("lambda-extension-identifier"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_millis(10))
+                    .set_body_string(r#"{"eventType": "INVOKE", "deadlineMs": 0}"#),
+            )
+            .expect(1..)
+            .mount(&mock_server)
+            .await;
+
+        let runtime_api = format!("localhost:{}", mock_server.address().port());
+
+        let vm = Vm::new().await.unwrap();
+
+        async fn run_with_handler(vm: &Vm, handler: &str, runtime_api: &str) {
+            run_with_concurrency(vm, handler, runtime_api, 1).await
+        }
+
+        async fn run_with_concurrency(
+            vm: &Vm,
+            handler: &str,
+            runtime_api: &str,
+            concurrency: usize,
+        ) {
+            println!("Testing {} with concurrency {}", handler, concurrency);
+            let mock_config = RuntimeConfig {
+                runtime_api: runtime_api.into(),
+                handler: handler.into(),
+                iterations: 10,
+                concurrency,
+            };
+
+            async_with!(vm.ctx => |ctx|{
+                ctx.globals().set("__MOCK_ENDPOINT", ["http://",runtime_api].concat()).unwrap();
+                runtime_client::start_with_cfg(&ctx,mock_config).await.catch(&ctx).unwrap()
+            })
+            .await;
+        }
+
+        run_with_handler(&vm, "../fixtures/handler.handler", &runtime_api).await;
+        run_with_handler(&vm, "../fixtures/primitive-handler.handler", &runtime_api).await;
+        run_with_handler(&vm, "../fixtures/throwing-handler.handler", &runtime_api).await;
+        run_with_handler(&vm, "../fixtures/sdk-handler.handler", &runtime_api).await;
+        run_with_handler(&vm, "../fixtures/tla-webcall-handler.handler", &runtime_api).await;
+        run_with_handler(&vm, "../fixtures/cjs-handler.handler", &runtime_api).await;
+        run_with_handler(&vm, "../fixtures/streaming-handler.handler", &runtime_api).await;
+        run_with_handler(
+            &vm,
+            "../fixtures/throwing-streaming-handler.handler",
+            &runtime_api,
+        )
+        .await;
+        run_with_handler(&vm, "../fixtures/callback-handler.handler", &runtime_api).await;
+        run_with_handler(&vm, "../fixtures/callback-handler.fail", &runtime_api).await;
+        run_with_concurrency(&vm, "../fixtures/handler.handler", &runtime_api, 4).await;
+        run_with_concurrency(&vm, "../fixtures/throwing-handler.handler", &runtime_api, 4).await;
+        run_with_handler(&vm, "../fixtures/extension-handler.handler", &runtime_api).await;
+
+        vm.runtime.idle().await;
+    }
+
+    #[tokio::test]
+    async fn extension_without_invoke_subscription() {
+        let mock_server = MockServer::start().await;
+
+        // Delaying the invocation leaves time for the extension to poll before the runtime exits
+        Mock::given(matchers::method("GET"))
+            .and(matchers::path(format!(
+                "{}/invocation/next",
+                ENV_RUNTIME_PATH
+            )))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_millis(100))
+                    .insert_header(&HEADER_REQUEST_ID, "1")
+                    .set_body_string(r#"{"hello": "world"}"#),
+            )
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path_regex(
+                r#"invocation/[A-z0-9-]{1,}/response$"#,
+            ))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/2020-01-01/extension/register"))
+            .and(matchers::body_string(r#"{"events":[]}"#))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("lambda-extension-identifier", "1"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(matchers::method("GET"))
+            .and(matchers::path("/2020-01-01/extension/event/next"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(60)))
+            .mount(&mock_server)
+            .await;
+
+        let vm = Vm::new().await.unwrap();
+        let config = RuntimeConfig {
+            runtime_api: format!("localhost:{}", mock_server.address().port()),
+            handler: "../fixtures/shutdown-extension-handler.handler".into(),
+            iterations: 1,
+            concurrency: 1,
+        };
+        async_with!(vm.ctx => |ctx|{
+            runtime_client::start_with_cfg(&ctx, config).await.catch(&ctx).unwrap()
+        })
+        .await;
+
+        let requests = mock_server.received_requests().await.unwrap();
+        assert!(requests
+            .iter()
+            .any(|req| req.url.path() == "/2020-01-01/extension/event/next"));
+
+        vm.runtime.idle().await;
+    }
+
+    #[tokio::test]
+    async fn streaming_error() {
+        // wiremock drops request trailers, so the runtime API is served by hand here
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let runtime_api = listener.local_addr().unwrap().to_string();
+        let (response_tx, mut response_rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let response_tx = response_tx.clone();
+                let service = service_fn(move |req: Request<Incoming>| {
+                    let response_tx = response_tx.clone();
+                    async move {
+                        let mut res = Response::new(Full::<Bytes>::default());
+                        if req.uri().path().ends_with("/invocation/next") {
+                            res.headers_mut()
+                                .insert(&HEADER_REQUEST_ID, HeaderValue::from_static("1"));
+                            *res.body_mut() = Full::from(r#"{"hello": "world"}"#);
+                        } else {
+                            let collected = req.into_body().collect().await.unwrap();
+                            let trailers = collected.trailers().cloned();
+                            let _ = response_tx.send((collected.to_bytes(), trailers));
+                            *res.status_mut() = StatusCode::ACCEPTED;
+                        }
+                        Ok::<_, Infallible>(res)
+                    }
+                });
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+            }
+        });
+
+        let vm = Vm::new().await.unwrap();
+        let config = RuntimeConfig {
+            runtime_api,
+            handler: "../fixtures/throwing-streaming-handler.handler".into(),
+            iterations: 1,
+            concurrency: 1,
+        };
+        async_with!(vm.ctx => |ctx|{
+            runtime_client::start_with_cfg(&ctx, config).await.catch(&ctx).unwrap()
+        })
+        .await;
+
+        let (body, trailers) = response_rx.recv().await.unwrap();
+        assert_eq!(body.as_ref(), b"partial");
+
+        let trailers = trailers.expect("missing error trailers");
+        assert_eq!(trailers.get(&HEADER_ERROR_TYPE).unwrap(), "Error");
+        let error_body =
+            bytes_from_b64(trailers.get(&HEADER_ERROR_BODY).unwrap().as_bytes()).unwrap();
+        let error_body = String::from_utf8(error_body).unwrap();
+        assert!(error_body.contains(r#""errorMessage":"Error: kaboom""#));
+
+        vm.runtime.idle().await;
+    }
+}
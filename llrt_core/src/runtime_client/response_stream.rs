@@ -0,0 +1,399 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::{
+    convert::Infallible,
+    io,
+    pin::Pin,
+    result::Result as StdResult,
+    sync::{Arc, RwLock},
+    task::{ready, Context, Poll},
+};
+
+use bytes::Bytes;
+use http_body_util::{combinators::BoxBody, BodyExt};
+use hyper::{
+    body::{Body, Frame},
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE, TRAILER},
+    http::header::HeaderName,
+    Request, StatusCode,
+};
+use llrt_stream::{
+    impl_stream_events,
+    writable::{WritableStream, WritableStreamInner},
+};
+use rquickjs::{
+    atom::PredefinedAtom,
+    class::{Trace, Tracer},
+    prelude::{Func, Opt, This},
+    CaughtError, Class, Ctx, Exception, Function, IntoJs, JsLifetime, Object, Result, Value,
+};
+use tokio::{
+    io::AsyncWrite,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+
+use crate::libs::{
+    encoding::bytes_to_b64_string, json::stringify::json_stringify, utils::result::ResultExt,
+};
+#[cfg(not(test))]
+use crate::modules::console::log_error;
+use crate::modules::{
+    events::{Emitter, EventEmitter, EventList},
+    fetch::HyperClient,
+};
+#[cfg(not(test))]
+use rquickjs::function::Rest;
+
+use super::{
+    await_handler_result, build_error_object, timeout_error_object, Deadline, Settled,
+    ERROR_TYPE_TIMEOUT, HEADER_ERROR_TYPE,
+};
+
+const HANDLER_STREAMING_SYMBOL: &str = "aws.lambda.runtime.handler.streaming";
+const HANDLER_STREAMING_RESPONSE: &str = "response";
+
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+const HTTP_INTEGRATION_CONTENT_TYPE: &str = "application/vnd.awslambda.http-integration-response";
+const HTTP_INTEGRATION_DELIMITER: &str = "\0\0\0\0\0\0\0\0";
+
+static HEADER_RESPONSE_MODE: HeaderName =
+    HeaderName::from_static("lambda-runtime-function-response-mode");
+pub(super) static HEADER_ERROR_BODY: HeaderName =
+    HeaderName::from_static("lambda-runtime-function-error-body");
+
+#[rquickjs::class]
+pub struct ResponseStream<'js> {
+    emitter: EventEmitter<'js>,
+    writable_stream_inner: WritableStreamInner<'js>,
+    content_type: String,
+}
+
+unsafe impl<'js> JsLifetime<'js> for ResponseStream<'js> {
+    type Changed<'to> = ResponseStream<'to>;
+}
+
+impl<'js> Trace<'js> for ResponseStream<'js> {
+    fn trace<'a>(&self, tracer: Tracer<'a, 'js>) {
+        self.emitter.trace(tracer);
+    }
+}
+
+impl_stream_events!(ResponseStream);
+
+impl<'js> Emitter<'js> for ResponseStream<'js> {
+    fn get_event_list(&self) -> Arc<RwLock<EventList<'js>>> {
+        self.emitter.get_event_list()
+    }
+}
+
+impl<'js> WritableStream<'js> for ResponseStream<'js> {
+    fn inner_mut(&mut self) -> &mut WritableStreamInner<'js> {
+        &mut self.writable_stream_inner
+    }
+
+    fn inner(&self) -> &WritableStreamInner<'js> {
+        &self.writable_stream_inner
+    }
+}
+
+#[rquickjs::methods(rename_all = "camelCase")]
+impl<'js> ResponseStream<'js> {
+    pub fn write(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        value: Value<'js>,
+        cb: Opt<Function<'js>>,
+    ) -> Result<bool> {
+        //flush every chunk so that server-sent events are not held back by the buffer
+        WritableStream::write_flushed(this, ctx, value, cb)?;
+        Ok(true)
+    }
+
+    pub fn end(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        value: Opt<Value<'js>>,
+    ) -> Result<Class<'js, Self>> {
+        if let Some(value) = value.0 {
+            if let Some(cb) = value.as_function() {
+                Self::add_event_listener_str(
+                    This(this.clone()),
+                    &ctx,
+                    "finish",
+                    cb.clone(),
+                    false,
+                    true,
+                )?;
+            } else if !value.is_undefined() && !value.is_null() {
+                WritableStream::write_flushed(This(this.clone()), ctx, value, Opt(None))?;
+            }
+        }
+        Ok(WritableStream::end(this))
+    }
+
+    pub fn destroy(this: This<Class<'js, Self>>, error: Opt<Value<'js>>) -> Class<'js, Self> {
+        WritableStream::destroy(this, error)
+    }
+
+    pub fn set_content_type(&mut self, content_type: String) {
+        self.content_type = content_type;
+    }
+}
+
+impl<'js> ResponseStream<'js> {
+    fn new(ctx: Ctx<'js>) -> Result<Class<'js, Self>> {
+        let emitter = EventEmitter::new();
+        let writable_stream_inner = WritableStreamInner::new(emitter.clone(), true);
+
+        Class::instance(
+            ctx,
+            Self {
+                emitter,
+                writable_stream_inner,
+                content_type: DEFAULT_CONTENT_TYPE.into(),
+            },
+        )
+    }
+}
+
+// Forwards everything written to the response stream as body frames of the pending request
+struct ResponseWriter {
+    tx: UnboundedSender<Frame<Bytes>>,
+}
+
+impl AsyncWrite for ResponseWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(
+            self.tx
+                .send(Frame::data(Bytes::copy_from_slice(buf)))
+                .map(|_| buf.len())
+                .map_err(|_| io::ErrorKind::BrokenPipe.into()),
+        )
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+struct ResponseStreamBody {
+    first: Option<Frame<Bytes>>,
+    rx: UnboundedReceiver<Frame<Bytes>>,
+    done: bool,
+}
+
+impl Body for ResponseStreamBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<StdResult<Frame<Self::Data>, Self::Error>>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        let frame = match self.first.take() {
+            Some(frame) => Some(frame),
+            None => ready!(self.rx.poll_recv(cx)),
+        };
+        match frame {
+            Some(frame) => {
+                //trailers are always the last frame, anything written after an error is dropped
+                if frame.is_trailers() {
+                    self.done = true;
+                }
+                Poll::Ready(Some(Ok(frame)))
+            },
+            None => {
+                self.done = true;
+                Poll::Ready(None)
+            },
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done
+    }
+}
+
+fn streaming_symbol<'js>(ctx: &Ctx<'js>) -> Result<Value<'js>> {
+    let symbol_ctor: Object = ctx.globals().get(PredefinedAtom::Symbol)?;
+    let symbol_for: Function = symbol_ctor.get(PredefinedAtom::For)?;
+    symbol_for.call((HANDLER_STREAMING_SYMBOL,))
+}
+
+pub fn is_streaming_handler<'js>(ctx: &Ctx<'js>, handler: &Function<'js>) -> Result<bool> {
+    let mode: Option<String> = handler.get(streaming_symbol(ctx)?)?;
+    Ok(mode.as_deref() == Some(HANDLER_STREAMING_RESPONSE))
+}
+
+fn streamify_response<'js>(
+    ctx: Ctx<'js>,
+    handler: Function<'js>,
+    _options: Opt<Object<'js>>,
+) -> Result<Function<'js>> {
+    handler.set(streaming_symbol(&ctx)?, HANDLER_STREAMING_RESPONSE)?;
+    Ok(handler)
+}
+
+fn http_response_stream_from<'js>(
+    ctx: Ctx<'js>,
+    stream: Class<'js, ResponseStream<'js>>,
+    prelude: Opt<Value<'js>>,
+) -> Result<Class<'js, ResponseStream<'js>>> {
+    stream.borrow_mut().content_type = HTTP_INTEGRATION_CONTENT_TYPE.into();
+
+    let prelude = match prelude.0 {
+        Some(prelude) => json_stringify(&ctx, prelude)?,
+        None => None,
+    };
+    let prelude = [
+        prelude.as_deref().unwrap_or("{}"),
+        HTTP_INTEGRATION_DELIMITER,
+    ]
+    .concat();
+
+    WritableStream::write_flushed(
+        This(stream.clone()),
+        ctx.clone(),
+        prelude.into_js(&ctx)?,
+        Opt(None),
+    )?;
+
+    Ok(stream)
+}
+
+pub fn init(ctx: &Ctx<'_>) -> Result<()> {
+    ResponseStream::add_event_emitter_prototype(ctx)?;
+
+    let http_response_stream = Object::new(ctx.clone())?;
+    http_response_stream.set("from", Func::from(http_response_stream_from))?;
+
+    let awslambda = Object::new(ctx.clone())?;
+    awslambda.set("streamifyResponse", Func::from(streamify_response))?;
+    awslambda.set("HttpResponseStream", http_response_stream)?;
+
+    ctx.globals().set("awslambda", awslambda)?;
+
+    Ok(())
+}
+
+// handler: (event: any, responseStream: ResponseStream, context: Context) => Promise<void>
+#[allow(clippy::too_many_arguments)]
+pub async fn invoke_streaming_response<'js>(
+    ctx: &Ctx<'js>,
+    client: &HyperClient,
+    handler: &Function<'js>,
+    base_url: &str,
+    request_id: &str,
+    event: Value<'js>,
+    js_context: Value<'js>,
+    deadline: &Deadline<'js>,
+    promise_constructor: &Value<'js>,
+) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let stream = ResponseStream::new(ctx.clone())?;
+    let finished = ResponseStream::process(stream.clone(), ctx, ResponseWriter { tx: tx.clone() })?;
+
+    let handler_future = async {
+        let settled = deadline
+            .settle(ctx, async {
+                let handler_result =
+                    handler.call::<_, Value>((event, stream.clone(), js_context))?;
+                await_handler_result(handler_result, promise_constructor).await
+            })
+            .await?;
+
+        let error = match settled {
+            Settled::Ok(_) => None,
+            Settled::Err(err) => {
+                let err = CaughtError::from_error(ctx, err);
+                let request_id = request_id.to_string();
+                Some(build_error_object(ctx, &err, Some(&request_id))?)
+            },
+            //the response may have started already, so a timeout ends it like an error would
+            Settled::TimedOut(elapsed) => Some((
+                ERROR_TYPE_TIMEOUT.into(),
+                timeout_error_object(ctx, request_id, elapsed)?,
+            )),
+        };
+
+        //a settled handler always completes the response, even if it forgot to call end()
+        WritableStream::end(This(stream.clone()));
+
+        if let Some((error_type, error_object)) = error {
+            //everything written so far has to reach the body before the trailers end it
+            let _ = finished.await;
+
+            #[cfg(not(test))]
+            {
+                log_error(ctx.clone(), Rest(vec![error_object.clone()]))?;
+            }
+
+            let error_body = json_stringify(ctx, error_object)?.unwrap_or_default();
+
+            let mut trailers = HeaderMap::with_capacity(2);
+            trailers.insert(
+                &HEADER_ERROR_TYPE,
+                HeaderValue::from_str(&error_type).or_throw(ctx)?,
+            );
+            trailers.insert(
+                &HEADER_ERROR_BODY,
+                HeaderValue::from_str(&bytes_to_b64_string(error_body.as_bytes())).or_throw(ctx)?,
+            );
+
+            let _ = tx.send(Frame::trailers(trailers));
+        }
+        drop(tx);
+        Ok::<_, rquickjs::Error>(())
+    };
+
+    let response_future = async {
+        //headers can't be changed once the body has started, so wait for the first chunk
+        let first = rx.recv().await;
+        let content_type = stream.borrow().content_type.clone();
+
+        let req = Request::builder()
+            .method("POST")
+            .uri([base_url, "/invocation/", request_id, "/response"].concat())
+            .header(CONTENT_TYPE, content_type)
+            .header(&HEADER_RESPONSE_MODE, "streaming")
+            .header(
+                TRAILER,
+                [HEADER_ERROR_TYPE.as_str(), ", ", HEADER_ERROR_BODY.as_str()].concat(),
+            )
+            .body(BoxBody::new(ResponseStreamBody {
+                done: first.is_none(),
+                first,
+                rx,
+            }))
+            .or_throw(ctx)?;
+
+        let res = client.request(req).await.or_throw(ctx)?;
+        match res.status() {
+            StatusCode::ACCEPTED => Ok(()),
+            _ => {
+                let res_bytes = res.collect().await.or_throw(ctx)?.to_bytes();
+                let res_str = String::from_utf8_lossy(&res_bytes);
+                Err(Exception::throw_message(
+                    ctx,
+                    &["Unexpected /invocation/response response: ", &res_str].concat(),
+                ))
+            },
+        }
+    };
+
+    let (handler_result, response_result) = tokio::join!(handler_future, response_future);
+    handler_result?;
+    response_result
+}
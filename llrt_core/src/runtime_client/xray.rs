@@ -0,0 +1,167 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::{env, fmt::Write};
+
+use hyper::http::header::HeaderName;
+use rquickjs::{atom::PredefinedAtom, Array, CaughtError, Ctx, Exception, Object, Result, Value};
+
+use crate::libs::json::stringify::json_stringify;
+
+use super::{exception_type, MAX_ERROR_DEPTH};
+
+pub(super) static HEADER_XRAY_ERROR_CAUSE: HeaderName =
+    HeaderName::from_static("lambda-runtime-function-xray-error-cause");
+
+/// Causes larger than this are not reported, as they would exceed the header size limits
+const MAX_ERROR_CAUSE_SIZE: usize = 64 * 1024;
+
+/// Builds the X-Ray error cause of an uncaught exception, listing the exception along
+/// with its `cause` chain and the `errors` of an `AggregateError`.
+pub(super) fn error_cause<'js>(ctx: &Ctx<'js>, error: &CaughtError<'js>) -> Result<Option<String>> {
+    let CaughtError::Exception(ex) = error else {
+        return Ok(None);
+    };
+
+    let exceptions = Array::new(ctx.clone())?;
+    let mut paths = Vec::new();
+    add_exception(ctx, &exceptions, &mut paths, ex, 0)?;
+
+    let working_directory = env::current_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let cause = Object::new(ctx.clone())?;
+    cause.set("working_directory", working_directory)?;
+    cause.set("exceptions", exceptions)?;
+    cause.set("paths", paths)?;
+
+    let Some(cause) = json_stringify(ctx, cause.into_value())? else {
+        return Ok(None);
+    };
+    let cause = escape_non_ascii(cause);
+    Ok((cause.len() <= MAX_ERROR_CAUSE_SIZE).then_some(cause))
+}
+
+fn add_exception<'js>(
+    ctx: &Ctx<'js>,
+    exceptions: &Array<'js>,
+    paths: &mut Vec<String>,
+    ex: &Exception<'js>,
+    depth: usize,
+) -> Result<()> {
+    let stack = Array::new(ctx.clone())?;
+    for (index, frame) in ex
+        .stack()
+        .unwrap_or_default()
+        .lines()
+        .filter_map(parse_stack_frame)
+        .enumerate()
+    {
+        if !paths.iter().any(|path| path == frame.path) {
+            paths.push(frame.path.to_string());
+        }
+        let entry = Object::new(ctx.clone())?;
+        entry.set("path", frame.path)?;
+        entry.set("line", frame.line)?;
+        entry.set("label", frame.label)?;
+        stack.set(index, entry)?;
+    }
+
+    let exception = Object::new(ctx.clone())?;
+    exception.set("type", exception_type(ex))?;
+    exception.set("message", ex.message().unwrap_or_default())?;
+    exception.set("stack", stack)?;
+    exceptions.set(exceptions.len(), exception)?;
+
+    if depth + 1 >= MAX_ERROR_DEPTH {
+        return Ok(());
+    }
+
+    if let Some(cause) = ex.get::<_, Value>("cause")?.as_exception() {
+        add_exception(ctx, exceptions, paths, cause, depth + 1)?;
+    }
+    if let Some(errors) = ex.get::<_, Value>(PredefinedAtom::Errors)?.into_array() {
+        for error in errors.iter::<Value>() {
+            if let Some(error) = error?.as_exception() {
+                add_exception(ctx, exceptions, paths, error, depth + 1)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+struct StackFrame<'a> {
+    path: &'a str,
+    line: u32,
+    label: &'a str,
+}
+
+/// Parses a stack line such as `at handler (file:///var/task/index.mjs:3:11)`
+fn parse_stack_frame(line: &str) -> Option<StackFrame<'_>> {
+    let frame = line.trim().strip_prefix("at ")?;
+    let (label, location) = match frame.strip_suffix(')').and_then(|f| f.split_once(" (")) {
+        Some((label, location)) => (label, location),
+        None => ("anonymous", frame),
+    };
+    let location = location.strip_prefix("file://").unwrap_or(location);
+
+    // Locations are either `path:line:column` or `path:line`
+    let mut parts = location.rsplitn(3, ':');
+    let last = parts.next()?;
+    let (path, line) = match (parts.next(), parts.next()) {
+        (Some(line), Some(path)) if line.parse::<u32>().is_ok() => (path, line),
+        (Some(path), _) => (path, last),
+        _ => return None,
+    };
+
+    Some(StackFrame {
+        path,
+        line: line.parse().ok()?,
+        label,
+    })
+}
+
+/// Header values can only contain visible ASCII characters
+fn escape_non_ascii(json: String) -> String {
+    if json.bytes().all(|byte| byte.is_ascii() && byte != 0x7f) {
+        return json;
+    }
+    let mut escaped = String::with_capacity(json.len() + 16);
+    for char in json.chars() {
+        if char.is_ascii() && char != '\x7f' {
+            escaped.push(char);
+            continue;
+        }
+        for unit in char.encode_utf16(&mut [0; 2]) {
+            let _ = write!(escaped, "\\u{:04x}", unit);
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_non_ascii, parse_stack_frame};
+
+    #[test]
+    fn parses_stack_frames() {
+        let frame = parse_stack_frame("    at handler (file:///var/task/index.mjs:3:11)").unwrap();
+        assert_eq!(frame.path, "/var/task/index.mjs");
+        assert_eq!(frame.line, 3);
+        assert_eq!(frame.label, "handler");
+
+        let frame = parse_stack_frame("    at /var/task/index.js:12").unwrap();
+        assert_eq!(frame.path, "/var/task/index.js");
+        assert_eq!(frame.line, 12);
+        assert_eq!(frame.label, "anonymous");
+
+        assert!(parse_stack_frame("    at map (native)").is_none());
+        assert!(parse_stack_frame("Error: kaboom").is_none());
+    }
+
+    #[test]
+    fn escapes_non_ascii() {
+        assert_eq!(escape_non_ascii(r#"{"a":"b"}"#.into()), r#"{"a":"b"}"#);
+        assert_eq!(escape_non_ascii("é😀".into()), r"\u00e9\ud83d\ude00");
+    }
+}
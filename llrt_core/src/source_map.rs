@@ -0,0 +1,424 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    env, fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use once_cell::sync::Lazy;
+use rquickjs::{function::This, Array, Ctx, FromJs, Function, Object, Result, Value};
+use simd_json::{prelude::Writable, BorrowedValue};
+use tracing::trace;
+
+use crate::environment::ENV_LLRT_SOURCE_MAPS;
+use crate::libs::encoding::bytes_from_b64;
+use crate::modules::path::{dirname, is_absolute, join_path_with_separator};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Source maps embedded in bytecode archives, by module name
+static EMBEDDED_SOURCE_MAPS: Lazy<Mutex<HashMap<String, Vec<u8>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Source maps parsed so far, by file name. Files without a source map are cached as `None`.
+static SOURCE_MAPS: Lazy<Mutex<HashMap<String, Option<Arc<SourceMap>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Rewrites stack traces to original locations when `LLRT_SOURCE_MAPS` is enabled. Source
+/// maps are only loaded when a stack trace is created.
+pub fn init(ctx: &Ctx<'_>) -> Result<()> {
+    let enabled = env::var(ENV_LLRT_SOURCE_MAPS).unwrap_or_default();
+    if enabled != "1" && enabled != "true" {
+        return Ok(());
+    }
+    ENABLED.store(true, Ordering::Relaxed);
+
+    let error: Object = ctx.globals().get("Error")?;
+    error.set(
+        "prepareStackTrace",
+        Function::new(ctx.clone(), prepare_stack_trace)?,
+    )?;
+    Ok(())
+}
+
+/// Makes the source map of an archive module available to stack traces.
+pub fn register(name: &str, source_map: Vec<u8>) {
+    if ENABLED.load(Ordering::Relaxed) {
+        EMBEDDED_SOURCE_MAPS
+            .lock()
+            .unwrap()
+            .insert(name.into(), source_map);
+    }
+}
+
+// Formats frames like QuickJS does, mapping their locations to the original sources
+fn prepare_stack_trace<'js>(_error: Value<'js>, call_sites: Array<'js>) -> Result<String> {
+    let mut stack = String::with_capacity(call_sites.len() * 64);
+    for call_site in call_sites.iter::<Object>() {
+        let call_site = call_site?;
+        let function_name: Option<String> = call_method(&call_site, "getFunctionName")?;
+        let function_name = function_name.as_deref().unwrap_or("<anonymous>");
+
+        if call_method(&call_site, "isNative")? {
+            stack.push_str("    at ");
+            stack.push_str(function_name);
+            stack.push_str(" (native)\n");
+            continue;
+        }
+
+        let file_name: Option<String> = call_method(&call_site, "getFileName")?;
+        let mut file_name = file_name.unwrap_or_else(|| "<null>".into());
+        let mut line: i32 = call_method(&call_site, "getLineNumber")?;
+        let mut column: i32 = call_method(&call_site, "getColumnNumber")?;
+
+        if line > 0 && column > 0 {
+            if let Some((source, source_line, source_column)) =
+                map_location(&file_name, line as u32, column as u32)
+            {
+                file_name = source;
+                line = source_line as i32;
+                column = source_column as i32;
+            }
+        }
+
+        let location = if line != -1 {
+            [
+                file_name.as_str(),
+                ":",
+                &line.to_string(),
+                ":",
+                &column.to_string(),
+            ]
+            .concat()
+        } else {
+            file_name
+        };
+
+        // The location of a syntax error has no function
+        let function: Value = call_method(&call_site, "getFunction")?;
+        stack.push_str("    at ");
+        if function.is_null() {
+            stack.push_str(&location);
+        } else {
+            stack.push_str(function_name);
+            stack.push_str(" (");
+            stack.push_str(&location);
+            stack.push(')');
+        }
+        stack.push('\n');
+    }
+    Ok(stack)
+}
+
+/// Maps a 1-based location in a generated file to its original source, if source maps are
+/// enabled and the file has one.
+pub fn map_location(file_name: &str, line: u32, column: u32) -> Option<(String, u32, u32)> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    lookup_location(file_name, line, column)
+}
+
+/// Maps a 1-based location in a generated file to its original source, if the file has a
+/// source map, whether or not `LLRT_SOURCE_MAPS` is enabled.
+pub fn lookup_location(file_name: &str, line: u32, column: u32) -> Option<(String, u32, u32)> {
+    if line == 0 || column == 0 {
+        return None;
+    }
+    let source_map = get_source_map(file_name)?;
+    let location = source_map.lookup(line - 1, column - 1)?;
+    Some((
+        source_map.sources[location.source].clone(),
+        location.line + 1,
+        location.column + 1,
+    ))
+}
+
+fn call_method<'js, T: FromJs<'js>>(object: &Object<'js>, name: &str) -> Result<T> {
+    object
+        .get::<_, Function>(name)?
+        .call((This(object.clone()),))
+}
+
+fn get_source_map(file_name: &str) -> Option<Arc<SourceMap>> {
+    let mut source_maps = SOURCE_MAPS.lock().unwrap();
+    if let Some(source_map) = source_maps.get(file_name) {
+        return source_map.clone();
+    }
+
+    let embedded = EMBEDDED_SOURCE_MAPS.lock().unwrap().remove(file_name);
+    let source_map = match embedded {
+        Some(json) => SourceMap::parse(json, ""),
+        None => {
+            let path = file_name.strip_prefix("file://").unwrap_or(file_name);
+            fs::read(path)
+                .ok()
+                .and_then(|source| read_source_map(path, &source))
+                .and_then(|(json, base)| SourceMap::parse(json, &base))
+        },
+    }
+    .map(Arc::new);
+
+    trace!(
+        "Source map for {}: {}",
+        file_name,
+        if source_map.is_some() {
+            "found"
+        } else {
+            "none"
+        }
+    );
+    source_maps.insert(file_name.into(), source_map.clone());
+    source_map
+}
+
+/// Reads the source map of a file from its `sourceMappingURL` comment, which can be a data
+/// URL or a path relative to the file, or else from an adjacent `.map` file. Returns the
+/// source map along with the directory its sources are relative to.
+pub fn read_source_map(path: &str, source: &[u8]) -> Option<(Vec<u8>, String)> {
+    let dir = dirname(path);
+    let Some(url) = find_source_mapping_url(source) else {
+        let map_path = [path, ".map"].concat();
+        return fs::read(&map_path).ok().map(|json| (json, dir));
+    };
+
+    if let Some(data) = url.strip_prefix("data:") {
+        let (media_type, data) = data.split_once(',')?;
+        let json = if media_type.ends_with(";base64") {
+            bytes_from_b64(data.as_bytes()).ok()?
+        } else {
+            data.as_bytes().to_vec()
+        };
+        return Some((json, dir));
+    }
+
+    let url = url.strip_prefix("file://").unwrap_or(url);
+    let map_path = if is_absolute(url) {
+        url.to_string()
+    } else {
+        join_path_with_separator([dir.as_str(), url], true)
+    };
+    let json = fs::read(&map_path).ok()?;
+    Some((json, dirname(map_path)))
+}
+
+/// Reads the source map of a module being compiled, resolving its sources so that it can
+/// be embedded in bytecode.
+pub fn read_embeddable_source_map(path: &Path, source: &[u8]) -> Option<Vec<u8>> {
+    let path = fs::canonicalize(path).ok()?;
+    let (mut json, base) = read_source_map(&path.to_string_lossy(), source)?;
+    let source_map = SourceMap::parse(json.clone(), &base)?;
+
+    let mut value = simd_json::to_borrowed_value(&mut json).ok()?;
+    let BorrowedValue::Object(map) = &mut value else {
+        return None;
+    };
+    map.remove("sourceRoot");
+    map.remove("sourcesContent");
+    map.insert(
+        "sources".into(),
+        BorrowedValue::Array(Box::new(
+            source_map
+                .sources
+                .into_iter()
+                .map(|source| BorrowedValue::String(source.into()))
+                .collect(),
+        )),
+    );
+    Some(value.encode().into_bytes())
+}
+
+fn find_source_mapping_url(source: &[u8]) -> Option<&str> {
+    let source = std::str::from_utf8(source).ok()?;
+    source.lines().rev().find_map(|line| {
+        let line = line.trim();
+        line.strip_prefix("//# sourceMappingURL=")
+            .or_else(|| line.strip_prefix("//@ sourceMappingURL="))
+            .map(str::trim)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Mapping {
+    generated_column: u32,
+    source: usize,
+    line: u32,
+    column: u32,
+}
+
+#[derive(Debug)]
+struct SourceMap {
+    sources: Vec<String>,
+    /// Mappings of each generated line, sorted by column
+    lines: Vec<Vec<Mapping>>,
+}
+
+impl SourceMap {
+    fn parse(mut json: Vec<u8>, base: &str) -> Option<Self> {
+        let value = simd_json::to_borrowed_value(&mut json).ok()?;
+        let BorrowedValue::Object(map) = value else {
+            return None;
+        };
+        let source_root = match map.get("sourceRoot") {
+            Some(BorrowedValue::String(root)) => root.as_ref(),
+            _ => "",
+        };
+        let Some(BorrowedValue::Array(sources)) = map.get("sources") else {
+            return None;
+        };
+        let sources = sources
+            .iter()
+            .map(|source| match source {
+                BorrowedValue::String(source) => resolve_source(base, source_root, source),
+                _ => String::new(),
+            })
+            .collect::<Vec<_>>();
+        let Some(BorrowedValue::String(mappings)) = map.get("mappings") else {
+            return None;
+        };
+        let lines = decode_mappings(mappings, sources.len())?;
+        Some(Self { sources, lines })
+    }
+
+    fn lookup(&self, line: u32, column: u32) -> Option<Mapping> {
+        let mappings = self.lines.get(line as usize)?;
+        let index = mappings.partition_point(|mapping| mapping.generated_column <= column);
+        mappings.get(index.checked_sub(1)?).copied()
+    }
+}
+
+fn resolve_source(base: &str, source_root: &str, source: &str) -> String {
+    if let Some(path) = source.strip_prefix("file://") {
+        return path.into();
+    }
+    // Sources with another scheme, such as `webpack://`, are kept as is
+    if is_absolute(source) || source.contains("://") {
+        return source.into();
+    }
+    let source = if source_root.is_empty() {
+        Cow::Borrowed(source)
+    } else {
+        Cow::Owned([source_root.trim_end_matches('/'), "/", source].concat())
+    };
+    if base.is_empty() || is_absolute(&source) || source.contains("://") {
+        return source.into_owned();
+    }
+    join_path_with_separator([base, source.as_ref()], true)
+}
+
+/// Decodes the base64 VLQ `mappings` of a source map, skipping segments without a source.
+fn decode_mappings(mappings: &str, source_count: usize) -> Option<Vec<Vec<Mapping>>> {
+    let mut lines = Vec::with_capacity(mappings.len() / 16);
+    let mut source = 0i64;
+    let mut line = 0i64;
+    let mut column = 0i64;
+
+    for generated_line in mappings.split(';') {
+        let mut generated_column = 0i64;
+        let mut segments = Vec::new();
+        for segment in generated_line.split(',').filter(|s| !s.is_empty()) {
+            let mut input = segment.as_bytes();
+            generated_column += decode_vlq(&mut input)?;
+            if input.is_empty() {
+                continue;
+            }
+            source += decode_vlq(&mut input)?;
+            line += decode_vlq(&mut input)?;
+            column += decode_vlq(&mut input)?;
+            // The name of the segment is not used
+            if generated_column < 0 || line < 0 || column < 0 || source < 0 {
+                return None;
+            }
+            if (source as usize) < source_count {
+                segments.push(Mapping {
+                    generated_column: generated_column as u32,
+                    source: source as usize,
+                    line: line as u32,
+                    column: column as u32,
+                });
+            }
+        }
+        segments.sort_by_key(|mapping| mapping.generated_column);
+        lines.push(segments);
+    }
+    Some(lines)
+}
+
+fn decode_vlq(input: &mut &[u8]) -> Option<i64> {
+    let mut value = 0i64;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = input.split_first()?;
+        *input = rest;
+        let digit = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        } as i64;
+        value |= (digit & 31) << shift;
+        if digit & 32 == 0 {
+            break;
+        }
+        shift += 5;
+        if shift > 60 {
+            return None;
+        }
+    }
+    Some(if value & 1 == 1 {
+        -(value >> 1)
+    } else {
+        value >> 1
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_vlq, find_source_mapping_url, Mapping, SourceMap};
+
+    #[test]
+    fn decodes_vlq() {
+        let decode = |value: &str| decode_vlq(&mut value.as_bytes());
+        assert_eq!(decode("A"), Some(0));
+        assert_eq!(decode("C"), Some(1));
+        assert_eq!(decode("D"), Some(-1));
+        assert_eq!(decode("gB"), Some(16));
+        assert_eq!(decode("2H"), Some(123));
+        assert_eq!(decode("g"), None);
+    }
+
+    #[test]
+    fn looks_up_original_locations() {
+        let json = br#"{"version":3,"sourceRoot":"src","sources":["a.ts"],"names":[],"mappings":"AAIA;AACA,IAAE;;AAEF"}"#;
+        let source_map = SourceMap::parse(json.to_vec(), "/app").unwrap();
+
+        assert_eq!(source_map.sources, vec!["/app/src/a.ts"]);
+        let mapping = |generated_column, line, column| Mapping {
+            generated_column,
+            source: 0,
+            line,
+            column,
+        };
+        assert_eq!(source_map.lookup(0, 10), Some(mapping(0, 4, 0)));
+        assert_eq!(source_map.lookup(1, 2), Some(mapping(0, 5, 0)));
+        assert_eq!(source_map.lookup(1, 12), Some(mapping(4, 5, 2)));
+        assert_eq!(source_map.lookup(2, 0), None);
+        assert_eq!(source_map.lookup(3, 0), Some(mapping(0, 7, 0)));
+        assert_eq!(source_map.lookup(9, 0), None);
+    }
+
+    #[test]
+    fn finds_source_mapping_url() {
+        let source = b"export {};\n//# sourceMappingURL=index.js.map\n";
+        assert_eq!(find_source_mapping_url(source), Some("index.js.map"));
+        assert_eq!(find_source_mapping_url(b"export {};\n"), None);
+    }
+}
@@ -4,8 +4,8 @@ use std::{env, result::Result as StdResult};
 
 use ring::rand::SecureRandom;
 use rquickjs::{
-    context::EvalOptions, loader::FileResolver, prelude::Func, AsyncContext, AsyncRuntime,
-    CatchResultExt, Ctx, Error, Result, Value,
+    context::EvalOptions, loader::FileResolver, prelude::Func, promise::PromiseHookType,
+    runtime::PromiseHook, AsyncContext, AsyncRuntime, CatchResultExt, Ctx, Error, Result, Value,
 };
 
 use crate::libs::{
@@ -20,14 +20,17 @@ use crate::libs::{
         time,
     },
 };
+#[cfg(not(feature = "lambda"))]
+use crate::modules::llrt::mock::{MockLoader, MockResolver};
 use crate::modules::{
     async_hooks::promise_hook_tracker,
     crypto::SYSTEM_RANDOM,
-    embedded::{loader::EmbeddedLoader, resolver::EmbeddedResolver},
+    embedded::{archive::ArchiveResolver, loader::EmbeddedLoader, resolver::EmbeddedResolver},
     module_builder::ModuleBuilder,
     require::{loader::NpmJsLoader, resolver::NpmJsResolver},
 };
-use crate::{environment, http, security};
+use crate::runtime_client::{self, invocation_context::LAMBDA_CONCURRENCY};
+use crate::{environment, http, security, source_map};
 
 pub struct Vm {
     pub runtime: AsyncRuntime,
@@ -47,7 +50,9 @@ impl Default for VmOptions {
             .with_global(crate::modules::embedded::init)
             .with_global(crate::modules::module::init)
             .with_module(crate::modules::module::ModuleModule)
+            .with_module(crate::modules::llrt::heap::LlrtHeapModule)
             .with_module(crate::modules::llrt::hex::LlrtHexModule)
+            .with_module(crate::modules::llrt::lambda::LlrtLambdaModule)
             .with_module(crate::modules::llrt::util::LlrtUtilModule)
             .with_module(crate::modules::llrt::xml::LlrtXmlModule);
 
@@ -58,6 +63,12 @@ impl Default for VmOptions {
                 .with_module(crate::modules::console::ConsoleModule);
         }
 
+        // Module mocks are only used by the test runner, which isn't part of Lambda builds
+        #[cfg(not(feature = "lambda"))]
+        {
+            module_builder = module_builder.with_module(crate::modules::llrt::mock::LlrtMockModule);
+        }
+
         Self {
             module_builder,
             max_stack_size: 512 * 1024,
@@ -113,10 +124,13 @@ impl Vm {
         let resolver = (
             module_resolver,
             EmbeddedResolver,
+            ArchiveResolver,
             NpmJsResolver,
             file_resolver,
         );
         let loader = (module_loader, EmbeddedLoader, NpmJsLoader);
+        #[cfg(not(feature = "lambda"))]
+        let (resolver, loader) = ((MockResolver, resolver), (MockLoader, loader));
 
         let runtime = AsyncRuntime::new()?;
         runtime.set_max_stack_size(vm_options.max_stack_size).await;
@@ -137,8 +151,23 @@ impl Vm {
         })
         .await?;
 
-        if HOOKING_MODE.to_owned() {
-            runtime.set_promise_hook(Some(promise_hook_tracker())).await;
+        let async_hooks = HOOKING_MODE.then(promise_hook_tracker);
+        let invocation_context =
+            (*LAMBDA_CONCURRENCY > 1).then(runtime_client::invocation_context::promise_hook);
+        let promise_hook: Option<PromiseHook> = match (async_hooks, invocation_context) {
+            (Some(async_hooks), Some(invocation_context)) => Some(Box::new(
+                move |ctx: Ctx<'_>,
+                      type_: PromiseHookType,
+                      promise: Value<'_>,
+                      parent: Value<'_>| {
+                    invocation_context(ctx.clone(), type_, promise.clone(), parent.clone());
+                    async_hooks(ctx, type_, promise, parent);
+                },
+            )),
+            (async_hooks, invocation_context) => async_hooks.or(invocation_context),
+        };
+        if promise_hook.is_some() {
+            runtime.set_promise_hook(promise_hook).await;
         }
 
         Ok(Vm { runtime, ctx })
@@ -221,6 +250,7 @@ fn init(ctx: &Ctx<'_>) -> Result<()> {
 
     numbers::redefine_prototype(ctx)?;
     json::redefine_static_methods(ctx)?;
+    source_map::init(ctx)?;
 
     Ok(())
 }
@@ -0,0 +1,50 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// `llrt --watch` runs programs in a child process and restarts them when one of the files they
+// loaded changes. Children append the files resolved by the module loader, with the module that
+// imported them, to the report file named by this environment variable.
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    sync::Mutex,
+};
+
+use once_cell::sync::Lazy;
+
+use crate::modules::require::CJS_LOADER_PREFIX;
+
+pub const ENV_LLRT_WATCH_REPORT: &str = "__LLRT_WATCH_REPORT";
+
+static REPORT: Lazy<Option<Mutex<File>>> = Lazy::new(|| {
+    let path = env::var(ENV_LLRT_WATCH_REPORT).ok()?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .ok()
+        .map(Mutex::new)
+});
+
+/// Reports that `importer` resolved the file at `path`, when running under `llrt --watch`.
+pub fn record_dependency(importer: &str, path: &str) {
+    let Some(report) = REPORT.as_ref() else {
+        return;
+    };
+    // Builtin and embedded modules are not files
+    let path = path.trim_start_matches(CJS_LOADER_PREFIX);
+    let Ok(path) = fs::canonicalize(path) else {
+        return;
+    };
+    if !path.is_file() {
+        return;
+    }
+    // Entry points are imported from scripts, which are not files either
+    let importer = fs::canonicalize(importer)
+        .map(|importer| importer.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| importer.into());
+    // One write per line, so that lines of concurrent test workers are not interleaved
+    let line = [importer.as_str(), "\t", &path.to_string_lossy(), "\n"].concat();
+    let _ = report.lock().unwrap().write_all(line.as_bytes());
+}
@@ -6,27 +6,51 @@ use llrt_utils::result::ResultExt;
 use rquickjs::{prelude::Opt, Ctx, Exception, Result};
 use tokio::fs;
 
+use crate::vfs;
+
 #[allow(dead_code, unused_imports)]
 use super::{CONSTANT_F_OK, CONSTANT_R_OK, CONSTANT_W_OK, CONSTANT_X_OK};
 
 pub async fn access(ctx: Ctx<'_>, path: String, mode: Opt<u32>) -> Result<()> {
-    let metadata = fs::metadata(&path).await.or_throw_msg(
-        &ctx,
-        &["No such file or directory \"", &path, "\""].concat(),
-    )?;
+    if vfs::is_file(&path) {
+        return verify_asset(&ctx, mode);
+    }
+
+    let metadata = match fs::metadata(&path).await {
+        Err(_) if vfs::is_dir(&path) => return verify_asset(&ctx, mode),
+        metadata => metadata.or_throw_msg(
+            &ctx,
+            &["No such file or directory \"", &path, "\""].concat(),
+        )?,
+    };
 
     verify_metadata(&ctx, mode, metadata)
 }
 
 pub fn access_sync(ctx: Ctx<'_>, path: String, mode: Opt<u32>) -> Result<()> {
-    let metadata = std::fs::metadata(path.clone()).or_throw_msg(
-        &ctx,
-        &["No such file or directory \"", &path, "\""].concat(),
-    )?;
+    if vfs::is_file(&path) {
+        return verify_asset(&ctx, mode);
+    }
+
+    let metadata = match std::fs::metadata(path.clone()) {
+        Err(_) if vfs::is_dir(&path) => return verify_asset(&ctx, mode),
+        metadata => metadata.or_throw_msg(
+            &ctx,
+            &["No such file or directory \"", &path, "\""].concat(),
+        )?,
+    };
 
     verify_metadata(&ctx, mode, metadata)
 }
 
+// Embedded assets are read-only
+fn verify_asset(ctx: &Ctx, mode: Opt<u32>) -> Result<()> {
+    if mode.unwrap_or(CONSTANT_F_OK) & (CONSTANT_W_OK | CONSTANT_X_OK) != 0 {
+        return Err(Exception::throw_message(ctx, "Permission denied"));
+    }
+    Ok(())
+}
+
 fn verify_metadata(ctx: &Ctx, mode: Opt<u32>, metadata: Metadata) -> Result<()> {
     let permissions = metadata.permissions();
 
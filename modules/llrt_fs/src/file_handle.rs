@@ -4,6 +4,7 @@
 
 use std::borrow::Cow;
 use std::path::PathBuf;
+use std::rc::Rc;
 
 use either::Either;
 use llrt_buffer::{ArrayBufferView, Buffer};
@@ -12,10 +13,11 @@ use llrt_utils::{
     object::ObjectExt,
     result::{OptionExt, ResultExt},
 };
-use rquickjs::function::Opt;
-use rquickjs::{Ctx, Error, Exception, FromJs, Null, Object, Result, Value};
+use rquickjs::function::{Async, Func, Opt, This};
+use rquickjs::{Constructor, Ctx, Error, Exception, FromJs, Function, Null, Object, Result, Value};
 use tokio::fs::File;
 use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekFrom};
+use tokio::sync::Mutex;
 
 use super::{read_file, Stats};
 
@@ -96,21 +98,7 @@ impl FileHandle {
 
     #[qjs(get)]
     async fn fd(&self, ctx: Ctx<'_>) -> Result<i32> {
-        #[cfg(unix)]
-        {
-            use std::os::fd::AsRawFd;
-            Ok(self.file(&ctx)?.as_raw_fd())
-        }
-        #[cfg(windows)]
-        {
-            use std::os::windows::io::AsRawHandle;
-            let handle = self.file(&ctx)?.as_raw_handle();
-            Ok(handle as i32)
-        }
-        #[cfg(not(any(unix, windows)))]
-        {
-            Ok(0)
-        }
+        Ok(raw_fd(self.file(&ctx)?))
     }
 
     async fn read<'js>(
@@ -232,6 +220,44 @@ impl FileHandle {
         read_file::handle_read_file_bytes(&ctx, options, bytes)
     }
 
+    /// Returns a byte oriented web `ReadableStream` reading from the current position of the
+    /// file. The stream reads from a duplicate of the file descriptor, which shares its offset
+    /// with this handle, so reads on the handle while the stream is in use move the stream too.
+    fn readable_web_stream<'js>(&self, ctx: Ctx<'js>) -> Result<Value<'js>> {
+        let file = try_clone_file(self.file(&ctx)?).or_throw_msg(&ctx, "Can't clone file")?;
+        let file = Rc::new(Mutex::new(File::from_std(file)));
+
+        let source = Object::new(ctx.clone())?;
+        source.set("type", "bytes")?;
+        source.set(
+            "pull",
+            Func::from(Async(move |ctx: Ctx<'js>, controller: Object<'js>| {
+                let file = file.clone();
+                async move {
+                    let mut buffer = vec![0; DEFAULT_BUFFER_SIZE];
+                    let bytes_read = file
+                        .lock()
+                        .await
+                        .read(&mut buffer)
+                        .await
+                        .or_throw_msg(&ctx, "Failed to read file")?;
+                    if bytes_read == 0 {
+                        let close: Function = controller.get("close")?;
+                        close.call::<_, ()>((This(controller),))?;
+                    } else {
+                        buffer.truncate(bytes_read);
+                        let enqueue: Function = controller.get("enqueue")?;
+                        enqueue.call::<_, ()>((This(controller), Buffer(buffer)))?;
+                    }
+                    Ok::<_, Error>(())
+                }
+            })),
+        )?;
+
+        let constructor: Constructor = ctx.globals().get("ReadableStream")?;
+        constructor.construct((source,))
+    }
+
     async fn stat(&self, ctx: Ctx<'_>) -> Result<Stats> {
         let metadata = self
             .file(&ctx)?
@@ -389,6 +415,42 @@ impl FileHandle {
     }
 }
 
+pub(crate) fn raw_fd(file: &File) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsRawFd;
+        file.as_raw_fd()
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::AsRawHandle;
+        file.as_raw_handle() as i32
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        _ = file;
+        0
+    }
+}
+
+fn try_clone_file(file: &File) -> std::io::Result<std::fs::File> {
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsFd;
+        Ok(file.as_fd().try_clone_to_owned()?.into())
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::AsHandle;
+        Ok(file.as_handle().try_clone_to_owned()?.into())
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        _ = file;
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
 fn validate_length_offset(
     ctx: &Ctx<'_>,
     length: usize,
@@ -3,6 +3,7 @@
 mod access;
 mod chmod;
 mod file_handle;
+mod link;
 mod mkdir;
 mod open;
 mod read_dir;
@@ -10,8 +11,12 @@ mod read_file;
 mod rename;
 mod rm;
 mod stats;
+mod stream;
+pub mod vfs;
+mod watch;
 mod write_file;
 
+use llrt_events::Emitter;
 use llrt_utils::module::{export_default, ModuleInfo};
 use rquickjs::{
     module::{Declarations, Exports, ModuleDef},
@@ -22,15 +27,71 @@ use rquickjs::{Class, Ctx, Object, Result};
 use self::access::{access, access_sync};
 use self::chmod::{chmod, chmod_sync};
 use self::file_handle::FileHandle;
+use self::link::{
+    link, link_cb, link_sync, readlink, readlink_cb, readlink_sync, realpath, realpath_cb,
+    realpath_sync, symlink, symlink_cb, symlink_sync,
+};
 use self::mkdir::{mkdir, mkdir_sync, mkdtemp, mkdtemp_sync};
 use self::open::open;
 use self::read_dir::{read_dir, read_dir_sync, Dirent};
 use self::read_file::{read_file, read_file_sync};
 use self::rename::{rename, rename_sync};
-use self::rm::{rmdir, rmdir_sync, rmfile, rmfile_sync};
-use self::stats::{stat_fn, stat_fn_sync, Stats};
+use self::rm::{rmdir, rmdir_sync, rmfile, rmfile_sync, unlink, unlink_cb, unlink_sync};
+use self::stats::{lstat_cb, lstat_fn, lstat_fn_sync, stat_fn, stat_fn_sync, Stats};
+use self::stream::{create_read_stream, create_write_stream, ReadStream, WriteStream};
+use self::watch::{unwatch_file, watch, watch_file, watch_promise, FSWatcher, StatWatcher};
 use self::write_file::{write_file, write_file_sync};
 
+// Defines a Node.js style callback function calling `$async_fn`, with the callback as last
+// argument after the required `$arg`s and the optional `$opt`.
+macro_rules! define_cb_function {
+    ($fn_name:ident, $async_fn:ident, $($arg:ident: $ty:ty),+ $(; $opt:ident: $opt_ty:ty)?) => {
+        pub(crate) fn $fn_name<'js>(
+            ctx: ::rquickjs::Ctx<'js>,
+            $($arg: $ty,)+
+            args: ::rquickjs::prelude::Rest<::rquickjs::Value<'js>>,
+        ) -> ::rquickjs::Result<()> {
+            let mut args_iter = args.0.into_iter().rev();
+            let cb: ::rquickjs::Function = ::llrt_utils::result::ResultExt::or_throw_msg(
+                args_iter.next().and_then(|v| v.into_function()),
+                &ctx,
+                "Callback parameter is not a function",
+            )?;
+            $(
+                let $opt = ::rquickjs::prelude::Opt(
+                    args_iter
+                        .next()
+                        .filter(|v| !v.is_undefined() && !v.is_null())
+                        .map(|v| <$opt_ty as ::rquickjs::FromJs>::from_js(&ctx, v))
+                        .transpose()?,
+                );
+            )?
+
+            ::llrt_context::CtxExtension::spawn_exit(&ctx.clone(), async move {
+                match $async_fn(ctx.clone(), $($arg,)+ $($opt)?).await {
+                    Ok(value) => {
+                        let null = ::rquickjs::IntoJs::into_js(::rquickjs::Null, &ctx)?;
+                        () = cb.call((null, value))?;
+                        Ok::<_, ::rquickjs::Error>(())
+                    },
+                    Err(::rquickjs::Error::Exception) => {
+                        () = cb.call((ctx.catch(),))?;
+                        Ok(())
+                    },
+                    Err(err) => {
+                        let error = ::rquickjs::Exception::from_message(ctx, &err.to_string())?;
+                        () = cb.call((error,))?;
+                        Ok(())
+                    },
+                }
+            })?;
+            Ok(())
+        }
+    };
+}
+
+pub(crate) use define_cb_function;
+
 pub const CONSTANT_F_OK: u32 = 0;
 pub const CONSTANT_R_OK: u32 = 4;
 pub const CONSTANT_W_OK: u32 = 2;
@@ -52,9 +113,16 @@ impl ModuleDef for FsPromisesModule {
         declare.declare("mkdtemp")?;
         declare.declare("rm")?;
         declare.declare("rmdir")?;
+        declare.declare("lstat")?;
+        declare.declare("symlink")?;
+        declare.declare("readlink")?;
+        declare.declare("link")?;
+        declare.declare("realpath")?;
+        declare.declare("unlink")?;
         declare.declare("stat")?;
         declare.declare("constants")?;
         declare.declare("chmod")?;
+        declare.declare("watch")?;
 
         declare.declare("default")?;
 
@@ -90,6 +158,10 @@ pub struct FsModule;
 impl ModuleDef for FsModule {
     fn declare(declare: &Declarations) -> Result<()> {
         declare.declare("promises")?;
+        declare.declare("ReadStream")?;
+        declare.declare("WriteStream")?;
+        declare.declare("createReadStream")?;
+        declare.declare("createWriteStream")?;
         declare.declare("accessSync")?;
         declare.declare("mkdirSync")?;
         declare.declare("mkdtempSync")?;
@@ -102,6 +174,21 @@ impl ModuleDef for FsModule {
         declare.declare("constants")?;
         declare.declare("chmodSync")?;
         declare.declare("renameSync")?;
+        declare.declare("watch")?;
+        declare.declare("watchFile")?;
+        declare.declare("unwatchFile")?;
+        declare.declare("lstat")?;
+        declare.declare("lstatSync")?;
+        declare.declare("symlink")?;
+        declare.declare("symlinkSync")?;
+        declare.declare("readlink")?;
+        declare.declare("readlinkSync")?;
+        declare.declare("link")?;
+        declare.declare("linkSync")?;
+        declare.declare("realpath")?;
+        declare.declare("realpathSync")?;
+        declare.declare("unlink")?;
+        declare.declare("unlinkSync")?;
 
         declare.declare("default")?;
 
@@ -116,11 +203,21 @@ impl ModuleDef for FsModule {
         Class::<Stats>::define(&globals)?;
 
         export_default(ctx, exports, |default| {
+            Class::<ReadStream>::define(default)?;
+            Class::<WriteStream>::define(default)?;
+
+            ReadStream::add_event_emitter_prototype(ctx)?;
+            WriteStream::add_event_emitter_prototype(ctx)?;
+            FSWatcher::add_event_emitter_prototype(ctx)?;
+            StatWatcher::add_event_emitter_prototype(ctx)?;
+
             let promises = Object::new(ctx.clone())?;
             export_promises(ctx, &promises)?;
             export_constants(ctx, default)?;
 
             default.set("promises", promises)?;
+            default.set("createReadStream", Func::from(create_read_stream))?;
+            default.set("createWriteStream", Func::from(create_write_stream))?;
             default.set("accessSync", Func::from(access_sync))?;
             default.set("mkdirSync", Func::from(mkdir_sync))?;
             default.set("mkdtempSync", Func::from(mkdtemp_sync))?;
@@ -132,6 +229,21 @@ impl ModuleDef for FsModule {
             default.set("writeFileSync", Func::from(write_file_sync))?;
             default.set("chmodSync", Func::from(chmod_sync))?;
             default.set("renameSync", Func::from(rename_sync))?;
+            default.set("watch", Func::from(watch))?;
+            default.set("watchFile", Func::from(watch_file))?;
+            default.set("unwatchFile", Func::from(unwatch_file))?;
+            default.set("lstat", Func::from(lstat_cb))?;
+            default.set("lstatSync", Func::from(lstat_fn_sync))?;
+            default.set("symlink", Func::from(symlink_cb))?;
+            default.set("symlinkSync", Func::from(symlink_sync))?;
+            default.set("readlink", Func::from(readlink_cb))?;
+            default.set("readlinkSync", Func::from(readlink_sync))?;
+            default.set("link", Func::from(link_cb))?;
+            default.set("linkSync", Func::from(link_sync))?;
+            default.set("realpath", Func::from(realpath_cb))?;
+            default.set("realpathSync", Func::from(realpath_sync))?;
+            default.set("unlink", Func::from(unlink_cb))?;
+            default.set("unlinkSync", Func::from(unlink_sync))?;
 
             Ok(())
         })
@@ -155,6 +267,13 @@ fn export_promises<'js>(ctx: &Ctx<'js>, exports: &Object<'js>) -> Result<()> {
     exports.set("rmdir", Func::from(Async(rmdir)))?;
     exports.set("stat", Func::from(Async(stat_fn)))?;
     exports.set("chmod", Func::from(Async(chmod)))?;
+    exports.set("lstat", Func::from(Async(lstat_fn)))?;
+    exports.set("symlink", Func::from(Async(symlink)))?;
+    exports.set("readlink", Func::from(Async(readlink)))?;
+    exports.set("link", Func::from(Async(link)))?;
+    exports.set("realpath", Func::from(Async(realpath)))?;
+    exports.set("unlink", Func::from(Async(unlink)))?;
+    exports.set("watch", Func::from(watch_promise))?;
 
     Ok(())
 }
@@ -0,0 +1,133 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::{io, path::PathBuf};
+
+use llrt_utils::result::ResultExt;
+use rquickjs::{prelude::Opt, Ctx, Result};
+use tokio::fs;
+
+use super::define_cb_function;
+
+fn symlink_error(target: &str, path: &str) -> String {
+    ["Can't create symlink \"", path, "\" to \"", target, "\""].concat()
+}
+
+fn link_error(existing_path: &str, new_path: &str) -> String {
+    ["Can't link \"", new_path, "\" to \"", existing_path, "\""].concat()
+}
+
+fn path_to_string(path: PathBuf) -> String {
+    let path = path.to_string_lossy().into_owned();
+    // Windows returns verbatim paths from canonicalize, Node.js doesn't
+    #[cfg(windows)]
+    {
+        if let Some(path) = path.strip_prefix(r"\\?\") {
+            return path.to_string();
+        }
+    }
+    path
+}
+
+#[cfg(windows)]
+fn is_dir_link(target: &str, path: &str, link_type: Option<&str>) -> bool {
+    match link_type {
+        Some(link_type) => link_type == "dir" || link_type == "junction",
+        // Without an explicit type, the target is resolved relative to the link like Node.js does
+        None => std::path::Path::new(path)
+            .parent()
+            .unwrap_or(std::path::Path::new(""))
+            .join(target)
+            .is_dir(),
+    }
+}
+
+#[cfg(unix)]
+async fn create_symlink(target: &str, path: &str, _link_type: Option<&str>) -> io::Result<()> {
+    fs::symlink(target, path).await
+}
+
+#[cfg(windows)]
+async fn create_symlink(target: &str, path: &str, link_type: Option<&str>) -> io::Result<()> {
+    if is_dir_link(target, path, link_type) {
+        fs::symlink_dir(target, path).await
+    } else {
+        fs::symlink_file(target, path).await
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink_sync(target: &str, path: &str, _link_type: Option<&str>) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn create_symlink_sync(target: &str, path: &str, link_type: Option<&str>) -> io::Result<()> {
+    if is_dir_link(target, path, link_type) {
+        std::os::windows::fs::symlink_dir(target, path)
+    } else {
+        std::os::windows::fs::symlink_file(target, path)
+    }
+}
+
+pub async fn symlink(
+    ctx: Ctx<'_>,
+    target: String,
+    path: String,
+    link_type: Opt<String>,
+) -> Result<()> {
+    create_symlink(&target, &path, link_type.0.as_deref())
+        .await
+        .or_throw_msg(&ctx, &symlink_error(&target, &path))
+}
+
+pub fn symlink_sync(
+    ctx: Ctx<'_>,
+    target: String,
+    path: String,
+    link_type: Opt<String>,
+) -> Result<()> {
+    create_symlink_sync(&target, &path, link_type.0.as_deref())
+        .or_throw_msg(&ctx, &symlink_error(&target, &path))
+}
+
+pub async fn readlink(ctx: Ctx<'_>, path: String) -> Result<String> {
+    let target = fs::read_link(&path)
+        .await
+        .or_throw_msg(&ctx, &["Can't read link \"", &path, "\""].concat())?;
+    Ok(path_to_string(target))
+}
+
+pub fn readlink_sync(ctx: Ctx<'_>, path: String) -> Result<String> {
+    let target = std::fs::read_link(&path)
+        .or_throw_msg(&ctx, &["Can't read link \"", &path, "\""].concat())?;
+    Ok(path_to_string(target))
+}
+
+pub async fn link(ctx: Ctx<'_>, existing_path: String, new_path: String) -> Result<()> {
+    fs::hard_link(&existing_path, &new_path)
+        .await
+        .or_throw_msg(&ctx, &link_error(&existing_path, &new_path))
+}
+
+pub fn link_sync(ctx: Ctx<'_>, existing_path: String, new_path: String) -> Result<()> {
+    std::fs::hard_link(&existing_path, &new_path)
+        .or_throw_msg(&ctx, &link_error(&existing_path, &new_path))
+}
+
+pub async fn realpath(ctx: Ctx<'_>, path: String) -> Result<String> {
+    let resolved = fs::canonicalize(&path)
+        .await
+        .or_throw_msg(&ctx, &["Can't resolve path \"", &path, "\""].concat())?;
+    Ok(path_to_string(resolved))
+}
+
+pub fn realpath_sync(ctx: Ctx<'_>, path: String) -> Result<String> {
+    let resolved = std::fs::canonicalize(&path)
+        .or_throw_msg(&ctx, &["Can't resolve path \"", &path, "\""].concat())?;
+    Ok(path_to_string(resolved))
+}
+
+define_cb_function!(symlink_cb, symlink, target: String, path: String; link_type: String);
+define_cb_function!(readlink_cb, readlink, path: String);
+define_cb_function!(link_cb, link, existing_path: String, new_path: String);
+define_cb_function!(realpath_cb, realpath, path: String);
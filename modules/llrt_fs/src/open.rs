@@ -14,8 +14,20 @@ pub async fn open(
     flags: Opt<String>,
     mode: Opt<u32>,
 ) -> Result<FileHandle> {
+    let options = open_options(&ctx, flags.0.as_deref().unwrap_or("r"), mode.0)?;
+
+    let path = PathBuf::from(path);
+    let file = options
+        .open(&path)
+        .await
+        .or_throw_msg(&ctx, "Cannot open file")?;
+
+    Ok(FileHandle::new(file, path))
+}
+
+pub(crate) fn open_options(ctx: &Ctx<'_>, flags: &str, mode: Option<u32>) -> Result<OpenOptions> {
     let mut options = OpenOptions::new();
-    match flags.0.as_deref().unwrap_or("r") {
+    match flags {
         // We are not supporting the sync modes
         "a" => options.append(true).create(true),
         "ax" => options.append(true).create_new(true),
@@ -28,28 +40,21 @@ pub async fn open(
         "wx+" => options.write(true).read(true).create_new(true),
         flags => {
             return Err(Exception::throw_message(
-                &ctx,
+                ctx,
                 &["Invalid flags '", flags, "'"].concat(),
             ))
         },
     };
     #[cfg(unix)]
     {
-        let mode = mode.0.unwrap_or(0o666);
+        let mode = mode.unwrap_or(0o666);
         options.mode(mode);
     }
     #[cfg(not(unix))]
     {
         _ = mode;
     }
-
-    let path = PathBuf::from(path);
-    let file = options
-        .open(&path)
-        .await
-        .or_throw_msg(&ctx, "Cannot open file")?;
-
-    Ok(FileHandle::new(file, path))
+    Ok(options)
 }
 
 #[cfg(test)]
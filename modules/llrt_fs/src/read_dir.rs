@@ -9,6 +9,9 @@ use llrt_utils::fs::DirectoryWalker;
 use rquickjs::{
     atom::PredefinedAtom, prelude::Opt, Array, Class, Ctx, IntoJs, Object, Result, Value,
 };
+use tokio::fs;
+
+use crate::vfs;
 
 #[derive(rquickjs::class::Trace, rquickjs::JsLifetime)]
 #[rquickjs::class]
@@ -106,20 +109,24 @@ impl<'js> IntoJs<'js> for ReadDir {
 }
 
 pub async fn read_dir(mut path: String, options: Opt<Object<'_>>) -> Result<ReadDir> {
-    let (with_file_types, skip_root_pos, mut directory_walker) =
+    let (with_file_types, is_recursive, skip_root_pos, mut directory_walker) =
         process_options_and_create_directory_walker(&mut path, options);
 
     let mut items = Vec::with_capacity(64);
 
-    while let Some((child, metadata)) = directory_walker.walk().await? {
-        append_directory_and_metadata_to_vec(
-            with_file_types,
-            skip_root_pos,
-            &mut items,
-            child,
-            metadata,
-        );
+    let assets = vfs::read_dir(&path, is_recursive);
+    if assets.is_none() || fs::metadata(&path).await.is_ok() {
+        while let Some((child, metadata)) = directory_walker.walk().await? {
+            append_directory_and_metadata_to_vec(
+                with_file_types,
+                skip_root_pos,
+                &mut items,
+                child,
+                metadata,
+            );
+        }
     }
+    append_assets_to_vec(with_file_types, &mut items, assets);
 
     items.sort_by(|a, b| a.name.partial_cmp(&b.name).unwrap());
 
@@ -127,26 +134,30 @@ pub async fn read_dir(mut path: String, options: Opt<Object<'_>>) -> Result<Read
 }
 
 pub fn read_dir_sync(mut path: String, options: Opt<Object<'_>>) -> Result<ReadDir> {
-    let (with_file_types, skip_root_pos, mut directory_walker) =
+    let (with_file_types, is_recursive, skip_root_pos, mut directory_walker) =
         process_options_and_create_directory_walker(&mut path, options);
 
     let mut items = Vec::with_capacity(64);
-    while let Some((child, metadata)) = directory_walker.walk_sync()? {
-        append_directory_and_metadata_to_vec(
-            with_file_types,
-            skip_root_pos,
-            &mut items,
-            child,
-            metadata,
-        );
+    let assets = vfs::read_dir(&path, is_recursive);
+    if assets.is_none() || std::fs::metadata(&path).is_ok() {
+        while let Some((child, metadata)) = directory_walker.walk_sync()? {
+            append_directory_and_metadata_to_vec(
+                with_file_types,
+                skip_root_pos,
+                &mut items,
+                child,
+                metadata,
+            );
+        }
     }
+    append_assets_to_vec(with_file_types, &mut items, assets);
 
     items.sort_by(|a, b| a.name.partial_cmp(&b.name).unwrap());
 
     Ok(ReadDir { items, root: path })
 }
 
-type OptionsAndDirectoryWalker = (bool, usize, DirectoryWalker<fn(&str) -> bool>);
+type OptionsAndDirectoryWalker = (bool, bool, usize, DirectoryWalker<fn(&str) -> bool>);
 
 fn process_options_and_create_directory_walker(
     path: &mut String,
@@ -188,7 +199,12 @@ fn process_options_and_create_directory_walker(
     if is_recursive {
         directory_walker.set_recursive(true);
     }
-    (with_file_types, skip_root_pos, directory_walker)
+    (
+        with_file_types,
+        is_recursive,
+        skip_root_pos,
+        directory_walker,
+    )
 }
 
 fn append_directory_and_metadata_to_vec(
@@ -208,3 +224,16 @@ fn append_directory_and_metadata_to_vec(
 
     items.push(ReadDirItem { name, metadata })
 }
+
+// Embedded assets are listed along with the files on disk, shadowing those with the same name
+fn append_assets_to_vec(
+    with_file_types: bool,
+    items: &mut Vec<ReadDirItem>,
+    assets: Option<Vec<(String, Metadata)>>,
+) {
+    for (name, metadata) in assets.into_iter().flatten() {
+        items.retain(|item| item.name != name);
+        let metadata = with_file_types.then_some(metadata);
+        items.push(ReadDirItem { name, metadata })
+    }
+}
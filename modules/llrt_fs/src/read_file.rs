@@ -6,14 +6,19 @@ use llrt_utils::{object::ObjectExt, result::ResultExt};
 use rquickjs::{function::Opt, Ctx, Error, FromJs, IntoJs, Result, Value};
 use tokio::fs;
 
+use crate::vfs;
+
 pub async fn read_file(
     ctx: Ctx<'_>,
     path: String,
     options: Opt<Either<String, ReadFileOptions>>,
 ) -> Result<Value<'_>> {
-    let bytes = fs::read(&path)
-        .await
-        .or_throw_msg(&ctx, &["Can't read \"", &path, "\""].concat())?;
+    let bytes = match vfs::read(&path) {
+        Some(bytes) => bytes.to_vec(),
+        None => fs::read(&path)
+            .await
+            .or_throw_msg(&ctx, &["Can't read \"", &path, "\""].concat())?,
+    };
 
     handle_read_file_bytes(&ctx, options, bytes)
 }
@@ -23,8 +28,12 @@ pub fn read_file_sync(
     path: String,
     options: Opt<Either<String, ReadFileOptions>>,
 ) -> Result<Value<'_>> {
-    let bytes =
-        std::fs::read(&path).or_throw_msg(&ctx, &["Can't read \"", &path, "\""].concat())?;
+    let bytes = match vfs::read(&path) {
+        Some(bytes) => bytes.to_vec(),
+        None => {
+            std::fs::read(&path).or_throw_msg(&ctx, &["Can't read \"", &path, "\""].concat())?
+        },
+    };
 
     handle_read_file_bytes(&ctx, options, bytes)
 }
@@ -4,6 +4,8 @@ use llrt_utils::result::ResultExt;
 use rquickjs::{function::Opt, Ctx, Object, Result};
 use tokio::fs;
 
+use super::define_cb_function;
+
 #[allow(clippy::manual_async_fn)]
 pub async fn rmdir<'js>(ctx: Ctx<'js>, path: String, options: Opt<Object<'js>>) -> Result<()> {
     let recursive = get_params_rm_dir(options);
@@ -104,3 +106,17 @@ fn get_params_rm(options: Opt<Object>) -> (bool, bool) {
     }
     (recursive, force)
 }
+
+pub async fn unlink(ctx: Ctx<'_>, path: String) -> Result<()> {
+    fs::remove_file(&path)
+        .await
+        .or_throw_msg(&ctx, &["Can't unlink \"", &path, "\""].concat())?;
+    Ok(())
+}
+
+pub fn unlink_sync(ctx: Ctx<'_>, path: String) -> Result<()> {
+    std::fs::remove_file(&path).or_throw_msg(&ctx, &["Can't unlink \"", &path, "\""].concat())?;
+    Ok(())
+}
+
+define_cb_function!(unlink_cb, unlink, path: String);
@@ -9,6 +9,7 @@ use std::os::windows::fs::MetadataExt;
 #[allow(unused_imports)]
 use std::{
     fs::Metadata,
+    io,
     time::{Duration, SystemTime},
 };
 
@@ -16,6 +17,9 @@ use llrt_utils::result::ResultExt;
 use rquickjs::{Ctx, Result};
 use tokio::fs;
 
+use super::define_cb_function;
+use crate::vfs::{self, AssetMetadata};
+
 // The Stats implementation is very much based on Unix. The Windows implementation
 // tries its best to mimic the implementation of libuv since it is the standard.
 // See: https://github.com/libuv/libuv/blob/90648ea3e55125a5a819b32106da6462da310da6/src/win/fs.c
@@ -30,13 +34,25 @@ use tokio::fs;
 pub struct Stats {
     #[qjs(skip_trace)]
     metadata: Metadata,
+    asset_size: Option<u64>,
 }
 
 #[rquickjs::methods(rename_all = "camelCase")]
 impl Stats {
     #[qjs(skip)]
     pub fn new(metadata: Metadata) -> Self {
-        Self { metadata }
+        Self {
+            metadata,
+            asset_size: None,
+        }
+    }
+
+    #[qjs(skip)]
+    pub fn asset(asset: AssetMetadata) -> Self {
+        Self {
+            metadata: asset.metadata,
+            asset_size: Some(asset.size),
+        }
     }
 
     #[qjs(get, enumerable)]
@@ -68,12 +84,15 @@ impl Stats {
     #[qjs(get, enumerable)]
     pub fn mode(&self) -> u32 {
         #[cfg(unix)]
-        {
-            self.metadata.mode()
-        }
+        let mode = self.metadata.mode();
         #[cfg(not(unix))]
-        {
-            0o666
+        let mode = 0o666;
+
+        match self.asset_size {
+            // Assets are read-only and not executable
+            Some(_) if self.metadata.is_dir() => mode & !0o222,
+            Some(_) => mode & !0o333,
+            None => mode,
         }
     }
 
@@ -128,6 +147,9 @@ impl Stats {
 
     #[qjs(get, enumerable)]
     pub fn size(&self) -> u64 {
+        if let Some(size) = self.asset_size {
+            return size;
+        }
         #[cfg(unix)]
         {
             self.metadata.size()
@@ -315,24 +337,55 @@ impl Stats {
 }
 
 pub async fn stat_fn(ctx: Ctx<'_>, path: String) -> Result<Stats> {
-    let metadata = fs::metadata(&path)
-        .await
-        .or_throw_msg(&ctx, &["Can't stat \"", &path, "\""].concat())?;
+    if let Some(asset) = vfs::file_metadata(&path) {
+        return Ok(Stats::asset(asset));
+    }
 
-    let stats = Stats::new(metadata);
+    let metadata = fs::metadata(&path).await;
 
-    Ok(stats)
+    with_asset_dir(&path, metadata).or_throw_msg(&ctx, &["Can't stat \"", &path, "\""].concat())
 }
 
 pub fn stat_fn_sync(ctx: Ctx<'_>, path: String) -> Result<Stats> {
-    let metadata =
-        std::fs::metadata(&path).or_throw_msg(&ctx, &["Can't stat \"", &path, "\""].concat())?;
+    if let Some(asset) = vfs::file_metadata(&path) {
+        return Ok(Stats::asset(asset));
+    }
 
-    let stats = Stats::new(metadata);
+    let metadata = std::fs::metadata(&path);
 
-    Ok(stats)
+    with_asset_dir(&path, metadata).or_throw_msg(&ctx, &["Can't stat \"", &path, "\""].concat())
 }
 
+pub async fn lstat_fn(ctx: Ctx<'_>, path: String) -> Result<Stats> {
+    if let Some(asset) = vfs::file_metadata(&path) {
+        return Ok(Stats::asset(asset));
+    }
+
+    let metadata = fs::symlink_metadata(&path).await;
+
+    with_asset_dir(&path, metadata).or_throw_msg(&ctx, &["Can't lstat \"", &path, "\""].concat())
+}
+
+pub fn lstat_fn_sync(ctx: Ctx<'_>, path: String) -> Result<Stats> {
+    if let Some(asset) = vfs::file_metadata(&path) {
+        return Ok(Stats::asset(asset));
+    }
+
+    let metadata = std::fs::symlink_metadata(&path);
+
+    with_asset_dir(&path, metadata).or_throw_msg(&ctx, &["Can't lstat \"", &path, "\""].concat())
+}
+
+// Directories holding embedded assets are only used where none exists on disk
+fn with_asset_dir(path: &str, metadata: io::Result<Metadata>) -> io::Result<Stats> {
+    match metadata {
+        Ok(metadata) => Ok(Stats::new(metadata)),
+        Err(err) => vfs::dir_metadata(path).map(Stats::asset).ok_or(err),
+    }
+}
+
+define_cb_function!(lstat_cb, lstat_fn, path: String);
+
 #[allow(dead_code)]
 #[inline(always)]
 fn to_msec(time: SystemTime) -> u64 {
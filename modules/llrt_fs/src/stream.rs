@@ -0,0 +1,579 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::{
+    cell::{Cell, RefCell},
+    io,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, RwLock},
+    task::{Context, Poll, Waker},
+};
+
+use llrt_context::CtxExtension;
+use llrt_events::{EmitError, Emitter, EventEmitter, EventKey, EventList};
+use llrt_stream::{
+    impl_stream_events,
+    readable::{ReadableStream, ReadableStreamInner},
+    writable::{WritableStream, WritableStreamInner},
+    SteamEvents,
+};
+use llrt_utils::{bytes::ObjectBytes, object::ObjectExt, result::ResultExt};
+use rquickjs::{
+    class::{Trace, Tracer},
+    prelude::{Opt, This},
+    Class, Ctx, Error, Exception, Function, IntoJs, JsLifetime, Object, Result, Value,
+};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, ReadBuf, SeekFrom},
+};
+
+use super::{file_handle::raw_fd, open::open_options};
+
+const DEFAULT_HIGH_WATER_MARK: usize = 64 * 1024;
+const DEFAULT_WRITABLE_HIGH_WATER_MARK: usize = 16 * 1024;
+
+impl_stream_events!(ReadStream, WriteStream);
+
+#[rquickjs::class]
+pub struct ReadStream<'js> {
+    emitter: EventEmitter<'js>,
+    readable_stream_inner: ReadableStreamInner<'js>,
+    path: String,
+    pending: bool,
+    bytes_read: Rc<Cell<u64>>,
+    high_water_mark: usize,
+    pause: Rc<Pause>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for ReadStream<'js> {
+    type Changed<'to> = ReadStream<'to>;
+}
+
+impl<'js> Trace<'js> for ReadStream<'js> {
+    fn trace<'a>(&self, tracer: Tracer<'a, 'js>) {
+        self.emitter.trace(tracer);
+    }
+}
+
+impl<'js> Emitter<'js> for ReadStream<'js> {
+    fn get_event_list(&self) -> Arc<RwLock<EventList<'js>>> {
+        self.emitter.get_event_list()
+    }
+
+    fn on_event_changed(&mut self, event: EventKey<'js>, added: bool) -> Result<()> {
+        self.readable_stream_inner.on_event_changed(event, added)
+    }
+}
+
+impl<'js> ReadableStream<'js> for ReadStream<'js> {
+    fn inner_mut(&mut self) -> &mut ReadableStreamInner<'js> {
+        &mut self.readable_stream_inner
+    }
+
+    fn inner(&self) -> &ReadableStreamInner<'js> {
+        &self.readable_stream_inner
+    }
+}
+
+#[rquickjs::methods(rename_all = "camelCase")]
+impl<'js> ReadStream<'js> {
+    #[qjs(get, enumerable)]
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    #[qjs(get, enumerable)]
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    #[qjs(get, enumerable)]
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.get()
+    }
+
+    #[qjs(get, enumerable)]
+    pub fn readable_high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    pub fn read(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        size: Opt<usize>,
+    ) -> Result<Value<'js>> {
+        ReadableStream::read(this, ctx, size)
+    }
+
+    pub fn pause(this: This<Class<'js, Self>>) -> Class<'js, Self> {
+        this.borrow().pause.set(true);
+        this.0
+    }
+
+    pub fn resume(this: This<Class<'js, Self>>) -> Class<'js, Self> {
+        this.borrow().pause.set(false);
+        this.0
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pause.paused.get()
+    }
+
+    /// Writes all data to `destination` and ends it when this stream ends, unless `end` is set
+    /// to false in the options. Reading is paused while `write` returns false, until the
+    /// destination emits 'drain'.
+    pub fn pipe(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        destination: Object<'js>,
+        options: Opt<Object<'js>>,
+    ) -> Result<Object<'js>> {
+        let end = match options.0 {
+            Some(options) => options.get_optional("end")?.unwrap_or(true),
+            None => true,
+        };
+
+        let write: Function = destination.get("write")?;
+        let once: Option<Function> = destination.get_optional("once")?;
+        let pause = this.borrow().pause.clone();
+        let destination2 = destination.clone();
+        let on_data = Function::new(ctx.clone(), move |ctx: Ctx<'js>, chunk: Value<'js>| {
+            let flushed: Value = write.call((This(destination2.clone()), chunk))?;
+            if let (Some(false), Some(once)) = (flushed.as_bool(), &once) {
+                pause.set(true);
+                let pause = pause.clone();
+                let on_drain = Function::new(ctx, move || pause.set(false))?;
+                once.call::<_, Value>((This(destination2.clone()), "drain", on_drain))?;
+            }
+            Ok::<_, Error>(())
+        })?;
+        Self::add_event_listener_str(This(this.0.clone()), &ctx, "data", on_data, false, false)?;
+
+        if end {
+            let destination2 = destination.clone();
+            let on_end = Function::new(ctx.clone(), move || {
+                let end: Function = destination2.get("end")?;
+                end.call::<_, Value>((This(destination2.clone()),))?;
+                Ok::<_, Error>(())
+            })?;
+            Self::add_event_listener_str(This(this.0), &ctx, "end", on_end, false, true)?;
+        }
+
+        Ok(destination)
+    }
+
+    pub fn destroy(this: This<Class<'js, Self>>, error: Opt<Value<'js>>) -> Class<'js, Self> {
+        ReadableStream::destroy(this, error)
+    }
+}
+
+#[rquickjs::class]
+pub struct WriteStream<'js> {
+    emitter: EventEmitter<'js>,
+    writable_stream_inner: WritableStreamInner<'js>,
+    path: String,
+    pending: bool,
+    bytes_written: Rc<Cell<u64>>,
+    high_water_mark: usize,
+    // Bytes passed to `write` that were not written yet
+    length: Rc<Cell<usize>>,
+    need_drain: Rc<Cell<bool>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for WriteStream<'js> {
+    type Changed<'to> = WriteStream<'to>;
+}
+
+impl<'js> Trace<'js> for WriteStream<'js> {
+    fn trace<'a>(&self, tracer: Tracer<'a, 'js>) {
+        self.emitter.trace(tracer);
+    }
+}
+
+impl<'js> Emitter<'js> for WriteStream<'js> {
+    fn get_event_list(&self) -> Arc<RwLock<EventList<'js>>> {
+        self.emitter.get_event_list()
+    }
+}
+
+impl<'js> WritableStream<'js> for WriteStream<'js> {
+    fn inner_mut(&mut self) -> &mut WritableStreamInner<'js> {
+        &mut self.writable_stream_inner
+    }
+
+    fn inner(&self) -> &WritableStreamInner<'js> {
+        &self.writable_stream_inner
+    }
+}
+
+#[rquickjs::methods(rename_all = "camelCase")]
+impl<'js> WriteStream<'js> {
+    #[qjs(get, enumerable)]
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    #[qjs(get, enumerable)]
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    #[qjs(get, enumerable)]
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.get()
+    }
+
+    #[qjs(get, enumerable)]
+    pub fn writable_high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    #[qjs(get, enumerable)]
+    pub fn writable_length(&self) -> usize {
+        self.length.get()
+    }
+
+    #[qjs(get, enumerable)]
+    pub fn writable_need_drain(&self) -> bool {
+        self.need_drain.get()
+    }
+
+    /// Queues `value` for writing. Returns false once the queued bytes reach the high water
+    /// mark, 'drain' is then emitted when all of them are written.
+    pub fn write(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        value: Value<'js>,
+        cb: Opt<Function<'js>>,
+    ) -> Result<bool> {
+        let len = ObjectBytes::from(&ctx, &value)?.as_bytes(&ctx)?.len();
+        let borrow = this.borrow();
+        let length = borrow.length.clone();
+        let need_drain = borrow.need_drain.clone();
+        let high_water_mark = borrow.high_water_mark;
+        drop(borrow);
+
+        length.set(length.get() + len);
+        let flushed = length.get() < high_water_mark;
+        if !flushed {
+            need_drain.set(true);
+        }
+
+        let this2 = this.0.clone();
+        let callback = cb.0;
+        let on_written =
+            Function::new(ctx.clone(), move |ctx: Ctx<'js>, error: Opt<Value<'js>>| {
+                length.set(length.get().saturating_sub(len));
+                if let Some(callback) = &callback {
+                    callback.call::<_, ()>((error.0,))?;
+                }
+                if length.get() == 0 && need_drain.replace(false) {
+                    Self::emit_str(This(this2.clone()), &ctx, "drain", vec![], false)?;
+                }
+                Ok::<_, Error>(())
+            })?;
+        WritableStream::write(this, ctx, value, Opt(Some(on_written)))?;
+        Ok(flushed)
+    }
+
+    pub fn end(
+        this: This<Class<'js, Self>>,
+        ctx: Ctx<'js>,
+        chunk_or_callback: Opt<Value<'js>>,
+        callback: Opt<Function<'js>>,
+    ) -> Result<Class<'js, Self>> {
+        let mut callback = callback.0;
+        if let Some(chunk) = chunk_or_callback.0 {
+            if let Some(function) = chunk.as_function() {
+                callback = Some(function.clone());
+            } else if !chunk.is_undefined() && !chunk.is_null() {
+                WritableStream::write(This(this.0.clone()), ctx.clone(), chunk, Opt(None))?;
+            }
+        }
+        if let Some(callback) = callback {
+            Self::add_event_listener_str(
+                This(this.0.clone()),
+                &ctx,
+                "finish",
+                callback,
+                false,
+                true,
+            )?;
+        }
+        Ok(WritableStream::end(this))
+    }
+
+    pub fn destroy(this: This<Class<'js, Self>>, error: Opt<Value<'js>>) -> Class<'js, Self> {
+        WritableStream::destroy(this, error)
+    }
+}
+
+#[derive(Default)]
+struct StreamOptions {
+    flags: Option<String>,
+    mode: Option<u32>,
+    start: Option<u64>,
+    end: Option<u64>,
+    high_water_mark: Option<usize>,
+}
+
+impl StreamOptions {
+    fn from_object(ctx: &Ctx<'_>, options: Option<Object<'_>>) -> Result<Self> {
+        let Some(options) = options else {
+            return Ok(Self::default());
+        };
+        let options = Self {
+            flags: options.get_optional("flags")?,
+            mode: options.get_optional("mode")?,
+            start: options.get_optional("start")?,
+            end: options.get_optional("end")?,
+            high_water_mark: options.get_optional("highWaterMark")?,
+        };
+        if let (Some(start), Some(end)) = (options.start, options.end) {
+            if start > end {
+                return Err(Exception::throw_range(
+                    ctx,
+                    &[
+                        "start (",
+                        &start.to_string(),
+                        ") <= end (",
+                        &end.to_string(),
+                        ")",
+                    ]
+                    .concat(),
+                ));
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Stops reading from the file while a stream is paused.
+#[derive(Default)]
+struct Pause {
+    paused: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl Pause {
+    fn set(&self, paused: bool) {
+        self.paused.set(paused);
+        if !paused {
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+struct Paused<T> {
+    inner: T,
+    pause: Rc<Pause>,
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Paused<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.pause.paused.get() {
+            self.pause.waker.replace(Some(cx.waker().clone()));
+            return Poll::Pending;
+        }
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+/// Counts the bytes read from or written to the file.
+struct Counted<T> {
+    inner: T,
+    bytes: Rc<Cell<u64>>,
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Counted<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = (buf.filled().len() - filled) as u64;
+        self.bytes.set(self.bytes.get() + read);
+        result
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Counted<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = result {
+            self.bytes.set(self.bytes.get() + written as u64);
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+async fn open_file(
+    ctx: &Ctx<'_>,
+    path: &str,
+    options: OpenOptions,
+    start: Option<u64>,
+) -> Result<File> {
+    let mut file = options
+        .open(path)
+        .await
+        .or_throw_msg(ctx, &["Can't open \"", path, "\""].concat())?;
+    if let Some(start) = start {
+        file.seek(SeekFrom::Start(start))
+            .await
+            .or_throw_msg(ctx, "Failed to seek file")?;
+    }
+    Ok(file)
+}
+
+/// Emits the open events, or the error and close events when the file could not be opened.
+fn emit_open<'js, T>(
+    this: &Class<'js, T>,
+    ctx: &Ctx<'js>,
+    file: Result<File>,
+) -> Result<Option<File>>
+where
+    T: Emitter<'js> + SteamEvents<'js>,
+{
+    match file {
+        Ok(file) => {
+            let fd = raw_fd(&file).into_js(ctx)?;
+            T::emit_str(This(this.clone()), ctx, "open", vec![fd], false)?;
+            T::emit_str(This(this.clone()), ctx, "ready", vec![], false)?;
+            Ok(Some(file))
+        },
+        Err(err) => {
+            let had_error = Err::<(), _>(err).emit_error("open", ctx, this.clone())?;
+            T::emit_close(this.clone(), ctx, had_error)?;
+            Ok(None)
+        },
+    }
+}
+
+pub fn create_read_stream<'js>(
+    ctx: Ctx<'js>,
+    path: String,
+    options: Opt<Object<'js>>,
+) -> Result<Class<'js, ReadStream<'js>>> {
+    let options = StreamOptions::from_object(&ctx, options.0)?;
+    let open_options = open_options(&ctx, options.flags.as_deref().unwrap_or("r"), options.mode)?;
+    let high_water_mark = options.high_water_mark.unwrap_or(DEFAULT_HIGH_WATER_MARK);
+    // `end` is inclusive
+    let limit = options
+        .end
+        .map(|end| end + 1 - options.start.unwrap_or(0))
+        .unwrap_or(u64::MAX);
+
+    let emitter = EventEmitter::new();
+    let bytes_read = Rc::new(Cell::new(0));
+    let pause = Rc::new(Pause::default());
+    let this = Class::instance(
+        ctx.clone(),
+        ReadStream {
+            readable_stream_inner: ReadableStreamInner::with_high_water_mark(
+                emitter.clone(),
+                true,
+                high_water_mark,
+            ),
+            emitter,
+            path: path.clone(),
+            pending: true,
+            bytes_read: bytes_read.clone(),
+            high_water_mark,
+            pause: pause.clone(),
+        },
+    )?;
+
+    let this2 = this.clone();
+    let ctx2 = ctx.clone();
+    ctx.spawn_exit(async move {
+        let file = open_file(&ctx2, &path, open_options, options.start).await;
+        if file.is_ok() {
+            this2.borrow_mut().pending = false;
+        }
+        let Some(file) = emit_open(&this2, &ctx2, file)? else {
+            return Ok(());
+        };
+
+        let reader = Counted {
+            inner: file,
+            bytes: bytes_read,
+        };
+        let reader = Paused {
+            inner: reader.take(limit),
+            pause,
+        };
+        ReadableStream::process(this2, &ctx2, reader)?;
+        Ok(())
+    })?;
+
+    Ok(this)
+}
+
+pub fn create_write_stream<'js>(
+    ctx: Ctx<'js>,
+    path: String,
+    options: Opt<Object<'js>>,
+) -> Result<Class<'js, WriteStream<'js>>> {
+    let options = StreamOptions::from_object(&ctx, options.0)?;
+    let open_options = open_options(&ctx, options.flags.as_deref().unwrap_or("w"), options.mode)?;
+
+    let emitter = EventEmitter::new();
+    let bytes_written = Rc::new(Cell::new(0));
+    let this = Class::instance(
+        ctx.clone(),
+        WriteStream {
+            writable_stream_inner: WritableStreamInner::new(emitter.clone(), true),
+            emitter,
+            path: path.clone(),
+            pending: true,
+            bytes_written: bytes_written.clone(),
+            high_water_mark: options
+                .high_water_mark
+                .unwrap_or(DEFAULT_WRITABLE_HIGH_WATER_MARK),
+            length: Rc::new(Cell::new(0)),
+            need_drain: Rc::new(Cell::new(false)),
+        },
+    )?;
+
+    let this2 = this.clone();
+    let ctx2 = ctx.clone();
+    ctx.spawn_exit(async move {
+        let file = open_file(&ctx2, &path, open_options, options.start).await;
+        if file.is_ok() {
+            this2.borrow_mut().pending = false;
+        }
+        let Some(file) = emit_open(&this2, &ctx2, file)? else {
+            return Ok(());
+        };
+
+        let writer = Counted {
+            inner: file,
+            bytes: bytes_written,
+        };
+        WritableStream::process(this2, &ctx2, writer)?;
+        Ok(())
+    })?;
+
+    Ok(this)
+}
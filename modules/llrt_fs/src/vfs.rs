@@ -0,0 +1,254 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+// Read-only virtual filesystem holding the assets embedded in self-contained executables.
+//
+// Assets are stored by their path relative to the directory of the compiled entry file and
+// are mounted relative to the directory of the executable, so they are found the same way
+// no matter where the executable is started from. An asset shadows any file on disk with
+// the same path, while the directories holding assets are only used where none exists on
+// disk.
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::Metadata,
+    io,
+    path::{Path, MAIN_SEPARATOR_STR},
+    sync::OnceLock,
+};
+
+use llrt_path::resolve_path_with_separator;
+use rquickjs::Result;
+
+struct Mount {
+    files: HashMap<String, Vec<u8>>,
+    // Names of the files and directories directly inside each asset directory
+    dirs: HashMap<String, BTreeSet<String>>,
+    // Assets have no metadata of their own, they report the one of the executable
+    // and of its directory
+    file_metadata: Metadata,
+    dir_metadata: Metadata,
+}
+
+pub struct AssetMetadata {
+    pub metadata: Metadata,
+    pub size: u64,
+}
+
+static MOUNT: OnceLock<Mount> = OnceLock::new();
+
+/// Mounts the assets relative to the directory of `executable`.
+pub fn mount(assets: Vec<(String, Vec<u8>)>, executable: &Path) -> Result<()> {
+    let root = match executable.parent() {
+        Some(root) if !root.as_os_str().is_empty() => root,
+        _ => Path::new("."),
+    };
+    let root_path = root.to_string_lossy();
+    let root_key = resolve_path_with_separator([root_path.as_ref()], true)?;
+
+    let mut files = HashMap::with_capacity(assets.len());
+    let mut dirs: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for (path, bytes) in assets {
+        let key = resolve_path_with_separator([root_path.as_ref(), &path], true)?;
+        let mut child = key.as_str();
+        while let Some((parent, name)) = child.rsplit_once('/') {
+            let parent = if parent.is_empty() { "/" } else { parent };
+            dirs.entry(parent.to_string())
+                .or_default()
+                .insert(name.to_string());
+            if parent.len() <= root_key.len() {
+                break;
+            }
+            child = parent;
+        }
+        files.insert(key, bytes);
+    }
+
+    _ = MOUNT.set(Mount {
+        files,
+        dirs,
+        file_metadata: std::fs::metadata(executable)?,
+        dir_metadata: std::fs::metadata(root)?,
+    });
+    Ok(())
+}
+
+fn lookup(path: &str) -> Option<(&'static Mount, String)> {
+    let mount = MOUNT.get()?;
+    let path = resolve_path_with_separator([path], true).ok()?;
+    Some((mount, path))
+}
+
+fn join(dir: &str, name: &str) -> String {
+    [dir.trim_end_matches('/'), "/", name].concat()
+}
+
+pub fn read(path: &str) -> Option<&'static [u8]> {
+    let (mount, path) = lookup(path)?;
+    mount.files.get(&path).map(Vec::as_slice)
+}
+
+pub fn is_file(path: &str) -> bool {
+    read(path).is_some()
+}
+
+pub fn is_dir(path: &str) -> bool {
+    lookup(path).is_some_and(|(mount, path)| mount.dirs.contains_key(&path))
+}
+
+pub fn file_metadata(path: &str) -> Option<AssetMetadata> {
+    let (mount, path) = lookup(path)?;
+    let bytes = mount.files.get(&path)?;
+    Some(AssetMetadata {
+        metadata: mount.file_metadata.clone(),
+        size: bytes.len() as u64,
+    })
+}
+
+pub fn dir_metadata(path: &str) -> Option<AssetMetadata> {
+    let (mount, path) = lookup(path)?;
+    mount.dirs.get(&path)?;
+    Some(AssetMetadata {
+        metadata: mount.dir_metadata.clone(),
+        size: mount.dir_metadata.len(),
+    })
+}
+
+/// Lists the assets and asset directories inside `path`, named relative to it.
+pub fn read_dir(path: &str, recursive: bool) -> Option<Vec<(String, Metadata)>> {
+    let (mount, path) = lookup(path)?;
+    mount.dirs.get(&path)?;
+
+    let mut entries = Vec::new();
+    let mut pending = vec![(path, String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        for name in &mount.dirs[&dir] {
+            let child = join(&dir, name);
+            let relative = if prefix.is_empty() {
+                name.clone()
+            } else {
+                [prefix.as_str(), MAIN_SEPARATOR_STR, name].concat()
+            };
+            if mount.dirs.contains_key(&child) {
+                entries.push((relative.clone(), mount.dir_metadata.clone()));
+                if recursive {
+                    pending.push((child, relative));
+                }
+            } else {
+                entries.push((relative, mount.file_metadata.clone()));
+            }
+        }
+    }
+    Some(entries)
+}
+
+/// Serializes assets as their count followed by, for each asset, its length-prefixed
+/// path and contents.
+pub fn write_assets(assets: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(assets.iter().map(|(_, data)| data.len() + 64).sum());
+    write_u32(&mut bytes, assets.len());
+    for (path, data) in assets {
+        write_bytes(&mut bytes, path.as_bytes());
+        write_bytes(&mut bytes, data);
+    }
+    bytes
+}
+
+pub fn read_assets(mut input: &[u8]) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let count = read_u32(&mut input)?;
+    let mut assets = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        let path =
+            String::from_utf8(read_bytes(&mut input)?.to_vec()).map_err(|_| invalid_assets())?;
+        assets.push((path, read_bytes(&mut input)?.to_vec()));
+    }
+    Ok(assets)
+}
+
+fn invalid_assets() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Invalid embedded assets")
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    bytes.extend_from_slice(&(value as u32).to_le_bytes());
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, value: &[u8]) {
+    write_u32(bytes, value.len());
+    bytes.extend_from_slice(value);
+}
+
+fn read_u32(input: &mut &[u8]) -> io::Result<usize> {
+    let (value, rest) = input.split_first_chunk::<4>().ok_or_else(invalid_assets)?;
+    *input = rest;
+    Ok(u32::from_le_bytes(*value) as usize)
+}
+
+fn read_bytes<'a>(input: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    let len = read_u32(input)?;
+    if input.len() < len {
+        return Err(invalid_assets());
+    }
+    let (value, rest) = input.split_at(len);
+    *input = rest;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::{
+        dir_metadata, file_metadata, is_dir, is_file, mount, read_assets, read_dir, write_assets,
+    };
+
+    #[test]
+    fn roundtrips_assets() {
+        let assets = vec![
+            ("config/app.json".to_string(), b"{}".to_vec()),
+            ("templates/index.html".to_string(), b"<p></p>".to_vec()),
+        ];
+
+        let bytes = write_assets(&assets);
+        assert_eq!(read_assets(&bytes).unwrap(), assets);
+
+        assert!(read_assets(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn mounts_assets_next_to_executable() {
+        let executable = env::current_exe().unwrap();
+        let root = executable.parent().unwrap();
+        let assets = vec![
+            ("config/app.json".to_string(), b"{}".to_vec()),
+            (
+                "config/env/prod.json".to_string(),
+                b"{\"prod\":true}".to_vec(),
+            ),
+        ];
+        mount(assets, &executable).unwrap();
+
+        let config = root.join("config");
+        let config = config.to_str().unwrap();
+        let prod = root.join("config/env/prod.json");
+        let prod = prod.to_str().unwrap();
+        assert!(is_file(prod));
+        assert!(!is_file(config));
+        assert!(is_dir(config));
+        assert!(!is_dir("config"));
+
+        assert_eq!(file_metadata(prod).unwrap().size, 13);
+        assert!(dir_metadata(config).unwrap().metadata.is_dir());
+
+        let names = |recursive| {
+            read_dir(config, recursive)
+                .unwrap()
+                .into_iter()
+                .map(|(name, metadata)| (name, metadata.is_dir()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(false),
+            [("app.json".to_string(), false), ("env".to_string(), true)]
+        );
+        assert_eq!(names(true).len(), 3);
+    }
+}
@@ -0,0 +1,511 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// File system watching. `watch` and `promises.watch` use the native notification API of the
+// platform (inotify on Linux) through a background thread owned by `notify`, whose events are
+// forwarded to the event loop over a channel. `watchFile` polls the file with `stat` instead.
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fs::Metadata,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use llrt_abort::AbortSignal;
+use llrt_context::CtxExtension;
+use llrt_events::{EmitError, Emitter, EventEmitter, EventList};
+use llrt_utils::{
+    mc_oneshot,
+    object::ObjectExt,
+    result::{OptionExt, ResultExt},
+};
+use notify::{event::ModifyKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rquickjs::{
+    atom::PredefinedAtom,
+    class::{Trace, Tracer},
+    prelude::{Opt, This},
+    Class, Ctx, Exception, Function, IntoJs, JsLifetime, Object, Result, Undefined, Value,
+};
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+use super::Stats;
+
+// Same default as Node.js
+const DEFAULT_INTERVAL: u64 = 5007;
+
+struct WatchEvents {
+    rx: mpsc::UnboundedReceiver<notify::Result<Event>>,
+    root: PathBuf,
+    canonical_root: PathBuf,
+    pending: VecDeque<(&'static str, String)>,
+}
+
+impl WatchEvents {
+    /// Returns the next change as an event type and a file name relative to the watched path,
+    /// or `None` once the watcher has been dropped.
+    async fn next(&mut self) -> Option<notify::Result<(&'static str, String)>> {
+        loop {
+            if let Some(change) = self.pending.pop_front() {
+                return Some(Ok(change));
+            }
+            let event = match self.rx.recv().await? {
+                Ok(event) => event,
+                Err(err) => return Some(Err(err)),
+            };
+            let Some(event_type) = event_type(&event.kind) else {
+                continue;
+            };
+            for path in &event.paths {
+                self.pending.push_back((event_type, self.filename(path)));
+            }
+        }
+    }
+
+    fn filename(&self, path: &Path) -> String {
+        [&self.root, &self.canonical_root]
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .or_else(|| path.file_name().map(Path::new))
+            .map(|filename| filename.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+// Node.js only distinguishes between renames, which includes creating and removing files, and
+// changes to the contents or metadata of a file
+fn event_type(kind: &EventKind) -> Option<&'static str> {
+    match kind {
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+            Some("rename")
+        },
+        EventKind::Modify(_) | EventKind::Any => Some("change"),
+        EventKind::Access(_) | EventKind::Other => None,
+    }
+}
+
+fn start_watcher(
+    ctx: &Ctx<'_>,
+    path: &str,
+    recursive: bool,
+) -> Result<(RecommendedWatcher, WatchEvents)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        //it doesn't matter if the channel is closed because then the watcher is closed
+        let _ = tx.send(event);
+    })
+    .or_throw(ctx)?;
+
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    let root = PathBuf::from(path);
+    watcher
+        .watch(&root, mode)
+        .or_throw_msg(ctx, &["Can't watch \"", path, "\""].concat())?;
+
+    let canonical_root = std::fs::canonicalize(&root).unwrap_or_else(|_| root.clone());
+    let events = WatchEvents {
+        rx,
+        root,
+        canonical_root,
+        pending: VecDeque::new(),
+    };
+    Ok((watcher, events))
+}
+
+struct WatchOptions<'js> {
+    recursive: bool,
+    abort: Option<mc_oneshot::Receiver<Value<'js>>>,
+}
+
+impl<'js> WatchOptions<'js> {
+    fn from_object(options: Option<Object<'js>>) -> Result<Self> {
+        let Some(options) = options else {
+            return Ok(Self {
+                recursive: false,
+                abort: None,
+            });
+        };
+        let signal: Option<Class<AbortSignal>> = options.get_optional("signal")?;
+        Ok(Self {
+            recursive: options.get_optional("recursive")?.unwrap_or(false),
+            abort: signal.map(|signal| signal.borrow().sender.subscribe()),
+        })
+    }
+}
+
+/// Splits the optional options and listener arguments, where the options may be omitted.
+fn options_and_listener<'js>(
+    options_or_listener: Opt<Value<'js>>,
+    listener: Opt<Function<'js>>,
+) -> (Option<Object<'js>>, Option<Function<'js>>) {
+    match options_or_listener.0 {
+        Some(value) if value.is_function() => (None, value.into_function()),
+        Some(value) => (value.into_object(), listener.0),
+        None => (None, listener.0),
+    }
+}
+
+#[rquickjs::class]
+pub struct FSWatcher<'js> {
+    emitter: EventEmitter<'js>,
+    watcher: Option<RecommendedWatcher>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for FSWatcher<'js> {
+    type Changed<'to> = FSWatcher<'to>;
+}
+
+impl<'js> Trace<'js> for FSWatcher<'js> {
+    fn trace<'a>(&self, tracer: Tracer<'a, 'js>) {
+        self.emitter.trace(tracer);
+    }
+}
+
+impl<'js> Emitter<'js> for FSWatcher<'js> {
+    fn get_event_list(&self) -> Arc<RwLock<EventList<'js>>> {
+        self.emitter.get_event_list()
+    }
+}
+
+#[rquickjs::methods(rename_all = "camelCase")]
+impl<'js> FSWatcher<'js> {
+    /// Stops watching, the 'close' event is emitted once pending events are discarded.
+    pub fn close(&mut self) {
+        self.watcher.take();
+    }
+}
+
+pub fn watch<'js>(
+    ctx: Ctx<'js>,
+    path: String,
+    options_or_listener: Opt<Value<'js>>,
+    listener: Opt<Function<'js>>,
+) -> Result<Class<'js, FSWatcher<'js>>> {
+    let (options, listener) = options_and_listener(options_or_listener, listener);
+    let WatchOptions { recursive, abort } = WatchOptions::from_object(options)?;
+
+    let (watcher, mut events) = start_watcher(&ctx, &path, recursive)?;
+    let this = Class::instance(
+        ctx.clone(),
+        FSWatcher {
+            emitter: EventEmitter::new(),
+            watcher: Some(watcher),
+        },
+    )?;
+    if let Some(listener) = listener {
+        FSWatcher::add_event_listener_str(
+            This(this.clone()),
+            &ctx,
+            "change",
+            listener,
+            false,
+            false,
+        )?;
+    }
+
+    let this2 = this.clone();
+    let ctx2 = ctx.clone();
+    ctx.spawn_exit(async move {
+        loop {
+            let change = match abort.as_ref() {
+                Some(abort) => tokio::select! {
+                    change = events.next() => change,
+                    _ = abort.recv() => None,
+                },
+                None => events.next().await,
+            };
+            if this2.borrow().watcher.is_none() {
+                break;
+            }
+            match change {
+                Some(Ok((event_type, filename))) => {
+                    let args = vec![event_type.into_js(&ctx2)?, filename.into_js(&ctx2)?];
+                    FSWatcher::emit_str(This(this2.clone()), &ctx2, "change", args, false)?;
+                },
+                Some(Err(err)) => {
+                    this2.borrow_mut().watcher.take();
+                    Err::<(), _>(Exception::throw_message(&ctx2, &err.to_string())).emit_error(
+                        "watch",
+                        &ctx2,
+                        this2.clone(),
+                    )?;
+                    break;
+                },
+                None => break,
+            }
+        }
+        this2.borrow_mut().watcher.take();
+        FSWatcher::emit_str(This(this2), &ctx2, "close", vec![], false)?;
+        Ok(())
+    })?;
+
+    Ok(this)
+}
+
+#[rquickjs::class]
+#[derive(rquickjs::class::Trace)]
+pub struct WatchIterator<'js> {
+    #[qjs(skip_trace)]
+    watcher: RefCell<Option<RecommendedWatcher>>,
+    #[qjs(skip_trace)]
+    events: Rc<Mutex<WatchEvents>>,
+    #[qjs(skip_trace)]
+    abort: Option<mc_oneshot::Receiver<Value<'js>>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for WatchIterator<'js> {
+    type Changed<'to> = WatchIterator<'to>;
+}
+
+#[rquickjs::methods(rename_all = "camelCase")]
+impl<'js> WatchIterator<'js> {
+    async fn next(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        let result = Object::new(ctx.clone())?;
+        result.set("value", Undefined)?;
+        result.set("done", true)?;
+        if self.watcher.borrow().is_none() {
+            return Ok(result);
+        }
+
+        let events = self.events.clone();
+        let mut events = events.lock().await;
+        let change = match self.abort.as_ref() {
+            Some(abort) => tokio::select! {
+                change = events.next() => change,
+                reason = abort.recv() => {
+                    self.watcher.borrow_mut().take();
+                    return Err(ctx.throw(reason));
+                }
+            },
+            None => events.next().await,
+        };
+        if self.watcher.borrow().is_none() {
+            return Ok(result);
+        }
+
+        match change {
+            Some(Ok((event_type, filename))) => {
+                let value = Object::new(ctx.clone())?;
+                value.set("eventType", event_type)?;
+                value.set("filename", filename)?;
+                result.set("value", value)?;
+                result.set("done", false)?;
+            },
+            Some(Err(err)) => {
+                self.watcher.borrow_mut().take();
+                return Err(Exception::throw_message(&ctx, &err.to_string()));
+            },
+            None => {
+                self.watcher.borrow_mut().take();
+            },
+        }
+        Ok(result)
+    }
+
+    #[qjs(rename = "return")]
+    async fn close(&self, ctx: Ctx<'js>) -> Result<Object<'js>> {
+        self.watcher.borrow_mut().take();
+        let result = Object::new(ctx)?;
+        result.set("value", Undefined)?;
+        result.set("done", true)?;
+        Ok(result)
+    }
+
+    #[qjs(rename = PredefinedAtom::SymbolAsyncIterator)]
+    fn async_iterator(this: This<Class<'js, Self>>) -> Class<'js, Self> {
+        this.0
+    }
+}
+
+pub fn watch_promise<'js>(
+    ctx: Ctx<'js>,
+    path: String,
+    options: Opt<Object<'js>>,
+) -> Result<Class<'js, WatchIterator<'js>>> {
+    let WatchOptions { recursive, abort } = WatchOptions::from_object(options.0)?;
+    let (watcher, events) = start_watcher(&ctx, &path, recursive)?;
+    Class::instance(
+        ctx,
+        WatchIterator {
+            watcher: RefCell::new(Some(watcher)),
+            events: Rc::new(Mutex::new(events)),
+            abort,
+        },
+    )
+}
+
+#[rquickjs::class]
+pub struct StatWatcher<'js> {
+    emitter: EventEmitter<'js>,
+    stop_tx: Option<oneshot::Sender<()>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for StatWatcher<'js> {
+    type Changed<'to> = StatWatcher<'to>;
+}
+
+impl<'js> Trace<'js> for StatWatcher<'js> {
+    fn trace<'a>(&self, tracer: Tracer<'a, 'js>) {
+        self.emitter.trace(tracer);
+    }
+}
+
+impl<'js> Emitter<'js> for StatWatcher<'js> {
+    fn get_event_list(&self) -> Arc<RwLock<EventList<'js>>> {
+        self.emitter.get_event_list()
+    }
+}
+
+impl StatWatcher<'_> {
+    fn stop(&mut self) {
+        if let Some(stop_tx) = self.stop_tx.take() {
+            let _ = stop_tx.send(());
+        }
+    }
+}
+
+#[derive(Default)]
+struct StatWatchers<'js> {
+    watchers: HashMap<String, Class<'js, StatWatcher<'js>>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for StatWatchers<'js> {
+    type Changed<'to> = StatWatchers<'to>;
+}
+
+fn is_changed(prev: &Metadata, curr: &Metadata) -> bool {
+    if prev.len() != curr.len() || prev.modified().ok() != curr.modified().ok() {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let key = |metadata: &Metadata| {
+            (
+                metadata.ino(),
+                metadata.mode(),
+                metadata.nlink(),
+                metadata.uid(),
+                metadata.gid(),
+                metadata.ctime(),
+                metadata.ctime_nsec(),
+            )
+        };
+        key(prev) != key(curr)
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Polls the file every `interval` milliseconds and emits 'change' with the current and previous
+/// stats when they differ. Changes are only reported while the file exists.
+fn start_stat_watcher<'js>(
+    ctx: &Ctx<'js>,
+    path: String,
+    interval: u64,
+) -> Result<Class<'js, StatWatcher<'js>>> {
+    let (stop_tx, mut stop_rx) = oneshot::channel();
+    let this = Class::instance(
+        ctx.clone(),
+        StatWatcher {
+            emitter: EventEmitter::new(),
+            stop_tx: Some(stop_tx),
+        },
+    )?;
+
+    let this2 = this.clone();
+    let ctx2 = ctx.clone();
+    ctx.spawn_exit(async move {
+        let mut prev = tokio::fs::metadata(&path).await.ok();
+        let mut interval = tokio::time::interval(Duration::from_millis(interval.max(1)));
+        // The first tick completes immediately
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                _ = &mut stop_rx => break,
+            }
+            let curr = tokio::fs::metadata(&path).await.ok();
+            if let (Some(prev), Some(curr)) = (&prev, &curr) {
+                if is_changed(prev, curr) {
+                    let args = vec![
+                        Stats::new(curr.clone()).into_js(&ctx2)?,
+                        Stats::new(prev.clone()).into_js(&ctx2)?,
+                    ];
+                    StatWatcher::emit_str(This(this2.clone()), &ctx2, "change", args, false)?;
+                }
+            }
+            prev = curr;
+        }
+        Ok(())
+    })?;
+
+    Ok(this)
+}
+
+pub fn watch_file<'js>(
+    ctx: Ctx<'js>,
+    path: String,
+    options_or_listener: Opt<Value<'js>>,
+    listener: Opt<Function<'js>>,
+) -> Result<Class<'js, StatWatcher<'js>>> {
+    let (options, listener) = options_and_listener(options_or_listener, listener);
+    let listener = listener.or_throw_msg(&ctx, "\"listener\" must be a function")?;
+    let interval = match options {
+        Some(options) => options.get_optional("interval")?,
+        None => None,
+    };
+
+    if ctx.userdata::<RefCell<StatWatchers>>().is_none() {
+        let _ = ctx.store_userdata(RefCell::new(StatWatchers::default()));
+    }
+    let state = ctx.userdata::<RefCell<StatWatchers>>().or_throw(&ctx)?;
+
+    let existing = state.borrow().watchers.get(&path).cloned();
+    let watcher = match existing {
+        Some(watcher) => watcher,
+        None => {
+            let watcher =
+                start_stat_watcher(&ctx, path.clone(), interval.unwrap_or(DEFAULT_INTERVAL))?;
+            state.borrow_mut().watchers.insert(path, watcher.clone());
+            watcher
+        },
+    };
+    StatWatcher::add_event_listener_str(
+        This(watcher.clone()),
+        &ctx,
+        "change",
+        listener,
+        false,
+        false,
+    )
+}
+
+/// Removes `listener`, or all listeners, for the file and stops polling once there are none left.
+pub fn unwatch_file<'js>(ctx: Ctx<'js>, path: String, listener: Opt<Function<'js>>) -> Result<()> {
+    let Some(state) = ctx.userdata::<RefCell<StatWatchers>>() else {
+        return Ok(());
+    };
+    let Some(watcher) = state.borrow().watchers.get(&path).cloned() else {
+        return Ok(());
+    };
+
+    if let Some(listener) = listener.0 {
+        StatWatcher::remove_event_listener_str(This(watcher.clone()), &ctx, "change", listener)?;
+        if watcher.borrow().has_listener_str("change") {
+            return Ok(());
+        }
+    }
+    watcher.borrow_mut().stop();
+    state.borrow_mut().watchers.remove(&path);
+    Ok(())
+}
@@ -1,5 +1,6 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
+use llrt_timers::fake;
 use llrt_utils::{
     module::{export_default, ModuleInfo},
     time,
@@ -17,8 +18,8 @@ fn get_time_origin() -> f64 {
     time_origin / 1e6
 }
 
-fn now() -> f64 {
-    let now = time::now_nanos();
+fn now(ctx: Ctx<'_>) -> f64 {
+    let now = fake::now_nanos(&ctx);
     let started = time::origin_nanos();
     let elapsed = now.checked_sub(started).unwrap_or_default();
 
@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use std::env;
 use std::sync::atomic::{AtomicU8, Ordering};
 
+use llrt_timers::fake;
 use llrt_utils::primordials::{BasePrimordials, Primordial};
 pub use llrt_utils::sysinfo;
 use llrt_utils::{
@@ -22,6 +23,8 @@ use rquickjs::{
     Array, BigInt, Ctx, Error, Function, IntoJs, Object, Result, Value,
 };
 
+pub mod memory;
+
 pub static EXIT_CODE: AtomicU8 = AtomicU8::new(0);
 
 fn cwd(ctx: Ctx<'_>) -> Result<String> {
@@ -31,7 +34,7 @@ fn cwd(ctx: Ctx<'_>) -> Result<String> {
 }
 
 fn hr_time_big_int(ctx: Ctx<'_>) -> Result<BigInt<'_>> {
-    let now = time::now_nanos();
+    let now = fake::now_nanos(&ctx);
     let started = time::origin_nanos();
 
     let elapsed = now.checked_sub(started).unwrap_or_default();
@@ -40,7 +43,7 @@ fn hr_time_big_int(ctx: Ctx<'_>) -> Result<BigInt<'_>> {
 }
 
 fn hr_time(ctx: Ctx<'_>) -> Result<Array<'_>> {
-    let now = time::now_nanos();
+    let now = fake::now_nanos(&ctx);
     let started = time::origin_nanos();
     let elapsed = now.checked_sub(started).unwrap_or_default();
 
@@ -140,6 +143,9 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
     let hr_time = Function::new(ctx.clone(), hr_time)?;
     hr_time.set("bigint", Func::from(hr_time_big_int))?;
 
+    let memory_usage = Function::new(ctx.clone(), memory::memory_usage)?;
+    memory_usage.set("rss", Func::from(memory::rss))?;
+
     let release = Object::new(ctx.clone())?;
     release.prop("name", Property::from("llrt").enumerable())?;
 
@@ -166,6 +172,7 @@ pub fn init(ctx: &Ctx<'_>) -> Result<()> {
     process.set("platform", PLATFORM)?;
     process.set("arch", ARCH)?;
     process.set("hrtime", hr_time)?;
+    process.set("memoryUsage", memory_usage)?;
     process.set("release", release)?;
     process.set("version", VERSION)?;
     process.set("versions", process_versions)?;
@@ -222,6 +229,7 @@ impl ModuleDef for ProcessModule {
         declare.declare("platform")?;
         declare.declare("arch")?;
         declare.declare("hrtime")?;
+        declare.declare("memoryUsage")?;
         declare.declare("release")?;
         declare.declare("version")?;
         declare.declare("versions")?;
@@ -312,6 +320,36 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_memory_usage() {
+        test_async_with(|ctx| {
+            Box::pin(async move {
+                init(&ctx).unwrap();
+                ModuleEvaluator::eval_rust::<ProcessModule>(ctx.clone(), "process")
+                    .await
+                    .unwrap();
+
+                let module = ModuleEvaluator::eval_js(
+                    ctx.clone(),
+                    "test",
+                    r#"
+                        import { memoryUsage } from 'process';
+
+                        export async function test() {
+                            const usage = memoryUsage();
+                            return [usage.rss, usage.heapTotal, usage.heapUsed, memoryUsage.rss()]
+                        }
+                    "#,
+                )
+                .await
+                .unwrap();
+                let result = call_test::<Vec<f64>, _>(&ctx, &module, ()).await;
+                assert!(result.iter().all(|bytes| *bytes > 0.0));
+            })
+        })
+        .await;
+    }
+
     #[tokio::test]
     async fn test_hr_time_bigint() {
         time::init();
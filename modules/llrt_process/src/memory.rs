@@ -0,0 +1,67 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+use std::mem::MaybeUninit;
+
+use rquickjs::{qjs, Ctx, Object, Result};
+
+/// Computes the memory usage of the QuickJS runtime, walking all allocated objects.
+pub fn compute_memory_usage(ctx: &Ctx<'_>) -> qjs::JSMemoryUsage {
+    let mut usage = MaybeUninit::<qjs::JSMemoryUsage>::uninit();
+    unsafe {
+        let rt = qjs::JS_GetRuntime(ctx.as_raw().as_ptr());
+        qjs::JS_ComputeMemoryUsage(rt, usage.as_mut_ptr());
+        usage.assume_init()
+    }
+}
+
+/// Resident set size of the process in bytes.
+#[cfg(target_os = "linux")]
+pub fn resident_set_size() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * page_size as u64)
+}
+
+/// Peak resident set size of the process in bytes, the current size is not available without
+/// platform specific APIs.
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn resident_set_size() -> Option<u64> {
+    let mut usage = MaybeUninit::<libc::rusage>::uninit();
+    let usage = unsafe {
+        if libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) != 0 {
+            return None;
+        }
+        usage.assume_init()
+    };
+    let max_rss = usage.ru_maxrss as u64;
+    // macOS reports bytes, other BSDs kilobytes
+    if cfg!(target_os = "macos") {
+        Some(max_rss)
+    } else {
+        Some(max_rss * 1024)
+    }
+}
+
+#[cfg(not(unix))]
+pub fn resident_set_size() -> Option<u64> {
+    None
+}
+
+pub fn memory_usage(ctx: Ctx<'_>) -> Result<Object<'_>> {
+    let usage = compute_memory_usage(&ctx);
+    let rss = resident_set_size().unwrap_or(usage.malloc_size as u64);
+
+    let object = Object::new(ctx)?;
+    object.set("rss", rss)?;
+    object.set("heapTotal", usage.malloc_size)?;
+    object.set("heapUsed", usage.memory_used_size)?;
+    // QuickJS doesn't track array buffers separately, their contents are part of the heap
+    object.set("external", 0)?;
+    object.set("arrayBuffers", 0)?;
+    Ok(object)
+}
+
+pub fn rss(ctx: Ctx<'_>) -> u64 {
+    resident_set_size().unwrap_or_else(|| compute_memory_usage(&ctx).malloc_size as u64)
+}
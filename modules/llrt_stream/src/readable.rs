@@ -1,6 +1,9 @@
 // Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
 // SPDX-License-Identifier: Apache-2.0
-use std::sync::{atomic::AtomicUsize, Arc, RwLock};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, RwLock,
+};
 
 use llrt_buffer::Buffer;
 use llrt_context::CtxExtension;
@@ -79,6 +82,16 @@ impl<'js> ReadableStreamInner<'js> {
     }
 
     pub fn new(emitter: EventEmitter<'js>, emit_close: bool) -> Self {
+        Self::with_high_water_mark(emitter, emit_close, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Creates a stream that reads and buffers at most `high_water_mark` bytes at a time.
+    pub fn with_high_water_mark(
+        emitter: EventEmitter<'js>,
+        emit_close: bool,
+        high_water_mark: usize,
+    ) -> Self {
+        let high_water_mark = high_water_mark.max(1);
         let (destroy_tx, _) = broadcast::channel::<Option<Value<'js>>>(1);
         let (listener_attached_tx, _) = broadcast::channel::<()>(1);
         Self {
@@ -86,9 +99,9 @@ impl<'js> ReadableStreamInner<'js> {
             destroy_tx,
             is_ended: false,
             data_listener_attached_tx: listener_attached_tx,
-            buffer: BytearrayBuffer::new(DEFAULT_BUFFER_SIZE),
+            buffer: BytearrayBuffer::new(high_water_mark),
             state: ReadableState::Init,
-            high_water_mark: DEFAULT_BUFFER_SIZE.into(),
+            high_water_mark: high_water_mark.into(),
             listener: None,
             is_destroyed: false,
             emit_close,
@@ -243,6 +256,7 @@ where
             let is_ended = inner.is_ended;
             let mut is_destroyed = inner.is_destroyed;
             let emit_close = inner.emit_close;
+            let high_water_mark = inner.high_water_mark.load(Ordering::Relaxed);
 
             let mut listener_attached_tx = inner.data_listener_attached_tx.subscribe();
             let ba_buffer = inner.buffer.clone();
@@ -250,8 +264,8 @@ where
             drop(borrow);
 
             let read_function = async move {
-                let mut reader: BufReader<T> = BufReader::new(readable);
-                let mut buffer = Vec::<u8>::with_capacity(DEFAULT_BUFFER_SIZE);
+                let mut reader: BufReader<T> = BufReader::with_capacity(high_water_mark, readable);
+                let mut buffer = Vec::<u8>::with_capacity(high_water_mark);
                 let mut last_state = ReadableState::Init;
                 let mut error_value = None;
 
@@ -0,0 +1,233 @@
+// Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: Apache-2.0
+
+// Fake timers for tests.
+//
+// While fake timers are used, new timers are queued on a fake clock instead of being driven by
+// the event loop, and only fire when the clock is advanced. `performance.now` and
+// `process.hrtime` follow the fake clock through [`now_nanos`]. Timers created before fake timers were installed keep using
+// the real clock.
+use llrt_hooking::{invoke_async_hook, HookType};
+use llrt_utils::{provider::ProviderType, time};
+use rquickjs::{qjs, Ctx, Exception, Function, Persistent, Result};
+
+use crate::{get_timer_state, RT_TIMER_STATE};
+
+const MAX_TIMERS: usize = 100_000;
+
+pub(crate) struct FakeTimers {
+    // Milliseconds the fake clock has been advanced since it was installed
+    clock: u64,
+    // Wall clock time, in milliseconds since the epoch, when the fake clock was installed
+    system_time: f64,
+    start_nanos: u64,
+    next_order: u64,
+    timers: Vec<FakeTimeout>,
+}
+
+struct FakeTimeout {
+    callback: Persistent<Function<'static>>,
+    deadline: u64,
+    // Timers with the same deadline fire in the order they were scheduled
+    order: u64,
+    id: usize,
+    interval: Option<u64>,
+}
+
+impl FakeTimers {
+    pub(crate) fn push(
+        &mut self,
+        id: usize,
+        callback: Persistent<Function<'static>>,
+        delay: u64,
+        provider_type: ProviderType,
+    ) {
+        let (deadline, interval) = match provider_type {
+            ProviderType::Interval => (self.clock + delay, Some(delay.max(1))),
+            ProviderType::Immediate => (self.clock, None),
+            _ => (self.clock + delay, None),
+        };
+        let order = self.next_order();
+        self.timers.push(FakeTimeout {
+            callback,
+            deadline,
+            order,
+            id,
+            interval,
+        });
+    }
+
+    pub(crate) fn remove(&mut self, id: usize) {
+        self.timers.retain(|timeout| timeout.id != id);
+    }
+
+    fn next_order(&mut self) -> u64 {
+        self.next_order += 1;
+        self.next_order
+    }
+}
+
+fn rt_ptr(ctx: &Ctx<'_>) -> *mut qjs::JSRuntime {
+    unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) }
+}
+
+fn fake_timers<R>(ctx: &Ctx<'_>, f: impl FnOnce(&mut FakeTimers) -> R) -> Option<R> {
+    let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
+    let state = get_timer_state(&mut rt_timers, rt_ptr(ctx));
+    state.fake.as_mut().map(f)
+}
+
+fn with_fake_timers<R>(ctx: &Ctx<'_>, f: impl FnOnce(&mut FakeTimers) -> R) -> Result<R> {
+    match fake_timers(ctx, f) {
+        Some(result) => Ok(result),
+        None => Err(Exception::throw_message(
+            ctx,
+            "Fake timers are not in use, call useFakeTimers() first",
+        )),
+    }
+}
+
+/// Takes the next timer that is due according to `is_due`, advancing the clock to its deadline
+/// and rescheduling it if it is an interval. Timers may switch back to real timers, which ends
+/// the run.
+fn take_next<'js>(
+    ctx: &Ctx<'js>,
+    is_due: impl Fn(u64, u64) -> bool,
+) -> Result<Option<Function<'js>>> {
+    let callback = fake_timers(ctx, |fake| {
+        let index = fake
+            .timers
+            .iter()
+            .enumerate()
+            .filter(|(_, timeout)| is_due(timeout.deadline, timeout.order))
+            .min_by_key(|(_, timeout)| (timeout.deadline, timeout.order))
+            .map(|(index, _)| index)?;
+
+        let deadline = fake.timers[index].deadline;
+        if deadline > fake.clock {
+            fake.clock = deadline;
+        }
+        match fake.timers[index].interval {
+            Some(interval) => {
+                let order = fake.next_order();
+                let timeout = &mut fake.timers[index];
+                timeout.deadline += interval;
+                timeout.order = order;
+                Some(timeout.callback.clone())
+            },
+            None => Some(fake.timers.remove(index).callback),
+        }
+    })
+    .flatten();
+    callback.map(|callback| callback.restore(ctx)).transpose()
+}
+
+fn run_timers(ctx: &Ctx<'_>, is_due: impl Fn(u64, u64) -> bool) -> Result<()> {
+    for _ in 0..MAX_TIMERS {
+        let Some(callback) = take_next(ctx, &is_due)? else {
+            return Ok(());
+        };
+        // SAFETY: Since it checks in advance whether it is an Function type, we can always get a pointer to the Function.
+        let uid = unsafe { qjs::JS_VALUE_GET_PTR(callback.as_raw()) } as usize;
+
+        invoke_async_hook(ctx, HookType::Before, ProviderType::None, uid)?;
+        callback.call::<_, ()>(())?;
+        invoke_async_hook(ctx, HookType::After, ProviderType::None, uid)?;
+
+        while ctx.execute_pending_job() {}
+    }
+    Err(Exception::throw_message(
+        ctx,
+        &[
+            "Aborting after running ",
+            &MAX_TIMERS.to_string(),
+            " timers, assuming an infinite loop!",
+        ]
+        .concat(),
+    ))
+}
+
+/// Queues timers created from now on on a fake clock. `now` sets the time returned by
+/// [`now`], it defaults to the current time.
+pub fn use_fake_timers(ctx: &Ctx<'_>, now: Option<f64>) {
+    let start_nanos = time::now_nanos();
+    let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
+    let state = get_timer_state(&mut rt_timers, rt_ptr(ctx));
+    state.fake = Some(FakeTimers {
+        clock: 0,
+        system_time: now.unwrap_or((start_nanos / 1_000_000) as f64),
+        start_nanos,
+        next_order: 0,
+        timers: Vec::new(),
+    });
+}
+
+/// Restores the real clock. Pending fake timers are discarded.
+pub fn use_real_timers(ctx: &Ctx<'_>) {
+    let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
+    let state = get_timer_state(&mut rt_timers, rt_ptr(ctx));
+    state.fake = None;
+}
+
+pub fn is_using_fake_timers(ctx: &Ctx<'_>) -> bool {
+    let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
+    let state = get_timer_state(&mut rt_timers, rt_ptr(ctx));
+    state.fake.is_some()
+}
+
+/// Advances the fake clock by `ms`, running the timers that become due in order.
+pub fn advance_timers_by_time(ctx: &Ctx<'_>, ms: u64) -> Result<()> {
+    let target = with_fake_timers(ctx, |fake| fake.clock + ms)?;
+    run_timers(ctx, |deadline, _| deadline <= target)?;
+    fake_timers(ctx, |fake| {
+        if fake.clock < target {
+            fake.clock = target;
+        }
+    });
+    Ok(())
+}
+
+/// Runs timers until none are left, including the ones scheduled while running.
+pub fn run_all_timers(ctx: &Ctx<'_>) -> Result<()> {
+    run_timers(ctx, |_, _| true)
+}
+
+/// Runs the timers that are pending now, but not the ones they schedule.
+pub fn run_only_pending_timers(ctx: &Ctx<'_>) -> Result<()> {
+    let last_order = with_fake_timers(ctx, |fake| fake.next_order)?;
+    run_timers(ctx, |_, order| order <= last_order)
+}
+
+/// Returns the number of fake timers that have not fired or been cleared.
+pub fn get_timer_count(ctx: &Ctx<'_>) -> Result<usize> {
+    with_fake_timers(ctx, |fake| fake.timers.len())
+}
+
+/// Changes the time returned by [`now`] without running any timers.
+pub fn set_system_time(ctx: &Ctx<'_>, now: f64) -> Result<()> {
+    with_fake_timers(ctx, |fake| fake.system_time = now - fake.clock as f64)
+}
+
+/// Returns the current time in milliseconds since the epoch, following the fake clock when
+/// fake timers are used.
+pub fn now(ctx: &Ctx<'_>) -> f64 {
+    let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
+    let state = get_timer_state(&mut rt_timers, rt_ptr(ctx));
+    match state.fake.as_ref() {
+        Some(fake) => fake.system_time + fake.clock as f64,
+        None => time::now_millis() as f64,
+    }
+}
+
+/// Returns the current time in nanoseconds like [`time::now_nanos`], following the fake clock
+/// when fake timers are used.
+pub fn now_nanos(ctx: &Ctx<'_>) -> u64 {
+    let rt = rt_ptr(ctx);
+    let rt_timers = RT_TIMER_STATE.lock().unwrap();
+    rt_timers
+        .iter()
+        .find(|state| state.rt == rt)
+        .and_then(|state| state.fake.as_ref())
+        .map(|fake| fake.start_nanos + fake.clock * 1_000_000)
+        .unwrap_or_else(time::now_nanos)
+}
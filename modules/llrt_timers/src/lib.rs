@@ -29,6 +29,10 @@ use tokio::{
     time::{Instant, Sleep},
 };
 
+use crate::fake::FakeTimers;
+
+pub mod fake;
+
 static TIMER_ID: AtomicUsize = AtomicUsize::new(0);
 static RT_TIMER_STATE: Lazy<Mutex<Vec<RuntimeTimerState>>> = Lazy::new(|| Mutex::new(Vec::new()));
 
@@ -38,6 +42,7 @@ pub struct RuntimeTimerState {
     running: bool,
     deadline: Instant,
     notify: Rc<Notify>,
+    fake: Option<FakeTimers>,
 }
 impl RuntimeTimerState {
     fn new(rt: *mut qjs::JSRuntime) -> Self {
@@ -48,6 +53,7 @@ impl RuntimeTimerState {
             deadline,
             running: false,
             notify: Default::default(),
+            fake: None,
         }
     }
 }
@@ -124,6 +130,15 @@ pub fn set_timeout_interval<'js>(
 
     let callback = Persistent::<Function>::save(ctx, cb);
 
+    let rt_ptr = unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) };
+
+    let mut rt_timer = RT_TIMER_STATE.lock().unwrap();
+    let state = get_timer_state(&mut rt_timer, rt_ptr);
+    if let Some(fake) = state.fake.as_mut() {
+        fake.push(id, callback, delay, provider_type);
+        return Ok(id);
+    }
+
     let timeout = Timeout {
         deadline,
         callback: Some(callback),
@@ -133,10 +148,6 @@ pub fn set_timeout_interval<'js>(
         interval: delay,
     };
 
-    let rt_ptr = unsafe { qjs::JS_GetRuntime(ctx.as_raw().as_ptr()) };
-
-    let mut rt_timer = RT_TIMER_STATE.lock().unwrap();
-    let state = get_timer_state(&mut rt_timer, rt_ptr);
     state.timers.push(timeout);
     let task_running = state.running;
     if task_running {
@@ -171,6 +182,9 @@ fn clear_timeout_interval(ctx: Ctx<'_>, id: Opt<Value>) -> Result<()> {
         let mut rt_timers = RT_TIMER_STATE.lock().unwrap();
 
         let state = get_timer_state(&mut rt_timers, rt);
+        if let Some(fake) = state.fake.as_mut() {
+            fake.remove(id);
+        }
         if let Some(timeout) = state.timers.iter_mut().find(|t| t.id == id) {
             let _ = timeout.callback.take();
             timeout.repeating = false;
@@ -399,7 +413,9 @@ pub fn poll_timers(
 
 #[cfg(test)]
 mod tests {
-    use llrt_test::{call_test, test_async_with, ModuleEvaluator};
+    use llrt_test::{call_test, given_runtime, test_async_with, ModuleEvaluator};
+    use llrt_utils::time;
+    use rquickjs::async_with;
 
     use super::*;
 
@@ -553,4 +569,25 @@ mod tests {
         })
         .await;
     }
+
+    #[tokio::test]
+    async fn test_fake_clock_is_per_runtime() {
+        let (_fake_rt, fake_ctx) = given_runtime().await;
+        let (_real_rt, real_ctx) = given_runtime().await;
+
+        async_with!(fake_ctx => |ctx| {
+            init(&ctx).unwrap();
+            fake::use_fake_timers(&ctx, None);
+            let start = fake::now_nanos(&ctx);
+            fake::advance_timers_by_time(&ctx, 60_000).unwrap();
+            assert_eq!(fake::now_nanos(&ctx) - start, 60_000_000_000);
+        })
+        .await;
+
+        async_with!(real_ctx => |ctx| {
+            init(&ctx).unwrap();
+            assert!(fake::now_nanos(&ctx) < time::now_nanos() + 1_000_000_000);
+        })
+        .await;
+    }
 }
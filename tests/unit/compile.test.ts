@@ -2,8 +2,14 @@ import fs from "node:fs/promises";
 import { spawn } from "node:child_process";
 import { tmpdir } from "node:os";
 
-const spawnCapture = async (cmd: string, args: string[]) => {
-  const child = spawn(cmd, args);
+const TIMEOUT = 30000;
+
+const spawnCapture = async (
+  cmd: string,
+  args: string[],
+  env: Record<string, string> = {}
+) => {
+  const child = spawn(cmd, args, { env: { ...process.env, ...env } });
 
   let stdout = "";
   let stderr = "";
@@ -30,17 +36,22 @@ const spawnCapture = async (cmd: string, args: string[]) => {
 const compile = async (
   filename: string,
   outputFilename: string,
-  executable = false
+  executable = false,
+  bundle = false,
+  extraArgs: string[] = []
 ) => {
-  const args = ["compile", filename, outputFilename];
+  const args = ["compile", filename, outputFilename, ...extraArgs];
   if (executable) {
     args.push("--executable");
   }
+  if (bundle) {
+    args.push("--bundle");
+  }
   return await spawnCapture(process.argv0, args);
 };
 
-const run = async (filename: string) =>
-  await spawnCapture(process.argv0, [filename]);
+const run = async (filename: string, env: Record<string, string> = {}) =>
+  await spawnCapture(process.argv0, [filename], env);
 
 if (false) {
   describe("llrt compile", async () => {
@@ -113,3 +124,189 @@ if (false) {
     });
   });
 }
+
+describe("llrt compile --bundle", () => {
+  let tmpDir: string;
+
+  beforeEach(async () => {
+    tmpDir = await fs.mkdtemp(`${tmpdir()}/llrt-test-compile`);
+  });
+
+  afterEach(async () => {
+    await fs.rm(tmpDir, { recursive: true, force: true });
+  });
+
+  it(
+    "can compile and run a bundle",
+    async () => {
+      const srcDir = `${tmpDir}/src`;
+      const tmpOutput = `${tmpDir}/bundle.lrt`;
+      await fs.mkdir(srcDir);
+      for (const file of ["index.mjs", "lib.mjs", "data.json"]) {
+        await fs.writeFile(
+          `${srcDir}/${file}`,
+          await fs.readFile(`fixtures/bundle/${file}`)
+        );
+      }
+
+      const compileResult = await compile(
+        `${srcDir}/index.mjs`,
+        tmpOutput,
+        false,
+        true
+      );
+
+      expect(compileResult.stderr).toEqual("");
+      expect(compileResult.status).toEqual(0);
+
+      // Dependencies must be loaded from the archive, not from disk
+      await fs.rm(srcDir, { recursive: true });
+
+      const runResult = await run(tmpOutput);
+
+      expect(runResult.stdout).toEqual("hello world!\n");
+      expect(runResult.stderr).toEqual("");
+      expect(runResult.status).toEqual(0);
+    },
+    TIMEOUT
+  );
+
+  it(
+    "fails on missing dependencies",
+    async () => {
+      const srcDir = `${tmpDir}/src`;
+      await fs.mkdir(srcDir);
+      await fs.writeFile(`${srcDir}/index.mjs`, 'import "./missing.mjs";');
+
+      const compileResult = await compile(
+        `${srcDir}/index.mjs`,
+        `${tmpDir}/bundle.lrt`,
+        false,
+        true
+      );
+
+      expect(compileResult.status).not.toEqual(0);
+    },
+    TIMEOUT
+  );
+});
+
+describe("llrt compile bytecode integrity", () => {
+  const PUBLIC_KEY = "fixtures/signing/key.pub.pem";
+  const OTHER_PUBLIC_KEY = "fixtures/signing/other.pub.pem";
+  let tmpDir: string;
+
+  beforeEach(async () => {
+    tmpDir = await fs.mkdtemp(`${tmpdir()}/llrt-test-compile`);
+  });
+
+  afterEach(async () => {
+    await fs.rm(tmpDir, { recursive: true, force: true });
+  });
+
+  const compileSigned = async (tmpOutput: string) => {
+    const compileResult = await compile(
+      "fixtures/hello.js",
+      tmpOutput,
+      false,
+      false,
+      ["--sign", "fixtures/signing/key.pem"]
+    );
+    expect(compileResult.stderr).toEqual("");
+    expect(compileResult.status).toEqual(0);
+  };
+
+  it(
+    "refuses to run modified bytecode",
+    async () => {
+      const tmpOutput = `${tmpDir}/modified.lrt`;
+
+      const compileResult = await compile("fixtures/hello.js", tmpOutput);
+
+      expect(compileResult.stderr).toEqual("");
+
+      const bytes = await fs.readFile(tmpOutput);
+      bytes[bytes.length - 1] ^= 0xff;
+      await fs.writeFile(tmpOutput, bytes);
+
+      const runResult = await run(tmpOutput);
+
+      expect(runResult.stdout).toEqual("");
+      expect(runResult.stderr).toContain("integrity check failed");
+      expect(runResult.status).toEqual(1);
+    },
+    TIMEOUT
+  );
+
+  it(
+    "runs signed bytecode with the matching public key",
+    async () => {
+      const tmpOutput = `${tmpDir}/signed.lrt`;
+      await compileSigned(tmpOutput);
+
+      const runResult = await run(tmpOutput, {
+        LLRT_BYTECODE_PUBLIC_KEY: PUBLIC_KEY,
+      });
+
+      expect(runResult.stdout).toEqual("hello world!\n");
+      expect(runResult.stderr).toEqual("");
+      expect(runResult.status).toEqual(0);
+    },
+    TIMEOUT
+  );
+
+  it(
+    "refuses to run unsigned bytecode when a public key is set",
+    async () => {
+      const tmpOutput = `${tmpDir}/unsigned.lrt`;
+      const compileResult = await compile("fixtures/hello.js", tmpOutput);
+      expect(compileResult.status).toEqual(0);
+
+      const runResult = await run(tmpOutput, {
+        LLRT_BYTECODE_PUBLIC_KEY: PUBLIC_KEY,
+      });
+
+      expect(runResult.stdout).toEqual("");
+      expect(runResult.stderr).toContain("Bytecode is not signed");
+      expect(runResult.status).toEqual(1);
+    },
+    TIMEOUT
+  );
+
+  it(
+    "refuses to run bytecode signed with another key",
+    async () => {
+      const tmpOutput = `${tmpDir}/other.lrt`;
+      await compileSigned(tmpOutput);
+
+      const runResult = await run(tmpOutput, {
+        LLRT_BYTECODE_PUBLIC_KEY: OTHER_PUBLIC_KEY,
+      });
+
+      expect(runResult.stdout).toEqual("");
+      expect(runResult.stderr).toContain("signature verification failed");
+      expect(runResult.status).toEqual(1);
+    },
+    TIMEOUT
+  );
+
+  it(
+    "refuses to run modified signed bytecode",
+    async () => {
+      const tmpOutput = `${tmpDir}/modified-signed.lrt`;
+      await compileSigned(tmpOutput);
+
+      const bytes = await fs.readFile(tmpOutput);
+      bytes[bytes.length - 1] ^= 0xff;
+      await fs.writeFile(tmpOutput, bytes);
+
+      const runResult = await run(tmpOutput, {
+        LLRT_BYTECODE_PUBLIC_KEY: PUBLIC_KEY,
+      });
+
+      expect(runResult.stdout).toEqual("");
+      expect(runResult.status).toEqual(1);
+    },
+    TIMEOUT
+  );
+});
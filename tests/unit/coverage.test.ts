@@ -0,0 +1,99 @@
+import { spawn } from "node:child_process";
+import fs from "node:fs/promises";
+import os from "node:os";
+import path from "node:path";
+
+const run = (args: string[], cwd: string) =>
+  new Promise<{ code: number | null; stdout: string; stderr: string }>(
+    (resolve, reject) => {
+      const proc = spawn(process.argv0, args, { cwd });
+      let stdout = "";
+      let stderr = "";
+      proc.stdout.on("data", (data) => (stdout += data.toString()));
+      proc.stderr.on("data", (data) => (stderr += data.toString()));
+      proc.on("error", reject);
+      proc.on("close", (code) => resolve({ code, stdout, stderr }));
+    }
+  );
+
+describe("coverage", () => {
+  let dir: string;
+
+  beforeEach(async () => {
+    dir = await fs.mkdtemp(path.join(os.tmpdir(), "llrt-coverage-"));
+    await fs.writeFile(
+      path.join(dir, "math.js"),
+      [
+        "export function add(a, b) {",
+        "  return a + b;",
+        "}",
+        "",
+        "export function sub(a, b) {",
+        "  return a - b;",
+        "}",
+        "",
+      ].join("\n")
+    );
+    await fs.writeFile(
+      path.join(dir, "math.test.js"),
+      [
+        "import { add } from './math.js';",
+        "",
+        "it('should add', () => {",
+        "  expect(add(1, 2)).toBe(3);",
+        "});",
+        "",
+      ].join("\n")
+    );
+  });
+
+  afterEach(async () => {
+    await fs.rm(dir, { recursive: true, force: true });
+  });
+
+  it("should write lcov and istanbul reports", async () => {
+    const { code, stderr } = await run(["test", "--coverage"], dir);
+    expect(code).toBe(0);
+    expect(stderr).toContain("math.js");
+
+    const lcov = await fs.readFile(
+      path.join(dir, "coverage", "lcov.info"),
+      "utf8"
+    );
+    expect(lcov).toContain("math.js");
+    expect(lcov).not.toContain("math.test.js");
+    expect(lcov).toContain("FNDA:1,add");
+    expect(lcov).toContain("FNDA:0,sub");
+    expect(lcov).toContain("DA:2,1");
+    expect(lcov).toContain("DA:6,0");
+
+    const json = JSON.parse(
+      await fs.readFile(
+        path.join(dir, "coverage", "coverage-final.json"),
+        "utf8"
+      )
+    );
+    const [file] = Object.keys(json);
+    expect(file).toContain("math.js");
+    expect(Object.keys(json[file].statementMap).length).toBeGreaterThan(0);
+    expect(Object.values(json[file].f)).toEqual([1, 0]);
+  });
+
+  it("should write reports to the given directory", async () => {
+    const { code } = await run(["test", "--coverage-dir", "out"], dir);
+    expect(code).toBe(0);
+
+    const files = await fs.readdir(path.join(dir, "out"));
+    expect(files.sort()).toEqual(["coverage-final.json", "lcov.info"]);
+  });
+
+  it("should keep the summary out of reporter output", async () => {
+    const { code, stdout, stderr } = await run(
+      ["test", "--coverage", "--reporter", "json"],
+      dir
+    );
+    expect(code).toBe(0);
+    expect(() => JSON.parse(stdout)).not.toThrow();
+    expect(stderr).toContain("math.js");
+  });
+});
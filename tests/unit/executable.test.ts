@@ -2,6 +2,7 @@ import { spawn } from "node:child_process";
 import {
   accessSync,
   constants,
+  mkdirSync,
   mkdtempSync,
   readFileSync,
   rmSync,
@@ -14,23 +15,34 @@ import { join } from "node:path";
 const TIMEOUT = 30000;
 const IS_WINDOWS = platform() === "win32";
 
-const spawnCapture = async (cmd: string, args: string[] = []) =>
+const spawnCapture = async (cmd: string, args: string[] = [], cwd?: string) =>
   await new Promise<number>((resolve, reject) =>
-    spawn(cmd, args)
+    spawn(cmd, args, { cwd })
       .on("close", (code) => resolve(code ?? -1))
       .on("error", (err) => reject(err))
   );
 
-const compile = async (filename: string, outputFilename: string) => {
-  const args = ["compile", filename, outputFilename, "--executable"];
-  const compileResult = await spawnCapture(process.argv0, args);
+const compile = async (
+  filename: string,
+  outputFilename: string,
+  extraArgs: string[] = [],
+  cwd?: string
+) => {
+  const args = [
+    "compile",
+    filename,
+    outputFilename,
+    "--executable",
+    ...extraArgs,
+  ];
+  const compileResult = await spawnCapture(process.argv0, args, cwd);
   if (compileResult !== 0) {
     throw new Error(`Compilation failed with exit code ${compileResult}`);
   }
 };
 
-const run = async (filename: string, args: string[] = []) =>
-  await spawnCapture(filename, args);
+const run = async (filename: string, args: string[] = [], cwd?: string) =>
+  await spawnCapture(filename, args, cwd);
 
 describe("executable compilation", () => {
   let tmpDir: string;
@@ -135,4 +147,65 @@ describe("executable compilation", () => {
     },
     TIMEOUT
   );
+
+  it(
+    "should read embedded assets",
+    async () => {
+      mkdirSync(join(tmpDir, "config"));
+      writeFileSync(join(tmpDir, "config", "app.json"), '{"name":"app"}');
+      writeFileSync(join(tmpDir, "template.txt"), "Hello");
+      const scriptPath = createTestScript(`
+        import { accessSync, readdirSync, readFileSync, statSync } from 'fs';
+        import { access, readdir, readFile, stat } from 'fs/promises';
+        import { join } from 'path';
+
+        const dir = process.argv.at(-1);
+        const config = require('./config/app.json');
+        const template = readFileSync(join(dir, 'template.txt'), 'utf8');
+        const json = JSON.parse(await readFile(join(dir, 'config/app.json'), 'utf8'));
+        accessSync(join(dir, 'config'));
+        await access(join(dir, 'config/app.json'));
+        const file = statSync(join(dir, 'template.txt'));
+        const folder = await stat(join(dir, 'config'));
+        const entries = readdirSync(dir, { withFileTypes: true })
+          .filter((entry) => !entry.name.startsWith('exe_test'))
+          .map((entry) => entry.name + ':' + entry.isDirectory());
+        const nested = await readdir(join(dir, 'config'));
+        const ok =
+          config.name === 'app' &&
+          json.name === 'app' &&
+          template === 'Hello' &&
+          file.isFile() &&
+          file.size === 5 &&
+          folder.isDirectory() &&
+          entries.join() === 'config:true,template.txt:false' &&
+          nested.join() === 'app.json';
+        process.exit(ok ? 0 : 1);
+      `);
+
+      await compile(
+        scriptPath,
+        exePath,
+        ["--asset", "config", "--asset", "template.txt"],
+        tmpDir
+      );
+
+      // Assets must be read from the executable rather than from disk
+      rmSync(join(tmpDir, "config"), { recursive: true });
+      rmSync(join(tmpDir, "template.txt"));
+      rmSync(scriptPath);
+
+      // and be found next to it regardless of the working directory
+      const cwd = mkdtempSync(join(tmpdir(), "llrt-test-cwd"));
+      try {
+        const execResult = await run(exePath, [tmpDir], cwd);
+        if (execResult !== 0) {
+          throw new Error(`Expected exit code 0, got ${execResult}`);
+        }
+      } finally {
+        rmSync(cwd, { recursive: true });
+      }
+    },
+    TIMEOUT
+  );
 });
@@ -0,0 +1,136 @@
+describe("fake timers", () => {
+  afterEach(() => {
+    jest.useRealTimers();
+  });
+
+  it("should run timers when the clock is advanced", () => {
+    jest.useFakeTimers();
+    const calls: string[] = [];
+    setTimeout(() => calls.push("second"), 200);
+    setTimeout(() => calls.push("first"), 100);
+
+    jest.advanceTimersByTime(99);
+    expect(calls).toEqual([]);
+    jest.advanceTimersByTime(1);
+    expect(calls).toEqual(["first"]);
+    jest.advanceTimersByTime(100);
+    expect(calls).toEqual(["first", "second"]);
+    expect(jest.getTimerCount()).toBe(0);
+  });
+
+  it("should repeat intervals until cleared", () => {
+    jest.useFakeTimers();
+    const callback = jest.fn();
+    const id = setInterval(callback, 10);
+
+    jest.advanceTimersByTime(35);
+    expect(callback).toHaveBeenCalledTimes(3);
+    clearInterval(id);
+    jest.advanceTimersByTime(100);
+    expect(callback).toHaveBeenCalledTimes(3);
+  });
+
+  it("should not run cleared timers", () => {
+    jest.useFakeTimers();
+    const callback = jest.fn();
+    const id = setTimeout(callback, 10);
+
+    expect(jest.getTimerCount()).toBe(1);
+    clearTimeout(id);
+    expect(jest.getTimerCount()).toBe(0);
+    jest.runAllTimers();
+    expect(callback).not.toHaveBeenCalled();
+  });
+
+  it("should run all timers including nested ones", () => {
+    jest.useFakeTimers();
+    const calls: number[] = [];
+    setTimeout(() => {
+      calls.push(1);
+      setTimeout(() => calls.push(2), 1000);
+      setImmediate(() => calls.push(3));
+    }, 1000);
+
+    jest.runAllTimers();
+    expect(calls).toEqual([1, 3, 2]);
+  });
+
+  it("should only run pending timers", () => {
+    jest.useFakeTimers();
+    const calls: number[] = [];
+    setTimeout(() => {
+      calls.push(1);
+      setTimeout(() => calls.push(2), 10);
+    }, 10);
+
+    jest.runOnlyPendingTimers();
+    expect(calls).toEqual([1]);
+    expect(jest.getTimerCount()).toBe(1);
+    jest.runOnlyPendingTimers();
+    expect(calls).toEqual([1, 2]);
+  });
+
+  it("should run pending timers due after an interval", () => {
+    jest.useFakeTimers();
+    const interval = jest.fn();
+    const timeout = jest.fn();
+    setInterval(interval, 10);
+    setTimeout(timeout, 50);
+
+    jest.runOnlyPendingTimers();
+    expect(interval).toHaveBeenCalledTimes(1);
+    expect(timeout).toHaveBeenCalledTimes(1);
+    expect(jest.getTimerCount()).toBe(1);
+  });
+
+  it("should abort infinite timer loops", () => {
+    jest.useFakeTimers();
+    setInterval(() => {}, 1);
+
+    expect(() => jest.runAllTimers()).toThrow(
+      "Aborting after running 100000 timers, assuming an infinite loop!"
+    );
+  });
+
+  it("should mock Date and performance.now", () => {
+    jest.useFakeTimers({ now: new Date("2024-01-01T00:00:00Z") });
+    const started = performance.now();
+
+    expect(Date.now()).toBe(1704067200000);
+    expect(new Date().toISOString()).toBe("2024-01-01T00:00:00.000Z");
+    expect(new Date(0).getTime()).toBe(0);
+    expect(new Date()).toBeInstanceOf(Date);
+
+    jest.advanceTimersByTime(1500);
+    expect(Date.now()).toBe(1704067201500);
+    expect(performance.now() - started).toBeCloseTo(1500);
+
+    jest.setSystemTime(new Date("2030-01-01T00:00:00Z"));
+    expect(new Date().toISOString()).toBe("2030-01-01T00:00:00.000Z");
+    expect(performance.now() - started).toBeCloseTo(1500);
+  });
+
+  it("should mock process.hrtime", () => {
+    jest.useFakeTimers();
+    const started = process.hrtime.bigint();
+
+    jest.advanceTimersByTime(1500);
+    expect(process.hrtime.bigint() - started).toBe(1_500_000_000n);
+  });
+
+  it("should restore real timers", async () => {
+    jest.useFakeTimers({ now: 0 });
+    expect(jest.isFakeTimers()).toBe(true);
+    jest.useRealTimers();
+
+    expect(jest.isFakeTimers()).toBe(false);
+    expect(Date.now()).toBeGreaterThan(0);
+    await new Promise((resolve) => setTimeout(resolve, 1));
+  });
+
+  it("should throw when fake timers are not in use", () => {
+    expect(() => jest.advanceTimersByTime(10)).toThrow(
+      "Fake timers are not in use"
+    );
+  });
+});
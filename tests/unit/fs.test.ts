@@ -12,6 +12,8 @@ it("node:fs should be the same as fs", () => {
 const {
   constants,
   accessSync,
+  createReadStream,
+  createWriteStream,
   readdirSync,
   readFileSync,
   mkdtempSync,
@@ -20,19 +22,41 @@ const {
   rmSync,
   rmdirSync,
   statSync,
+  lstat: lstatCb,
+  lstatSync,
+  symlink: symlinkCb,
+  symlinkSync,
+  readlink: readlinkCb,
+  readlinkSync,
+  link: linkCb,
+  linkSync,
+  realpath: realpathCb,
+  realpathSync,
+  unlink: unlinkCb,
+  unlinkSync,
+  unwatchFile,
+  watch,
+  watchFile,
   writeFileSync,
   promises,
 } = defaultImport;
 
 const {
   access,
+  link,
+  lstat,
   mkdir,
   mkdtemp,
+  open,
   readdir,
   readFile,
+  readlink,
+  realpath,
   rename,
   rm,
   rmdir,
+  symlink,
+  unlink,
   writeFile,
 } = promises;
 
@@ -505,6 +529,488 @@ describe("renameSync", () => {
   });
 });
 
+const readAll = (stream: ReturnType<typeof createReadStream>) =>
+  new Promise<Buffer[]>((resolve, reject) => {
+    const chunks: Buffer[] = [];
+    stream.on("data", (chunk) => chunks.push(chunk));
+    stream.on("error", reject);
+    stream.on("end", () => resolve(chunks));
+  });
+
+describe("createReadStream", () => {
+  it("should read a file", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+    await writeFile(filePath, "hello world");
+
+    const stream = createReadStream(filePath);
+    expect(stream.path).toEqual(filePath);
+    expect(stream.pending).toBeTruthy();
+
+    const chunks = await readAll(stream);
+
+    expect(Buffer.concat(chunks).toString()).toEqual("hello world");
+    expect(stream.bytesRead).toEqual(11);
+    expect(stream.pending).toBeFalsy();
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should read a range of a file", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+    await writeFile(filePath, "hello world");
+
+    const chunks = await readAll(
+      createReadStream(filePath, { start: 6, end: 9 })
+    );
+
+    expect(Buffer.concat(chunks).toString()).toEqual("worl");
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should read in chunks of at most highWaterMark bytes", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+    await writeFile(filePath, Buffer.alloc(100, "a"));
+
+    const stream = createReadStream(filePath, { highWaterMark: 16 });
+    expect(stream.readableHighWaterMark).toEqual(16);
+    const chunks = await readAll(stream);
+
+    expect(chunks.every((chunk) => chunk.length <= 16)).toBeTruthy();
+    expect(Buffer.concat(chunks).length).toEqual(100);
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should emit open and close", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+    await writeFile(filePath, "hello");
+
+    const events: string[] = [];
+    const stream = createReadStream(filePath);
+    stream.on("open", (fd) => events.push(`open ${typeof fd}`));
+    stream.on("ready", () => events.push("ready"));
+    stream.on("data", () => {});
+    stream.on("end", () => events.push("end"));
+    await new Promise((resolve) => stream.on("close", resolve));
+
+    expect(events).toEqual(["open number", "ready", "end"]);
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should emit an error if the file doesn't exist", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "nonexistent");
+
+    await expect(readAll(createReadStream(filePath))).rejects.toThrow(
+      /[Nn]o such file or directory/
+    );
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should throw if start is greater than end", () => {
+    expect(() => createReadStream("test", { start: 2, end: 1 })).toThrow(
+      RangeError
+    );
+  });
+
+  it("should pipe to a write stream", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const source = path.join(tmpDir, "source");
+    const destination = path.join(tmpDir, "destination");
+    const data = Buffer.alloc(256 * 1024);
+    for (let i = 0; i < data.length; i++) {
+      data[i] = i % 251;
+    }
+    await writeFile(source, data);
+
+    const writeStream = createWriteStream(destination);
+    const result = createReadStream(source).pipe(writeStream);
+    expect(result).toBe(writeStream);
+    await new Promise((resolve) => writeStream.on("close", resolve));
+
+    expect((await readFile(destination)).equals(data)).toBeTruthy();
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should pause and resume reading", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+    await writeFile(filePath, Buffer.alloc(64, "a"));
+
+    const stream = createReadStream(filePath, { highWaterMark: 16 });
+    let readWhilePaused = -1;
+    let length = 0;
+    stream.on("data", (chunk) => {
+      if (length === 0) {
+        stream.pause();
+        expect(stream.isPaused()).toBeTruthy();
+        setTimeout(() => {
+          readWhilePaused = length - chunk.length;
+          stream.resume();
+        }, 20);
+      }
+      length += chunk.length;
+    });
+    await new Promise((resolve) => stream.on("end", resolve));
+
+    expect(readWhilePaused).toEqual(0);
+    expect(length).toEqual(64);
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should pause the source while the destination drains", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const source = path.join(tmpDir, "source");
+    const destination = path.join(tmpDir, "destination");
+    const data = Buffer.alloc(1024, "a");
+    await writeFile(source, data);
+
+    const readStream = createReadStream(source, { highWaterMark: 16 });
+    const writeStream = createWriteStream(destination, { highWaterMark: 16 });
+    readStream.pipe(writeStream);
+    let paused = false;
+    let maxLength = 0;
+    readStream.on("data", () => {
+      paused ||= readStream.isPaused();
+      maxLength = Math.max(maxLength, writeStream.writableLength);
+    });
+    await new Promise((resolve) => writeStream.on("close", resolve));
+
+    expect(paused).toBeTruthy();
+    expect(maxLength).toBeLessThanOrEqual(16);
+    expect((await readFile(destination)).equals(data)).toBeTruthy();
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+});
+
+describe("createWriteStream", () => {
+  it("should write a file", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+
+    const stream = createWriteStream(filePath);
+    expect(stream.write("hello ")).toBeTruthy();
+    await new Promise((resolve) => stream.end("world", resolve));
+
+    expect((await readFile(filePath)).toString()).toEqual("hello world");
+    expect(stream.bytesWritten).toEqual(11);
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should append to a file", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+    await writeFile(filePath, "hello");
+
+    const stream = createWriteStream(filePath, { flags: "a" });
+    await new Promise((resolve) => stream.end(" world", resolve));
+
+    expect((await readFile(filePath)).toString()).toEqual("hello world");
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should write at the start position", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+    await writeFile(filePath, "hello world");
+
+    const stream = createWriteStream(filePath, { flags: "r+", start: 6 });
+    await new Promise((resolve) => stream.end("WORLD", resolve));
+
+    expect((await readFile(filePath)).toString()).toEqual("hello WORLD");
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should emit drain once queued data is written", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+
+    const stream = createWriteStream(filePath, { highWaterMark: 4 });
+    expect(stream.writableHighWaterMark).toEqual(4);
+    expect(stream.write("ab")).toBeTruthy();
+    expect(stream.write("cd")).toBeFalsy();
+    expect(stream.writableLength).toEqual(4);
+    expect(stream.writableNeedDrain).toBeTruthy();
+    await new Promise((resolve) => stream.once("drain", resolve));
+
+    expect(stream.writableLength).toEqual(0);
+    expect(stream.writableNeedDrain).toBeFalsy();
+    await new Promise((resolve) => stream.end(resolve));
+    expect((await readFile(filePath)).toString()).toEqual("abcd");
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should throw on invalid flags", () => {
+    expect(() => createWriteStream("test", { flags: "x" as any })).toThrow(
+      "Invalid flags 'x'"
+    );
+  });
+});
+
+describe("FileHandle.readableWebStream", () => {
+  it("should read a file", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+    await writeFile(filePath, "hello world");
+
+    const file = await open(filePath);
+    const chunks: Uint8Array[] = [];
+    for await (const chunk of file.readableWebStream()) {
+      chunks.push(chunk);
+    }
+    await file.close();
+
+    expect(Buffer.concat(chunks).toString()).toEqual("hello world");
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+});
+
+describe("watch", () => {
+  it("should emit rename and change events", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+
+    const events: [string, string][] = [];
+    const watcher = watch(tmpDir, (eventType, filename) => {
+      events.push([eventType, filename]);
+    });
+    const closed = new Promise((resolve) => watcher.on("close", resolve));
+
+    await writeFile(filePath, "hello");
+    await new Promise((resolve) => setTimeout(resolve, 100));
+    await writeFile(filePath, "hello world");
+    await new Promise((resolve) => setTimeout(resolve, 100));
+    watcher.close();
+    await closed;
+
+    expect(events).toContainEqual(["rename", "test"]);
+    expect(events).toContainEqual(["change", "test"]);
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should stop on abort", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const controller = new AbortController();
+    const watcher = watch(tmpDir, { signal: controller.signal });
+    const closed = new Promise((resolve) => watcher.on("close", resolve));
+
+    controller.abort();
+    await closed;
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+});
+
+describe("promises.watch", () => {
+  it("should yield change events", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+
+    setTimeout(() => writeFile(filePath, "hello"), 100);
+    for await (const event of promises.watch(tmpDir)) {
+      expect(event).toEqual({ eventType: "rename", filename: "test" });
+      break;
+    }
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should reject when aborted", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const controller = new AbortController();
+
+    setTimeout(() => controller.abort(), 100);
+    const iterate = async () => {
+      for await (const _ of promises.watch(tmpDir, {
+        signal: controller.signal,
+      })) {
+      }
+    };
+    await expect(iterate()).rejects.toThrow();
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+});
+
+describe("watchFile", () => {
+  it("should report changes with current and previous stats", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+    await writeFile(filePath, "hello");
+
+    const [curr, prev] = await new Promise<any[]>((resolve) => {
+      watchFile(filePath, { interval: 10 }, (curr, prev) =>
+        resolve([curr, prev])
+      );
+      setTimeout(() => writeFile(filePath, "hello world"), 50);
+    });
+    unwatchFile(filePath);
+
+    expect(prev.size).toEqual(5);
+    expect(curr.size).toEqual(11);
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should require a listener", () => {
+    expect(() => watchFile("test")).toThrow(/listener/);
+  });
+
+  it("should stop reporting after unwatchFile", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "test");
+    await writeFile(filePath, "hello");
+
+    let calls = 0;
+    const listener = () => calls++;
+    watchFile(filePath, { interval: 10 }, listener);
+    unwatchFile(filePath, listener);
+
+    await writeFile(filePath, "hello world");
+    await new Promise((resolve) => setTimeout(resolve, 100));
+    expect(calls).toEqual(0);
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+});
+
+describe("symlinks", () => {
+  it("should create and read a symlink", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "target");
+    const linkPath = path.join(tmpDir, "link");
+    await writeFile(filePath, "hello");
+
+    await symlink(filePath, linkPath);
+
+    expect(await readlink(linkPath)).toEqual(filePath);
+    expect((await lstat(linkPath)).isSymbolicLink()).toBe(true);
+    expect((await lstat(filePath)).isSymbolicLink()).toBe(false);
+    expect((await promises.stat(linkPath)).isSymbolicLink()).toBe(false);
+    expect((await readFile(linkPath)).toString()).toEqual("hello");
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should resolve symlinks with realpath", async () => {
+    const tmpDir = await realpath(
+      await mkdtemp(path.join(os.tmpdir(), "test-"))
+    );
+    const dirPath = path.join(tmpDir, "dir");
+    const linkPath = path.join(tmpDir, "link");
+    await mkdir(dirPath);
+
+    await symlink(dirPath, linkPath, "dir");
+
+    expect(await realpath(linkPath)).toEqual(dirPath);
+    expect(await realpath(path.join(linkPath, ".."))).toEqual(tmpDir);
+    await expect(realpath(path.join(tmpDir, "missing"))).rejects.toThrow(
+      /resolve/
+    );
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should remove the link and keep the target with unlink", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "target");
+    const linkPath = path.join(tmpDir, "link");
+    await writeFile(filePath, "hello");
+    await symlink(filePath, linkPath);
+
+    await unlink(linkPath);
+
+    expect(await checkDirExists(linkPath)).toBe(false);
+    expect(await checkDirExists(filePath)).toBe(true);
+    await expect(unlink(linkPath)).rejects.toThrow(/unlink/);
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should create a hard link", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "target");
+    const linkPath = path.join(tmpDir, "link");
+    await writeFile(filePath, "hello");
+
+    await link(filePath, linkPath);
+
+    const stats = await lstat(linkPath);
+    expect(stats.isSymbolicLink()).toBe(false);
+    expect(stats.isFile()).toBe(true);
+    expect((await readFile(linkPath)).toString()).toEqual("hello");
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+
+  it("should handle links synchronously", () => {
+    const tmpDir = realpathSync(mkdtempSync(path.join(os.tmpdir(), "test-")));
+    const filePath = path.join(tmpDir, "target");
+    const linkPath = path.join(tmpDir, "link");
+    const hardLinkPath = path.join(tmpDir, "hardlink");
+    writeFileSync(filePath, "hello");
+
+    symlinkSync(filePath, linkPath);
+    linkSync(filePath, hardLinkPath);
+
+    expect(readlinkSync(linkPath)).toEqual(filePath);
+    expect(lstatSync(linkPath).isSymbolicLink()).toBe(true);
+    expect(lstatSync(hardLinkPath).isSymbolicLink()).toBe(false);
+    expect(realpathSync(linkPath)).toEqual(filePath);
+
+    unlinkSync(linkPath);
+    expect(() => lstatSync(linkPath)).toThrow(/lstat/);
+    expect(() => readlinkSync(filePath)).toThrow(/read link/);
+
+    rmSync(tmpDir, { recursive: true });
+  });
+
+  it("should handle links with callbacks", async () => {
+    const tmpDir = await mkdtemp(path.join(os.tmpdir(), "test-"));
+    const filePath = path.join(tmpDir, "target");
+    const linkPath = path.join(tmpDir, "link");
+    const hardLinkPath = path.join(tmpDir, "hardlink");
+    await writeFile(filePath, "hello");
+
+    const call = (fn: Function, ...args: any[]) =>
+      new Promise<any>((resolve, reject) =>
+        fn(...args, (err: Error | null, value: any) =>
+          err ? reject(err) : resolve(value)
+        )
+      );
+
+    await call(symlinkCb, filePath, linkPath);
+    await call(symlinkCb, filePath, path.join(tmpDir, "typed"), "file");
+    await call(linkCb, filePath, hardLinkPath);
+
+    expect(await call(readlinkCb, linkPath)).toEqual(filePath);
+    expect((await call(lstatCb, linkPath)).isSymbolicLink()).toBe(true);
+    expect(await call(realpathCb, linkPath)).toEqual(await realpath(filePath));
+
+    await call(unlinkCb, linkPath);
+    await expect(call(lstatCb, linkPath)).rejects.toThrow(/lstat/);
+    expect(() => unlinkCb(linkPath)).toThrow(/Callback/);
+
+    await rmdir(tmpDir, { recursive: true });
+  });
+});
+
 // Helper function to check if directory exists
 const checkDirExists = async (dirPath: string) => {
   return await promises
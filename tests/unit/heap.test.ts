@@ -0,0 +1,66 @@
+import fs from "node:fs/promises";
+import os from "node:os";
+import path from "node:path";
+import { gc, getHeapStatistics, getHeapSnapshot, writeHeapSnapshot } from "llrt:heap";
+
+class LeakyEntry {
+  constructor(public id: number) {}
+}
+
+describe("llrt:heap", () => {
+  it("should return heap statistics", () => {
+    const statistics = getHeapStatistics();
+    expect(statistics.total_heap_size).toBeGreaterThan(0);
+    expect(statistics.used_heap_size).toBeGreaterThan(0);
+    expect(statistics.peak_malloced_memory).toBeGreaterThanOrEqual(
+      statistics.malloced_memory
+    );
+    expect(statistics.number_of_native_contexts).toBe(1);
+  });
+
+  it("should run the garbage collector", () => {
+    expect(gc()).toBeUndefined();
+  });
+
+  it("should count objects by class with their retainers", () => {
+    const cache = new Map<string, LeakyEntry>();
+    for (let i = 0; i < 100; i++) {
+      cache.set(`entry${i}`, new LeakyEntry(i));
+    }
+    (globalThis as any).__heapTestCache = { cache };
+
+    try {
+      const snapshot = getHeapSnapshot();
+      expect(snapshot.objects.reachable).toBeGreaterThan(0);
+      expect(snapshot.objects.total).toBeGreaterThanOrEqual(
+        snapshot.objects.reachable
+      );
+      expect(snapshot.counts.objects.count).toBe(snapshot.objects.total);
+
+      const entries = snapshot.classes.find(
+        (c: any) => c.name === "LeakyEntry"
+      );
+      expect(entries.count).toBe(100);
+      expect(entries.shapes).toBe(1);
+      expect(entries.retainers[0]).toEqual({
+        name: "Map[value]",
+        count: 100,
+        path: "globalThis.__heapTestCache.cache[value]",
+      });
+    } finally {
+      delete (globalThis as any).__heapTestCache;
+    }
+  });
+
+  it("should write a snapshot to a file", async () => {
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), "llrt-heap-"));
+    try {
+      const filename = path.join(dir, "snapshot.json");
+      expect(writeHeapSnapshot(filename)).toBe(filename);
+      const snapshot = JSON.parse((await fs.readFile(filename)).toString());
+      expect(snapshot.classes.length).toBeGreaterThan(0);
+    } finally {
+      await fs.rm(dir, { recursive: true, force: true });
+    }
+  });
+});
@@ -0,0 +1,68 @@
+import { spawn } from "node:child_process";
+import fs from "node:fs/promises";
+import os from "node:os";
+import path from "node:path";
+
+const runTest = async (source: string) => {
+  const dir = await fs.mkdtemp(path.join(os.tmpdir(), "llrt-expect-"));
+  try {
+    await fs.writeFile(path.join(dir, "failing.test.js"), source);
+    return await new Promise<{ code: number | null; stderr: string }>(
+      (resolve, reject) => {
+        const proc = spawn(process.argv0, ["test"], { cwd: dir });
+        let stderr = "";
+        proc.stderr.on("data", (data) => (stderr += data.toString()));
+        proc.on("error", reject);
+        proc.on("close", (code) => resolve({ code, stderr }));
+      }
+    );
+  } finally {
+    await fs.rm(dir, { recursive: true, force: true });
+  }
+};
+
+describe("failure messages", () => {
+  it("should fail when expect.assertions does not match", async () => {
+    const { code, stderr } = await runTest(
+      "it('counts', () => { expect.assertions(2); expect(1).toBe(1); });"
+    );
+    expect(code).toBe(1);
+    expect(stderr).toContain(
+      "Expected 2 assertions to be called but received 1 assertion call."
+    );
+  });
+
+  it("should fail when expect.hasAssertions has none", async () => {
+    const { code, stderr } = await runTest(
+      "it('counts', () => { expect.hasAssertions(); expect(1); });"
+    );
+    expect(code).toBe(1);
+    expect(stderr).toContain(
+      "Expected at least one assertion to be called but received none."
+    );
+  });
+
+  it("should show the expected and received values", async () => {
+    const { stderr } = await runTest(
+      "it('compares', () => { expect(1).toBe(2); });"
+    );
+    expect(stderr).toContain("Expected: 2\nReceived: 1");
+  });
+
+  it("should show a diff of multi-line values", async () => {
+    const { stderr } = await runTest(
+      "it('compares', () => { expect({ a: 1, b: 2 }).toEqual({ a: 1, b: 3 }); });"
+    );
+    expect(stderr).toContain("- Expected\n+ Received");
+    expect(stderr).toContain("-   b: 3\n+   b: 2");
+  });
+
+  it("should fail when a function does not throw", async () => {
+    const { stderr } = await runTest(
+      "it('throws', () => { expect(() => {}).toThrow(TypeError); });"
+    );
+    expect(stderr).toContain(
+      "expected function to throw an error, but it didn't"
+    );
+  });
+});
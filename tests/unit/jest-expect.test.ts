@@ -500,6 +500,53 @@ describe("toSatisfy()", () => {
 
 it("timeout", () => new Promise((resolve) => setTimeout(resolve, 0)));
 
+describe("resolves and rejects", () => {
+  it("should match the resolved value", async () => {
+    await expect(Promise.resolve({ a: 1 })).resolves.toEqual({ a: 1 });
+    await expect(Promise.resolve(1)).resolves.not.toBe(2);
+  });
+
+  it("should match the rejection", async () => {
+    await expect(Promise.reject(new TypeError("boom"))).rejects.toThrow("boom");
+    await expect(Promise.reject(new TypeError("boom"))).rejects.toThrow(
+      TypeError
+    );
+    await expect(Promise.reject(new Error("boom"))).rejects.toThrowError(/bo/);
+    await expect(Promise.reject("reason")).rejects.toBe("reason");
+  });
+
+  it("should fail when the promise settles the other way", async () => {
+    await expect(
+      expect(Promise.reject(new Error("boom"))).resolves.toBe(1)
+    ).rejects.toThrow("instead of resolving");
+    await expect(expect(Promise.resolve(1)).rejects.toBe(1)).rejects.toThrow(
+      "instead of rejecting"
+    );
+  });
+});
+
+describe("expect.assertions()", () => {
+  it("should pass when the number of assertions matches", async () => {
+    expect.assertions(2);
+    await Promise.resolve();
+    expect(1).toBe(1);
+    expect(2).toBe(2);
+  });
+
+  it("should pass when there are assertions", () => {
+    expect.hasAssertions();
+    expect(true).toBeTruthy();
+  });
+
+  it("should count matcher calls", () => {
+    expect.assertions(3);
+    const assertion = expect(1);
+    assertion.toBe(1);
+    assertion.not.toBe(2);
+    expect(() => {}).not.toThrow();
+  });
+});
+
 describe("beforeEach", () => {
   let value: string;
   beforeEach(() => {
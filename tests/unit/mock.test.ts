@@ -0,0 +1,220 @@
+globalThis._require = require; //used to preserve require during bundling/minification
+const CWD = process.cwd();
+// Not resolved when bundling
+const SOME_PACKAGE = "some-package";
+
+describe("jest.fn", () => {
+  it("should track calls and results", () => {
+    const mock = jest.fn((a: number, b: number) => a + b);
+
+    expect(mock(1, 2)).toBe(3);
+    expect(mock(3, 4)).toBe(7);
+
+    expect(jest.isMockFunction(mock)).toBe(true);
+    expect(mock.mock.calls).toEqual([
+      [1, 2],
+      [3, 4],
+    ]);
+    expect(mock.mock.results).toEqual([
+      { type: "return", value: 3 },
+      { type: "return", value: 7 },
+    ]);
+    expect(mock.mock.lastCall).toEqual([3, 4]);
+  });
+
+  it("should record thrown errors", () => {
+    const error = new Error("boom");
+    const mock = jest.fn(() => {
+      throw error;
+    });
+
+    expect(() => mock()).toThrow("boom");
+    expect(mock.mock.results[0]).toEqual({ type: "throw", value: error });
+    expect(mock).not.toHaveReturned();
+  });
+
+  it("should support implementations and return values", async () => {
+    const mock = jest
+      .fn()
+      .mockReturnValue("default")
+      .mockReturnValueOnce("first")
+      .mockImplementationOnce(() => "second");
+
+    expect(mock()).toBe("first");
+    expect(mock()).toBe("second");
+    expect(mock()).toBe("default");
+
+    mock.mockResolvedValueOnce("resolved").mockRejectedValueOnce("rejected");
+    await expect(mock()).resolves.toBe("resolved");
+    await expect(mock()).rejects.toBe("rejected");
+
+    const object = { method: jest.fn().mockReturnThis() };
+    expect(object.method()).toBe(object);
+  });
+
+  it("should record instances", () => {
+    const Mock = jest.fn();
+    const instance = new Mock();
+
+    expect(Mock.mock.instances).toEqual([instance]);
+  });
+
+  it("should clear and reset", () => {
+    const mock = jest.fn(() => 1).mockName("counter");
+    mock();
+
+    mock.mockClear();
+    expect(mock).not.toHaveBeenCalled();
+    expect(mock()).toBe(1);
+
+    mock.mockReset();
+    expect(mock.mock.calls).toHaveLength(0);
+    expect(mock()).toBeUndefined();
+    expect(mock.getMockName()).toBe("counter");
+  });
+});
+
+describe("jest.spyOn", () => {
+  it("should call through and restore the original method", () => {
+    const object = {
+      multiply(a: number, b: number) {
+        return a * b;
+      },
+    };
+    const original = object.multiply;
+
+    const spy = jest.spyOn(object, "multiply");
+    expect(object.multiply(2, 3)).toBe(6);
+    expect(spy).toHaveBeenCalledWith(2, 3);
+
+    spy.mockImplementation(() => 0);
+    expect(object.multiply(2, 3)).toBe(0);
+
+    spy.mockRestore();
+    expect(object.multiply).toBe(original);
+    expect(object.multiply(2, 3)).toBe(6);
+  });
+
+  it("should spy on getters", () => {
+    const object = {
+      get value() {
+        return 1;
+      },
+    };
+
+    const spy = jest.spyOn(object, "value", "get").mockReturnValue(2);
+    expect(object.value).toBe(2);
+    expect(spy).toHaveBeenCalledTimes(1);
+
+    spy.mockRestore();
+    expect(object.value).toBe(1);
+  });
+
+  it("should restore all spies", () => {
+    const original = Math.max;
+    jest.spyOn(Math, "max").mockReturnValue(0);
+    expect(Math.max(1, 2)).toBe(0);
+
+    jest.restoreAllMocks();
+    expect(Math.max).toBe(original);
+  });
+
+  it("should reject non functions", () => {
+    expect(() => jest.spyOn({ value: 1 } as any, "value")).toThrow(
+      TypeError
+    );
+    expect(() => jest.spyOn({} as any, "missing")).toThrow(TypeError);
+  });
+});
+
+describe("call matchers", () => {
+  it("should match calls", () => {
+    const mock = jest.fn((value?: any) => value);
+    expect(mock).not.toHaveBeenCalled();
+
+    mock("a");
+    mock({ b: 1 });
+
+    expect(mock).toHaveBeenCalled();
+    expect(mock).toHaveBeenCalledTimes(2);
+    expect(mock).toHaveBeenCalledWith("a");
+    expect(mock).toHaveBeenCalledWith(expect.objectContaining({ b: 1 }));
+    expect(mock).not.toHaveBeenCalledWith("c");
+    expect(mock).toHaveBeenNthCalledWith(1, "a");
+    expect(mock).toHaveBeenLastCalledWith({ b: 1 });
+    expect(mock).toHaveReturnedTimes(2);
+    expect(mock).toHaveReturnedWith("a");
+    expect(mock).toHaveNthReturnedWith(2, { b: 1 });
+    expect(mock).toHaveLastReturnedWith({ b: 1 });
+  });
+
+  it("should report the received calls", () => {
+    const mock = jest.fn();
+    mock(1);
+
+    expect(() => expect(mock).toHaveBeenCalledWith(2)).toThrow(
+      /Received:\n  1st call: \[ 1 \]/
+    );
+    expect(() => expect(mock).toHaveBeenCalledTimes(2)).toThrow(
+      'expected "jest.fn()" to be called 2 times, but got 1 times'
+    );
+    expect(() => expect(() => {}).toHaveBeenCalled()).toThrow(
+      "is not a spy"
+    );
+  });
+});
+
+describe("jest.mock", () => {
+  it("should mock imported modules", async () => {
+    const greeting = jest.fn(() => "hi");
+    jest.mock(`${CWD}/fixtures/mock/greeting.js`, () => ({ greeting }));
+    jest.mock("node:os", () => ({ platform: () => "mocked" }));
+
+    const { greet } = await import(`${CWD}/fixtures/mock/greeter.js`);
+
+    expect(greet("llrt")).toBe("hi llrt from mocked");
+    expect(greeting).toHaveBeenCalledTimes(1);
+  });
+
+  it("should mock required modules", () => {
+    const exports = { platform: () => "required" };
+    jest.mock("os", () => exports);
+
+    expect(_require("os")).toBe(exports);
+    expect(_require("node:os").platform()).toBe("required");
+    expect(jest.requireActual("os").platform()).toBe(process.platform);
+  });
+
+  it("should call the factory once per mock", async () => {
+    const factory = jest.fn(() => ({ default: "value" }));
+    jest.mock(SOME_PACKAGE, factory);
+
+    const { default: first } = await import(SOME_PACKAGE);
+    const second = _require(SOME_PACKAGE);
+
+    expect(first).toBe("value");
+    expect(second).toEqual({ default: "value" });
+    expect(factory).toHaveBeenCalledTimes(1);
+  });
+
+  it("should unmock modules", () => {
+    jest.mock("os", () => ({}));
+    jest.unmock("os");
+
+    expect(_require("os").platform()).toBe(process.platform);
+  });
+
+  it("should reload modules importing changed mocks", async () => {
+    const greeter = `${CWD}/fixtures/mock/greeter.js`;
+    jest.unmock(`${CWD}/fixtures/mock/greeting.js`);
+    jest.unmock("os");
+
+    const { greet } = await import(greeter);
+    expect(greet("llrt")).toBe(`hello llrt from ${process.platform}`);
+
+    jest.mock("os", () => ({ platform: () => "mocked" }));
+    const { greet: mocked } = await import(greeter);
+    expect(mocked("llrt")).toBe("hello llrt from mocked");
+    expect(greet("llrt")).toBe(`hello llrt from ${process.platform}`);
+  });
+});
@@ -64,3 +64,13 @@ it("should have a process versions", () => {
 it("should have a process exit", () => {
   expect(exit).toEqual(process.exit);
 });
+
+it("should have a process memoryUsage", () => {
+  const usage = process.memoryUsage();
+  expect(usage.rss).toBeGreaterThan(0);
+  expect(usage.heapTotal).toBeGreaterThan(0);
+  expect(usage.heapUsed).toBeGreaterThan(0);
+  expect(typeof usage.external).toBe("number");
+  expect(typeof usage.arrayBuffers).toBe("number");
+  expect(process.memoryUsage.rss()).toBeGreaterThan(0);
+});
@@ -0,0 +1,85 @@
+import { spawn } from "node:child_process";
+import fs from "node:fs/promises";
+import os from "node:os";
+import path from "node:path";
+
+const CWD = process.cwd();
+
+const run = async (args: string[], env: Record<string, string> = {}) =>
+  await new Promise<number>((resolve) => {
+    const proc = spawn(process.argv0, [...args, `${CWD}/fixtures/busy.js`], {
+      env: { ...process.env, ...env },
+    });
+    proc.on("close", (code) => resolve(code ?? -1));
+  });
+
+const readProfile = async (dir: string) => {
+  const files = await fs.readdir(dir);
+  expect(files).toHaveLength(1);
+  expect(files[0]).toMatch(/^CPU\.\d{8}\.\d{6}\.\d+\.cpuprofile$/);
+  return JSON.parse((await fs.readFile(path.join(dir, files[0]))).toString());
+};
+
+describe("cpu profiler", () => {
+  let dir: string;
+
+  beforeEach(async () => {
+    dir = await fs.mkdtemp(path.join(os.tmpdir(), "llrt-cpu-prof-"));
+  });
+
+  afterEach(async () => {
+    await fs.rm(dir, { recursive: true, force: true });
+  });
+
+  it("should write a cpuprofile on exit", async () => {
+    expect(await run(["--cpu-prof", `--cpu-prof-dir=${dir}`])).toBe(0);
+
+    const profile = await readProfile(dir);
+    expect(profile.nodes[0].callFrame.functionName).toBe("(root)");
+    expect(profile.samples.length).toBeGreaterThan(0);
+    expect(profile.timeDeltas).toHaveLength(profile.samples.length);
+    expect(profile.endTime).toBeGreaterThanOrEqual(profile.startTime);
+
+    const ids = new Set(profile.nodes.map((node: any) => node.id));
+    for (const sample of profile.samples) {
+      expect(ids.has(sample)).toBe(true);
+    }
+
+    // Nodes are scripts, QuickJS doesn't expose the functions on the stack
+    const script = profile.nodes.find(
+      (node: any) => node.callFrame.functionName === "busy.js"
+    );
+    expect(script.callFrame.url).toContain("fixtures/busy.js");
+    expect(script.callFrame.lineNumber).toBe(-1);
+    expect(script.hitCount).toBeGreaterThan(0);
+  });
+
+  it("should be enabled with LLRT_CPU_PROF", async () => {
+    const status = await run([], {
+      LLRT_CPU_PROF: "1",
+      LLRT_CPU_PROF_DIR: dir,
+      LLRT_CPU_PROF_INTERVAL: "500",
+    });
+    expect(status).toBe(0);
+
+    const profile = await readProfile(dir);
+    expect(profile.samples.length).toBeGreaterThan(0);
+  });
+
+  it("should let flags override LLRT_CPU_PROF", async () => {
+    const envDir = path.join(dir, "env");
+    const flagDir = path.join(dir, "flag");
+    const status = await run([`--cpu-prof-dir=${flagDir}`], {
+      LLRT_CPU_PROF: "1",
+      LLRT_CPU_PROF_DIR: envDir,
+    });
+    expect(status).toBe(0);
+
+    await readProfile(flagDir);
+    await expect(fs.readdir(envDir)).rejects.toThrow();
+  });
+
+  it("should reject an invalid interval", async () => {
+    expect(await run(["--cpu-prof-interval=fast"])).toBe(1);
+  });
+});
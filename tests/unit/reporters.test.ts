@@ -0,0 +1,106 @@
+import { spawn } from "node:child_process";
+import fs from "node:fs/promises";
+import os from "node:os";
+import path from "node:path";
+
+const run = (args: string[], cwd: string) =>
+  new Promise<{ code: number | null; stdout: string }>((resolve, reject) => {
+    const proc = spawn(process.argv0, args, { cwd });
+    let stdout = "";
+    proc.stdout.on("data", (data) => (stdout += data.toString()));
+    proc.on("error", reject);
+    proc.on("close", (code) => resolve({ code, stdout }));
+  });
+
+describe("test reporters", () => {
+  let dir: string;
+
+  beforeEach(async () => {
+    dir = await fs.mkdtemp(path.join(os.tmpdir(), "llrt-reporters-"));
+    await fs.writeFile(
+      path.join(dir, "sample.test.js"),
+      [
+        "it('passes', () => {",
+        "  expect(1).toBe(1);",
+        "});",
+        "",
+        "it.skip('is skipped', () => {});",
+        "",
+        "describe('group', () => {",
+        "  it('fails', () => {",
+        "    expect(1).toBe(2);",
+        "  });",
+        "});",
+        "",
+      ].join("\n")
+    );
+  });
+
+  afterEach(async () => {
+    await fs.rm(dir, { recursive: true, force: true });
+  });
+
+  it("should print a json report", async () => {
+    const { code, stdout } = await run(["test", "--reporter", "json"], dir);
+    expect(code).toBe(1);
+
+    const report = JSON.parse(stdout);
+    expect(report.success).toBe(false);
+    expect(report.numTotalTests).toBe(3);
+    expect(report.numPassedTests).toBe(1);
+    expect(report.numFailedTests).toBe(1);
+    expect(report.numSkippedTests).toBe(1);
+
+    const [file] = report.testResults;
+    expect(file.name).toContain("sample.test.js");
+    const statuses = file.assertionResults.map(
+      ({ fullName, status }: any) => `${fullName}: ${status}`
+    );
+    expect(statuses).toEqual([
+      "passes: passed",
+      "is skipped: skipped",
+      "group fails: failed",
+    ]);
+    expect(file.assertionResults[2].failureMessages[0]).toContain(
+      "expected 1 to be 2"
+    );
+    expect(typeof file.assertionResults[0].duration).toBe("number");
+  });
+
+  it("should write a junit report to the output file", async () => {
+    const output = path.join(dir, "reports", "junit.xml");
+    const { code } = await run(
+      ["test", "--reporter", "junit", "--output", output],
+      dir
+    );
+    expect(code).toBe(1);
+
+    const xml = await fs.readFile(output, "utf8");
+    expect(xml).toMatch(/^<\?xml version="1.0" encoding="UTF-8"\?>/);
+    expect(xml).toContain('<testsuite name="sample.test.js"');
+    expect(xml).toContain('tests="3" failures="1" errors="0" skipped="1"');
+    expect(xml).toMatch(
+      /<testcase classname="sample.test.js" name="passes" time="\d+\.\d{3}"\/>/
+    );
+    expect(xml).toContain("<skipped/>");
+    expect(xml).toContain('<failure message="expected 1 to be 2');
+  });
+
+  it("should print a tap report", async () => {
+    const { code, stdout } = await run(["test", "--reporter", "tap"], dir);
+    expect(code).toBe(1);
+
+    const lines = stdout.split("\n");
+    expect(lines[0]).toBe("TAP version 13");
+    expect(lines[1]).toBe("1..3");
+    expect(lines[2]).toMatch(/^ok 1 - sample.test.js > passes # time=/);
+    expect(lines[3]).toBe("ok 2 - sample.test.js > is skipped # SKIP");
+    expect(lines[4]).toMatch(/^not ok 3 - sample.test.js > group > fails/);
+    expect(stdout).toContain('message: "expected 1 to be 2');
+  });
+
+  it("should reject unknown reporters", async () => {
+    const { code } = await run(["test", "--reporter", "xml"], dir);
+    expect(code).toBe(1);
+  });
+});
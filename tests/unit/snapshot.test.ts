@@ -0,0 +1,102 @@
+import { spawn } from "node:child_process";
+import fs from "node:fs/promises";
+import os from "node:os";
+import path from "node:path";
+
+const run = (args: string[], cwd: string) =>
+  new Promise<{ code: number | null; stderr: string }>((resolve, reject) => {
+    const proc = spawn(process.argv0, args, { cwd });
+    let stderr = "";
+    proc.stderr.on("data", (data) => (stderr += data.toString()));
+    proc.on("error", reject);
+    proc.on("close", (code) => resolve({ code, stderr }));
+  });
+
+describe("snapshots", () => {
+  let dir: string;
+  let testFile: string;
+
+  const writeTest = (value: string) =>
+    fs.writeFile(
+      testFile,
+      [
+        "describe('api', () => {",
+        "  it('responds', () => {",
+        `    expect(${value}).toMatchSnapshot();`,
+        "    expect('text').toMatchSnapshot('hint');",
+        "  });",
+        "});",
+        "",
+      ].join("\n")
+    );
+
+  beforeEach(async () => {
+    dir = await fs.mkdtemp(path.join(os.tmpdir(), "llrt-snapshot-"));
+    testFile = path.join(dir, "api.test.js");
+  });
+
+  afterEach(async () => {
+    await fs.rm(dir, { recursive: true, force: true });
+  });
+
+  it("should write, check and update snapshot files", async () => {
+    const snapshotFile = path.join(dir, "__snapshots__", "api.test.js.snap");
+    await writeTest("{ id: 1, tags: ['a', 'b'] }");
+
+    expect((await run(["test"], dir)).code).toBe(0);
+    const snapshots = await fs.readFile(snapshotFile, "utf8");
+    expect(snapshots).toContain("// LLRT Snapshot v1");
+    expect(snapshots).toContain("exports[`api responds 1`] = `\n{\n  id: 1,");
+    expect(snapshots).toContain('exports[`api responds: hint 1`] = `"text"`;');
+
+    expect((await run(["test"], dir)).code).toBe(0);
+    expect(await fs.readFile(snapshotFile, "utf8")).toBe(snapshots);
+
+    await writeTest("{ id: 2, tags: ['a', 'b'] }");
+    const { code, stderr } = await run(["test"], dir);
+    expect(code).toBe(1);
+    expect(stderr).toContain("Snapshot `api responds 1` mismatched");
+    expect(stderr).toContain("-   id: 1,");
+    expect(stderr).toContain("+   id: 2,");
+    expect(await fs.readFile(snapshotFile, "utf8")).toBe(snapshots);
+
+    expect((await run(["test", "-u"], dir)).code).toBe(0);
+    expect(await fs.readFile(snapshotFile, "utf8")).toContain("id: 2,");
+  });
+
+  it("should write inline snapshots into the test file", async () => {
+    await fs.writeFile(
+      testFile,
+      [
+        "it('inline', () => {",
+        "  expect({ a: 1 }).toMatchInlineSnapshot();",
+        "  expect(42).toMatchInlineSnapshot();",
+        "});",
+        "",
+      ].join("\n")
+    );
+
+    expect((await run(["test"], dir)).code).toBe(0);
+    expect(await fs.readFile(testFile, "utf8")).toBe(
+      [
+        "it('inline', () => {",
+        "  expect({ a: 1 }).toMatchInlineSnapshot(`",
+        "    {",
+        "      a: 1",
+        "    }",
+        "  `);",
+        "  expect(42).toMatchInlineSnapshot(`42`);",
+        "});",
+        "",
+      ].join("\n")
+    );
+
+    expect((await run(["test"], dir)).code).toBe(0);
+
+    const source = await fs.readFile(testFile, "utf8");
+    await fs.writeFile(testFile, source.replace("`42`", "`43`"));
+    expect((await run(["test"], dir)).code).toBe(1);
+    expect((await run(["test", "-u"], dir)).code).toBe(0);
+    expect(await fs.readFile(testFile, "utf8")).toBe(source);
+  });
+});
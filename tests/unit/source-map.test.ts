@@ -0,0 +1,47 @@
+import { spawn } from "node:child_process";
+
+const CWD = process.cwd();
+
+const run = async (filename: string, env: Record<string, string> = {}) =>
+  await new Promise<{ stderr: string; status: number }>((resolve) => {
+    const proc = spawn(
+      process.argv0,
+      [`${CWD}/fixtures/source-map/${filename}`],
+      { env: { ...process.env, ...env } }
+    );
+    let stderr = "";
+    proc.stderr.on("data", (data) => {
+      stderr += data.toString();
+    });
+    proc.on("close", (code) => {
+      resolve({ stderr, status: code ?? -1 });
+    });
+  });
+
+describe("source maps", () => {
+  it("should keep generated locations by default", async () => {
+    const { stderr, status } = await run("throw.js");
+
+    expect(status).toBe(1);
+    expect(stderr).toContain("kaboom");
+    expect(stderr).toContain("source-map/throw.js:2:");
+  });
+
+  it("should map stack traces with an adjacent source map", async () => {
+    const { stderr, status } = await run("throw.js", { LLRT_SOURCE_MAPS: "1" });
+
+    expect(status).toBe(1);
+    expect(stderr).toContain("at fail (");
+    expect(stderr).toContain("source-map/src/throw.ts:6:3");
+    expect(stderr).toContain("source-map/src/throw.ts:9:1");
+  });
+
+  it("should map stack traces with an inline source map", async () => {
+    const { stderr, status } = await run("inline.js", {
+      LLRT_SOURCE_MAPS: "1",
+    });
+
+    expect(status).toBe(1);
+    expect(stderr).toContain("source-map/src/throw.ts:6:3");
+  });
+});
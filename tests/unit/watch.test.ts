@@ -0,0 +1,79 @@
+import { spawn } from "node:child_process";
+import fs from "node:fs/promises";
+import os from "node:os";
+import path from "node:path";
+
+const waitFor = async (condition: () => boolean, timeout = 5000) => {
+  const start = Date.now();
+  while (!condition()) {
+    if (Date.now() - start > timeout) {
+      throw new Error("Timed out");
+    }
+    await new Promise((resolve) => setTimeout(resolve, 50));
+  }
+};
+
+describe("watch mode", () => {
+  let dir: string;
+
+  beforeEach(async () => {
+    dir = await fs.mkdtemp(path.join(os.tmpdir(), "llrt-watch-"));
+  });
+
+  afterEach(async () => {
+    await fs.rm(dir, { recursive: true, force: true });
+  });
+
+  it("should restart when a dependency changes", async () => {
+    const entry = path.join(dir, "index.js");
+    const dependency = path.join(dir, "dependency.js");
+    await fs.writeFile(dependency, "export default 'first';");
+    await fs.writeFile(
+      entry,
+      "import value from './dependency.js';\nconsole.log(value);"
+    );
+
+    const proc = spawn(process.argv0, ["--watch", entry]);
+    let stdout = "";
+    let stderr = "";
+    proc.stdout.on("data", (data) => (stdout += data.toString()));
+    proc.stderr.on("data", (data) => (stderr += data.toString()));
+
+    try {
+      await waitFor(() => stderr.includes("Waiting for file changes"));
+      expect(stdout.trim()).toBe("first");
+
+      await fs.writeFile(dependency, "export default 'second';");
+      await waitFor(() => stdout.includes("second"));
+      expect(stderr).toContain("Restarting");
+    } finally {
+      proc.kill();
+    }
+  });
+
+  it("should not restart when an unrelated file changes", async () => {
+    const entry = path.join(dir, "index.js");
+    await fs.writeFile(entry, "console.log('run');");
+
+    const proc = spawn(process.argv0, ["--watch", entry]);
+    let stdout = "";
+    let stderr = "";
+    proc.stdout.on("data", (data) => (stdout += data.toString()));
+    proc.stderr.on("data", (data) => (stderr += data.toString()));
+
+    try {
+      await waitFor(() => stderr.includes("Waiting for file changes"));
+      await fs.writeFile(path.join(dir, "unrelated.js"), "");
+      await new Promise((resolve) => setTimeout(resolve, 500));
+      expect(stdout.trim()).toBe("run");
+    } finally {
+      proc.kill();
+    }
+  });
+
+  it("should require a file", async () => {
+    const proc = spawn(process.argv0, ["--watch"]);
+    const code = await new Promise((resolve) => proc.on("close", resolve));
+    expect(code).toBe(1);
+  });
+});
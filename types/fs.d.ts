@@ -19,6 +19,8 @@
 declare module "fs" {
   import * as promises from "fs/promises";
   import { Buffer, BufferEncoding } from "buffer";
+  import { DefaultReadableStream, DefaultWritableStream } from "stream";
+  import { EventEmitter } from "events";
   export { promises };
 
   /**
@@ -341,4 +343,414 @@ declare module "fs" {
    * this API: {@link promises.rename}.
    */
   export function renameSync(oldPath: PathLike, newPath: PathLike): void;
+
+  /**
+   * Instances of `fs.ReadStream` are created and returned using the {@link createReadStream} function.
+   */
+  export class ReadStream extends DefaultReadableStream {
+    /**
+     * The number of bytes that have been read so far.
+     */
+    readonly bytesRead: number;
+    /**
+     * The path to the file the stream is reading from.
+     */
+    readonly path: string;
+    /**
+     * This property is `true` if the underlying file has not been opened yet,
+     * i.e. before the `'ready'` event is emitted.
+     */
+    readonly pending: boolean;
+    /**
+     * The maximum number of bytes read from the file at once.
+     */
+    readonly readableHighWaterMark: number;
+    /**
+     * Stops reading from the file until {@link resume} is called.
+     */
+    pause(): this;
+    /**
+     * Resumes reading from the file after {@link pause}.
+     */
+    resume(): this;
+    /**
+     * Returns `true` if the stream is paused.
+     */
+    isPaused(): boolean;
+    /**
+     * Writes all data of the stream to `destination`. By default, `end()` is called
+     * on the destination when the source emits `'end'`. To disable this behavior,
+     * pass `{ end: false }`.
+     *
+     * Reading is paused whenever `destination.write()` returns `false`, until the
+     * destination emits `'drain'`.
+     *
+     * ```js
+     * import { createReadStream, createWriteStream } from 'fs';
+     *
+     * createReadStream('input.txt').pipe(createWriteStream('output.txt'));
+     * ```
+     * @param destination The destination for writing data
+     * @return The destination, allowing for a chain of pipes
+     */
+    pipe<T extends QuickJS.WritableStream>(
+      destination: T,
+      options?: { end?: boolean }
+    ): T;
+  }
+
+  /**
+   * Instances of `fs.WriteStream` are created and returned using the {@link createWriteStream} function.
+   */
+  export class WriteStream extends DefaultWritableStream {
+    /**
+     * The number of bytes written so far. Does not include data that is still queued
+     * for writing.
+     */
+    readonly bytesWritten: number;
+    /**
+     * The path to the file the stream is writing to.
+     */
+    readonly path: string;
+    /**
+     * This property is `true` if the underlying file has not been opened yet,
+     * i.e. before the `'ready'` event is emitted.
+     */
+    readonly pending: boolean;
+    /**
+     * The number of queued bytes at which `write()` starts returning `false`.
+     */
+    readonly writableHighWaterMark: number;
+    /**
+     * The number of bytes queued for writing.
+     */
+    readonly writableLength: number;
+    /**
+     * This property is `true` if `write()` returned `false` and `'drain'` was not
+     * emitted yet.
+     */
+    readonly writableNeedDrain: boolean;
+    /**
+     * Queues `chunk` for writing. Returns `false` once the queued bytes reach
+     * `writableHighWaterMark`, `'drain'` is emitted when they are all written.
+     */
+    write(
+      chunk: string | Buffer | QuickJS.ArrayBufferView,
+      callback?: (error?: Error) => void
+    ): boolean;
+    /**
+     * Writes `chunk`, if provided, and signals that no more data will be written.
+     * `callback` is called once the `'finish'` event is emitted.
+     */
+    end(callback?: () => void): this;
+    end(
+      chunk: string | Buffer | QuickJS.ArrayBufferView,
+      callback?: () => void
+    ): this;
+  }
+
+  interface StreamOptions {
+    /**
+     * See `support of file system flags`.
+     */
+    flags?: promises.FileSystemFlags;
+    /**
+     * @default 0o666
+     */
+    mode?: Mode;
+    /**
+     * The byte position to start reading or writing at.
+     * @default 0
+     */
+    start?: number;
+  }
+
+  interface WriteStreamOptions extends StreamOptions {
+    /**
+     * The number of queued bytes at which `write()` starts returning `false`.
+     * @default 16384
+     */
+    highWaterMark?: number;
+  }
+
+  interface ReadStreamOptions extends StreamOptions {
+    /**
+     * The byte position to stop reading at, inclusive.
+     * @default Infinity
+     */
+    end?: number;
+    /**
+     * The maximum number of bytes to read from the file at once.
+     * @default 65536
+     */
+    highWaterMark?: number;
+  }
+
+  /**
+   * Returns a new `ReadStream` that reads the file in chunks of at most
+   * `highWaterMark` bytes. `start` and `end` read a range of bytes from the file
+   * instead of the entire file, both are inclusive and start counting at 0.
+   *
+   * The file is opened asynchronously, `'open'` and `'ready'` are emitted once
+   * it is open. If the file can't be opened, an `'error'` event is emitted.
+   *
+   * ```js
+   * import { createReadStream } from 'fs';
+   *
+   * createReadStream('sample.txt', { start: 90, end: 99 });
+   * ```
+   * @param [options.flags='r']
+   */
+  export function createReadStream(
+    path: PathLike,
+    options?: ReadStreamOptions
+  ): ReadStream;
+
+  /**
+   * Returns a new `WriteStream`. `start` allows writing data at some position past
+   * the beginning of the file, which requires the `r+` flag to keep the existing
+   * contents of the file.
+   *
+   * ```js
+   * import { createWriteStream } from 'fs';
+   *
+   * const stream = createWriteStream('output.txt');
+   * stream.write('Hello ');
+   * stream.end('World', () => console.log('done'));
+   * ```
+   * @param [options.flags='w']
+   */
+  export function createWriteStream(
+    path: PathLike,
+    options?: WriteStreamOptions
+  ): WriteStream;
+
+  export type WatchEventType = "rename" | "change";
+
+  export type WatchListener = (
+    eventType: WatchEventType,
+    filename: string
+  ) => void;
+
+  export interface WatchOptions {
+    /**
+     * Indicates whether all subdirectories should be watched, or only the
+     * current directory.
+     * @default false
+     */
+    recursive?: boolean;
+    /**
+     * Allows closing the watcher with an AbortSignal.
+     */
+    signal?: AbortSignal;
+  }
+
+  /**
+   * Instances of `FSWatcher` are returned by {@link watch}. The `'change'` event
+   * is emitted whenever a change is detected in the watched directory or file,
+   * the `'close'` event once the watcher stops.
+   */
+  export interface FSWatcher extends EventEmitter {
+    /**
+     * Stop watching for changes on the given `FSWatcher`. Once stopped, the
+     * `FSWatcher` object is no longer usable.
+     */
+    close(): void;
+  }
+
+  /**
+   * Watch for changes on `filename`, where `filename` is either a file or a
+   * directory.
+   *
+   * The listener callback gets two arguments `(eventType, filename)`. `eventType`
+   * is either `'rename'`, when a file is created, removed or renamed, or
+   * `'change'`. `filename` is relative to the watched directory, or the name of
+   * the watched file.
+   *
+   * The watcher keeps the event loop alive until it is closed.
+   *
+   * ```js
+   * import { watch } from 'fs';
+   *
+   * const watcher = watch('src', { recursive: true }, (eventType, filename) => {
+   *   console.log(eventType, filename);
+   * });
+   * ```
+   */
+  export function watch(
+    filename: PathLike,
+    options?: WatchOptions,
+    listener?: WatchListener
+  ): FSWatcher;
+  export function watch(
+    filename: PathLike,
+    listener?: WatchListener
+  ): FSWatcher;
+
+  /**
+   * Returned by {@link watchFile}, emits `'change'` with the current and previous
+   * `Stats` of the file.
+   */
+  export interface StatWatcher extends EventEmitter {}
+
+  export interface WatchFileOptions {
+    /**
+     * How often the file is polled, in milliseconds.
+     * @default 5007
+     */
+    interval?: number;
+  }
+
+  /**
+   * Watch for changes on `filename` by polling it. The `listener` gets called
+   * with the current and the previous `Stats` each time the file changes. Changes
+   * are only reported while the file exists.
+   *
+   * Using {@link watch} is more efficient than `watchFile` and `unwatchFile`.
+   *
+   * ```js
+   * import { watchFile } from 'fs';
+   *
+   * watchFile('message.text', (curr, prev) => {
+   *   console.log(`the current mtime is: ${curr.mtime}`);
+   *   console.log(`the previous mtime was: ${prev.mtime}`);
+   * });
+   * ```
+   */
+  export function watchFile(
+    filename: PathLike,
+    options: WatchFileOptions,
+    listener: (curr: Stats, prev: Stats) => void
+  ): StatWatcher;
+  export function watchFile(
+    filename: PathLike,
+    listener: (curr: Stats, prev: Stats) => void
+  ): StatWatcher;
+
+  /**
+   * Stop watching for changes on `filename`. If `listener` is specified, only that
+   * particular listener is removed. Otherwise, all listeners are removed,
+   * effectively stopping the polling of `filename`.
+   */
+  export function unwatchFile(
+    filename: PathLike,
+    listener?: (curr: Stats, prev: Stats) => void
+  ): void;
+
+  export type SymlinkType = "dir" | "file" | "junction";
+
+  export type NoParamCallback = (err: Error | null) => void;
+
+  /**
+   * Retrieves the `fs.Stats` for the symbolic link referred to by the path. If
+   * `path` is a symbolic link, then the link itself is stat-ed, not the file that
+   * it refers to.
+   */
+  export function lstat(
+    path: PathLike,
+    callback: (err: Error | null, stats: Stats) => void
+  ): void;
+
+  /**
+   * Synchronous lstat - Get file status without following symbolic links.
+   *
+   * For detailed information, see the documentation of the asynchronous version of
+   * this API: {@link lstat}.
+   */
+  export function lstatSync(path: PathLike): Stats;
+
+  /**
+   * Creates the link called `path` pointing to `target`.
+   *
+   * The `type` argument is only used on Windows and ignored on other platforms.
+   * It can be set to `'dir'`, `'file'`, or `'junction'`. If the `type` argument
+   * is not set, the type of `target` is detected automatically.
+   *
+   * Relative targets are relative to the link's parent directory.
+   */
+  export function symlink(
+    target: PathLike,
+    path: PathLike,
+    type: SymlinkType | undefined | null,
+    callback: NoParamCallback
+  ): void;
+  export function symlink(
+    target: PathLike,
+    path: PathLike,
+    callback: NoParamCallback
+  ): void;
+
+  /**
+   * Synchronously creates the link called `path` pointing to `target`.
+   *
+   * For detailed information, see the documentation of the asynchronous version of
+   * this API: {@link symlink}.
+   */
+  export function symlinkSync(
+    target: PathLike,
+    path: PathLike,
+    type?: SymlinkType | null
+  ): void;
+
+  /**
+   * Reads the contents of the symbolic link referred to by `path`. The callback
+   * gets two arguments `(err, linkString)`.
+   */
+  export function readlink(
+    path: PathLike,
+    callback: (err: Error | null, linkString: string) => void
+  ): void;
+
+  /**
+   * Returns the symbolic link's string value.
+   *
+   * For detailed information, see the documentation of the asynchronous version of
+   * this API: {@link readlink}.
+   */
+  export function readlinkSync(path: PathLike): string;
+
+  /**
+   * Creates a new hard link from the `existingPath` to the `newPath`.
+   */
+  export function link(
+    existingPath: PathLike,
+    newPath: PathLike,
+    callback: NoParamCallback
+  ): void;
+
+  /**
+   * Synchronously creates a new hard link from the `existingPath` to the
+   * `newPath`.
+   */
+  export function linkSync(existingPath: PathLike, newPath: PathLike): void;
+
+  /**
+   * Asynchronously computes the canonical pathname by resolving `.`, `..`, and
+   * symbolic links.
+   *
+   * The path must exist, otherwise the callback receives an error.
+   */
+  export function realpath(
+    path: PathLike,
+    callback: (err: Error | null, resolvedPath: string) => void
+  ): void;
+
+  /**
+   * Returns the resolved pathname.
+   *
+   * For detailed information, see the documentation of the asynchronous version of
+   * this API: {@link realpath}.
+   */
+  export function realpathSync(path: PathLike): string;
+
+  /**
+   * Asynchronously removes a file or symbolic link. Symbolic links are removed
+   * without affecting the file or directory they refer to.
+   */
+  export function unlink(path: PathLike, callback: NoParamCallback): void;
+
+  /**
+   * Synchronously removes a file or symbolic link.
+   */
+  export function unlinkSync(path: PathLike): void;
 }
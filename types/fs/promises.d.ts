@@ -13,6 +13,9 @@ declare module "fs/promises" {
     RmDirOptions,
     RmOptions,
     Stats,
+    SymlinkType,
+    WatchEventType,
+    WatchOptions,
   } from "fs";
 
   export type FileSystemFlags =
@@ -137,6 +140,23 @@ declare module "fs/promises" {
         | BufferEncoding
     ): Promise<string>;
 
+    /**
+     * Returns a byte oriented `ReadableStream` that reads the contents of the file,
+     * starting at the current position.
+     *
+     * ```js
+     * import { open } from 'fs/promises';
+     *
+     * const file = await open('./some/file/to/read');
+     *
+     * for await (const chunk of file.readableWebStream())
+     *   console.log(chunk);
+     *
+     * await file.close();
+     * ```
+     */
+    readableWebStream(): ReadableStream<Buffer>;
+
     /**
      * Get {FileHandle} status.
      * @return Fulfills with the {fs.Stats} object.
@@ -399,6 +419,54 @@ declare module "fs/promises" {
    */
   function stat(path: PathLike): Promise<Stats>;
 
+  /**
+   * Equivalent to `fsPromises.stat()` unless `path` refers to a symbolic link,
+   * in which case the link itself is stat-ed, not the file that it refers to.
+   * @return Fulfills with the {fs.Stats} object for the given symbolic link
+   * `path`.
+   */
+  function lstat(path: PathLike): Promise<Stats>;
+
+  /**
+   * Creates a symbolic link.
+   *
+   * The `type` argument is only used on Windows platforms and can be one of
+   * `'dir'`, `'file'`, or `'junction'`. If the `type` argument is not set, the
+   * type of `target` is detected automatically.
+   * @return Fulfills with `undefined` upon success.
+   */
+  function symlink(
+    target: PathLike,
+    path: PathLike,
+    type?: SymlinkType | null
+  ): Promise<void>;
+
+  /**
+   * Reads the contents of the symbolic link referred to by `path`.
+   * @return Fulfills with the `linkString` upon success.
+   */
+  function readlink(path: PathLike): Promise<string>;
+
+  /**
+   * Creates a new link from the `existingPath` to the `newPath`.
+   * @return Fulfills with `undefined` upon success.
+   */
+  function link(existingPath: PathLike, newPath: PathLike): Promise<void>;
+
+  /**
+   * Determines the actual location of `path` by resolving `.`, `..`, and
+   * symbolic links.
+   * @return Fulfills with the resolved path upon success.
+   */
+  function realpath(path: PathLike): Promise<string>;
+
+  /**
+   * If `path` refers to a symbolic link, then the link is removed without
+   * affecting the file or directory to which that link refers.
+   * @return Fulfills with `undefined` upon success.
+   */
+  function unlink(path: PathLike): Promise<void>;
+
   /**
    * Creates a unique temporary directory. A unique directory name is generated by
    * appending six random characters to the end of the provided `prefix`. Due to
@@ -528,4 +596,36 @@ declare module "fs/promises" {
    * @return Fulfills with `undefined` upon success.
    */
   function rename(oldPath: PathLike, newPath: PathLike): Promise<void>;
+
+  interface FileChangeInfo {
+    eventType: WatchEventType;
+    filename: string;
+  }
+
+  /**
+   * Returns an async iterator that watches for changes on `filename`, where
+   * `filename` is either a file or a directory.
+   *
+   * ```js
+   * import { watch } from 'fs/promises';
+   *
+   * const ac = new AbortController();
+   * const { signal } = ac;
+   * setTimeout(() => ac.abort(), 10000);
+   *
+   * try {
+   *   const watcher = watch(__filename, { signal });
+   *   for await (const event of watcher)
+   *     console.log(event);
+   * } catch (err) {
+   *   if (err.name === 'AbortError')
+   *     return;
+   *   throw err;
+   * }
+   * ```
+   */
+  function watch(
+    filename: PathLike,
+    options?: WatchOptions
+  ): AsyncIterableIterator<FileChangeInfo>;
 }
@@ -34,6 +34,40 @@ declare module "process" {
      */
     bigint(): bigint;
   }
+  interface MemoryUsage {
+    rss: number;
+    heapTotal: number;
+    heapUsed: number;
+    external: number;
+    arrayBuffers: number;
+  }
+  interface MemoryUsageFn {
+    /**
+     * Returns an object describing the memory usage of the llrt process measured in
+     * bytes. `heapTotal` and `heapUsed` refer to the QuickJS heap. QuickJS doesn't
+     * track array buffers separately, so `external` and `arrayBuffers` are always `0`.
+     *
+     * ```js
+     * import { memoryUsage } from 'process';
+     *
+     * console.log(memoryUsage());
+     * // Prints:
+     * // {
+     * //  rss: 4935680,
+     * //  heapTotal: 1826816,
+     * //  heapUsed: 650472,
+     * //  external: 0,
+     * //  arrayBuffers: 0
+     * // }
+     * ```
+     */
+    (): MemoryUsage;
+    /**
+     * Returns the Resident Set Size (RSS) of the process in bytes. Outside of Linux, the
+     * peak RSS is returned.
+     */
+    rss(): number;
+  }
   interface ProcessRelease {
     name: string;
   }
@@ -42,6 +76,7 @@ declare module "process" {
   }
   interface Process extends EventEmitter {
     hrtime: HRTime;
+    memoryUsage: MemoryUsageFn;
     /**
      * The `process.cwd()` method returns the current working directory of the llrt
      * process.